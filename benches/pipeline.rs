@@ -0,0 +1,70 @@
+// Criterion benchmarks of the proving pipeline for every circuit profile, see
+// zkyc::circuit::bench. Run with `cargo bench --features bench`: estimates are
+// written as JSON under target/criterion, and `cargo bench --features bench --
+// --save-baseline before` then `--baseline before` compares a branch against
+// another.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use zkyc::{
+    circuit::{self, bench::Fixture, inputs::Features, CircuitProfile},
+    schnorr::signature::{Context, Signature},
+};
+
+const SEED: u64 = 3;
+
+const PROFILES: [CircuitProfile; 4] = [
+    CircuitProfile::Standard,
+    CircuitProfile::FastProve,
+    CircuitProfile::SmallProof,
+    CircuitProfile::Test,
+];
+
+fn native(c: &mut Criterion) {
+    let fixture = Fixture::from_seed(SEED).unwrap();
+    let ctx = Context::new(&fixture.credential);
+    let mut group = c.benchmark_group("native");
+    group.bench_function("sign", |b| {
+        b.iter(|| Signature::prove(&fixture.issuer_sk, &ctx))
+    });
+    group.bench_function("verify_signature", |b| {
+        b.iter(|| fixture.signature.verify(&ctx))
+    });
+    group.finish();
+}
+
+fn pipeline(c: &mut Criterion) {
+    let fixture = Fixture::from_seed(SEED).unwrap();
+    for profile in PROFILES {
+        let mut group = c.benchmark_group(format!("{profile:?}"));
+        // a single proof takes seconds
+        group.sample_size(10);
+        group.bench_function("build", |b| {
+            b.iter(|| circuit::circuit_with_profile(Features::default(), profile))
+        });
+        let circuit = circuit::circuit_with_profile(Features::default(), profile);
+        group.bench_function("witness", |b| b.iter(|| fixture.witness(&circuit).unwrap()));
+        group.bench_function("prove", |b| {
+            b.iter_batched(
+                || fixture.witness(&circuit).unwrap(),
+                |pw| pw.prove(&circuit.circuit).unwrap(),
+                BatchSize::PerIteration,
+            )
+        });
+        let proof = fixture
+            .witness(&circuit)
+            .unwrap()
+            .prove(&circuit.circuit)
+            .unwrap();
+        group.bench_function("verify", |b| {
+            b.iter_batched(
+                || proof.clone(),
+                |proof| circuit.circuit.verify(proof).unwrap(),
+                BatchSize::PerIteration,
+            )
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, native, pipeline);
+criterion_main!(benches);
@@ -0,0 +1,353 @@
+// Self-contained record of a verified presentation, so that an audit years
+// later only needs this crate's verifier, not the exact build of the circuit.
+// The digest of the circuit must still be a trusted one: the archive carries
+// its own verifier key, which anyone can build for a vacuous circuit.
+//
+// Layout: MAGIC || VERSION || circuit digest || policy || number of public
+//         inputs (u32) || public inputs || verifier key length (u32)
+//         || verifier key || proof
+// Policy: nationality (u16) || today_days (u32) || min_age_days (u32)
+//         || has issuer || [issuer public key]
+// Integers and field elements (canonical u64s) are little-endian.
+
+use std::path::Path;
+
+use plonky2::field::types::PrimeField64;
+use thiserror::Error;
+
+use crate::{
+    circuit::{Circuit, ZkProof, F},
+    core::{codec::Codec, policy::Policy},
+    encoding::{conversion::ToPointField, LEN_HASH, LEN_POINT},
+    schnorr::keys::PublicKey,
+    verify::{self, verify_bytes, VerificationReport},
+};
+
+const MAGIC: &[u8; 8] = b"ZKYCARCH";
+pub const VERSION: u8 = 1;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Archive unreadable: {0}")]
+    Io(String),
+    #[error("Not an archive")]
+    NotAnArchive,
+    #[error("Unsupported archive version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Malformed archive")]
+    Malformed,
+    #[error("The archived circuit digest is not a trusted one")]
+    UntrustedCircuit,
+    #[error("The verifier key is not the one of the archived circuit digest")]
+    DigestMismatch,
+    #[error("The public inputs are not the ones of the archived policy")]
+    PolicyMismatch,
+    #[error(transparent)]
+    Verify(#[from] verify::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Values of the policy a presentation was verified against, see
+/// core::policy::Policy. Codes & keys are kept raw, so that archives stay
+/// readable whatever the policies of later versions
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PolicySnapshot {
+    pub nationality: u16,
+    pub today_days: u32,
+    pub min_age_days: u32,
+    pub issuer: Option<[u8; 40]>,
+}
+
+impl From<&Policy> for PolicySnapshot {
+    fn from(policy: &Policy) -> Self {
+        Self {
+            nationality: policy.nationality.code(),
+            today_days: policy.today_days,
+            min_age_days: policy.min_age_days,
+            issuer: policy.issuer.as_ref().map(PublicKey::to_bytes),
+        }
+    }
+}
+
+impl PolicySnapshot {
+    /// Whether the public inputs start with the values of the policy, in the
+    /// registration order of circuit::inputs::register_with. Without issuer,
+    /// the key in the public inputs is a root the policy does not name
+    fn matches(&self, public_inputs: &[u64]) -> bool {
+        let Some((&nationality, rest)) = public_inputs.split_first() else {
+            return false;
+        };
+        if rest.len() < LEN_POINT + 2 || nationality != self.nationality as u64 {
+            return false;
+        }
+        let (issuer, rest) = rest.split_at(LEN_POINT);
+        let issuer_matches = match self.issuer.as_ref().map(|pk| PublicKey::from_bytes(pk)) {
+            None => true,
+            Some(Err(_)) => false,
+            Some(Ok(pk)) => {
+                let expected: [F; LEN_POINT] = pk.0.to_field().into();
+                expected
+                    .iter()
+                    .zip(issuer)
+                    .all(|(x, &y)| x.to_canonical_u64() == y)
+            }
+        };
+        issuer_matches && rest[0] == self.today_days as u64 && rest[1] == self.min_age_days as u64
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Archive {
+    /// See Circuit::digest
+    pub circuit_digest: [u64; LEN_HASH],
+    pub policy: PolicySnapshot,
+    /// Canonical values, as proved
+    pub public_inputs: Vec<u64>,
+    /// See Circuit::verifier_key_bytes
+    pub verifier_key: Vec<u8>,
+    /// See ZkProof::to_bytes
+    pub proof: Vec<u8>,
+}
+
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if bytes.len() < len {
+        return Err(Error::Malformed);
+    }
+    let (head, tail) = bytes.split_at(len);
+    *bytes = tail;
+    Ok(head)
+}
+
+fn take_u32(bytes: &mut &[u8]) -> Result<u32> {
+    Ok(u32::from_le_bytes(take(bytes, 4)?.try_into().unwrap()))
+}
+
+fn take_u64(bytes: &mut &[u8]) -> Result<u64> {
+    Ok(u64::from_le_bytes(take(bytes, 8)?.try_into().unwrap()))
+}
+
+impl Archive {
+    /// Archive of a proof of circuit, verified against policy
+    pub fn new(circuit: &Circuit, proof: &ZkProof, policy: &Policy) -> Self {
+        Self {
+            circuit_digest: circuit.digest().0.map(|x| x.to_canonical_u64()),
+            policy: policy.into(),
+            public_inputs: proof
+                .public_inputs
+                .iter()
+                .map(|x| x.to_canonical_u64())
+                .collect(),
+            verifier_key: circuit.verifier_key_bytes(),
+            proof: proof.to_bytes(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut res = Vec::with_capacity(
+            MAGIC.len()
+                + 100
+                + 8 * self.public_inputs.len()
+                + self.verifier_key.len()
+                + self.proof.len(),
+        );
+        res.extend_from_slice(MAGIC);
+        res.push(VERSION);
+        for x in self.circuit_digest {
+            res.extend_from_slice(&x.to_le_bytes());
+        }
+        res.extend_from_slice(&self.policy.nationality.to_le_bytes());
+        res.extend_from_slice(&self.policy.today_days.to_le_bytes());
+        res.extend_from_slice(&self.policy.min_age_days.to_le_bytes());
+        res.push(self.policy.issuer.is_some().into());
+        if let Some(issuer) = &self.policy.issuer {
+            res.extend_from_slice(issuer);
+        }
+        res.extend_from_slice(&(self.public_inputs.len() as u32).to_le_bytes());
+        for x in &self.public_inputs {
+            res.extend_from_slice(&x.to_le_bytes());
+        }
+        res.extend_from_slice(&(self.verifier_key.len() as u32).to_le_bytes());
+        res.extend_from_slice(&self.verifier_key);
+        res.extend_from_slice(&self.proof);
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let Some(mut bytes) = bytes.strip_prefix(&MAGIC[..]) else {
+            return Err(Error::NotAnArchive);
+        };
+        let version = take(&mut bytes, 1)?[0];
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        let mut circuit_digest = [0; LEN_HASH];
+        for x in circuit_digest.iter_mut() {
+            *x = take_u64(&mut bytes)?;
+        }
+        let nationality = u16::from_le_bytes(take(&mut bytes, 2)?.try_into().unwrap());
+        let today_days = take_u32(&mut bytes)?;
+        let min_age_days = take_u32(&mut bytes)?;
+        let issuer = match take(&mut bytes, 1)?[0] {
+            0 => None,
+            1 => Some(take(&mut bytes, 40)?.try_into().unwrap()),
+            _ => return Err(Error::Malformed),
+        };
+        let num_public_inputs = take_u32(&mut bytes)? as usize;
+        if bytes.len() / 8 < num_public_inputs {
+            return Err(Error::Malformed);
+        }
+        let public_inputs = (0..num_public_inputs)
+            .map(|_| take_u64(&mut bytes))
+            .collect::<Result<_>>()?;
+        let len = take_u32(&mut bytes)? as usize;
+        let verifier_key = take(&mut bytes, len)?.to_vec();
+        Ok(Self {
+            circuit_digest,
+            policy: PolicySnapshot {
+                nationality,
+                today_days,
+                min_age_days,
+                issuer,
+            },
+            public_inputs,
+            verifier_key,
+            proof: bytes.to_vec(),
+        })
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.to_bytes()).map_err(|err| Error::Io(err.to_string()))
+    }
+
+    /// Verifies the proof with the archived verifier key, and checks that
+    /// both the circuit and the public inputs are the archived ones. The
+    /// archive names its own circuit, so its digest must be one of trusted,
+    /// e.g. the digests of the circuits the verifier accepted at the time
+    pub fn verify(&self, trusted: &[[u64; LEN_HASH]]) -> Result<VerificationReport> {
+        if !trusted.contains(&self.circuit_digest) {
+            return Err(Error::UntrustedCircuit);
+        }
+        let report = verify_bytes(&self.verifier_key, &self.proof, &self.public_inputs)?;
+        if report.circuit_digest != self.circuit_digest {
+            return Err(Error::DigestMismatch);
+        }
+        if !self.policy.matches(&self.public_inputs) {
+            return Err(Error::PolicyMismatch);
+        }
+        Ok(report)
+    }
+}
+
+/// Reads the archive at path, and verifies it against the trusted circuit
+/// digests, see Archive::verify
+pub fn verify_archive(
+    path: impl AsRef<Path>,
+    trusted: &[[u64; LEN_HASH]],
+) -> Result<(Archive, VerificationReport)> {
+    let bytes = std::fs::read(path).map_err(|err| Error::Io(err.to_string()))?;
+    let archive = Archive::from_bytes(&bytes)?;
+    let report = archive.verify(trusted)?;
+    Ok((archive, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        field::types::Field,
+        iop::witness::{PartialWitness, WitnessWrite},
+        plonk::{circuit_builder::CircuitBuilder, circuit_data::CircuitConfig},
+        util::serialization::DefaultGateSerializer,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        circuit,
+        core::{credential::Nationality, date::ADULT_AGE_DAYS},
+        schnorr::keys::SecretKey,
+    };
+
+    /// Archive of a circuit which only exposes the inputs of the policy
+    fn archive(policy: &Policy) -> Archive {
+        let mut builder = CircuitBuilder::<F, { circuit::D }>::new(CircuitConfig::default());
+        let targets = builder.add_virtual_targets(1 + LEN_POINT + 2);
+        builder.register_public_inputs(&targets);
+        let data = builder.build::<circuit::C>();
+
+        let issuer: [F; LEN_POINT] = policy.issuer.as_ref().unwrap().0.to_field().into();
+        let mut values = vec![F::from_canonical_u16(policy.nationality.code())];
+        values.extend(issuer);
+        values.push(F::from_canonical_u32(policy.today_days));
+        values.push(F::from_canonical_u32(policy.min_age_days));
+        let mut pw = PartialWitness::new();
+        for (target, value) in targets.into_iter().zip(values) {
+            pw.set_target(target, value).unwrap();
+        }
+        let proof = data.prove(pw).unwrap();
+        Archive {
+            circuit_digest: data
+                .verifier_only
+                .circuit_digest
+                .elements
+                .map(|x| x.to_canonical_u64()),
+            policy: policy.into(),
+            public_inputs: proof
+                .public_inputs
+                .iter()
+                .map(|x| x.to_canonical_u64())
+                .collect(),
+            verifier_key: data
+                .verifier_data()
+                .to_bytes(&DefaultGateSerializer)
+                .unwrap(),
+            proof: proof.to_bytes(),
+        }
+    }
+
+    #[test]
+    fn verify_archive_reads_back_what_was_written() {
+        let issuer = PublicKey::from(&SecretKey::random(&mut StdRng::seed_from_u64(1)));
+        let policy = Policy {
+            nationality: Nationality::FR,
+            today_days: 20000,
+            min_age_days: ADULT_AGE_DAYS,
+            issuer: Some(issuer),
+        };
+        let archive = archive(&policy);
+        let path = std::env::temp_dir().join(format!("zkyc-archive-{}", std::process::id()));
+        archive.write(&path).unwrap();
+        let trusted = [archive.circuit_digest];
+        let (read, report) = verify_archive(&path, &trusted).unwrap();
+        assert_eq!(read, archive);
+        assert_eq!(report.circuit_digest, archive.circuit_digest);
+        // the archive proves its own circuit, which is no reason to trust it
+        assert_eq!(
+            verify_archive(&path, &[]),
+            Err(Error::UntrustedCircuit)
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        let mut other_digest = Archive::from_bytes(&archive.to_bytes()).unwrap();
+        other_digest.circuit_digest[0] ^= 1;
+        assert_eq!(other_digest.verify(&trusted), Err(Error::UntrustedCircuit));
+        assert_eq!(
+            other_digest.verify(&[other_digest.circuit_digest]),
+            Err(Error::DigestMismatch)
+        );
+        let mut other_policy = Archive::from_bytes(&archive.to_bytes()).unwrap();
+        other_policy.policy.today_days += 1;
+        assert_eq!(other_policy.verify(&trusted), Err(Error::PolicyMismatch));
+
+        let bytes = archive.to_bytes();
+        assert_eq!(Archive::from_bytes(&bytes[1..]), Err(Error::NotAnArchive));
+        assert_eq!(
+            Archive::from_bytes(&bytes[..MAGIC.len() + 20]),
+            Err(Error::Malformed)
+        );
+        assert!(matches!(
+            verify_archive(std::env::temp_dir().join("zkyc-no-such-archive"), &trusted),
+            Err(Error::Io(_))
+        ));
+    }
+}
@@ -7,6 +7,8 @@ use super::field::{GFp, GFp5};
 use super::multab::{G0, G120, G160, G200, G240, G280, G40, G80};
 use super::scalar::Scalar;
 use core::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use std::sync::OnceLock;
+use thiserror::Error;
 
 // ========================================================================
 
@@ -125,6 +127,27 @@ impl Point {
         (Self { X, Z, U, T }, c | w.iszero())
     }
 
+    /// Decode a point from its 40-byte wire encoding, i.e. encode().encode(),
+    /// for data which crosses a trust boundary. Unlike decode, fails with a
+    /// typed error instead of yielding the neutral.
+    pub fn decode_checked(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != 40 {
+            return Err(Error::Length(bytes.len()));
+        }
+        let (w, c) = GFp5::decode(bytes);
+        if c != u64::MAX {
+            return Err(Error::NonCanonical);
+        }
+        let (P, c) = Self::decode(w);
+        // The group is made of N = (0, 0) (the neutral) and of the points
+        // whose x is not a square, the others having order 2n. decode only
+        // yields those, checked again as every proof relies on it (Z = 1).
+        if c != u64::MAX || P.X.legendre().isone() != 0 {
+            return Err(Error::NotInGroup);
+        }
+        Ok(P)
+    }
+
     // General point addition. Formulas are complete (no special case).
     fn set_add(&mut self, rhs: &Self) {
         // cost: 10M
@@ -370,6 +393,9 @@ impl Point {
     /// This function is faster than using the multiplication operator
     /// on the generator point.
     pub fn mulgen(s: Scalar) -> Self {
+        if let Some(table) = GENERATOR_TABLE.get() {
+            return table.mul(s);
+        }
         // Precomputed tables are for j*(2^(80*i))*G, for i = 0 to 3
         // and j = 1 to 16, i.e. 5-bit windows.
         let mut ss = [0i32; 64];
@@ -482,6 +508,121 @@ impl Point {
     }
 }
 
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Window of {0} bits, expected {MIN_WINDOW} to {MAX_WINDOW}")]
+    InvalidWindow(usize),
+    #[error("The generator tables are already set")]
+    AlreadySet,
+    #[error("Point encoding of {0} bytes, expected 40")]
+    Length(usize),
+    #[error("Non canonical point encoding")]
+    NonCanonical,
+    #[error("Not the encoding of a point of the group")]
+    NotInGroup,
+}
+
+pub const MIN_WINDOW: usize = 2;
+/// 8 * 2^9 affine points, i.e. 320 kB
+pub const MAX_WINDOW: usize = 10;
+
+// Number of tables of a FixedBaseTable: the doublings are shared by the
+// digits of all the tables, as in mulgen.
+const TABLES: usize = 8;
+
+/// Precomputed multiples of a fixed base point, for signed windows of
+/// `window` bits. Table j holds i*(2^(j*m*window))*B for i = 1 to
+/// 2^(window-1), m being the number of digits per table: larger windows
+/// trade memory (8*2^(window-1) points) for fewer additions and doublings.
+/// The built-in tables of mulgen are those of a 5-bit window.
+pub struct FixedBaseTable {
+    window: usize,
+    tables: Vec<Vec<PointAffine>>,
+}
+
+impl FixedBaseTable {
+    pub fn new(base: Point, window: usize) -> Result<Self, Error> {
+        if !(MIN_WINDOW..=MAX_WINDOW).contains(&window) {
+            return Err(Error::InvalidWindow(window));
+        }
+        let shift = (Self::digits(window) * window) as u32;
+        let mut tables = Vec::with_capacity(TABLES);
+        let mut b = base;
+        for _ in 0..TABLES {
+            let mut tmp = vec![Point::NEUTRAL; 1 << (window - 1)];
+            tmp[0] = b;
+            for i in 1..tmp.len() {
+                tmp[i] = if (i & 1) == 0 {
+                    b + tmp[i - 1]
+                } else {
+                    tmp[i >> 1].double()
+                };
+            }
+            let mut table = vec![PointAffine::NEUTRAL; tmp.len()];
+            Point::to_affine_array(&tmp, &mut table);
+            tables.push(table);
+            b.set_mdouble(shift);
+        }
+        Ok(Self { window, tables })
+    }
+
+    /// Same as mul, only for public scalars
+    pub fn mul_vartime(&self, s: Scalar) -> Point {
+        let m = Self::digits(self.window);
+        let mut ss = vec![0i32; TABLES * m];
+        s.recode_signed(&mut ss, self.window as i32);
+        let mut P = Point::NEUTRAL;
+        for i in (0..m).rev() {
+            if i != m - 1 {
+                P.set_mdouble(self.window as u32);
+            }
+            for (j, table) in self.tables.iter().enumerate() {
+                if ss[j * m + i] != 0 {
+                    P += PointAffine::lookup_vartime(table, ss[j * m + i]);
+                }
+            }
+        }
+        P
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    // Digits of the scalar per table.
+    fn digits(window: usize) -> usize {
+        ((319 + window) / window).div_ceil(TABLES)
+    }
+
+    /// Constant-time multiplication of the base point by a scalar
+    pub fn mul(&self, s: Scalar) -> Point {
+        let m = Self::digits(self.window);
+        let mut ss = vec![0i32; TABLES * m];
+        s.recode_signed(&mut ss, self.window as i32);
+        let mut P = PointAffine::lookup(&self.tables[0], ss[m - 1]).to_point();
+        for (j, table) in self.tables.iter().enumerate().skip(1) {
+            P += PointAffine::lookup(table, ss[j * m + m - 1]);
+        }
+        for i in (0..m - 1).rev() {
+            P.set_mdouble(self.window as u32);
+            for (j, table) in self.tables.iter().enumerate() {
+                P += PointAffine::lookup(table, ss[j * m + i]);
+            }
+        }
+        P
+    }
+}
+
+static GENERATOR_TABLE: OnceLock<FixedBaseTable> = OnceLock::new();
+
+/// Replaces the built-in tables of Point::mulgen, e.g. by small ones on
+/// constrained devices or by large ones on servers. Only the first call
+/// takes effect, so it should happen at startup.
+pub fn set_generator_window(window: usize) -> Result<(), Error> {
+    let table = FixedBaseTable::new(Point::GENERATOR, window)?;
+    GENERATOR_TABLE.set(table).map_err(|_| Error::AlreadySet)
+}
+
 // A curve point in affine (x,u) coordinates. This is used internally
 // to make "windows" that speed up point multiplications.
 #[derive(Clone, Copy, Debug)]
@@ -1035,7 +1176,7 @@ mod tests {
 
     use super::super::field::GFp5;
     use super::super::scalar::Scalar;
-    use super::{Point, PointAffine};
+    use super::{FixedBaseTable, Point, PointAffine, MAX_WINDOW, MIN_WINDOW};
     // use super::super::PRNG;
 
     #[test]
@@ -1329,6 +1470,25 @@ mod tests {
         }
     }
 
+    #[test]
+    fn fixed_base_tables_of_every_window() {
+        let mut prng = StdRng::seed_from_u64(43);
+        let base = Point::GENERATOR * Scalar::decode_reduce(&[7u8; 48]);
+        for window in MIN_WINDOW..=MAX_WINDOW {
+            let table = FixedBaseTable::new(base, window).unwrap();
+            for _ in 0..4 {
+                let mut ebuf = [0u8; 48];
+                prng.fill_bytes(&mut ebuf);
+                let e = Scalar::decode_reduce(&ebuf);
+                assert!(table.mul(e).equals(base * e) == 0xFFFFFFFFFFFFFFFF);
+                assert!(table.mul_vartime(e).equals(base * e) == 0xFFFFFFFFFFFFFFFF);
+            }
+            let minus_one = Scalar::ZERO - Scalar::ONE;
+            assert!(table.mul(minus_one).equals(-base) == 0xFFFFFFFFFFFFFFFF);
+        }
+        assert!(FixedBaseTable::new(base, MAX_WINDOW + 1).is_err());
+    }
+
     #[test]
     fn ecgfp5_verify_muladd() {
         let mut prng = StdRng::seed_from_u64(42);
@@ -1,6 +1,8 @@
 use core::convert::TryFrom;
 use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
+use super::wide::mul_wide;
+
 // ========================================================================
 // GF(p)
 
@@ -135,7 +137,7 @@ impl GFp {
         // R^2 = 2^64 - 2^33 + 1 mod p.
         // With v < 2^64, we have R*v < 2^128 - 2^97 + 2^64, which is in
         // range of montyred().
-        GFp(GFp::montyred((v as u128) * (GFp::R2 as u128)))
+        GFp(GFp::montyred(mul_wide(v, GFp::R2)))
     }
 
     /// Get the element as an integer, normalized in the 0..p-1
@@ -191,7 +193,7 @@ impl GFp {
     pub const fn mul_small(self, rhs: u32) -> Self {
         // Since the 'rhs' value is not in Montgomery representation,
         // we need to do a manual reduction instead.
-        let x = (self.0 as u128) * (rhs as u128);
+        let x = mul_wide(self.0, rhs);
         let xl = x as u64;
         let xh = (x >> 64) as u64;
 
@@ -209,7 +211,7 @@ impl GFp {
     const fn mul(self, rhs: Self) -> Self {
         // If x < p and y < p, then x*y <= (p-1)^2, and is thus in
         // range of montyred().
-        GFp(GFp::montyred((self.0 as u128) * (rhs.0 as u128)))
+        GFp(GFp::montyred(mul_wide(self.0, rhs.0)))
     }
 
     /// Squaring in GF(p)
@@ -716,11 +718,11 @@ impl GFp5 {
 
     #[inline(always)]
     fn mul_to_k0(&self, rhs: &Self) -> GFp {
-        let pp0 = (self.0[0].0 as u128) * (rhs.0[0].0 as u128);
-        let pp1 = (self.0[1].0 as u128) * (rhs.0[4].0 as u128);
-        let pp2 = (self.0[2].0 as u128) * (rhs.0[3].0 as u128);
-        let pp3 = (self.0[3].0 as u128) * (rhs.0[2].0 as u128);
-        let pp4 = (self.0[4].0 as u128) * (rhs.0[1].0 as u128);
+        let pp0 = mul_wide(self.0[0].0, rhs.0[0].0);
+        let pp1 = mul_wide(self.0[1].0, rhs.0[4].0);
+        let pp2 = mul_wide(self.0[2].0, rhs.0[3].0);
+        let pp3 = mul_wide(self.0[3].0, rhs.0[2].0);
+        let pp4 = mul_wide(self.0[4].0, rhs.0[1].0);
         let zhi = (pp0 >> 64) + 3 * ((pp1 >> 64) + (pp2 >> 64) + (pp3 >> 64) + (pp4 >> 64));
         let zlo = ((pp0 as u64) as u128)
             + 3 * (((pp1 as u64) as u128)
@@ -732,11 +734,11 @@ impl GFp5 {
 
     #[inline(always)]
     fn mul_to_k1(&self, rhs: &Self) -> GFp {
-        let pp0 = (self.0[0].0 as u128) * (rhs.0[1].0 as u128);
-        let pp1 = (self.0[1].0 as u128) * (rhs.0[0].0 as u128);
-        let pp2 = (self.0[2].0 as u128) * (rhs.0[4].0 as u128);
-        let pp3 = (self.0[3].0 as u128) * (rhs.0[3].0 as u128);
-        let pp4 = (self.0[4].0 as u128) * (rhs.0[2].0 as u128);
+        let pp0 = mul_wide(self.0[0].0, rhs.0[1].0);
+        let pp1 = mul_wide(self.0[1].0, rhs.0[0].0);
+        let pp2 = mul_wide(self.0[2].0, rhs.0[4].0);
+        let pp3 = mul_wide(self.0[3].0, rhs.0[3].0);
+        let pp4 = mul_wide(self.0[4].0, rhs.0[2].0);
         let zhi = (pp0 >> 64) + (pp1 >> 64) + 3 * ((pp2 >> 64) + (pp3 >> 64) + (pp4 >> 64));
         let zlo = ((pp0 as u64) as u128)
             + ((pp1 as u64) as u128)
@@ -746,11 +748,11 @@ impl GFp5 {
 
     #[inline(always)]
     fn mul_to_k2(&self, rhs: &Self) -> GFp {
-        let pp0 = (self.0[0].0 as u128) * (rhs.0[2].0 as u128);
-        let pp1 = (self.0[1].0 as u128) * (rhs.0[1].0 as u128);
-        let pp2 = (self.0[2].0 as u128) * (rhs.0[0].0 as u128);
-        let pp3 = (self.0[3].0 as u128) * (rhs.0[4].0 as u128);
-        let pp4 = (self.0[4].0 as u128) * (rhs.0[3].0 as u128);
+        let pp0 = mul_wide(self.0[0].0, rhs.0[2].0);
+        let pp1 = mul_wide(self.0[1].0, rhs.0[1].0);
+        let pp2 = mul_wide(self.0[2].0, rhs.0[0].0);
+        let pp3 = mul_wide(self.0[3].0, rhs.0[4].0);
+        let pp4 = mul_wide(self.0[4].0, rhs.0[3].0);
         let zhi = (pp0 >> 64) + (pp1 >> 64) + (pp2 >> 64) + 3 * ((pp3 >> 64) + (pp4 >> 64));
         let zlo = ((pp0 as u64) as u128)
             + ((pp1 as u64) as u128)
@@ -761,11 +763,11 @@ impl GFp5 {
 
     #[inline(always)]
     fn mul_to_k3(&self, rhs: &Self) -> GFp {
-        let pp0 = (self.0[0].0 as u128) * (rhs.0[3].0 as u128);
-        let pp1 = (self.0[1].0 as u128) * (rhs.0[2].0 as u128);
-        let pp2 = (self.0[2].0 as u128) * (rhs.0[1].0 as u128);
-        let pp3 = (self.0[3].0 as u128) * (rhs.0[0].0 as u128);
-        let pp4 = (self.0[4].0 as u128) * (rhs.0[4].0 as u128);
+        let pp0 = mul_wide(self.0[0].0, rhs.0[3].0);
+        let pp1 = mul_wide(self.0[1].0, rhs.0[2].0);
+        let pp2 = mul_wide(self.0[2].0, rhs.0[1].0);
+        let pp3 = mul_wide(self.0[3].0, rhs.0[0].0);
+        let pp4 = mul_wide(self.0[4].0, rhs.0[4].0);
         let zhi = (pp0 >> 64) + (pp1 >> 64) + (pp2 >> 64) + (pp3 >> 64) + 3 * (pp4 >> 64);
         let zlo = ((pp0 as u64) as u128)
             + ((pp1 as u64) as u128)
@@ -777,11 +779,11 @@ impl GFp5 {
 
     #[inline(always)]
     fn mul_to_k4(&self, rhs: &Self) -> GFp {
-        let pp0 = (self.0[0].0 as u128) * (rhs.0[4].0 as u128);
-        let pp1 = (self.0[1].0 as u128) * (rhs.0[3].0 as u128);
-        let pp2 = (self.0[2].0 as u128) * (rhs.0[2].0 as u128);
-        let pp3 = (self.0[3].0 as u128) * (rhs.0[1].0 as u128);
-        let pp4 = (self.0[4].0 as u128) * (rhs.0[0].0 as u128);
+        let pp0 = mul_wide(self.0[0].0, rhs.0[4].0);
+        let pp1 = mul_wide(self.0[1].0, rhs.0[3].0);
+        let pp2 = mul_wide(self.0[2].0, rhs.0[2].0);
+        let pp3 = mul_wide(self.0[3].0, rhs.0[1].0);
+        let pp4 = mul_wide(self.0[4].0, rhs.0[0].0);
         let zhi = (pp0 >> 64) + (pp1 >> 64) + (pp2 >> 64) + (pp3 >> 64) + (pp4 >> 64);
         let zlo = ((pp0 as u64) as u128)
             + ((pp1 as u64) as u128)
@@ -810,9 +812,9 @@ impl GFp5 {
 
     #[inline(always)]
     fn square_to_k0(&self) -> GFp {
-        let pp0 = (self.0[0].0 as u128) * (self.0[0].0 as u128);
-        let pp1 = (self.0[1].0 as u128) * (self.0[4].0 as u128);
-        let pp2 = (self.0[2].0 as u128) * (self.0[3].0 as u128);
+        let pp0 = mul_wide(self.0[0].0, self.0[0].0);
+        let pp1 = mul_wide(self.0[1].0, self.0[4].0);
+        let pp2 = mul_wide(self.0[2].0, self.0[3].0);
         let zhi = (pp0 >> 64) + 6 * ((pp1 >> 64) + (pp2 >> 64));
         let zlo = ((pp0 as u64) as u128) + 6 * (((pp1 as u64) as u128) + ((pp2 as u64) as u128));
         GFp(GFp::montyred(zlo + (zhi << 32) - zhi))
@@ -820,9 +822,9 @@ impl GFp5 {
 
     #[inline(always)]
     fn square_to_k1(&self) -> GFp {
-        let pp0 = (self.0[0].0 as u128) * (self.0[1].0 as u128);
-        let pp2 = (self.0[2].0 as u128) * (self.0[4].0 as u128);
-        let pp3 = (self.0[3].0 as u128) * (self.0[3].0 as u128);
+        let pp0 = mul_wide(self.0[0].0, self.0[1].0);
+        let pp2 = mul_wide(self.0[2].0, self.0[4].0);
+        let pp3 = mul_wide(self.0[3].0, self.0[3].0);
         let zhi = 2 * (pp0 >> 64) + 6 * (pp2 >> 64) + 3 * (pp3 >> 64);
         let zlo =
             2 * ((pp0 as u64) as u128) + 6 * ((pp2 as u64) as u128) + 3 * ((pp3 as u64) as u128);
@@ -831,9 +833,9 @@ impl GFp5 {
 
     #[inline(always)]
     fn square_to_k2(&self) -> GFp {
-        let pp0 = (self.0[0].0 as u128) * (self.0[2].0 as u128);
-        let pp1 = (self.0[1].0 as u128) * (self.0[1].0 as u128);
-        let pp3 = (self.0[3].0 as u128) * (self.0[4].0 as u128);
+        let pp0 = mul_wide(self.0[0].0, self.0[2].0);
+        let pp1 = mul_wide(self.0[1].0, self.0[1].0);
+        let pp3 = mul_wide(self.0[3].0, self.0[4].0);
         let zhi = 2 * (pp0 >> 64) + (pp1 >> 64) + 6 * (pp3 >> 64);
         let zlo = 2 * ((pp0 as u64) as u128) + ((pp1 as u64) as u128) + 6 * ((pp3 as u64) as u128);
         GFp(GFp::montyred(zlo + (zhi << 32) - zhi))
@@ -841,9 +843,9 @@ impl GFp5 {
 
     #[inline(always)]
     fn square_to_k3(&self) -> GFp {
-        let pp0 = (self.0[0].0 as u128) * (self.0[3].0 as u128);
-        let pp1 = (self.0[1].0 as u128) * (self.0[2].0 as u128);
-        let pp4 = (self.0[4].0 as u128) * (self.0[4].0 as u128);
+        let pp0 = mul_wide(self.0[0].0, self.0[3].0);
+        let pp1 = mul_wide(self.0[1].0, self.0[2].0);
+        let pp4 = mul_wide(self.0[4].0, self.0[4].0);
         let zhi = 2 * ((pp0 >> 64) + (pp1 >> 64)) + 3 * (pp4 >> 64);
         let zlo =
             2 * (((pp0 as u64) as u128) + ((pp1 as u64) as u128)) + 3 * ((pp4 as u64) as u128);
@@ -852,9 +854,9 @@ impl GFp5 {
 
     #[inline(always)]
     fn square_to_k4(&self) -> GFp {
-        let pp0 = (self.0[0].0 as u128) * (self.0[4].0 as u128);
-        let pp1 = (self.0[1].0 as u128) * (self.0[3].0 as u128);
-        let pp2 = (self.0[2].0 as u128) * (self.0[2].0 as u128);
+        let pp0 = mul_wide(self.0[0].0, self.0[4].0);
+        let pp1 = mul_wide(self.0[1].0, self.0[3].0);
+        let pp2 = mul_wide(self.0[2].0, self.0[2].0);
         let zhi = 2 * ((pp0 >> 64) + (pp1 >> 64)) + (pp2 >> 64);
         let zlo = 2 * (((pp0 as u64) as u128) + ((pp1 as u64) as u128)) + ((pp2 as u64) as u128);
         GFp(GFp::montyred(zlo + (zhi << 32) - zhi))
@@ -1028,7 +1030,9 @@ impl GFp5 {
     /// Square root in GF(p^5); returns (s, cc):
     ///  - If the input is a square, s = sqrt(self) and cc = 0xFFFFFFFFFFFFFFFF
     ///  - If the input is not a square, s = zero and cc = 0
-    /// Which of the two square roots is obtained is unspecified.
+    /// Which of the two square roots is obtained is unspecified, so encodings
+    /// must not depend on it: Point::decode selects the x coordinate by its
+    /// Legendre symbol, not by the root.
     #[inline(always)]
     pub fn sqrt(self) -> (GFp5, u64) {
         let mut r = self;
@@ -2,6 +2,7 @@ pub mod curve;
 pub mod field;
 pub(crate) mod multab;
 pub mod scalar;
+mod wide;
 
-pub use curve::Point;
+pub use curve::{set_generator_window, FixedBaseTable, Point};
 pub use scalar::Scalar;
@@ -1,8 +1,8 @@
-use core::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use core::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
 
 use rand::{rand_core, rngs::OsRng, Rng, TryRngCore};
 
-use crate::arith::field::GFp5;
+use crate::arith::{field::GFp5, wide::mul_wide};
 
 /// A scalar (integer modulo the prime group order n).
 #[derive(Clone, Copy, Debug)]
@@ -196,13 +196,11 @@ impl Scalar {
             let mut cc1: u64 = 0;
             let mut cc2: u64 = 0;
             for j in 0..5 {
-                let mut z = (self.0[j] as u128)
-                    .wrapping_mul(m as u128)
+                let mut z = mul_wide(self.0[j], m)
                     .wrapping_add(r.0[j] as u128)
                     .wrapping_add(cc1 as u128);
                 cc1 = (z >> 64) as u64;
-                z = (f as u128)
-                    .wrapping_mul(Self::N.0[j] as u128)
+                z = mul_wide(f, Self::N.0[j])
                     .wrapping_add((z as u64) as u128)
                     .wrapping_add(cc2 as u128);
                 cc2 = (z >> 64) as u64;
@@ -228,6 +226,32 @@ impl Scalar {
         self.montymul(Self::R2).montymul(rhs)
     }
 
+    // n - 2, the exponent of inversions (Fermat's little theorem)
+    const N_MINUS_2: [u64; 5] = [
+        0xE80FD996948BFFDF,
+        0xE8885C39D724A09C,
+        0x7FFFFFE6CFB80639,
+        0x7FFFFFF100000016,
+        0x7FFFFFFD80000007,
+    ];
+
+    /// Inverse modulo n, None for zero. Constant-time: the exponentiation
+    /// only depends on the public exponent n - 2.
+    pub fn invert(self) -> Option<Self> {
+        // In Montgomery representation, x*2^320 mod n, where montymul
+        // multiplies: 2^320 mod n is the representation of one
+        let xm = self.montymul(Self::R2);
+        let mut r = Self::ONE.montymul(Self::R2);
+        for i in (0..Self::NB_BITS).rev() {
+            r = r.montymul(r);
+            if (Self::N_MINUS_2[i >> 6] >> (i & 63)) & 1 == 1 {
+                r = r.montymul(xm);
+            }
+        }
+        let r = r.montymul(Self::ONE);
+        (self.iszero() == 0).then_some(r)
+    }
+
     /// Decode the provided byte slice into a scalar. The bytes are
     /// interpreted into an integer in little-endian unsigned convention.
     /// All slice bytes are read. Return value is (s, c):
@@ -295,6 +319,13 @@ impl Scalar {
         acc
     }
 
+    /// Reduces 64 uniformly random bytes modulo n, e.g. the output of an
+    /// HSM or of a hash in another language, with a negligible bias. The
+    /// bytes are read in little-endian unsigned convention.
+    pub fn from_bytes_wide(bytes: &[u8; 64]) -> Self {
+        Self::decode_reduce(bytes)
+    }
+
     /// Encode this scalar over exactly 40 bytes.
     pub fn encode(self) -> [u8; 40] {
         let mut r = [0u8; 40];
@@ -447,7 +478,8 @@ impl Scalar {
         Self::N.bit_le(i)
     }
     /// Convert Scalar limbs -> little-endian bits.
-    /// bits[0] is the least-significant bit of limbs[0].
+    /// bits[0] is the least-significant bit of limbs[0]. n < 2^NB_BITS, so
+    /// from_bits_le(to_bits_le(s)) == s for every scalar s.
     pub fn to_bits_le(&self) -> [bool; Self::NB_BITS] {
         let mut bits = [false; Self::NB_BITS];
         for i in 0..Self::NB_BITS {
@@ -455,8 +487,8 @@ impl Scalar {
         }
         bits
     }
-    /// Convert little-endian bits -> Scalar limbs (little-endian).
-    /// Extra bits beyond 5*64 are ignored (or you can assert they are 0).
+    /// Convert little-endian bits -> Scalar limbs (little-endian), reduced
+    /// modulo n: 2^NB_BITS < 2n, so values of n or more lose n once.
     pub fn from_bits_le(bits: &[bool; Self::NB_BITS]) -> Self {
         let mut limbs = [0u64; 5];
         for i in 0..Self::NB_BITS {
@@ -466,7 +498,10 @@ impl Scalar {
                 limbs[limb] |= 1u64 << off;
             }
         }
-        Self(limbs)
+        let r = Self(limbs);
+        let (d, c) = r.sub_inner(Self::N);
+        // borrow when r < n
+        Self::select(c, d, r)
     }
 }
 
@@ -520,6 +555,15 @@ impl MulAssign<Scalar> for Scalar {
     }
 }
 
+/// None when dividing by zero
+impl Div<Scalar> for Scalar {
+    type Output = Option<Scalar>;
+
+    fn div(self, other: Scalar) -> Option<Scalar> {
+        Some(Scalar::mul(self, other.invert()?))
+    }
+}
+
 /// A custom 161-bit integer type; used for splitting a scalar into a
 /// fraction. Negative values use two's complement notation; the value
 /// is truncated to 161 bits (upper bits in the top limb are ignored).
@@ -685,7 +729,7 @@ impl Signed640 {
             let mut cc = 0u64;
             for j in 0..5 {
                 let bw = b.0[j];
-                let z = ((aw as u128) * (bw as u128))
+                let z = mul_wide(aw, bw)
                     .wrapping_add(r.0[i + j] as u128)
                     .wrapping_add(cc as u128);
                 r.0[i + j] = z as u64;
@@ -853,6 +897,28 @@ mod tests {
     use super::Scalar;
     // use super::super::PRNG;
 
+    #[test]
+    fn wide_bytes_and_bits_round_trip() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for _ in 0..20 {
+            let mut wide = [0u8; 64];
+            rng.fill_bytes(&mut wide);
+            let s = Scalar::from_bytes_wide(&wide);
+            assert_eq!(s.equals(Scalar::decode_reduce(&wide)), u64::MAX);
+            let (decoded, c) = Scalar::decode(&s.encode());
+            assert_eq!(c, u64::MAX);
+            assert_eq!(decoded.equals(s), u64::MAX);
+            assert_eq!(Scalar::from_bits_le(&s.to_bits_le()).equals(s), u64::MAX);
+        }
+
+        // 2^319 - 1 is above n, and reduced once
+        let ones = Scalar::from_bits_le(&[true; Scalar::NB_BITS]);
+        let expected = Scalar::decode_reduce(&[[0xFF; 39].as_slice(), &[0x7F]].concat());
+        assert_eq!(ones.equals(expected), u64::MAX);
+        let (_, c) = Scalar::decode(&ones.encode());
+        assert_eq!(c, u64::MAX);
+    }
+
     #[test]
     fn scalar_ops() {
         let buf1: [u8; 50] = [
@@ -955,6 +1021,26 @@ mod tests {
         assert!(ss5 == ref5);
     }
 
+    #[test]
+    fn invert_and_div() {
+        let mut prng = StdRng::seed_from_u64(42);
+        assert!(Scalar::ZERO.invert().is_none());
+        assert!(Scalar::ONE.invert().unwrap().equals(Scalar::ONE) == u64::MAX);
+        let minus_one = Scalar::ZERO - Scalar::ONE;
+        assert!(minus_one.invert().unwrap().equals(minus_one) == u64::MAX);
+        for _ in 0..20 {
+            let mut abuf = [0u8; 48];
+            let mut bbuf = [0u8; 48];
+            prng.fill_bytes(&mut abuf);
+            prng.fill_bytes(&mut bbuf);
+            let a = Scalar::decode_reduce(&abuf);
+            let b = Scalar::decode_reduce(&bbuf);
+            assert!((b * b.invert().unwrap()).equals(Scalar::ONE) == u64::MAX);
+            assert!(((a / b).unwrap() * b).equals(a) == u64::MAX);
+            assert!((a / Scalar::ZERO).is_none());
+        }
+    }
+
     #[test]
     fn lagrange() {
         let mut prng = StdRng::seed_from_u64(42);
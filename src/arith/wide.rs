@@ -0,0 +1,55 @@
+// 64x64->128 products. On 64-bit targets, a product of u128 operands which
+// fit on 64 bits is a single instruction. Elsewhere (32-bit targets, wasm32)
+// it is a call to a generic 128x128 multiplication, so the product is built
+// from four 32x32->64 ones instead.
+
+/// a*b, without overflow
+#[cfg(target_pointer_width = "64")]
+#[inline(always)]
+pub(crate) const fn mul_wide(a: u64, b: u64) -> u128 {
+    (a as u128) * (b as u128)
+}
+
+/// a*b, without overflow
+#[cfg(not(target_pointer_width = "64"))]
+#[inline(always)]
+pub(crate) const fn mul_wide(a: u64, b: u64) -> u128 {
+    mul_wide_portable(a, b)
+}
+
+/// Portable implementation of mul_wide, built on 32-bit limbs
+#[inline(always)]
+#[allow(dead_code)]
+pub(crate) const fn mul_wide_portable(a: u64, b: u64) -> u128 {
+    let (a0, a1) = (a & 0xFFFFFFFF, a >> 32);
+    let (b0, b1) = (b & 0xFFFFFFFF, b >> 32);
+    let lo = a0 * b0;
+    let m0 = a0 * b1;
+    let m1 = a1 * b0;
+    let hi = a1 * b1;
+    // at most 3*(2^32 - 1), no overflow
+    let mid = (lo >> 32) + (m0 & 0xFFFFFFFF) + (m1 & 0xFFFFFFFF);
+    let low = (lo & 0xFFFFFFFF) | (mid << 32);
+    let high = hi + (m0 >> 32) + (m1 >> 32) + (mid >> 32);
+    ((high as u128) << 64) | (low as u128)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn portable_product_matches_u128() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let edges = [0, 1, 0xFFFFFFFF, 1 << 32, u64::MAX - 1, u64::MAX];
+        let random = (0..1000).map(|_| rng.random::<u64>()).collect::<Vec<_>>();
+        for &a in edges.iter().chain(&random) {
+            for &b in edges.iter().chain(&random[..10]) {
+                assert_eq!(mul_wide_portable(a, b), (a as u128) * (b as u128));
+                assert_eq!(mul_wide(a, b), (a as u128) * (b as u128));
+            }
+        }
+    }
+}
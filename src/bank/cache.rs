@@ -0,0 +1,104 @@
+// Verdicts of recent verifications, so that a retried request or a replay by a
+// load balancer is answered without verifying the proof again.
+//
+// Entries are keyed by the hash of the proof, public inputs included: only the
+// very proof which was verified is answered from the cache, and any other one
+// is verified, and its nullifier checked against the store.
+//
+// Only acceptances are cached: nullifiers are public, so anyone could send an
+// invalid proof under the nullifier of an honest holder, and caching that
+// rejection would lock the holder out until it expires.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use plonky2::{field::types::Field, hash::poseidon::PoseidonHash, plonk::config::Hasher};
+
+use crate::{circuit, encoding, schnorr::transcript::message_to_goldilocks};
+
+/// Hash of the proof, see key, and digest of the circuit it was proved with
+pub type Key = (encoding::Hash<circuit::F>, encoding::Hash<circuit::F>);
+
+/// Key of the proof bytes, proved with the circuit of digest
+pub fn key(proof: &[u8], digest: encoding::Hash<circuit::F>) -> Key {
+    // the length tells trailing zeros apart from the padding
+    let mut elements = vec![circuit::F::from_canonical_usize(proof.len())];
+    elements.extend(message_to_goldilocks(proof));
+    let hash = PoseidonHash::hash_no_pad(&elements);
+    (encoding::Hash(hash.elements), digest)
+}
+
+pub struct Cache {
+    ttl: Duration,
+    accepted: HashMap<Key, Instant>,
+}
+
+impl Cache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            accepted: HashMap::new(),
+        }
+    }
+
+    /// Whether the proof of this key was accepted less than ttl ago
+    pub fn is_accepted(&self, key: &Key, now: Instant) -> bool {
+        self.accepted
+            .get(key)
+            .is_some_and(|at| now.saturating_duration_since(*at) < self.ttl)
+    }
+
+    /// Records an acceptance, and forgets the expired ones
+    pub fn accept(&mut self, key: Key, now: Instant) {
+        let ttl = self.ttl;
+        self.accepted
+            .retain(|_, at| now.saturating_duration_since(*at) < ttl);
+        self.accepted.insert(key, now);
+    }
+
+    pub fn len(&self) -> usize {
+        self.accepted.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accepted.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::types::Field;
+
+    use super::*;
+
+    fn key(x: u8) -> Key {
+        super::key(&[x], encoding::Hash([circuit::F::ONE; encoding::LEN_HASH]))
+    }
+
+    #[test]
+    fn acceptances_expire_after_ttl() {
+        let start = Instant::now();
+        let mut cache = Cache::new(Duration::from_secs(60));
+        assert!(!cache.is_accepted(&key(1), start));
+
+        cache.accept(key(1), start);
+        assert!(cache.is_accepted(&key(1), start + Duration::from_secs(59)));
+        assert!(!cache.is_accepted(&key(2), start + Duration::from_secs(59)));
+        assert!(!cache.is_accepted(&key(1), start + Duration::from_secs(60)));
+
+        cache.accept(key(2), start + Duration::from_secs(61));
+        assert_eq!(cache.len(), 1, "expired entries should be pruned");
+    }
+
+    #[test]
+    fn keys_tell_proofs_apart() {
+        let digest = encoding::Hash([circuit::F::ONE; encoding::LEN_HASH]);
+        let other_digest = encoding::Hash([circuit::F::TWO; encoding::LEN_HASH]);
+        assert_eq!(super::key(&[1, 2], digest), super::key(&[1, 2], digest));
+        assert_ne!(super::key(&[1, 2], digest), super::key(&[1, 3], digest));
+        assert_ne!(super::key(&[1, 2], digest), super::key(&[1, 2, 0], digest));
+        assert_ne!(super::key(&[1, 2], digest), super::key(&[1, 2], other_digest));
+    }
+}
@@ -0,0 +1,237 @@
+// Consortium of banks enforcing "one KYC credential per person" within a
+// regulatory scope. Presentations expose nullifier = Hash(holder secret, scope,
+// names, birth date, nationality), which stays the same across the credentials
+// and passport renewals of a person, whose holder key the issuer keeps when it
+// issues again (see issuer::recovery), but differs between scopes. Without the
+// holder secret, anyone knowing the identity of a customer could recompute it
+// and test the public inputs against it.
+//
+// Members share a consortium key and only send the registry nullifiers blinded
+// by it: the registry, run by a third party, can't test a known identity
+// against its entries, nor link them to the presentations seen by the banks.
+
+use std::collections::{hash_map::Entry, HashMap};
+
+use plonky2::field::{goldilocks_field::GoldilocksField, types::Field};
+use rand::Rng;
+use thiserror::Error;
+
+use crate::{
+    circuit::{self, Circuit, ZkProof},
+    core::credential::Credential,
+    encoding::{
+        self,
+        conversion::{ToScalarField, ToStringField},
+        LEN_GIVEN_NAMES, LEN_HASH, LEN_SCALAR_LIMBS, LEN_STRING, LEN_VAR_STRING,
+    },
+    merkle::hash,
+    schnorr::keys::SecretKey,
+};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Registry unavailable: {0}")]
+    Unavailable(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+pub type Nullifier = encoding::Hash<GoldilocksField>;
+/// Nullifier as seen by the registry, see ConsortiumKey::blind
+pub type BlindedNullifier = encoding::Hash<GoldilocksField>;
+
+/// Mirrors circuit::nym::CircuitBuilderNym::person_nullifier, sk being the
+/// holder secret key of the credential
+pub fn nullifier(scope: &str, credential: &Credential, sk: &SecretKey) -> Nullifier {
+    let encoded = credential.to_field::<GoldilocksField>();
+    let secret: [GoldilocksField; LEN_SCALAR_LIMBS] = sk.0.to_field().to_field();
+    let scope = scope.to_string().to_field();
+    let mut message = Vec::with_capacity(
+        LEN_SCALAR_LIMBS + LEN_STRING + LEN_VAR_STRING + LEN_GIVEN_NAMES + 2,
+    );
+    message.extend_from_slice(&secret);
+    message.extend_from_slice(&scope.0);
+    let family_name: [GoldilocksField; LEN_VAR_STRING] = encoded.family_name.into();
+    message.extend_from_slice(&family_name);
+    let given_names: [GoldilocksField; LEN_GIVEN_NAMES] = encoded.given_names.into();
+    message.extend_from_slice(&given_names);
+    message.push(encoded.birth_date);
+    message.push(encoded.nationality);
+    hash::poseidon(&message)
+}
+
+/// Secret shared by the members of the consortium, never by the registry
+#[derive(Clone)]
+pub struct ConsortiumKey([GoldilocksField; LEN_HASH]);
+
+impl ConsortiumKey {
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Self(std::array::from_fn(|_| {
+            GoldilocksField::from_noncanonical_u64(rng.random())
+        }))
+    }
+
+    pub fn blind(&self, scope: &str, nullifier: &Nullifier) -> BlindedNullifier {
+        let scope = scope.to_string().to_field();
+        let mut message = Vec::with_capacity(2 * LEN_HASH + LEN_STRING);
+        message.extend_from_slice(&self.0);
+        message.extend_from_slice(&scope.0);
+        message.extend_from_slice(&nullifier.0);
+        hash::poseidon(&message)
+    }
+}
+
+/// What a member sends the registry once it accepted a presentation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Claim {
+    pub scope: String,
+    pub blinded: BlindedNullifier,
+    pub member: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Registration {
+    /// First claim of the person in the scope, or a new claim by the same member
+    Registered,
+    /// The person already has a KYC credential at another member
+    AlreadyClaimed { member: String },
+}
+
+pub trait Registry {
+    /// Records the claim, unless another member already claimed the blinded
+    /// nullifier in the scope
+    fn register(&mut self, claim: Claim) -> Result<Registration>;
+}
+
+/// Registry of a single process, claims being lost when it stops; the shared
+/// registry of a consortium is run by a third party behind its own API
+#[derive(Default)]
+pub struct MemoryRegistry(HashMap<(String, BlindedNullifier), String>);
+
+impl Registry for MemoryRegistry {
+    fn register(&mut self, claim: Claim) -> Result<Registration> {
+        match self.0.entry((claim.scope, claim.blinded)) {
+            Entry::Occupied(entry) if *entry.get() != claim.member => {
+                Ok(Registration::AlreadyClaimed {
+                    member: entry.get().clone(),
+                })
+            }
+            Entry::Occupied(_) => Ok(Registration::Registered),
+            Entry::Vacant(entry) => {
+                entry.insert(claim.member);
+                Ok(Registration::Registered)
+            }
+        }
+    }
+}
+
+/// Bank side of the consortium
+pub struct Member<R: Registry> {
+    name: String,
+    key: ConsortiumKey,
+    registry: R,
+}
+
+impl<R: Registry> Member<R> {
+    pub fn new(name: &str, key: ConsortiumKey, registry: R) -> Self {
+        Self {
+            name: name.to_string(),
+            key,
+            registry,
+        }
+    }
+
+    pub fn registry(&self) -> &R {
+        &self.registry
+    }
+
+    /// Blinds the nullifier of an accepted presentation, and claims it
+    pub fn claim(&mut self, scope: &str, nullifier: &Nullifier) -> Result<Registration> {
+        self.registry.register(Claim {
+            scope: scope.to_string(),
+            blinded: self.key.blind(scope, nullifier),
+            member: self.name.clone(),
+        })
+    }
+
+    /// Verifies a proof of a circuit with the consortium feature, where
+    /// public_inputs.consortium holds the scope & nullifier, and claims the
+    /// nullifier
+    pub fn verify_and_claim(
+        &mut self,
+        circuit: &Circuit,
+        proof: ZkProof,
+        scope: &str,
+        public_inputs: circuit::inputs::Public<circuit::F>,
+    ) -> anyhow::Result<Registration> {
+        let consortium = public_inputs
+            .consortium
+            .ok_or_else(|| anyhow::anyhow!("the public inputs have no consortium nullifier"))?;
+        anyhow::ensure!(
+            consortium.scope == scope.to_string().to_field(),
+            "the proof is for another scope"
+        );
+        circuit::verify(&circuit.circuit, proof, public_inputs)?;
+        Ok(self.claim(scope, &consortium.nullifier)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn nullifier_is_per_person_and_scope() {
+        let (sk, _, credential) = Credential::from_seed(1);
+        let (other_sk, _, other) = Credential::from_seed(2);
+        let n = nullifier("EU-AML", &credential, &sk);
+        assert_eq!(n, nullifier("EU-AML", &credential, &sk));
+        assert_ne!(n, nullifier("EU-AML", &other, &other_sk));
+        assert_ne!(n, nullifier("US-BSA", &credential, &sk));
+    }
+
+    #[test]
+    fn nullifier_depends_on_the_holder_secret() {
+        let (sk, _, credential) = Credential::from_seed(1);
+        let (other_sk, _, _) = Credential::from_seed(2);
+        assert_ne!(
+            nullifier("EU-AML", &credential, &sk),
+            nullifier("EU-AML", &credential, &other_sk),
+            "the identity alone must not give the nullifier"
+        );
+    }
+
+    #[test]
+    fn registry_rejects_a_second_member() {
+        let key = ConsortiumKey::random(&mut StdRng::seed_from_u64(1));
+        let (sk, _, credential) = Credential::from_seed(3);
+        let n = nullifier("EU-AML", &credential, &sk);
+
+        let mut bank_a = Member::new("bank-a", key.clone(), MemoryRegistry::default());
+        assert_eq!(bank_a.claim("EU-AML", &n), Ok(Registration::Registered));
+        assert_eq!(bank_a.claim("EU-AML", &n), Ok(Registration::Registered));
+
+        // same registry, seen by another member
+        let mut bank_b = Member::new("bank-b", key, bank_a.registry);
+        assert_eq!(
+            bank_b.claim("EU-AML", &n),
+            Ok(Registration::AlreadyClaimed {
+                member: "bank-a".to_string()
+            })
+        );
+        assert_eq!(bank_b.claim("US-BSA", &n), Ok(Registration::Registered));
+    }
+
+    #[test]
+    fn blinding_depends_on_the_consortium_key() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let (sk, _, credential) = Credential::from_seed(4);
+        let n = nullifier("EU-AML", &credential, &sk);
+        let key = ConsortiumKey::random(&mut rng);
+        let other = ConsortiumKey::random(&mut rng);
+        assert_ne!(key.blind("EU-AML", &n), other.blind("EU-AML", &n));
+        assert_ne!(key.blind("EU-AML", &n), n);
+    }
+}
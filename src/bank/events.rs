@@ -0,0 +1,83 @@
+// Structured events of the bank verifier, for fraud monitoring: sinks are
+// registered once on the Verifier instead of wrapping every call site.
+
+use crate::{circuit, encoding, issuer::rate_limit::Nullifier};
+
+/// Digest of the circuit a presentation was proved with
+pub type CircuitDigest = encoding::Hash<circuit::F>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// cached when answered from the cache, without verifying the proof again
+    VerificationSucceeded {
+        circuit: CircuitDigest,
+        cached: bool,
+    },
+    VerificationFailed {
+        circuit: CircuitDigest,
+        reason: String,
+    },
+    /// A k-show credential shown more than k times in the epoch, or a replay
+    NullifierReused {
+        circuit: CircuitDigest,
+        nullifier: Nullifier,
+    },
+    /// A circuit which does not accept the current credential layout, see
+    /// circuit::schema::Compatibility
+    StaleCircuit { circuit: CircuitDigest },
+}
+
+pub trait EventSink {
+    fn emit(&mut self, event: &Event);
+}
+
+impl<S: FnMut(&Event)> EventSink for S {
+    fn emit(&mut self, event: &Event) {
+        self(event)
+    }
+}
+
+/// Sinks receive every event, in the order they were registered
+#[derive(Default)]
+pub struct Events(Vec<Box<dyn EventSink>>);
+
+impl Events {
+    pub fn register(&mut self, sink: impl EventSink + 'static) {
+        self.0.push(Box::new(sink));
+    }
+
+    pub fn emit(&mut self, event: Event) {
+        for sink in &mut self.0 {
+            sink.emit(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use plonky2::field::types::Field;
+
+    use super::*;
+
+    #[test]
+    fn every_sink_receives_every_event() {
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let mut events = Events::default();
+        for i in 0..2 {
+            let received = received.clone();
+            events.register(move |event: &Event| received.borrow_mut().push((i, event.clone())));
+        }
+
+        let circuit = encoding::Hash([circuit::F::ONE; encoding::LEN_HASH]);
+        events.emit(Event::StaleCircuit { circuit });
+        assert_eq!(
+            *received.borrow(),
+            [
+                (0, Event::StaleCircuit { circuit }),
+                (1, Event::StaleCircuit { circuit })
+            ]
+        );
+    }
+}
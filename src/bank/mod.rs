@@ -1,5 +1,43 @@
+pub mod cache;
+pub mod consortium;
+pub mod events;
+pub mod presentation;
+pub mod risk;
+pub mod store;
+
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use chrono::{NaiveDate, Utc};
+use plonky2::field::types::{Field, PrimeField64};
 use rand::{rngs::StdRng, Rng, SeedableRng};
 
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::{
+    bank::{
+        cache::{self, Cache},
+        events::{CircuitDigest, Event, EventSink, Events},
+        presentation::{Presentation, PresentationBundle},
+        risk::{Decision, RiskHook, RiskHooks},
+        store::NullifierStore,
+    },
+    circuit::{self, schema::Compatibility, Circuit, ZkProof},
+    core::{codec::Codec, credential::Nationality, date, policy::Policy},
+    encoding::{
+        self,
+        conversion::{ToPointField, ToSingleField, ToStringField},
+    },
+    issuer::{
+        self,
+        rate_limit::{Nullifier, RateLimit},
+    },
+    schnorr::keys::PublicKey,
+};
+
 // FIXME: generate nonce correctly, this is totally insecure
 pub fn nonce() -> String {
     // TODO: unify this with string generation for credential tests
@@ -16,21 +54,850 @@ pub fn service() -> String {
     String::from("ZBanK")
 }
 
+/// Challenge the bank sent the holder, which the presentation answers
+pub struct Session {
+    pub service: String,
+    pub nonce: String,
+}
+
+/// What verify_presentation accepted
+#[derive(Debug, PartialEq, Eq)]
+pub struct VerificationReport {
+    pub circuit: CircuitDigest,
+    pub pseudonym: encoding::Pseudonym<circuit::F>,
+    /// Recorded in the store, for k-show credentials and circuits exposing a
+    /// credential nullifier
+    pub nullifier: Option<Nullifier>,
+    /// Answered from the cache, without verifying the proof again
+    pub cached: bool,
+    pub attributes: Attributes,
+    /// Of the risk hooks, Allow or StepUp: denied presentations fail
+    pub decision: Decision,
+}
+
+/// Typed values of the public inputs a presentation was verified against,
+/// for downstream compliance systems
+#[derive(Debug, PartialEq, Eq)]
+pub struct Attributes {
+    pub nationality: Nationality,
+    /// Day the holder proved their age on, see date::reference_day
+    pub verified_on: NaiveDate,
+    /// The holder was born on or before this day
+    pub born_on_or_before: NaiveDate,
+    pub issuer: PublicKey,
+}
+
+impl Attributes {
+    /// From the public inputs of a circuit disclosing the nationality, and
+    /// the issuer whose key they hold
+    fn extract(
+        public_inputs: &circuit::inputs::Public<circuit::F>,
+        issuer: &PublicKey,
+    ) -> anyhow::Result<Self> {
+        let nationality = public_inputs
+            .nationality
+            .and_then(|code| u16::try_from(code.to_canonical_u64()).ok())
+            .and_then(Nationality::from_code)
+            .ok_or_else(|| anyhow::anyhow!("unknown nationality"))?;
+        let today = public_inputs.today_days.to_canonical_u64();
+        let cutoff = today
+            .checked_sub(public_inputs.min_age_days.to_canonical_u64())
+            .ok_or_else(|| anyhow::anyhow!("the minimal age exceeds the reference day"))?;
+        Ok(Self {
+            nationality,
+            verified_on: date::date_from_field(public_inputs.today_days)?,
+            born_on_or_before: date::date_from_field(circuit::F::from_canonical_u64(cutoff))?,
+            issuer: issuer.clone(),
+        })
+    }
+}
+
 pub fn verify_client_proof(
     circuit: &Circuit,
     proof: ZkProof,
     // claimed pseudonym for the client
     pseudonym: encoding::Pseudonym<circuit::F>,
+    issuer: &PublicKey,
 ) -> anyhow::Result<()> {
     let issuer_root = issuer::database::for_tests::DATABASE.root();
-    let public_inputs = circuit::inputs::Public {
-        cutoff18_days: date::cutoff18_from_today().to_field(),
-        nationality: Nationality::FR.to_field(),
-        issuer_pk: issuer::keys::public().0.to_field(),
-        nonce: nonce().to_field(),
-        service: service().to_field(),
+    let public_inputs = circuit::inputs::Public::register(
+        date::today().to_field(),
+        date::ADULT_AGE_DAYS.to_field(),
+        Some(Nationality::FR.to_field()),
+        issuer.0.to_field(),
+        nonce().to_field(),
+        service().to_field(),
         pseudonym,
-        merkle_root: issuer_root,
-    };
+        issuer_root,
+    );
     circuit::verify(&circuit.circuit, proof, public_inputs)
-}
\ No newline at end of file
+}
+
+/// Bank side of presentations: checks them against the issuer parameters,
+/// and records the nullifiers of k-show credentials in the store
+pub struct Verifier<S: NullifierStore> {
+    store: S,
+    rate_limit: RateLimit,
+    /// Of the tree of valid credentials, see with_root
+    root: Option<issuer::database::Root>,
+    cache: Option<Cache>,
+    compatibility: Option<Compatibility>,
+    /// Accepted when the policy names no issuer
+    issuers: Vec<PublicKey>,
+    events: Events,
+    risk_hooks: RiskHooks,
+    circuits: HashMap<CircuitDigest, Rc<Circuit>>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<Metrics>,
+}
+
+impl<S: NullifierStore> Verifier<S> {
+    pub fn new(store: S, rate_limit: RateLimit) -> Self {
+        Self {
+            store,
+            rate_limit,
+            root: None,
+            cache: None,
+            compatibility: None,
+            issuers: vec![],
+            events: Events::default(),
+            risk_hooks: RiskHooks::default(),
+            circuits: HashMap::new(),
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    /// Root of the tree of valid credentials presentations are proved
+    /// against, as published by the issuer, see issuer::revocation
+    pub fn with_root(mut self, root: issuer::database::Root) -> Self {
+        self.root = Some(root);
+        self
+    }
+
+    /// Accepts presentations proved with circuit, see verify_presentation
+    pub fn with_circuit(mut self, circuit: Circuit) -> Self {
+        self.circuits.insert(circuit.digest(), Rc::new(circuit));
+        self
+    }
+
+    /// Rejects proofs of circuits which don't accept the current credential
+    /// layout
+    pub fn with_compatibility(mut self, compatibility: Compatibility) -> Self {
+        self.compatibility = Some(compatibility);
+        self
+    }
+
+    /// Accepts presentations of credentials of any of issuers, when the
+    /// policy names none. The report tells which one issued the credential
+    pub fn with_issuers(mut self, issuers: Vec<PublicKey>) -> Self {
+        self.issuers = issuers;
+        self
+    }
+
+    /// Answers replays of the very proof of an accepted presentation for ttl
+    /// without verifying it again, see cache
+    pub fn with_cache(mut self, ttl: Duration) -> Self {
+        self.cache = Some(Cache::new(ttl));
+        self
+    }
+
+    /// Sends the events of verifications to sink, see events::Event
+    pub fn with_sink(mut self, sink: impl EventSink + 'static) -> Self {
+        self.events.register(sink);
+        self
+    }
+
+    /// Assesses every verified presentation with hook, see risk::RiskHooks
+    pub fn with_risk_hook(mut self, hook: impl RiskHook + 'static) -> Self {
+        self.risk_hooks.register(hook);
+        self
+    }
+
+    /// Records the outcomes of verifications in metrics, with the latency &
+    /// size of the proofs verified
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.events.register(metrics.clone());
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Verifies a proof from circuit::circuit_k_show for today's epoch,
+    /// against the policy and the session, and rejects nullifiers already in
+    /// the store, which are the ones of credentials shown more than k times
+    /// in the epoch. Records the nullifier on success.
+    #[allow(clippy::too_many_arguments)]
+    pub fn verify_k_show(
+        &mut self,
+        circuit: &Circuit,
+        proof: ZkProof,
+        policy: &Policy,
+        session: &Session,
+        // claimed pseudonym for the client
+        pseudonym: encoding::Pseudonym<circuit::F>,
+        nullifier: Nullifier,
+    ) -> anyhow::Result<()> {
+        let issuers = self.issuers(policy)?;
+        let epoch = self.rate_limit.epoch(Utc::now().date_naive());
+        let mut public_inputs = circuit::inputs::Public {
+            rate_limit: Some(circuit::inputs::RateLimit {
+                epoch: circuit::F::from_canonical_u32(epoch),
+                nullifier,
+            }),
+            ..circuit::inputs::Public::register(
+                policy.today_days.to_field(),
+                policy.min_age_days.to_field(),
+                Some(policy.nationality.to_field()),
+                // replaced by the key of the issuer of the proof
+                issuers[0].0.to_field(),
+                session.nonce.to_field(),
+                session.service.to_field(),
+                pseudonym,
+                self.root()?,
+            )
+        };
+        let verified = self.verify_proof(
+            circuit,
+            proof,
+            &mut public_inputs,
+            &issuers,
+            Some(nullifier),
+        )?;
+        self.record(Some(nullifier), &verified)
+    }
+
+    /// Parses a presentation envelope of one of the circuits given with
+    /// with_circuit, and verifies it against the policy and the session.
+    /// Nullifiers of k-show credentials are checked & recorded as in
+    /// verify_k_show, and so are credential nullifiers, so that a credential
+    /// is presented at most once to the service. Proofs of the universal
+    /// circuit must set the flags of policy_flags.
+    pub fn verify_presentation(
+        &mut self,
+        bytes: &[u8],
+        policy: &Policy,
+        session: &Session,
+    ) -> anyhow::Result<VerificationReport> {
+        let presentation = Presentation::from_bytes(bytes)?;
+        let (report, verified) = self.check_presentation(presentation, policy, session)?;
+        self.record(report.nullifier, &verified)?;
+        self.assess(report)
+    }
+
+    /// Verifies every presentation of a bundle as verify_presentation, each
+    /// with the circuit it names, against the same policy & session. The
+    /// bundle is accepted as a whole: nullifiers are only recorded once every
+    /// proof is verified.
+    pub fn verify_bundle(
+        &mut self,
+        bytes: &[u8],
+        policy: &Policy,
+        session: &Session,
+    ) -> anyhow::Result<Vec<VerificationReport>> {
+        let bundle = PresentationBundle::from_bytes(bytes)?;
+        let nullifiers: Vec<_> = bundle
+            .presentations()
+            .iter()
+            .filter_map(|presentation| presentation.nullifier)
+            .collect();
+        anyhow::ensure!(
+            nullifiers.iter().collect::<HashSet<_>>().len() == nullifiers.len(),
+            "the bundle shows a nullifier twice"
+        );
+        let reports = bundle
+            .into_presentations()
+            .into_iter()
+            .map(|presentation| self.check_presentation(presentation, policy, session))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        for (report, verified) in &reports {
+            self.record(report.nullifier, verified)?;
+        }
+        reports
+            .into_iter()
+            .map(|(report, _)| self.assess(report))
+            .collect()
+    }
+
+    /// Verifies a presentation without recording its nullifier, nor
+    /// assessing it
+    fn check_presentation(
+        &mut self,
+        presentation: Presentation,
+        policy: &Policy,
+        session: &Session,
+    ) -> anyhow::Result<(VerificationReport, Verified)> {
+        let circuit = self
+            .circuits
+            .get(&presentation.circuit)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("the presentation is for an unknown circuit"))?;
+        let targets = &circuit.public_inputs;
+        // the flags of a universal circuit leave the predicates the policy
+        // doesn't require unchecked
+        let universal = targets.flags.is_some();
+        // the policy & session give no value for the other optional inputs
+        anyhow::ensure!(
+            targets.nationality.is_some()
+                && targets.nym.is_none()
+                && (universal || targets.issued_after.is_none())
+                && (universal || targets.authority_country.is_none())
+                && targets.time_authority.is_none()
+                && targets.consortium.is_none()
+                && targets.portrait_hash.is_none()
+                && targets.credential_id.is_none()
+                && targets.account.is_none()
+                && targets.derived_key.is_none()
+                && targets.name_hash.is_none()
+                && targets.document_commitment.is_none()
+                && targets.previous_id.is_none(),
+            "presentations of this circuit can't be verified against a policy"
+        );
+        let issuers = self.issuers(policy)?;
+        // the nullifier of a k-show credential, or of the credential for the
+        // service, recorded alike
+        let (rate_limit, credential_nullifier) = match (
+            &targets.rate_limit,
+            targets.credential_nullifier,
+            presentation.nullifier,
+        ) {
+            (Some(_), None, Some(nullifier)) => (
+                Some(circuit::inputs::RateLimit {
+                    epoch: self.rate_limit.epoch(Utc::now().date_naive()).to_field(),
+                    nullifier,
+                }),
+                None,
+            ),
+            (None, Some(_), Some(nullifier)) => (None, Some(nullifier)),
+            (None, None, None) => (None, None),
+            _ => anyhow::bail!("the nullifier of the presentation does not match its circuit"),
+        };
+        let proof = ZkProof::from_bytes(presentation.proof, &circuit.circuit.common)
+            .map_err(|_| anyhow::anyhow!("malformed proof"))?;
+        // placeholders of the inputs of the predicates the policy doesn't
+        // require, taken from the proof, see take_inactive
+        let unchecked = |target: bool| (universal && target).then_some(circuit::F::ZERO);
+        let unchecked_hash = |target: bool| {
+            (universal && target).then_some(encoding::Hash([circuit::F::ZERO; encoding::LEN_HASH]))
+        };
+        let mut public_inputs = circuit::inputs::Public {
+            rate_limit,
+            credential_nullifier,
+            // the day of the verification, whatever the day of the policy
+            current_date_days: targets.current_date_days.map(|_| date::today().to_field()),
+            issued_after: unchecked(targets.issued_after.is_some()),
+            authority_country: unchecked(targets.authority_country.is_some()),
+            excluded_nationalities: unchecked_hash(targets.excluded_nationalities.is_some()),
+            min_age_years: unchecked(targets.min_age_years.is_some()),
+            allowed_nationalities: unchecked_hash(targets.allowed_nationalities.is_some()),
+            flags: targets.flags.map(|_| policy_flags(policy).to_field()),
+            ..circuit::inputs::Public::register(
+                policy.today_days.to_field(),
+                policy.min_age_days.to_field(),
+                Some(policy.nationality.to_field()),
+                // replaced by the key of the issuer of the proof
+                issuers[0].0.to_field(),
+                session.nonce.to_field(),
+                session.service.to_field(),
+                presentation.pseudonym,
+                self.root()?,
+            )
+        };
+        public_inputs.take_inactive(&proof.public_inputs)?;
+        let verified = self.verify_proof(
+            &circuit,
+            proof,
+            &mut public_inputs,
+            &issuers,
+            presentation.nullifier,
+        )?;
+        let attributes = Attributes::extract(&public_inputs, &issuers[verified.issuer])?;
+        let report = VerificationReport {
+            circuit: presentation.circuit,
+            pseudonym: presentation.pseudonym,
+            nullifier: presentation.nullifier,
+            cached: verified.cached,
+            attributes,
+            decision: Decision::Allow,
+        };
+        Ok((report, verified))
+    }
+
+    /// The issuer of the policy, else any of the accepted ones
+    fn issuers(&self, policy: &Policy) -> anyhow::Result<Vec<PublicKey>> {
+        let issuers = match &policy.issuer {
+            Some(issuer) => vec![issuer.clone()],
+            None => self.issuers.clone(),
+        };
+        anyhow::ensure!(
+            !issuers.is_empty(),
+            "neither the policy nor the verifier names an issuer key"
+        );
+        Ok(issuers)
+    }
+
+    fn root(&self) -> anyhow::Result<issuer::database::Root> {
+        self.root
+            .ok_or_else(|| anyhow::anyhow!("the verifier has no root of valid credentials"))
+    }
+
+    /// Fails if the risk hooks deny the presentation
+    fn assess(&self, mut report: VerificationReport) -> anyhow::Result<VerificationReport> {
+        report.decision = self.risk_hooks.assess(&report);
+        if let Decision::Deny(reason) = &report.decision {
+            anyhow::bail!("the presentation was denied: {reason}");
+        }
+        Ok(report)
+    }
+
+    /// Records the nullifier of a verified proof, if any, and caches its
+    /// acceptance, unless the cache answered
+    fn record(&mut self, nullifier: Option<Nullifier>, verified: &Verified) -> anyhow::Result<()> {
+        if verified.cached {
+            return Ok(());
+        }
+        if let Some(nullifier) = nullifier {
+            self.store.insert(nullifier)?;
+        }
+        if let (Some(cache), Some(key)) = (&mut self.cache, verified.key) {
+            cache.accept(key, Instant::now());
+        }
+        Ok(())
+    }
+
+    /// Checks the circuit, the public inputs for any of issuers, the
+    /// nullifier if any, and the proof, and emits the events of the outcome.
+    /// The cache only answers for the very same proof, see cache. See record
+    /// for the nullifier.
+    fn verify_proof(
+        &mut self,
+        circuit: &Circuit,
+        proof: ZkProof,
+        public_inputs: &mut circuit::inputs::Public<circuit::F>,
+        issuers: &[PublicKey],
+        nullifier: Option<Nullifier>,
+    ) -> anyhow::Result<Verified> {
+        let digest = circuit.digest();
+        if let Some(compatibility) = &self.compatibility {
+            if let Err(err) = compatibility.check_circuit(&digest) {
+                self.events.emit(Event::StaleCircuit { circuit: digest });
+                return Err(err);
+            }
+        }
+        let issuer = match public_inputs.check_any_issuer(&proof.public_inputs, issuers) {
+            Ok(issuer) => issuer,
+            Err(err) => {
+                self.events.emit(Event::VerificationFailed {
+                    circuit: digest,
+                    reason: err.to_string(),
+                });
+                return Err(err.into());
+            }
+        };
+        let key = self
+            .cache
+            .as_ref()
+            .map(|_| cache::key(&proof.to_bytes(), digest));
+        if let (Some(cache), Some(key)) = (&self.cache, &key) {
+            if cache.is_accepted(key, Instant::now()) {
+                self.events.emit(Event::VerificationSucceeded {
+                    circuit: digest,
+                    cached: true,
+                });
+                return Ok(Verified {
+                    cached: true,
+                    issuer,
+                    key: None,
+                });
+            }
+        }
+        if let Some(nullifier) = nullifier {
+            if self.store.contains(&nullifier)? {
+                self.events.emit(Event::NullifierReused {
+                    circuit: digest,
+                    nullifier,
+                });
+                anyhow::bail!("nullifier already seen");
+            }
+        }
+        #[cfg(feature = "metrics")]
+        let (started, proof_bytes) = (
+            Instant::now(),
+            self.metrics.as_ref().map(|_| proof.to_bytes().len()),
+        );
+        // the public inputs were checked above
+        let verified = circuit.circuit.verify(proof);
+        #[cfg(feature = "metrics")]
+        if let (Some(metrics), Some(proof_bytes)) = (&self.metrics, proof_bytes) {
+            metrics.verified(started.elapsed(), proof_bytes);
+        }
+        if let Err(err) = verified {
+            self.events.emit(Event::VerificationFailed {
+                circuit: digest,
+                reason: err.to_string(),
+            });
+            return Err(err);
+        }
+        self.events.emit(Event::VerificationSucceeded {
+            circuit: digest,
+            cached: false,
+        });
+        Ok(Verified {
+            cached: false,
+            issuer,
+            key,
+        })
+    }
+}
+
+/// Predicates a universal circuit must enforce for policy, see
+/// Policy::evaluate: the nationality, the age unless the policy requires
+/// none, and the expiration
+pub fn policy_flags(policy: &Policy) -> circuit::inputs::Flags<bool> {
+    circuit::inputs::Flags {
+        nationality: true,
+        majority: policy.min_age_days > 0,
+        expiration: true,
+        ..circuit::inputs::Flags::default()
+    }
+}
+
+/// Outcome of Verifier::verify_proof
+struct Verified {
+    /// Whether the cache answered
+    cached: bool,
+    /// Index of the issuer of the proof
+    issuer: usize,
+    /// Under which record caches the acceptance, if the verifier has a cache
+    key: Option<cache::Key>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bank::store::MemoryStore,
+        circuit::{circuit_with_profile, inputs::Features, CircuitProfile},
+        core::{credential::Credential, date::today_for_tests, set::CountrySet},
+        issuer::pseudonym,
+        merkle,
+        schnorr::{
+            authentification::{Authentification, Context as AuthentificationContext},
+            signature::{Context as SignatureContext, Signature},
+        },
+    };
+
+    #[test]
+    fn verify_presentation_checks_policy_and_session() {
+        let (client_sk, issuer_sk, credential) = Credential::from_seed(2);
+        let signature = Signature::prove(&issuer_sk, &SignatureContext::new(&credential));
+        let session = Session {
+            service: service(),
+            nonce: nonce(),
+        };
+        let auth_ctx = AuthentificationContext::new(
+            &credential.public_key(),
+            &session.service,
+            &session.nonce,
+        );
+        let authentification = Authentification::prove(&client_sk, &auth_ctx);
+        let merkle_path = issuer::database::for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let policy = Policy {
+            nationality: credential.nationality().clone(),
+            today_days: today_for_tests(),
+            min_age_days: date::ADULT_AGE_DAYS,
+            issuer: Some(credential.issuer()),
+        };
+        let pseudonym = pseudonym::hash_from_service(&session.service, &credential.public_key());
+        let public_inputs = circuit::inputs::Public::register(
+            policy.today_days.to_field(),
+            policy.min_age_days.to_field(),
+            Some(policy.nationality.to_field()),
+            credential.issuer().0.to_field(),
+            session.nonce.to_field(),
+            session.service.to_field(),
+            pseudonym,
+            issuer::database::for_tests::DATABASE.root(),
+        );
+        let c = circuit_with_profile(Features::default(), CircuitProfile::Test);
+        let proof = circuit::prove(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &public_inputs,
+        )
+        .unwrap();
+        let bytes = Presentation::new(&c, &proof, pseudonym, None).to_bytes();
+
+        let rate_limit = RateLimit::new(1, 7).unwrap();
+        assert!(Verifier::new(MemoryStore::default(), rate_limit)
+            .verify_presentation(&bytes, &policy, &session)
+            .is_err());
+        let issuers: Vec<PublicKey> = [3, 2, 4]
+            .map(|seed| Credential::from_seed(seed).2.issuer())
+            .to_vec();
+        let mut verifier = Verifier::new(MemoryStore::default(), rate_limit)
+            .with_root(issuer::database::for_tests::DATABASE.root())
+            .with_circuit(c)
+            .with_issuers(issuers);
+        let report = verifier
+            .verify_presentation(&bytes, &policy, &session)
+            .unwrap();
+        assert_eq!(report.pseudonym, pseudonym);
+        assert!(!report.cached);
+        assert_eq!(
+            report.attributes,
+            Attributes {
+                nationality: credential.nationality().clone(),
+                verified_on: date::date_from_days(policy.today_days).unwrap(),
+                born_on_or_before: date::date_from_days(policy.today_days - date::ADULT_AGE_DAYS)
+                    .unwrap(),
+                issuer: credential.issuer(),
+            }
+        );
+
+        let other_session = Session {
+            service: service(),
+            nonce: "other nonce".to_string(),
+        };
+        assert!(verifier
+            .verify_presentation(&bytes, &policy, &other_session)
+            .is_err());
+        let older_policy = Policy {
+            min_age_days: date::min_age_days(130),
+            ..policy
+        };
+        assert!(verifier
+            .verify_presentation(&bytes, &older_policy, &session)
+            .is_err());
+
+        // without an issuer in the policy, any of the accepted ones
+        let any_issuer = Policy {
+            issuer: None,
+            min_age_days: date::ADULT_AGE_DAYS,
+            ..older_policy
+        };
+        let report = verifier
+            .verify_presentation(&bytes, &any_issuer, &session)
+            .unwrap();
+        assert_eq!(report.attributes.issuer, credential.issuer());
+        assert_eq!(report.decision, Decision::Allow);
+
+        // bundles are accepted as a whole
+        let presentation = || Presentation::from_bytes(&bytes).unwrap();
+        let bundle = PresentationBundle::new(vec![presentation(), presentation()]).unwrap();
+        let reports = verifier
+            .verify_bundle(&bundle.to_bytes(), &any_issuer, &session)
+            .unwrap();
+        assert_eq!(reports.len(), 2);
+        let mut tampered = presentation();
+        let last = tampered.proof.len() - 1;
+        tampered.proof[last] ^= 1;
+        let bundle = PresentationBundle::new(vec![presentation(), tampered]).unwrap();
+        assert!(verifier
+            .verify_bundle(&bundle.to_bytes(), &any_issuer, &session)
+            .is_err());
+    }
+
+    #[test]
+    fn verify_presentation_of_the_universal_circuit() {
+        // a credential meeting the policy today, which the expiration is
+        // checked against
+        let (client_sk, issuer_sk, credential) = (0..issuer::database::SIZE as u64)
+            .map(Credential::from_seed)
+            .find(|(_, _, credential)| {
+                Policy::new(credential.nationality().clone())
+                    .evaluate(credential)
+                    .all()
+            })
+            .unwrap();
+        let policy = Policy {
+            issuer: Some(credential.issuer()),
+            ..Policy::new(credential.nationality().clone())
+        };
+        let signature = Signature::prove(&issuer_sk, &SignatureContext::new(&credential));
+        let session = Session {
+            service: service(),
+            nonce: nonce(),
+        };
+        let auth_ctx = AuthentificationContext::new(
+            &credential.public_key(),
+            &session.service,
+            &session.nonce,
+        );
+        let authentification = Authentification::prove(&client_sk, &auth_ctx);
+        let merkle_path = issuer::database::for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let pseudonym = pseudonym::hash_from_service(&session.service, &credential.public_key());
+        // the predicates the policy doesn't require would all fail
+        let excluded = CountrySet::new(&[credential.nationality().code()]).unwrap();
+        let allowed = CountrySet::new(&[]).unwrap();
+        let c = circuit_with_profile(Features::universal(), CircuitProfile::Test);
+        let present = |flags: circuit::inputs::Flags<bool>| {
+            let public_inputs = circuit::inputs::Public {
+                issued_after: Some(u32::MAX.to_field()),
+                authority_country: Some(circuit::F::ZERO),
+                excluded_nationalities: Some(excluded.commitment()),
+                min_age_years: Some(200u32.to_field()),
+                allowed_nationalities: Some(allowed.commitment()),
+                ..circuit::inputs::Public::register(
+                    policy.today_days.to_field(),
+                    policy.min_age_days.to_field(),
+                    Some(policy.nationality.to_field()),
+                    credential.issuer().0.to_field(),
+                    session.nonce.to_field(),
+                    session.service.to_field(),
+                    pseudonym,
+                    issuer::database::for_tests::DATABASE.root(),
+                )
+            }
+            .with_current_date(date::today())
+            .with_flags(flags);
+            let proof = circuit::prove_with(
+                &c,
+                &credential,
+                &signature,
+                &authentification,
+                &merkle_path,
+                &circuit::Extra {
+                    excluded_nationalities: Some(&excluded),
+                    allowed_nationalities: Some(&allowed),
+                    ..circuit::Extra::default()
+                },
+                &public_inputs,
+            )
+            .unwrap();
+            Presentation::new(&c, &proof, pseudonym, None).to_bytes()
+        };
+        let bytes = present(policy_flags(&policy));
+        // a proof leaving the nationality unchecked
+        let lax = present(circuit::inputs::Flags {
+            nationality: false,
+            ..policy_flags(&policy)
+        });
+
+        let mut verifier = Verifier::new(MemoryStore::default(), RateLimit::new(1, 7).unwrap())
+            .with_root(issuer::database::for_tests::DATABASE.root())
+            .with_circuit(c);
+        let report = verifier
+            .verify_presentation(&bytes, &policy, &session)
+            .unwrap();
+        assert_eq!(report.attributes.nationality, policy.nationality);
+        assert!(verifier
+            .verify_presentation(&lax, &policy, &session)
+            .is_err());
+    }
+
+    #[test]
+    fn verify_presentation_rejects_a_credential_presented_twice() {
+        let (client_sk, issuer_sk, credential) = Credential::from_seed(3);
+        let signature = Signature::prove(&issuer_sk, &SignatureContext::new(&credential));
+        let session = Session {
+            service: service(),
+            nonce: nonce(),
+        };
+        let auth_ctx = AuthentificationContext::new(
+            &credential.public_key(),
+            &session.service,
+            &session.nonce,
+        );
+        let authentification = Authentification::prove(&client_sk, &auth_ctx);
+        let merkle_path = issuer::database::for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let policy = Policy {
+            nationality: credential.nationality().clone(),
+            today_days: today_for_tests(),
+            min_age_days: date::ADULT_AGE_DAYS,
+            issuer: Some(credential.issuer()),
+        };
+        let pseudonym = pseudonym::hash_from_service(&session.service, &credential.public_key());
+        let nullifier = pseudonym::credential_nullifier(&credential, &client_sk, &session.service);
+        let features = Features {
+            credential_nullifier: true,
+            ..Features::default()
+        };
+        let c = circuit_with_profile(features, CircuitProfile::Test);
+        let mut public_inputs = circuit::inputs::Public::new(
+            issuer::database::for_tests::DATABASE.root(),
+            &credential.issuer(),
+        )
+        .with_credential_nullifier(nullifier);
+        public_inputs.pseudonym = pseudonym;
+        public_inputs.nationality = Some(policy.nationality.to_field());
+        let proof = circuit::prove_with(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &circuit::Extra {
+                holder_secret: Some(&client_sk),
+                ..circuit::Extra::default()
+            },
+            &public_inputs,
+        )
+        .unwrap();
+        let bytes = Presentation::new(&c, &proof, pseudonym, Some(nullifier)).to_bytes();
+        let without_nullifier = Presentation::new(&c, &proof, pseudonym, None).to_bytes();
+        // the same credential, with a fresh authentification
+        let authentification = Authentification::prove(&client_sk, &auth_ctx);
+        let other_proof = circuit::prove_with(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &circuit::Extra {
+                holder_secret: Some(&client_sk),
+                ..circuit::Extra::default()
+            },
+            &public_inputs,
+        )
+        .unwrap();
+        let other_bytes =
+            Presentation::new(&c, &other_proof, pseudonym, Some(nullifier)).to_bytes();
+
+        let rate_limit = RateLimit::new(1, 7).unwrap();
+        let mut verifier = Verifier::new(MemoryStore::default(), rate_limit)
+            .with_root(issuer::database::for_tests::DATABASE.root())
+            .with_circuit(c);
+        assert!(verifier
+            .verify_presentation(&without_nullifier, &policy, &session)
+            .is_err());
+        let report = verifier
+            .verify_presentation(&bytes, &policy, &session)
+            .unwrap();
+        assert_eq!(report.nullifier, Some(nullifier));
+        assert!(verifier.store().contains(&nullifier).unwrap());
+        assert!(verifier
+            .verify_presentation(&bytes, &policy, &session)
+            .is_err());
+
+        // the cache answers a retry of the very same proof, not another one
+        let mut verifier = Verifier::new(MemoryStore::default(), rate_limit)
+            .with_root(issuer::database::for_tests::DATABASE.root())
+            .with_circuit(circuit_with_profile(features, CircuitProfile::Test))
+            .with_cache(Duration::from_secs(60));
+        let report = verifier
+            .verify_presentation(&bytes, &policy, &session)
+            .unwrap();
+        assert!(!report.cached);
+        let report = verifier
+            .verify_presentation(&bytes, &policy, &session)
+            .unwrap();
+        assert!(report.cached);
+        assert!(verifier
+            .verify_presentation(&other_bytes, &policy, &session)
+            .is_err());
+    }
+}
@@ -0,0 +1,254 @@
+// Envelope of a presentation, as sent by the wallet to the bank.
+//
+// Layout: MAGIC || VERSION || circuit digest || pseudonym || has nullifier
+//         || [nullifier] || proof
+// Field elements are canonical u64s, little-endian. The other public inputs
+// come from the policy & session of the bank, so they are not repeated.
+//
+// A bundle carries several presentations of the same holder answering the
+// same session, e.g. proofs of different circuits, which the bank accepts or
+// rejects together.
+// Layout: BUNDLE_MAGIC || VERSION || count || (length u32 || presentation)*
+
+use plonky2::field::types::{Field, Field64, PrimeField64};
+use thiserror::Error;
+
+use crate::{
+    bank::events::CircuitDigest,
+    circuit::{self, Circuit, ZkProof},
+    encoding::{self, LEN_HASH},
+    issuer::rate_limit::Nullifier,
+};
+
+const MAGIC: &[u8; 8] = b"ZKYCPRES";
+const BUNDLE_MAGIC: &[u8; 8] = b"ZKYCPBDL";
+pub const VERSION: u8 = 1;
+/// Most presentations in a bundle
+pub const MAX_BUNDLE: usize = 8;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Not a presentation")]
+    NotAPresentation,
+    #[error("Unsupported presentation version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Malformed presentation")]
+    Malformed,
+    #[error("Not a presentation bundle")]
+    NotABundle,
+    #[error("A bundle holds 1 to {MAX_BUNDLE} presentations, not {0}")]
+    BundleSize(usize),
+    #[error("The presentations of a bundle are for different pseudonyms")]
+    MixedPseudonyms,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Presentation {
+    pub circuit: CircuitDigest,
+    pub pseudonym: encoding::Pseudonym<circuit::F>,
+    /// Only for circuits of k-show credentials, or exposing a credential
+    /// nullifier
+    pub nullifier: Option<Nullifier>,
+    /// See ZkProof::to_bytes
+    pub proof: Vec<u8>,
+}
+
+fn push_hash(res: &mut Vec<u8>, hash: &encoding::Hash<circuit::F>) {
+    for x in hash.0 {
+        res.extend_from_slice(&x.to_canonical_u64().to_le_bytes());
+    }
+}
+
+pub(crate) fn take_hash(bytes: &mut &[u8]) -> Option<encoding::Hash<circuit::F>> {
+    let mut res = [circuit::F::ZERO; LEN_HASH];
+    for x in res.iter_mut() {
+        let (head, tail) = bytes.split_first_chunk::<8>()?;
+        let v = u64::from_le_bytes(*head);
+        if v >= circuit::F::ORDER {
+            return None;
+        }
+        *x = circuit::F::from_canonical_u64(v);
+        *bytes = tail;
+    }
+    Some(encoding::Hash(res))
+}
+
+impl Presentation {
+    pub fn new(
+        circuit: &Circuit,
+        proof: &ZkProof,
+        pseudonym: encoding::Pseudonym<circuit::F>,
+        nullifier: Option<Nullifier>,
+    ) -> Self {
+        Self {
+            circuit: circuit.digest(),
+            pseudonym,
+            nullifier,
+            proof: proof.to_bytes(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut res = Vec::with_capacity(MAGIC.len() + 2 + 3 * 8 * LEN_HASH + self.proof.len());
+        res.extend_from_slice(MAGIC);
+        res.push(VERSION);
+        push_hash(&mut res, &self.circuit);
+        push_hash(&mut res, &self.pseudonym);
+        res.push(self.nullifier.is_some().into());
+        if let Some(nullifier) = &self.nullifier {
+            push_hash(&mut res, nullifier);
+        }
+        res.extend_from_slice(&self.proof);
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let Some(mut bytes) = bytes.strip_prefix(&MAGIC[..]) else {
+            return Err(Error::NotAPresentation);
+        };
+        let (&version, tail) = bytes.split_first().ok_or(Error::Malformed)?;
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        bytes = tail;
+        let circuit = take_hash(&mut bytes).ok_or(Error::Malformed)?;
+        let pseudonym = take_hash(&mut bytes).ok_or(Error::Malformed)?;
+        let (&has_nullifier, tail) = bytes.split_first().ok_or(Error::Malformed)?;
+        bytes = tail;
+        let nullifier = match has_nullifier {
+            0 => None,
+            1 => Some(take_hash(&mut bytes).ok_or(Error::Malformed)?),
+            _ => return Err(Error::Malformed),
+        };
+        Ok(Self {
+            circuit,
+            pseudonym,
+            nullifier,
+            proof: bytes.to_vec(),
+        })
+    }
+}
+
+/// Presentations verified together, see bank::Verifier::verify_bundle. They
+/// share the pseudonym, which binds them to one holder key for the service
+#[derive(Debug, PartialEq, Eq)]
+pub struct PresentationBundle(Vec<Presentation>);
+
+impl PresentationBundle {
+    pub fn new(presentations: Vec<Presentation>) -> Result<Self> {
+        if presentations.is_empty() || presentations.len() > MAX_BUNDLE {
+            return Err(Error::BundleSize(presentations.len()));
+        }
+        let pseudonym = presentations[0].pseudonym;
+        if presentations.iter().any(|p| p.pseudonym != pseudonym) {
+            return Err(Error::MixedPseudonyms);
+        }
+        Ok(Self(presentations))
+    }
+
+    pub fn presentations(&self) -> &[Presentation] {
+        &self.0
+    }
+
+    pub fn into_presentations(self) -> Vec<Presentation> {
+        self.0
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut res = vec![];
+        res.extend_from_slice(BUNDLE_MAGIC);
+        res.push(VERSION);
+        res.push(self.0.len() as u8);
+        for presentation in &self.0 {
+            let bytes = presentation.to_bytes();
+            res.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            res.extend_from_slice(&bytes);
+        }
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let Some(bytes) = bytes.strip_prefix(&BUNDLE_MAGIC[..]) else {
+            return Err(Error::NotABundle);
+        };
+        let (&version, bytes) = bytes.split_first().ok_or(Error::Malformed)?;
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        let (&count, mut bytes) = bytes.split_first().ok_or(Error::Malformed)?;
+        let mut presentations = Vec::with_capacity(count.into());
+        for _ in 0..count {
+            let (len, tail) = bytes.split_first_chunk::<4>().ok_or(Error::Malformed)?;
+            let len = u32::from_le_bytes(*len) as usize;
+            if tail.len() < len {
+                return Err(Error::Malformed);
+            }
+            let (presentation, tail) = tail.split_at(len);
+            presentations.push(Presentation::from_bytes(presentation)?);
+            bytes = tail;
+        }
+        if !bytes.is_empty() {
+            return Err(Error::Malformed);
+        }
+        Self::new(presentations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_bytes_inverts_to_bytes() {
+        let hash = |x| encoding::Hash([circuit::F::from_canonical_u64(x); LEN_HASH]);
+        let presentation = Presentation {
+            circuit: hash(1),
+            pseudonym: hash(2),
+            nullifier: Some(hash(3)),
+            proof: vec![4, 5, 6],
+        };
+        let bytes = presentation.to_bytes();
+        assert_eq!(Presentation::from_bytes(&bytes), Ok(presentation));
+
+        assert_eq!(
+            Presentation::from_bytes(&bytes[1..]),
+            Err(Error::NotAPresentation)
+        );
+        let mut other_version = bytes.clone();
+        other_version[MAGIC.len()] = VERSION + 1;
+        assert_eq!(
+            Presentation::from_bytes(&other_version),
+            Err(Error::UnsupportedVersion(VERSION + 1))
+        );
+        assert_eq!(
+            Presentation::from_bytes(&bytes[..MAGIC.len() + 10]),
+            Err(Error::Malformed)
+        );
+    }
+
+    #[test]
+    fn bundles_round_trip() {
+        let hash = |x| encoding::Hash([circuit::F::from_canonical_u64(x); LEN_HASH]);
+        let presentation = |circuit, pseudonym| Presentation {
+            circuit: hash(circuit),
+            pseudonym: hash(pseudonym),
+            nullifier: None,
+            proof: vec![7; 3],
+        };
+        let bundle = PresentationBundle::new(vec![presentation(1, 2), presentation(3, 2)]).unwrap();
+        let bytes = bundle.to_bytes();
+        assert_eq!(PresentationBundle::from_bytes(&bytes), Ok(bundle));
+        assert_eq!(
+            PresentationBundle::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(Error::Malformed)
+        );
+
+        assert_eq!(
+            PresentationBundle::new(vec![presentation(1, 2), presentation(1, 3)]),
+            Err(Error::MixedPseudonyms)
+        );
+        assert_eq!(PresentationBundle::new(vec![]), Err(Error::BundleSize(0)));
+    }
+}
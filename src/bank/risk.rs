@@ -0,0 +1,94 @@
+// Checks institutions layer on top of the cryptographic verification of a
+// presentation, e.g. velocity checks or fraud scoring: hooks are registered
+// once on the Verifier, see Verifier::with_risk_hook.
+
+use crate::bank::VerificationReport;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Decision {
+    Allow,
+    /// Accept once the holder passed an additional check, e.g. a video call
+    StepUp(String),
+    /// verify_presentation fails with the reason
+    Deny(String),
+}
+
+pub trait RiskHook {
+    /// Called once the proof is verified, with the attributes it proves
+    fn assess(&mut self, report: &VerificationReport) -> Decision;
+}
+
+impl<H: FnMut(&VerificationReport) -> Decision> RiskHook for H {
+    fn assess(&mut self, report: &VerificationReport) -> Decision {
+        self(report)
+    }
+}
+
+/// Hooks assess in the order they were registered, until one doesn't allow
+#[derive(Default)]
+pub struct RiskHooks(Vec<Box<dyn RiskHook>>);
+
+impl RiskHooks {
+    pub fn register(&mut self, hook: impl RiskHook + 'static) {
+        self.0.push(Box::new(hook));
+    }
+
+    pub fn assess(&mut self, report: &VerificationReport) -> Decision {
+        for hook in &mut self.0 {
+            let decision = hook.assess(report);
+            if decision != Decision::Allow {
+                return decision;
+            }
+        }
+        Decision::Allow
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::types::Field;
+
+    use super::*;
+    use crate::{
+        bank::Attributes,
+        circuit,
+        core::{
+            credential::{Credential, Nationality},
+            date,
+        },
+        encoding,
+    };
+
+    #[test]
+    fn first_hook_not_allowing_decides() {
+        let report = VerificationReport {
+            circuit: encoding::Hash([circuit::F::ONE; encoding::LEN_HASH]),
+            pseudonym: encoding::Hash([circuit::F::TWO; encoding::LEN_HASH]),
+            nullifier: None,
+            cached: false,
+            attributes: Attributes {
+                nationality: Nationality::FR,
+                verified_on: date::date_from_days(date::today_for_tests()).unwrap(),
+                born_on_or_before: date::date_from_days(0).unwrap(),
+                issuer: Credential::from_seed(1).2.issuer(),
+            },
+            decision: Decision::Allow,
+        };
+        let mut hooks = RiskHooks::default();
+        assert_eq!(hooks.assess(&report), Decision::Allow);
+
+        hooks.register(|_: &VerificationReport| Decision::Allow);
+        hooks.register(|report: &VerificationReport| {
+            if report.cached {
+                Decision::Allow
+            } else {
+                Decision::StepUp("first presentation".to_string())
+            }
+        });
+        hooks.register(|_: &VerificationReport| Decision::Deny("unreachable".to_string()));
+        assert_eq!(
+            hooks.assess(&report),
+            Decision::StepUp("first presentation".to_string())
+        );
+    }
+}
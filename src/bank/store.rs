@@ -0,0 +1,150 @@
+// Nullifiers already seen by a bank, to reject replayed presentations and
+// k-show credentials shown more than k times per epoch.
+
+use std::{
+    collections::HashSet,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
+
+use plonky2::field::{
+    goldilocks_field::GoldilocksField,
+    types::{Field, Field64, PrimeField64},
+};
+use thiserror::Error;
+
+use crate::{
+    encoding::{self, LEN_HASH},
+    issuer::rate_limit::Nullifier,
+};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Store I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Corrupted store at line {0}")]
+    Corrupted(usize),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+pub trait NullifierStore {
+    fn contains(&self, nullifier: &Nullifier) -> Result<bool>;
+    /// Records the nullifier, and returns false if it was already recorded
+    fn insert(&mut self, nullifier: Nullifier) -> Result<bool>;
+}
+
+/// Nullifiers seen since the verifier started: presentations made before a
+/// restart can be replayed after it, see FileStore to keep them
+#[derive(Default)]
+pub struct MemoryStore(HashSet<Nullifier>);
+
+impl NullifierStore for MemoryStore {
+    fn contains(&self, nullifier: &Nullifier) -> Result<bool> {
+        Ok(self.0.contains(nullifier))
+    }
+
+    fn insert(&mut self, nullifier: Nullifier) -> Result<bool> {
+        Ok(self.0.insert(nullifier))
+    }
+}
+
+/// Append-only file with one nullifier per line, as hexadecimal field
+/// elements, kept in memory once loaded
+pub struct FileStore {
+    seen: HashSet<Nullifier>,
+    file: File,
+}
+
+fn parse_line(line: &str) -> Option<Nullifier> {
+    let mut res = [GoldilocksField::ZERO; LEN_HASH];
+    let mut elements = line.split(' ');
+    for x in res.iter_mut() {
+        let v = u64::from_str_radix(elements.next()?, 16).ok()?;
+        if v >= GoldilocksField::ORDER {
+            return None;
+        }
+        *x = GoldilocksField::from_canonical_u64(v);
+    }
+    elements.next().is_none().then_some(encoding::Hash(res))
+}
+
+impl FileStore {
+    /// Opens the store at path, creating it if needed
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+        let mut seen = HashSet::new();
+        for (i, line) in BufReader::new(&file).lines().enumerate() {
+            seen.insert(parse_line(&line?).ok_or(Error::Corrupted(i + 1))?);
+        }
+        Ok(Self { seen, file })
+    }
+}
+
+impl NullifierStore for FileStore {
+    fn contains(&self, nullifier: &Nullifier) -> Result<bool> {
+        Ok(self.seen.contains(nullifier))
+    }
+
+    fn insert(&mut self, nullifier: Nullifier) -> Result<bool> {
+        if self.seen.contains(&nullifier) {
+            return Ok(false);
+        }
+        let line: Vec<String> = nullifier
+            .0
+            .iter()
+            .map(|x| format!("{:x}", x.to_canonical_u64()))
+            .collect();
+        writeln!(self.file, "{}", line.join(" "))?;
+        self.file.sync_data()?;
+        Ok(self.seen.insert(nullifier))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nullifier(x: u64) -> Nullifier {
+        encoding::Hash([
+            GoldilocksField::from_canonical_u64(x),
+            GoldilocksField::ONE,
+            GoldilocksField::ZERO,
+            GoldilocksField::NEG_ONE,
+        ])
+    }
+
+    #[test]
+    fn memory_store_rejects_seen_nullifiers() {
+        let mut store = MemoryStore::default();
+        assert!(!store.contains(&nullifier(1)).unwrap());
+        assert!(store.insert(nullifier(1)).unwrap());
+        assert!(store.contains(&nullifier(1)).unwrap());
+        assert!(!store.insert(nullifier(1)).unwrap());
+        assert!(store.insert(nullifier(2)).unwrap());
+    }
+
+    #[test]
+    fn file_store_persists_nullifiers() {
+        let path = std::env::temp_dir().join(format!("zkyc-nullifiers-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        {
+            let mut store = FileStore::open(&path).unwrap();
+            assert!(store.insert(nullifier(1)).unwrap());
+            assert!(!store.insert(nullifier(1)).unwrap());
+        }
+        let mut store = FileStore::open(&path).unwrap();
+        assert!(store.contains(&nullifier(1)).unwrap());
+        assert!(store.insert(nullifier(2)).unwrap());
+        assert!(!store.contains(&nullifier(3)).unwrap());
+
+        std::fs::write(&path, "not a nullifier\n").unwrap();
+        assert!(matches!(FileStore::open(&path), Err(Error::Corrupted(1))));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
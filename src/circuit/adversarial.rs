@@ -0,0 +1,207 @@
+// Soundness regression harness: mutates one value of an honest presentation at
+// a time, and expects the prover or the verifier to reject it. A constraint
+// which goes missing shows up as an accepted mutation, whichever check it
+// belonged to.
+//
+// Mutations are applied to the witness values, below the native types, so that
+// they may produce inputs no honest holder could build, e.g. points off the
+// curve.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use plonky2::{field::types::Field, iop::witness::PartialWitness};
+
+use crate::{
+    circuit::{self, bench::Fixture, inputs, Circuit, Extra, F},
+    encoding::{self, Point, LEN_FIELD, LEN_POINT, LEN_SCALAR},
+    issuer,
+};
+
+/// One change to an honest presentation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mutation {
+    /// Flips bit i of s of the signature of the credential
+    SignatureBit(usize),
+    /// Flips bit i of s of the authentification
+    AuthentificationBit(usize),
+    /// Adds one to limb i of r of the signature
+    SignatureNonceLimb(usize),
+    /// Adds one to limb i of the holder key of the credential
+    HolderKeyLimb(usize),
+    /// Adds one to the sibling at depth i of the Merkle path
+    MerkleSibling(usize),
+    /// Asks for one day more than the age of the holder, so that they turn of
+    /// age the day after the public date
+    BirthDateAcrossCutoff,
+    /// Adds one to public input i of the proof, after proving
+    PublicInput(usize),
+}
+
+/// What became of a mutated presentation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// The prover failed, or panicked
+    ProverRejected,
+    VerifierRejected,
+    Accepted,
+}
+
+impl Mutation {
+    /// Both ends and the middle of every mutated value, and every public
+    /// input of the circuit
+    pub fn all(circuit: &Circuit) -> Vec<Self> {
+        let ends = |len: usize| [0, len / 2, len - 1];
+        // first limb of every coordinate
+        let limbs = (0..LEN_POINT).step_by(LEN_FIELD);
+        let witness = ends(LEN_SCALAR)
+            .into_iter()
+            .flat_map(|i| [Self::SignatureBit(i), Self::AuthentificationBit(i)])
+            .chain(limbs.flat_map(|i| [Self::SignatureNonceLimb(i), Self::HolderKeyLimb(i)]))
+            .chain(ends(issuer::database::SIZE).map(Self::MerkleSibling))
+            .chain([Self::BirthDateAcrossCutoff]);
+        let public = (0..circuit.circuit.common.num_public_inputs).map(Self::PublicInput);
+        witness.chain(public).collect()
+    }
+
+    fn apply(self, values: &mut inputs::Private<F, bool>, public_inputs: &mut inputs::Public<F>) {
+        match self {
+            Self::SignatureBit(i) => {
+                let mut bits = encoding::Scalar::unpack(&values.signature.s)
+                    .expect("honest signatures are canonical");
+                bits.0[i] = !bits.0[i];
+                values.signature.s = bits.pack();
+            }
+            Self::AuthentificationBit(i) => {
+                let bit = &mut values.authentification.s.0[i];
+                *bit = !*bit;
+            }
+            Self::SignatureNonceLimb(i) => increment(&mut values.signature.r, i),
+            Self::HolderKeyLimb(i) => increment(&mut values.credential.public_key, i),
+            Self::MerkleSibling(i) => values.merkle_path.path[i].0[0] += F::ONE,
+            Self::BirthDateAcrossCutoff => {
+                let age_days = public_inputs.today_days - values.credential.birth_date;
+                public_inputs.min_age_days = age_days + F::ONE;
+            }
+            Self::PublicInput(_) => {}
+        }
+    }
+}
+
+fn increment(point: &mut Point<F>, i: usize) {
+    let coordinate = match i / LEN_FIELD {
+        0 => &mut point.x,
+        1 => &mut point.z,
+        2 => &mut point.u,
+        _ => &mut point.t,
+    };
+    coordinate.0[i % LEN_FIELD] += F::ONE;
+}
+
+/// Proves the presentation of the fixture of seed after edit, then verifies
+/// the proof, with public input i tampered with if any, against the edited
+/// public inputs
+fn outcome(
+    circuit: &Circuit,
+    seed: u64,
+    edit: impl FnOnce(&mut inputs::Private<F, bool>, &mut inputs::Public<F>),
+    tampered: Option<usize>,
+) -> anyhow::Result<Outcome> {
+    let Fixture {
+        credential,
+        signature,
+        authentification,
+        merkle_path,
+        mut public_inputs,
+        ..
+    } = Fixture::from_seed(seed)?;
+    let mut values = circuit::witness_values(
+        &credential,
+        &signature,
+        &authentification,
+        &merkle_path,
+        &Extra::default(),
+    );
+    edit(&mut values, &mut public_inputs);
+    let prove = || {
+        let mut pw = PartialWitness::new();
+        values.set(&mut pw, &circuit.private_inputs)?;
+        public_inputs.set(&mut pw, &circuit.public_inputs)?;
+        circuit.circuit.prove(pw)
+    };
+    // a generator may panic on values it doesn't expect rather than fail
+    let mut proof = match panic::catch_unwind(AssertUnwindSafe(prove)) {
+        Ok(Ok(proof)) => proof,
+        Ok(Err(_)) | Err(_) => return Ok(Outcome::ProverRejected),
+    };
+    if let Some(i) = tampered {
+        proof.public_inputs[i] += F::ONE;
+    }
+    let verify = || circuit::verify(&circuit.circuit, proof, public_inputs);
+    Ok(match panic::catch_unwind(AssertUnwindSafe(verify)) {
+        Ok(Ok(())) => Outcome::Accepted,
+        Ok(Err(_)) | Err(_) => Outcome::VerifierRejected,
+    })
+}
+
+/// Outcome of the presentation of the credential of seed, see
+/// bench::Fixture, with the mutation applied
+pub fn run(circuit: &Circuit, seed: u64, mutation: Mutation) -> anyhow::Result<Outcome> {
+    let tampered = match mutation {
+        Mutation::PublicInput(i) => Some(i),
+        _ => None,
+    };
+    outcome(
+        circuit,
+        seed,
+        |values, public_inputs| mutation.apply(values, public_inputs),
+        tampered,
+    )
+}
+
+/// Mutations of Mutation::all which the circuit accepts, none if it is sound.
+/// Fails unless the circuit accepts the presentation of the credential of
+/// seed as is, and on the day the holder comes of age, the control of
+/// Mutation::BirthDateAcrossCutoff.
+pub fn accepted(circuit: &Circuit, seed: u64) -> anyhow::Result<Vec<Mutation>> {
+    let honest = outcome(circuit, seed, |_, _| {}, None)?;
+    anyhow::ensure!(
+        honest == Outcome::Accepted,
+        "honest presentation: {honest:?}"
+    );
+    let cutoff = outcome(
+        circuit,
+        seed,
+        |values, public_inputs| {
+            public_inputs.min_age_days = public_inputs.today_days - values.credential.birth_date;
+        },
+        None,
+    )?;
+    anyhow::ensure!(
+        cutoff == Outcome::Accepted,
+        "presentation at the cutoff: {cutoff:?}"
+    );
+
+    let mut accepted = vec![];
+    for mutation in Mutation::all(circuit) {
+        if run(circuit, seed, mutation)? == Outcome::Accepted {
+            accepted.push(mutation);
+        }
+    }
+    Ok(accepted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{inputs::Features, CircuitProfile};
+
+    #[test]
+    fn every_mutation_is_rejected() {
+        let circuit = circuit::circuit_with_profile(Features::default(), CircuitProfile::Test);
+        assert_eq!(accepted(&circuit, 4).unwrap(), vec![]);
+        assert_eq!(
+            run(&circuit, 4, Mutation::BirthDateAcrossCutoff).unwrap(),
+            Outcome::ProverRejected
+        );
+    }
+}
@@ -10,15 +10,22 @@ use plonky2::{
 
 use crate::{
     circuit::schnorr::{CircuitBuilderSchnorr, PartialWitnessSchnorr},
-    encoding::{self, LEN_POINT, LEN_STRING},
+    encoding::{self, LEN_ACCOUNT, LEN_CHANNEL_BINDING, LEN_POINT, LEN_STRING},
 };
 
 pub type AuthentificationTarget = encoding::Authentification<Target, BoolTarget>;
 
 pub type AuthentificationContextTarget = encoding::AuthentificationContext<Target>;
 
+pub type ChannelBindingTarget = encoding::ChannelBinding<Target>;
+
+pub type AccountTarget = encoding::Account<Target>;
+
 pub trait CircuitBuilderAuthentification<F: RichField + Extendable<D>, const D: usize> {
     fn add_virtual_authentification_target(&mut self) -> AuthentificationTarget;
+    fn add_virtual_channel_binding_target(&mut self) -> ChannelBindingTarget;
+    fn add_virtual_account_target(&mut self) -> AccountTarget;
+    fn register_account_public_input(&mut self, target: AccountTarget);
     fn hash_authentification(
         &mut self,
         ctx: &AuthentificationContextTarget,
@@ -41,13 +48,46 @@ pub trait PartialWitnessAuthentification<F: RichField>: Witness<F> {
         target: AuthentificationTarget,
         value: encoding::Authentification<F, bool>,
     ) -> anyhow::Result<()>;
+    fn set_channel_binding_target(
+        &mut self,
+        target: ChannelBindingTarget,
+        value: encoding::ChannelBinding<F>,
+    ) -> anyhow::Result<()>;
+    fn set_account_target(
+        &mut self,
+        target: AccountTarget,
+        value: encoding::Account<F>,
+    ) -> anyhow::Result<()>;
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderAuthentification<F, D>
     for CircuitBuilder<F, D>
 {
     fn add_virtual_authentification_target(&mut self) -> AuthentificationTarget {
-        encoding::Authentification(self.add_virtual_schnorr_target())
+        self.add_virtual_schnorr_target()
+    }
+
+    fn add_virtual_channel_binding_target(&mut self) -> ChannelBindingTarget {
+        encoding::ChannelBinding(std::array::from_fn::<_, LEN_CHANNEL_BINDING, _>(|_| {
+            self.add_virtual_target()
+        }))
+    }
+
+    // the verifier sets the account, so its padding needs no constraint
+    fn add_virtual_account_target(&mut self) -> AccountTarget {
+        encoding::VarString {
+            value: encoding::String(std::array::from_fn::<_, LEN_ACCOUNT, _>(|_| {
+                self.add_virtual_target()
+            })),
+            len: self.add_virtual_target(),
+        }
+    }
+
+    fn register_account_public_input(&mut self, target: AccountTarget) {
+        for t in target.value.0.into_iter() {
+            self.register_public_input(t);
+        }
+        self.register_public_input(target.len);
     }
 
     fn hash_authentification(
@@ -55,12 +95,22 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderAuthentificatio
         ctx: &AuthentificationContextTarget,
         auth: &AuthentificationTarget,
     ) -> encoding::Scalar<BoolTarget> {
-        let mut message = Vec::with_capacity(2 * LEN_STRING + LEN_POINT);
+        let mut message =
+            Vec::with_capacity(2 * LEN_STRING + LEN_POINT + LEN_CHANNEL_BINDING + LEN_ACCOUNT + 8);
         message.extend_from_slice(&ctx.challenge.service.0);
         message.extend_from_slice(&ctx.challenge.nonce.0);
         let public_key: [Target; LEN_POINT] = ctx.public_key.into();
         message.extend_from_slice(&public_key);
-        self.schnorr_hash_with_message(auth.0, &message)
+        let channel_binding = ctx.channel_binding.map(|channel_binding| channel_binding.0);
+        push_optional(self, &mut message, channel_binding.as_ref().map(|b| &b[..]));
+        push_optional(self, &mut message, ctx.expires_at.as_ref().map(std::slice::from_ref));
+        let account = ctx.account.map(|account| {
+            let mut value = account.value.0.to_vec();
+            value.push(account.len);
+            value
+        });
+        push_optional(self, &mut message, account.as_deref());
+        self.schnorr_hash_with_message(*auth, &message)
     }
 
     fn verify_authentification(
@@ -69,16 +119,29 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderAuthentificatio
         auth: &AuthentificationTarget,
     ) {
         let e = self.hash_authentification(ctx, auth);
-        self.schnorr_final_verification(auth.0, e, ctx.public_key);
+        self.schnorr_final_verification(*auth, e, ctx.public_key);
     }
 }
 
+/// The circuit side of transcript::push_optional: whether the field is set is
+/// known when building, so its tag and length are constants
+fn push_optional<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    message: &mut Vec<Target>,
+    value: Option<&[Target]>,
+) {
+    message.push(builder.constant_bool(value.is_some()).target);
+    let value = value.unwrap_or_default();
+    message.push(builder.constant(F::from_canonical_usize(value.len())));
+    message.extend_from_slice(value);
+}
+
 impl<W: Witness<F>, F: RichField> PartialWitnessAuthentification<F> for W {
     fn get_authentification_target(
         &self,
         target: AuthentificationTarget,
     ) -> encoding::Authentification<F, bool> {
-        encoding::Authentification(self.get_schnorr_target(target.0))
+        self.get_schnorr_target(target)
     }
 
     fn set_authentification_target(
@@ -86,7 +149,29 @@ impl<W: Witness<F>, F: RichField> PartialWitnessAuthentification<F> for W {
         target: AuthentificationTarget,
         value: encoding::Authentification<F, bool>,
     ) -> anyhow::Result<()> {
-        self.set_schnorr_target(target.0, value.0)
+        self.set_schnorr_target(target, value)
+    }
+
+    fn set_channel_binding_target(
+        &mut self,
+        target: ChannelBindingTarget,
+        value: encoding::ChannelBinding<F>,
+    ) -> anyhow::Result<()> {
+        for (target, value) in target.0.into_iter().zip(value.0.into_iter()) {
+            self.set_target(target, value)?;
+        }
+        Ok(())
+    }
+
+    fn set_account_target(
+        &mut self,
+        target: AccountTarget,
+        value: encoding::Account<F>,
+    ) -> anyhow::Result<()> {
+        for (target, value) in target.value.0.into_iter().zip(value.value.0) {
+            self.set_target(target, value)?;
+        }
+        self.set_target(target.len, value.len)
     }
 }
 
@@ -94,7 +179,7 @@ impl<W: Witness<F>, F: RichField> PartialWitnessAuthentification<F> for W {
 mod tests {
     use plonky2::{
         field::{goldilocks_field::GoldilocksField as F, types::Field},
-        iop::witness::PartialWitness,
+        iop::witness::{PartialWitness, WitnessWrite},
         plonk::{circuit_data::CircuitConfig, config::PoseidonGoldilocksConfig},
     };
     use rand::{rngs::StdRng, SeedableRng};
@@ -105,7 +190,7 @@ mod tests {
             string::{CircuitBuilderString, PartialWitnessString},
         },
         encoding::{
-            conversion::{ToAuthentificationField, ToPointField},
+            conversion::{ToPointField, ToSchnorrField},
             AuthentificationChallenge, LEN_SCALAR,
         },
         schnorr::{
@@ -134,6 +219,14 @@ mod tests {
                 service: encoding::String(ctx.service().0.map(|x| F::from_canonical_u64(x.0))),
                 nonce: encoding::String(ctx.nonce().0.map(|x| F::from_canonical_u64(x.0))),
             },
+            channel_binding: ctx
+                .channel_binding()
+                .map(|b| encoding::ChannelBinding(b.0.map(|x| F::from_canonical_u64(x.0)))),
+            expires_at: ctx.expires_at().map(F::from_canonical_u32),
+            account: ctx.account().map(|account| encoding::VarString {
+                value: encoding::String(account.value.0.map(|x| F::from_canonical_u64(x.0))),
+                len: F::from_canonical_u64(account.len.0),
+            }),
         }
     }
 
@@ -146,6 +239,18 @@ mod tests {
                 service: builder.add_virtual_string_target(),
                 nonce: builder.add_virtual_string_target(),
             },
+            channel_binding: None,
+            expires_at: None,
+            account: None,
+        }
+    }
+
+    fn add_virtual_bound_authentification_context_target(
+        builder: &mut CircuitBuilder<F, D>,
+    ) -> AuthentificationContextTarget {
+        AuthentificationContextTarget {
+            channel_binding: Some(builder.add_virtual_channel_binding_target()),
+            ..add_virtual_authentification_context_target(builder)
         }
     }
 
@@ -160,6 +265,15 @@ mod tests {
             .unwrap();
         pw.set_string_target(target.challenge.nonce, value.challenge.nonce)
             .unwrap();
+        if let (Some(target), Some(value)) = (target.channel_binding, value.channel_binding) {
+            pw.set_channel_binding_target(target, value).unwrap();
+        }
+        if let (Some(target), Some(value)) = (target.expires_at, value.expires_at) {
+            pw.set_target(target, value).unwrap();
+        }
+        if let (Some(target), Some(value)) = (target.account, value.account) {
+            pw.set_account_target(target, value).unwrap();
+        }
     }
 
     #[test]
@@ -167,7 +281,7 @@ mod tests {
         let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
         let (sk, pk) = keypair_from_seed(1);
         let ctx = Context::new(&pk, "service-A", "nonce-1");
-        let auth = Authentification::sign(&sk, &ctx).to_field();
+        let auth = Authentification::prove(&sk, &ctx).to_field();
 
         let auth_t = builder.add_virtual_authentification_target();
         let ctx_t = add_virtual_authentification_context_target(&mut builder);
@@ -187,7 +301,7 @@ mod tests {
         let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
         let (sk, pk) = keypair_from_seed(2);
         let ctx_good = Context::new(&pk, "service-A", "nonce-1");
-        let auth = Authentification::sign(&sk, &ctx_good).to_field();
+        let auth = Authentification::prove(&sk, &ctx_good).to_field();
 
         let ctx_bad = Context::new(&pk, "service-B", "nonce-1");
 
@@ -209,7 +323,7 @@ mod tests {
         let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
         let (sk, pk) = keypair_from_seed(3);
         let ctx_good = Context::new(&pk, "service-A", "nonce-1");
-        let auth = Authentification::sign(&sk, &ctx_good).to_field();
+        let auth = Authentification::prove(&sk, &ctx_good).to_field();
 
         let ctx_bad = Context::new(&pk, "service-A", "nonce-2");
 
@@ -233,7 +347,7 @@ mod tests {
         let (_sk2, pk2) = keypair_from_seed(5);
 
         let ctx_good = Context::new(&pk1, "service-A", "nonce-1");
-        let auth = Authentification::sign(&sk1, &ctx_good).to_field();
+        let auth = Authentification::prove(&sk1, &ctx_good).to_field();
 
         let ctx_bad = Context::new(&pk2, "service-A", "nonce-1");
 
@@ -250,13 +364,118 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_verify_auth_with_channel_binding_accepts() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let (sk, pk) = keypair_from_seed(7);
+        let ctx = Context::new(&pk, "service-A", "nonce-1").with_channel_binding(&[7; 32]);
+        let auth = Authentification::prove(&sk, &ctx).to_field();
+
+        let auth_t = builder.add_virtual_authentification_target();
+        let ctx_t = add_virtual_bound_authentification_context_target(&mut builder);
+        builder.verify_authentification(&ctx_t, &auth_t);
+
+        let mut pw = PartialWitness::<F>::new();
+        set_authentification_context_target(&mut pw, ctx_t, ctx_to_target(&ctx));
+        pw.set_authentification_target(auth_t, auth).unwrap();
+
+        let data = builder.build::<Cfg>();
+        let proof = data.prove(pw).expect("prove should pass");
+        data.verify(proof).expect("verify should pass");
+    }
+
+    #[test]
+    fn test_verify_auth_fails_if_channel_binding_changes() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let (sk, pk) = keypair_from_seed(8);
+        let ctx_good = Context::new(&pk, "service-A", "nonce-1").with_channel_binding(&[7; 32]);
+        let auth = Authentification::prove(&sk, &ctx_good).to_field();
+
+        let ctx_bad = Context::new(&pk, "service-A", "nonce-1").with_channel_binding(&[8; 32]);
+
+        let auth_t = builder.add_virtual_authentification_target();
+        let ctx_t = add_virtual_bound_authentification_context_target(&mut builder);
+        builder.verify_authentification(&ctx_t, &auth_t);
+
+        let mut pw = PartialWitness::<F>::new();
+        set_authentification_context_target(&mut pw, ctx_t, ctx_to_target(&ctx_bad));
+        pw.set_authentification_target(auth_t, auth).unwrap();
+
+        let data = builder.build::<Cfg>();
+        let result = data.prove(pw);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_hash_auth_with_expiry_matches_native() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+
+        let (sk, pk) = keypair_from_seed(9);
+        let ctx = Context::new(&pk, "service-A", "nonce-1")
+            .with_channel_binding(&[9; 32])
+            .with_expiry(1_000);
+        let auth = Authentification::prove(&sk, &ctx);
+
+        let auth_t = builder.add_virtual_authentification_target();
+        let ctx_t = AuthentificationContextTarget {
+            expires_at: Some(builder.add_virtual_target()),
+            ..add_virtual_bound_authentification_context_target(&mut builder)
+        };
+        let e_t = builder.hash_authentification(&ctx_t, &auth_t);
+        for b in e_t.0.iter() {
+            builder.register_public_input(b.target);
+        }
+
+        let mut pw = PartialWitness::<F>::new();
+        set_authentification_context_target(&mut pw, ctx_t, ctx_to_target(&ctx));
+        pw.set_authentification_target(auth_t, auth.to_field())
+            .unwrap();
+
+        let data = builder.build::<Cfg>();
+        let proof = data.prove(pw).unwrap();
+
+        let auth_field: encoding::Authentification<F, bool> = auth.to_field();
+        let r_native: crate::arith::Point = auth_field.r.into();
+        let e_native = schnorr::transcript::hash(&r_native, &ctx);
+        let public_inputs: [F; LEN_SCALAR] = proof.public_inputs.try_into().unwrap();
+        let e_circuit = crate::arith::Scalar::from_bits_le(&public_inputs.map(|x| F::is_one(&x)));
+        assert!(e_native.equals(e_circuit) == u64::MAX);
+    }
+
+    #[test]
+    fn test_verify_auth_bound_to_account() {
+        let (sk, pk) = keypair_from_seed(10);
+        let bound = |account: &[u8]| {
+            Context::new(&pk, "service-A", "nonce-1")
+                .with_account(account)
+                .unwrap()
+        };
+        let auth = Authentification::prove(&sk, &bound(&[0xab; 20])).to_field();
+        let prove = |ctx: &Context| {
+            let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+            let auth_t = builder.add_virtual_authentification_target();
+            let ctx_t = AuthentificationContextTarget {
+                account: Some(builder.add_virtual_account_target()),
+                ..add_virtual_authentification_context_target(&mut builder)
+            };
+            builder.verify_authentification(&ctx_t, &auth_t);
+
+            let mut pw = PartialWitness::<F>::new();
+            set_authentification_context_target(&mut pw, ctx_t, ctx_to_target(ctx));
+            pw.set_authentification_target(auth_t, auth).unwrap();
+            builder.build::<Cfg>().prove(pw)
+        };
+        assert!(prove(&bound(&[0xab; 20])).is_ok());
+        assert!(prove(&bound(&[0xcd; 20])).is_err());
+    }
+
     #[test]
     fn test_hash_auth_matches_native() {
         let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
 
         let (sk, pk) = keypair_from_seed(6);
         let ctx = Context::new(&pk, "service-A", "nonce-1");
-        let auth = Authentification::sign(&sk, &ctx);
+        let auth = Authentification::prove(&sk, &ctx);
 
         let auth_t = builder.add_virtual_authentification_target();
         let ctx_t = add_virtual_authentification_context_target(&mut builder);
@@ -274,9 +493,8 @@ mod tests {
         let proof = data.prove(pw).unwrap();
 
         let auth_field: encoding::Authentification<F, bool> = auth.to_field();
-        let r_native: crate::arith::Point = auth_field.0.r.into();
-        let e_native =
-            schnorr::transcript::hash(&r_native, schnorr::transcript::Context::Auth(&ctx));
+        let r_native: crate::arith::Point = auth_field.r.into();
+        let e_native = schnorr::transcript::hash(&r_native, &ctx);
         let public_inputs: [F; LEN_SCALAR] = proof.public_inputs.try_into().unwrap();
         let e_circuit = crate::arith::Scalar::from_bits_le(&public_inputs.map(|x| F::is_one(&x)));
         assert!(e_native.equals(e_circuit) == u64::MAX);
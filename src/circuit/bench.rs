@@ -0,0 +1,174 @@
+// Timings of the proving pipeline, shared by benches/pipeline.rs and whoever
+// wants numbers without criterion, e.g. to compare two branches in a PR: a
+// presentation of a credential of the test database to the test bank, with the
+// base circuit built with a profile.
+
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::{
+    bank,
+    circuit::{self, inputs, secret::Witness, Circuit, CircuitProfile, Extra, F},
+    core::{
+        credential::Credential,
+        date::{today_for_tests, ADULT_AGE_DAYS},
+    },
+    encoding::{
+        conversion::{ToPointField, ToSingleField, ToStringField},
+        MerklePath,
+    },
+    issuer::{self, database::for_tests, pseudonym},
+    merkle,
+    schnorr::{
+        authentification::{self, Authentification},
+        keys::SecretKey,
+        signature::{self, Signature},
+    },
+};
+
+/// Everything a presentation of a credential of the test database needs
+pub struct Fixture {
+    pub issuer_sk: SecretKey,
+    pub credential: Credential,
+    pub signature: Signature,
+    pub authentification: Authentification,
+    pub merkle_path: MerklePath<{ issuer::database::SIZE }, F, bool>,
+    pub public_inputs: inputs::Public<F>,
+}
+
+impl Fixture {
+    /// Fails on seeds outside of the test database
+    pub fn from_seed(seed: u64) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            (seed as usize) < issuer::database::SIZE,
+            "seed {seed} is not in the test database"
+        );
+        let (client_sk, issuer_sk, credential) = Credential::from_seed(seed);
+        let signature = Signature::prove(&issuer_sk, &signature::Context::new(&credential));
+        let (service, nonce) = (bank::service(), bank::nonce());
+        let authentification = Authentification::prove(
+            &client_sk,
+            &authentification::Context::new(&credential.public_key(), &service, &nonce),
+        );
+        let merkle_path = for_tests::DATABASE.proof(&merkle::hash::credential(&credential))?;
+        let public_inputs = inputs::Public::register(
+            today_for_tests().to_field(),
+            ADULT_AGE_DAYS.to_field(),
+            Some(credential.nationality().to_field()),
+            credential.issuer().0.to_field(),
+            nonce.to_field(),
+            service.to_field(),
+            pseudonym::hash_from_service(&service, &credential.public_key()),
+            for_tests::DATABASE.root(),
+        );
+        Ok(Self {
+            issuer_sk,
+            credential,
+            signature,
+            authentification,
+            merkle_path,
+            public_inputs,
+        })
+    }
+
+    /// Full witness of the base circuit, public inputs included
+    pub fn witness(&self, circuit: &Circuit) -> anyhow::Result<Witness> {
+        let mut pw = circuit::witness(
+            &self.credential,
+            &self.signature,
+            &self.authentification,
+            &self.merkle_path,
+            &Extra::default(),
+            &circuit.private_inputs,
+        )?;
+        self.public_inputs.set(&mut pw, &circuit.public_inputs)?;
+        Ok(pw)
+    }
+}
+
+/// One run of every step, in wall-clock time
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Timings {
+    pub profile: CircuitProfile,
+    /// log2 of the number of rows of the circuit
+    pub degree_bits: usize,
+    pub proof_bytes: usize,
+    pub build: Duration,
+    pub witness: Duration,
+    pub prove: Duration,
+    pub verify: Duration,
+    /// Native Schnorr signature of the credential by the issuer
+    pub sign: Duration,
+    pub verify_signature: Duration,
+}
+
+fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let res = f();
+    (res, start.elapsed())
+}
+
+impl Timings {
+    /// Durations in nanoseconds, for tooling comparing runs
+    pub fn to_json(&self) -> Value {
+        json!({
+            "profile": format!("{:?}", self.profile),
+            "degree_bits": self.degree_bits,
+            "proof_bytes": self.proof_bytes,
+            "build_ns": self.build.as_nanos() as u64,
+            "witness_ns": self.witness.as_nanos() as u64,
+            "prove_ns": self.prove.as_nanos() as u64,
+            "verify_ns": self.verify.as_nanos() as u64,
+            "sign_ns": self.sign.as_nanos() as u64,
+            "verify_signature_ns": self.verify_signature.as_nanos() as u64,
+        })
+    }
+}
+
+/// Runs the pipeline once for the credential of seed, proving with the base
+/// circuit built with profile. Fails if any step does.
+pub fn measure(seed: u64, profile: CircuitProfile) -> anyhow::Result<Timings> {
+    let fixture = Fixture::from_seed(seed)?;
+    let ctx = signature::Context::new(&fixture.credential);
+    let (_, sign) = timed(|| Signature::prove(&fixture.issuer_sk, &ctx));
+    let (valid, verify_signature) = timed(|| fixture.signature.verify(&ctx));
+    anyhow::ensure!(valid, "invalid signature of the fixture");
+
+    let (circuit, build) =
+        timed(|| circuit::circuit_with_profile(inputs::Features::default(), profile));
+    let (pw, witness) = timed(|| fixture.witness(&circuit));
+    let pw = pw?;
+    let (proof, prove) = timed(|| pw.prove(&circuit.circuit));
+    let proof = proof?;
+    let proof_bytes = proof.to_bytes().len();
+    let (verified, verify) =
+        timed(|| circuit::verify(&circuit.circuit, proof, fixture.public_inputs));
+    verified?;
+    Ok(Timings {
+        profile,
+        degree_bits: circuit.circuit.common.degree_bits(),
+        proof_bytes,
+        build,
+        witness,
+        prove,
+        verify,
+        sign,
+        verify_signature,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_the_test_profile() {
+        let timings = measure(3, CircuitProfile::Test).unwrap();
+        assert_eq!(timings.profile, CircuitProfile::Test);
+        assert!(timings.proof_bytes > 0);
+        assert!(timings.prove > Duration::ZERO);
+        assert_eq!(timings.to_json()["profile"], "Test");
+        assert!(Fixture::from_seed(issuer::database::SIZE as u64).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+// Age on the calendar rather than in days: the dates are split into year,
+// month & day, constrained by civil_from_days, so that the holder comes of age
+// exactly on their birthday, whatever the number of leap days since their
+// birth. See core::date::is_of_age.
+
+use plonky2::{
+    field::extension::Extendable,
+    hash::hash_types::RichField,
+    iop::target::{BoolTarget, Target},
+    plonk::circuit_builder::CircuitBuilder,
+};
+
+use crate::circuit::mrz::CircuitBuilderMrz;
+
+/// Weights of the year & month in calendar_key: months are at most 12 and
+/// days at most 31
+const YEAR_WEIGHT: u64 = 1 << 9;
+const MONTH_WEIGHT: u64 = 1 << 5;
+
+pub trait CircuitBuilderCalendar<F: RichField + Extendable<D>, const D: usize> {
+    /// year * 2^9 + month * 2^5 + day of a number of days since core::date
+    /// ORIGIN, which orders dates like (year, month, day)
+    fn calendar_key(&mut self, days: Target) -> Target;
+    /// Asserts that someone born on birth_date is at least `years` old on
+    /// `today`, both in days since ORIGIN and below 2^32
+    fn assert_of_age(&mut self, birth_date: Target, today: Target, years: Target);
+    /// Same as assert_of_age, only when condition is true
+    fn assert_of_age_if(
+        &mut self,
+        condition: BoolTarget,
+        birth_date: Target,
+        today: Target,
+        years: Target,
+    );
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderCalendar<F, D>
+    for CircuitBuilder<F, D>
+{
+    fn calendar_key(&mut self, days: Target) -> Target {
+        let (y, m, d) = self.civil_from_days(days);
+        let key = self.mul_const_add(F::from_canonical_u64(MONTH_WEIGHT), m, d);
+        self.mul_const_add(F::from_canonical_u64(YEAR_WEIGHT), y, key)
+    }
+
+    fn assert_of_age(&mut self, birth_date: Target, today: Target, years: Target) {
+        let diff = age_margin(self, birth_date, today, years);
+        self.range_check(diff, 32);
+    }
+
+    fn assert_of_age_if(
+        &mut self,
+        condition: BoolTarget,
+        birth_date: Target,
+        today: Target,
+        years: Target,
+    ) {
+        let diff = age_margin(self, birth_date, today, years);
+        let diff = self.mul(condition.target, diff);
+        self.range_check(diff, 32);
+    }
+}
+
+/// today - birthday on the calendar keys, which wraps around the field when
+/// today is before the birthday
+fn age_margin<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    birth_date: Target,
+    today: Target,
+    years: Target,
+) -> Target {
+    let birth_key = builder.calendar_key(birth_date);
+    let today_key = builder.calendar_key(today);
+    // birthday = (year + years, month, day)
+    let birthday = builder.mul_const_add(F::from_canonical_u64(YEAR_WEIGHT), years, birth_key);
+    builder.range_check(years, 32);
+    builder.sub(today_key, birthday)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use plonky2::{
+        field::types::Field,
+        iop::witness::{PartialWitness, WitnessWrite},
+        plonk::circuit_data::CircuitConfig,
+    };
+
+    use super::*;
+    use crate::{
+        circuit::{C, D, F},
+        core::date::{days_from_origin, is_of_age},
+    };
+
+    fn prove_of_age(birth_date: NaiveDate, today: NaiveDate, years: u32) -> anyhow::Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let [birth_date_t, today_t, years_t] = builder.add_virtual_target_arr();
+        builder.assert_of_age(birth_date_t, today_t, years_t);
+        let mut pw = PartialWitness::new();
+        pw.set_target(
+            birth_date_t,
+            F::from_canonical_u32(days_from_origin(birth_date)),
+        )?;
+        pw.set_target(today_t, F::from_canonical_u32(days_from_origin(today)))?;
+        pw.set_target(years_t, F::from_canonical_u32(years))?;
+        builder.build::<C>().prove(pw).map(|_| ())
+    }
+
+    #[test]
+    fn of_age_matches_native() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        for (birth_date, today) in [
+            (date(2008, 1, 1), date(2026, 1, 1)),
+            (date(2008, 1, 1), date(2025, 12, 31)),
+            (date(2008, 2, 29), date(2026, 2, 28)),
+            (date(2008, 2, 29), date(2026, 3, 1)),
+            (date(2009, 1, 1), date(2027, 1, 1)),
+            (date(1900, 3, 1), date(2100, 2, 28)),
+        ] {
+            assert_eq!(
+                prove_of_age(birth_date, today, 18).is_ok(),
+                is_of_age(birth_date, today, 18),
+                "born on {birth_date}, on {today}"
+            );
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use plonky2::{
+    field::extension::Extendable,
+    hash::hash_types::RichField,
+    iop::{
+        target::{BoolTarget, Target},
+        witness::{Witness, WitnessWrite},
+    },
+    plonk::circuit_builder::CircuitBuilder,
+};
+
+use crate::{
+    circuit::{
+        curve::PointTarget,
+        scalar::ScalarTarget,
+        schnorr::{CircuitBuilderSchnorr, PartialWitnessSchnorr},
+    },
+    encoding::{self, LEN_POINT},
+};
+
+pub type CertificateTarget = encoding::Certificate<Target, BoolTarget>;
+
+pub trait CircuitBuilderCertificate<F: RichField + Extendable<D>, const D: usize> {
+    fn add_virtual_certificate_target(&mut self) -> CertificateTarget;
+    fn hash_certificate(
+        &mut self,
+        certificate: &CertificateTarget,
+        subject: PointTarget,
+        issuer: PointTarget,
+    ) -> ScalarTarget;
+    /// Verifies that `issuer` certified `subject`, for a validity period
+    /// including `today` (in days, see core::date::days_from_origin)
+    fn verify_certificate(
+        &mut self,
+        certificate: &CertificateTarget,
+        subject: PointTarget,
+        issuer: PointTarget,
+        today: Target,
+    );
+}
+
+pub trait PartialWitnessCertificate<F: RichField>: Witness<F> {
+    fn set_certificate_target(
+        &mut self,
+        target: CertificateTarget,
+        value: encoding::Certificate<F, bool>,
+    ) -> anyhow::Result<()>;
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderCertificate<F, D>
+    for CircuitBuilder<F, D>
+{
+    fn add_virtual_certificate_target(&mut self) -> CertificateTarget {
+        encoding::Certificate {
+            not_before: self.add_virtual_target(),
+            not_after: self.add_virtual_target(),
+            signature: self.add_virtual_schnorr_target(),
+        }
+    }
+
+    fn hash_certificate(
+        &mut self,
+        certificate: &CertificateTarget,
+        subject: PointTarget,
+        issuer: PointTarget,
+    ) -> ScalarTarget {
+        let mut message = Vec::with_capacity(2 * LEN_POINT + 2);
+        let subject: [Target; LEN_POINT] = subject.into();
+        message.extend_from_slice(&subject);
+        message.push(certificate.not_before);
+        message.push(certificate.not_after);
+        let issuer: [Target; LEN_POINT] = issuer.into();
+        message.extend_from_slice(&issuer);
+        self.schnorr_hash_with_message(certificate.signature, &message)
+    }
+
+    fn verify_certificate(
+        &mut self,
+        certificate: &CertificateTarget,
+        subject: PointTarget,
+        issuer: PointTarget,
+        today: Target,
+    ) {
+        let e = self.hash_certificate(certificate, subject, issuer);
+        self.schnorr_final_verification(certificate.signature, e, issuer);
+
+        // not_before <= today <= not_after, the three being 32 bits
+        self.range_check(certificate.not_before, 32);
+        self.range_check(certificate.not_after, 32);
+        self.range_check(today, 32);
+        let since = self.sub(today, certificate.not_before);
+        self.range_check(since, 32);
+        let until = self.sub(certificate.not_after, today);
+        self.range_check(until, 32);
+    }
+}
+
+impl<W: Witness<F>, F: RichField> PartialWitnessCertificate<F> for W {
+    fn set_certificate_target(
+        &mut self,
+        target: CertificateTarget,
+        value: encoding::Certificate<F, bool>,
+    ) -> anyhow::Result<()> {
+        self.set_target(target.not_before, value.not_before)?;
+        self.set_target(target.not_after, value.not_after)?;
+        self.set_schnorr_target(target.signature, value.signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use plonky2::{
+        field::{goldilocks_field::GoldilocksField as F, types::Field},
+        iop::witness::PartialWitness,
+        plonk::{circuit_data::CircuitConfig, config::PoseidonGoldilocksConfig},
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        circuit::curve::{CircuitBuilderCurve, PartialWitnessCurve},
+        encoding::conversion::ToPointField,
+        core::date::days_from_origin,
+        issuer::ca::Certificate,
+        schnorr::keys::{PublicKey, SecretKey},
+    };
+
+    const D: usize = 2;
+    type Cfg = PoseidonGoldilocksConfig;
+
+    fn keypair(rng: &mut StdRng) -> (SecretKey, PublicKey) {
+        let sk = SecretKey::random(rng);
+        let pk = PublicKey::from(&sk);
+        (sk, pk)
+    }
+
+    fn prove_certificate(
+        certificate: &Certificate,
+        subject: &PublicKey,
+        root: &PublicKey,
+        today: NaiveDate,
+    ) -> anyhow::Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let certificate_t = builder.add_virtual_certificate_target();
+        let subject_t = builder.add_virtual_point_target();
+        let root_t = builder.add_virtual_point_target();
+        let today_t = builder.add_virtual_target();
+        builder.verify_certificate(&certificate_t, subject_t, root_t, today_t);
+
+        let mut pw = PartialWitness::<F>::new();
+        pw.set_certificate_target(certificate_t, certificate.to_field())?;
+        pw.set_point_target(subject_t, subject.0.to_field())?;
+        pw.set_point_target(root_t, root.0.to_field())?;
+        pw.set_target(today_t, F::from_canonical_u32(days_from_origin(today)))?;
+
+        let data = builder.build::<Cfg>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_verify_certificate_matches_native() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (root_sk, root_pk) = keypair(&mut rng);
+        let (_, subject) = keypair(&mut rng);
+        let certificate = Certificate::issue(
+            &root_sk,
+            &subject,
+            NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2030, 1, 1).unwrap(),
+        );
+        assert!(certificate.verify(&root_pk));
+        let today = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        prove_certificate(&certificate, &subject, &root_pk, today).unwrap();
+    }
+
+    #[test]
+    fn test_verify_certificate_rejects_other_subject() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let (root_sk, root_pk) = keypair(&mut rng);
+        let (_, subject) = keypair(&mut rng);
+        let (_, other_subject) = keypair(&mut rng);
+        let certificate = Certificate::issue(
+            &root_sk,
+            &subject,
+            NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2030, 1, 1).unwrap(),
+        );
+        let today = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        assert!(prove_certificate(&certificate, &other_subject, &root_pk, today).is_err());
+    }
+
+    #[test]
+    fn test_verify_certificate_checks_the_validity_period() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let (root_sk, root_pk) = keypair(&mut rng);
+        let (_, subject) = keypair(&mut rng);
+        let not_before = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let not_after = NaiveDate::from_ymd_opt(2030, 1, 1).unwrap();
+        let certificate = Certificate::issue(&root_sk, &subject, not_before, not_after);
+        let prove_on = |today| prove_certificate(&certificate, &subject, &root_pk, today);
+        prove_on(not_before).unwrap();
+        prove_on(not_after).unwrap();
+        assert!(prove_on(not_before.pred_opt().unwrap()).is_err());
+        assert!(prove_on(not_after.succ_opt().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,69 @@
+use plonky2::{
+    field::extension::Extendable,
+    hash::{hash_types::RichField, poseidon::PoseidonHash},
+    iop::{
+        target::{BoolTarget, Target},
+        witness::Witness,
+    },
+    plonk::circuit_builder::CircuitBuilder,
+};
+
+use crate::{
+    circuit::{
+        credential::{CircuitBuilderCredential, CredentialTarget, PartialWitnessCredential},
+        schnorr::{CircuitBuilderSchnorr, PartialWitnessSchnorr},
+    },
+    encoding::{self, LEN_CREDENTIAL, LEN_HASH, LEN_POINT},
+};
+
+pub type ContinuityTarget = encoding::Continuity<Target, BoolTarget>;
+
+pub trait CircuitBuilderContinuity<F: RichField + Extendable<D>, const D: usize> {
+    fn add_virtual_continuity_target(&mut self) -> ContinuityTarget;
+    /// Verifies that the issuer of successor linked it to the predecessor of
+    /// continuity, see issuer::amendment::ContinuityContext
+    fn verify_continuity(&mut self, continuity: &ContinuityTarget, successor: &CredentialTarget);
+}
+
+pub trait PartialWitnessContinuity<F: RichField>: Witness<F> {
+    fn set_continuity_target(
+        &mut self,
+        target: ContinuityTarget,
+        value: encoding::Continuity<F, bool>,
+    ) -> anyhow::Result<()>;
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderContinuity<F, D>
+    for CircuitBuilder<F, D>
+{
+    fn add_virtual_continuity_target(&mut self) -> ContinuityTarget {
+        encoding::Continuity {
+            predecessor: self.add_virtual_credential_target(),
+            signature: self.add_virtual_schnorr_target(),
+        }
+    }
+
+    fn verify_continuity(&mut self, continuity: &ContinuityTarget, successor: &CredentialTarget) {
+        let mut message = Vec::with_capacity(2 * LEN_HASH + LEN_POINT);
+        for credential in [&continuity.predecessor, successor] {
+            let encoded: [Target; LEN_CREDENTIAL] = credential.into();
+            let hash = self.hash_n_to_hash_no_pad::<PoseidonHash>(encoded.to_vec());
+            message.extend_from_slice(&hash.elements);
+        }
+        let issuer: [Target; LEN_POINT] = successor.issuer.into();
+        message.extend_from_slice(&issuer);
+        let e = self.schnorr_hash_with_message(continuity.signature, &message);
+        self.schnorr_final_verification(continuity.signature, e, successor.issuer);
+    }
+}
+
+impl<W: Witness<F>, F: RichField> PartialWitnessContinuity<F> for W {
+    fn set_continuity_target(
+        &mut self,
+        target: ContinuityTarget,
+        value: encoding::Continuity<F, bool>,
+    ) -> anyhow::Result<()> {
+        self.set_credential_target(target.predecessor, value.predecessor)?;
+        self.set_schnorr_target(target.signature, value.signature)
+    }
+}
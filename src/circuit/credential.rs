@@ -11,6 +11,8 @@ use plonky2::{
 use crate::{
     circuit::{
         curve::{CircuitBuilderCurve, PartialWitnessCurve},
+        hash::{CircuitBuilderHash, PartialWitnessHash},
+        mrz::{CircuitBuilderMrz, PartialWitnessMrz},
         passport_number::{CircuitBuilderPassportNumber, PartialWitnessPassportNumber},
         string::{CircuitBuilderString, PartialWitnessString},
     },
@@ -51,14 +53,25 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderCredential<F, D
 {
     fn add_virtual_credential_target(&mut self) -> CredentialTarget {
         CredentialTarget {
-            first_name: self.add_virtual_string_target(),
-            family_name: self.add_virtual_string_target(),
-            place_of_birth: self.add_virtual_string_target(),
+            given_names: encoding::GivenNames(std::array::from_fn(|_| {
+                self.add_virtual_var_string_target()
+            })),
+            family_name: self.add_virtual_var_string_target(),
+            place_of_birth: self.add_virtual_var_string_target(),
+            document_type: self.add_virtual_target(),
             passport_number: self.add_virtual_passport_number_target(),
+            mrz_check_digits: self.add_virtual_mrz_check_digits_target(),
             birth_date: self.add_virtual_target(),
+            date_of_issue: self.add_virtual_target(),
             expiration_date: self.add_virtual_target(),
             gender: self.add_virtual_bool_target_safe(),
             nationality: self.add_virtual_target(),
+            issuing_authority: encoding::IssuingAuthority {
+                country: self.add_virtual_target(),
+                code: self.add_virtual_target(),
+            },
+            portrait_hash: self.add_virtual_hash_target(),
+            schema_version: self.add_virtual_target(),
             issuer: self.add_virtual_point_target(),
             public_key: self.add_virtual_point_target(),
         }
@@ -72,14 +85,28 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderCredential<F, D
 impl<W: Witness<F>, F: RichField> PartialWitnessCredential<F> for W {
     fn get_credential_target(&self, target: CredentialTarget) -> encoding::Credential<F, bool> {
         encoding::Credential {
-            first_name: self.get_string_target(target.first_name),
-            family_name: self.get_string_target(target.family_name),
-            place_of_birth: self.get_string_target(target.place_of_birth),
+            given_names: encoding::GivenNames(
+                target
+                    .given_names
+                    .0
+                    .map(|name| self.get_var_string_target(name)),
+            ),
+            family_name: self.get_var_string_target(target.family_name),
+            place_of_birth: self.get_var_string_target(target.place_of_birth),
+            document_type: self.get_target(target.document_type),
             passport_number: self.get_passport_number_target(target.passport_number),
+            mrz_check_digits: self.get_mrz_check_digits_target(target.mrz_check_digits),
             birth_date: self.get_target(target.birth_date),
+            date_of_issue: self.get_target(target.date_of_issue),
             expiration_date: self.get_target(target.expiration_date),
             gender: self.get_bool_target(target.gender),
             nationality: self.get_target(target.nationality),
+            issuing_authority: encoding::IssuingAuthority {
+                country: self.get_target(target.issuing_authority.country),
+                code: self.get_target(target.issuing_authority.code),
+            },
+            portrait_hash: PartialWitnessHash::get_hash_target(self, target.portrait_hash),
+            schema_version: self.get_target(target.schema_version),
             issuer: self.get_point_target(target.issuer),
             public_key: self.get_point_target(target.public_key),
         }
@@ -89,14 +116,26 @@ impl<W: Witness<F>, F: RichField> PartialWitnessCredential<F> for W {
         target: CredentialTarget,
         value: encoding::Credential<F, bool>,
     ) -> anyhow::Result<()> {
-        self.set_string_target(target.first_name, value.first_name)?;
-        self.set_string_target(target.family_name, value.family_name)?;
-        self.set_string_target(target.place_of_birth, value.place_of_birth)?;
+        for (name, value) in target.given_names.0.into_iter().zip(value.given_names.0) {
+            self.set_var_string_target(name, value)?;
+        }
+        self.set_var_string_target(target.family_name, value.family_name)?;
+        self.set_var_string_target(target.place_of_birth, value.place_of_birth)?;
+        self.set_target(target.document_type, value.document_type)?;
         self.set_passport_number_target(target.passport_number, value.passport_number)?;
+        self.set_mrz_check_digits_target(target.mrz_check_digits, value.mrz_check_digits)?;
         self.set_target(target.birth_date, value.birth_date)?;
+        self.set_target(target.date_of_issue, value.date_of_issue)?;
         self.set_target(target.expiration_date, value.expiration_date)?;
         self.set_bool_target(target.gender, value.gender)?;
         self.set_target(target.nationality, value.nationality)?;
+        self.set_target(
+            target.issuing_authority.country,
+            value.issuing_authority.country,
+        )?;
+        self.set_target(target.issuing_authority.code, value.issuing_authority.code)?;
+        PartialWitnessHash::set_hash_target(self, target.portrait_hash, value.portrait_hash)?;
+        self.set_target(target.schema_version, value.schema_version)?;
         self.set_point_target(target.issuer, value.issuer)?;
         self.set_point_target(target.public_key, value.public_key)
     }
@@ -105,13 +144,25 @@ impl<W: Witness<F>, F: RichField> PartialWitnessCredential<F> for W {
         target: CredentialTarget,
         value: encoding::Credential<F, bool>,
     ) -> anyhow::Result<()> {
-        self.set_string_target(target.first_name, value.first_name)?;
-        self.set_string_target(target.family_name, value.family_name)?;
-        self.set_string_target(target.place_of_birth, value.place_of_birth)?;
+        for (name, value) in target.given_names.0.into_iter().zip(value.given_names.0) {
+            self.set_var_string_target(name, value)?;
+        }
+        self.set_var_string_target(target.family_name, value.family_name)?;
+        self.set_var_string_target(target.place_of_birth, value.place_of_birth)?;
+        self.set_target(target.document_type, value.document_type)?;
         self.set_passport_number_target(target.passport_number, value.passport_number)?;
+        self.set_mrz_check_digits_target(target.mrz_check_digits, value.mrz_check_digits)?;
         self.set_target(target.birth_date, value.birth_date)?;
+        self.set_target(target.date_of_issue, value.date_of_issue)?;
         self.set_target(target.expiration_date, value.expiration_date)?;
         self.set_bool_target(target.gender, value.gender)?;
+        self.set_target(
+            target.issuing_authority.country,
+            value.issuing_authority.country,
+        )?;
+        self.set_target(target.issuing_authority.code, value.issuing_authority.code)?;
+        PartialWitnessHash::set_hash_target(self, target.portrait_hash, value.portrait_hash)?;
+        self.set_target(target.schema_version, value.schema_version)?;
         self.set_point_target(target.public_key, value.public_key)
     }
 }
@@ -28,6 +28,16 @@ pub trait CircuitBuilderCurve<F: RichField + Extendable<D>, const D: usize> {
         e: ScalarTarget,
         p: PointTarget,
     ) -> PointTarget;
+    /// Precomputes the table of double_scalar_mul_with_table for p, so that
+    /// several multiplications by the same point share it
+    fn shamir_table(&mut self, p: PointTarget) -> Vec<PointTarget>;
+    /// s*G + e*P, with table = shamir_table(P)
+    fn double_scalar_mul_with_table(
+        &mut self,
+        s: ScalarTarget,
+        e: ScalarTarget,
+        table: &[PointTarget],
+    ) -> PointTarget;
     fn neg_point(&mut self, p: PointTarget) -> PointTarget;
     fn is_zero_point(&mut self, p: PointTarget) -> BoolTarget;
     fn assert_non_zero_point(&mut self, p: PointTarget);
@@ -315,7 +325,9 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderCurve<F, D>
     }
 
     // TODO: generator multiplication can be optimized (see mul_gen in arith)
-    /// Shamir trick: compute s*G + e*P in one MSB->LSB loop.
+    /// Shamir trick with 2-bit windows: compute s*G + e*P in one MSB->LSB
+    /// loop, adding one of the precomputed i*G + j*P, 0 <= i, j < 4, every
+    /// two doublings.
     ///
     /// bits are provided as little-endian; we iterate from high to low index.
     fn double_scalar_mul_shamir(
@@ -324,34 +336,72 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderCurve<F, D>
         e: ScalarTarget,
         p: PointTarget,
     ) -> PointTarget {
-        let g = self.generator();
-        let gp = self.add_point(g, p);
-        let zero = self.zero_point();
-
-        let mut acc = zero;
+        let table = self.shamir_table(p);
+        self.double_scalar_mul_with_table(s, e, &table)
+    }
 
-        for i in (0..crate::arith::Scalar::NB_BITS).rev() {
-            // acc = 2*acc
-            acc = self.double_point(acc);
+    fn shamir_table(&mut self, p: PointTarget) -> Vec<PointTarget> {
+        // table[i + 4*j] = i*G + j*P, the multiples of G being constants
+        let mut g_multiples = vec![self.zero_point()];
+        let mut native = Point::GENERATOR;
+        for _ in 1..4 {
+            g_multiples.push(self.constant_point_unsafe(
+                native.X.into(),
+                native.Z.into(),
+                native.U.into(),
+                native.T.into(),
+            ));
+            native = native + Point::GENERATOR;
+        }
+        let p2 = self.double_point(p);
+        let p3 = self.add_point(p2, p);
+        let mut table = g_multiples.clone();
+        for pj in [p, p2, p3] {
+            table.push(pj);
+            for &gi in &g_multiples[1..] {
+                table.push(self.add_point(gi, pj));
+            }
+        }
+        table
+    }
 
-            let sb = s.0[i];
-            let eb = e.0[i];
+    fn double_scalar_mul_with_table(
+        &mut self,
+        s: ScalarTarget,
+        e: ScalarTarget,
+        table: &[PointTarget],
+    ) -> PointTarget {
+        assert_eq!(table.len(), 16, "the table is expected from shamir_table");
+        let nb_bits = crate::arith::Scalar::NB_BITS;
+        let bit = |scalar: &ScalarTarget, i: usize, builder: &mut Self| {
+            if i < nb_bits {
+                scalar.0[i]
+            } else {
+                builder._false()
+            }
+        };
 
-            // term = (sb,eb) ? {00:0, 10:g, 01:p, 11:g+p}
-            let sb_and_eb = self.and(sb, eb);
-            let not_eb = self.not(eb);
-            let not_sb = self.not(sb);
-            let sb_only = self.and(sb, not_eb);
-            let eb_only = self.and(eb, not_sb);
+        let mut acc = self.zero_point();
+        for w in (0..nb_bits.div_ceil(2)).rev() {
+            acc = self.double_point(acc);
+            acc = self.double_point(acc);
 
-            // Select term with mutually exclusive booleans
-            let mut term = zero;
-            term = self.select_point(eb_only, p, term);
-            term = self.select_point(sb_only, g, term);
-            term = self.select_point(sb_and_eb, gp, term);
+            // index bits of the table, least significant first
+            let index = [
+                bit(&s, 2 * w, self),
+                bit(&s, 2 * w + 1, self),
+                bit(&e, 2 * w, self),
+                bit(&e, 2 * w + 1, self),
+            ];
+            let mut term = table.to_vec();
+            for b in index {
+                term = term
+                    .chunks(2)
+                    .map(|pair| self.select_point(b, pair[1], pair[0]))
+                    .collect();
+            }
 
-            // acc += term
-            acc = self.add_point(acc, term);
+            acc = self.add_point(acc, term[0]);
         }
 
         acc
@@ -371,7 +421,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderCurve<F, D>
             t: self.constant_gfp5(t),
         }
     }
-    // Note: this function is not needed for the project, but is implemented for debugging purposes
+    // Note: only used to tie a secret key to its public key (see circuit::nym);
+    // Schnorr verification goes through double_scalar_mul_shamir instead
     fn scalar_mul(
         &mut self,
         base: PointTarget,
@@ -1063,7 +1114,7 @@ pub(crate) mod tests {
         let pk_field = pk_native.to_field();
 
         // Test a handful of scalar pairs.  We limit the range to keep tests fast.
-        let scalar_pairs: &[(u64, u64)] = &[(0, 0), (1, 1), (2, 3), (5, 4), (7, 9)];
+        let scalar_pairs: &[(u64, u64)] = &[(0, 0), (1, 1), (2, 3), (5, 4), (7, 9), (10, 15)];
 
         for &(s_u64, e_u64) in scalar_pairs {
             let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
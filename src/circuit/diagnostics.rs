@@ -0,0 +1,164 @@
+// Native evaluation of the checks of a circuit against the witness, to tell
+// which one a failing proof violates. plonky2 only reports the target whose
+// constraint failed, which says little about the credential.
+
+use plonky2::field::types::{Field, PrimeField64};
+use thiserror::Error;
+
+use crate::{
+    circuit::{inputs, Extra, F},
+    core::{
+        codec::Codec,
+        credential::{Credential, SCHEMA_VERSION},
+        date,
+    },
+    encoding::{conversion::ToPointField, AuthentificationChallengeRaw, MerklePath, LEN_POINT},
+    issuer,
+    merkle::{self, hash},
+    schnorr::{
+        authentification::{Authentification, Context},
+        signature::Signature,
+    },
+};
+
+/// Check of the circuit violated by the witness
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    #[error("the circuit does not accept credentials of this schema version")]
+    SchemaVersion,
+    #[error("the MRZ check digits of the credential don't match its data")]
+    Mrz,
+    #[error("the holder is under the minimal age at the public date")]
+    Majority,
+    #[error("the credential expired before the public current date")]
+    Expired,
+    #[error("the nationality of the credential differs from the public input, or is not allowed")]
+    Nationality,
+    #[error("the issuer of the credential differs from the public input")]
+    Issuer,
+    #[error("the signature of the credential is invalid")]
+    Signature,
+    #[error("the authentification is invalid for the service & nonce")]
+    Authentification,
+    #[error("the pseudonym differs from the one of the service & holder key")]
+    Pseudonym,
+    #[error("the credential is not in the Merkle tree of the public root")]
+    MerkleProof,
+}
+
+/// First check of the circuit violated by the witness, in the order they are
+/// registered, or None if the failure comes from an optional feature
+pub fn diagnose(
+    credential: &Credential,
+    signature: &Signature,
+    authentification: &Authentification,
+    merkle_path: &MerklePath<{ issuer::database::SIZE }, F, bool>,
+    extra: &Extra,
+    public_inputs: &inputs::Public<F>,
+) -> Option<Failure> {
+    let encoded = credential.to_field::<F>();
+    // predicates of universal circuits only hold when their flag is set
+    let active = |flag: fn(&inputs::Flags<F>) -> F| {
+        public_inputs
+            .flags
+            .as_ref()
+            .is_none_or(|flags| flag(flags) == F::ONE)
+    };
+
+    if credential.schema_version() != SCHEMA_VERSION {
+        return Some(Failure::SchemaVersion);
+    }
+    if !credential.check_mrz() {
+        return Some(Failure::Mrz);
+    }
+    // check_majority range checks today, min age and today - min age - birth
+    // date on 32 bits
+    let birth_date = encoded.birth_date.to_canonical_u64();
+    let today = public_inputs.today_days.to_canonical_u64();
+    let min_age = public_inputs.min_age_days.to_canonical_u64();
+    let in_range = |x: u64| x < 1 << 32;
+    if !(in_range(today) && in_range(min_age) && in_range(birth_date))
+        || (active(|flags| flags.majority) && birth_date + min_age > today)
+    {
+        return Some(Failure::Majority);
+    }
+    if let Some(years) = public_inputs
+        .min_age_years
+        .filter(|_| active(|flags| flags.calendar_age))
+    {
+        let today = date::date_from_field(public_inputs.today_days);
+        let years = u32::try_from(years.to_canonical_u64());
+        let of_age = match (today, years) {
+            (Ok(today), Ok(years)) => date::is_of_age(*credential.birth_date(), today, years),
+            _ => false,
+        };
+        if !of_age {
+            return Some(Failure::Majority);
+        }
+    }
+    // check_not_expired range checks both dates and their difference
+    if let Some(current_date) = public_inputs.current_date_days {
+        // only the difference is gated by the flag
+        let current_date = current_date.to_canonical_u64();
+        let expiration_date = encoded.expiration_date.to_canonical_u64();
+        if !(in_range(current_date) && in_range(expiration_date))
+            || (active(|flags| flags.expiration) && expiration_date < current_date)
+        {
+            return Some(Failure::Expired);
+        }
+    }
+    if active(|flags| flags.nationality)
+        && public_inputs
+            .nationality
+            .is_some_and(|nationality| encoded.nationality != nationality)
+    {
+        return Some(Failure::Nationality);
+    }
+    if active(|flags| flags.nationality_allow_set)
+        && extra
+            .allowed_nationalities
+            .is_some_and(|set| !set.contains(credential.nationality().code()))
+    {
+        return Some(Failure::Nationality);
+    }
+    // with a certificate, the public key is the root certifying the issuer,
+    // and with a set it is the neutral point
+    if let Some(set) = extra.issuer_set {
+        if !set.contains(&credential.issuer()) {
+            return Some(Failure::Issuer);
+        }
+    } else if extra.certificate.is_none() && encoded.issuer != public_inputs.issuer_pk {
+        return Some(Failure::Issuer);
+    }
+    if !credential.check(signature) {
+        return Some(Failure::Signature);
+    }
+    let ctx = Context::from_encoded(
+        &credential.public_key(),
+        AuthentificationChallengeRaw {
+            service: public_inputs.service,
+            nonce: public_inputs.nonce,
+        },
+    )
+    // signed along with the challenge in circuits binding an account
+    .with_encoded_account(public_inputs.account);
+    // derived keys authentify instead of the holder key, see check_derived_key
+    if public_inputs.derived_key.is_none() && !authentification.verify(&ctx) {
+        return Some(Failure::Authentification);
+    }
+    let mut message = Vec::with_capacity(public_inputs.service.0.len() + LEN_POINT);
+    message.extend_from_slice(&public_inputs.service.0);
+    let public_key: [F; LEN_POINT] = credential.public_key().0.to_field().into();
+    message.extend_from_slice(&public_key);
+    if hash::poseidon(&message) != public_inputs.pseudonym {
+        return Some(Failure::Pseudonym);
+    }
+    if !merkle::Tree::<{ issuer::database::SIZE }, F>::verify(
+        public_inputs.merkle_root,
+        credential,
+        *merkle_path,
+    ) {
+        return Some(Failure::MerkleProof);
+    }
+    None
+}
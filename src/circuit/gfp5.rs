@@ -1,11 +1,13 @@
 use plonky2::{
-    field::extension::Extendable,
+    field::{extension::Extendable, types::Field},
     hash::hash_types::RichField,
     iop::{
+        generator::{GeneratedValues, SimpleGenerator},
         target::{BoolTarget, Target},
-        witness::Witness,
+        witness::{PartitionWitness, Witness, WitnessWrite},
     },
-    plonk::circuit_builder::CircuitBuilder,
+    plonk::{circuit_builder::CircuitBuilder, circuit_data::CommonCircuitData},
+    util::serialization::{Buffer, IoError, IoResult, Read, Write},
 };
 
 use crate::encoding::{self, LEN_FIELD};
@@ -62,6 +64,58 @@ pub trait PartialWitnessGFp5<F: RichField>: Witness<F> {
     }
 }
 
+/// Witnesses a pseudo-inverse w of a, such that <a, w> = 1 if a != 0: the
+/// inverse of its first nonzero limb, and zeros elsewhere
+#[derive(Debug)]
+struct PseudoInverseGenerator {
+    a: GFp5Target,
+    inverse: GFp5Target,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D>
+    for PseudoInverseGenerator
+{
+    fn id(&self) -> String {
+        "PseudoInverseGenerator".to_string()
+    }
+    fn dependencies(&self) -> Vec<Target> {
+        self.a.0.to_vec()
+    }
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> anyhow::Result<()> {
+        let mut found = false;
+        for (&a, &inverse) in self.a.0.iter().zip(self.inverse.0.iter()) {
+            let value = match witness.get_target(a).try_inverse() {
+                Some(inv) if !found => {
+                    found = true;
+                    inv
+                }
+                _ => F::ZERO,
+            };
+            out_buffer.set_target(inverse, value)?;
+        }
+        Ok(())
+    }
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target_vec(&self.a.0)?;
+        dst.write_target_vec(&self.inverse.0)
+    }
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        let mut read = || -> IoResult<GFp5Target> {
+            let limbs: [Target; LEN_FIELD] =
+                src.read_target_vec()?.try_into().map_err(|_| IoError)?;
+            Ok(limbs.into())
+        };
+        Ok(Self {
+            a: read()?,
+            inverse: read()?,
+        })
+    }
+}
+
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderGFp5<F, D>
     for CircuitBuilder<F, D>
 {
@@ -83,16 +137,22 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderGFp5<F, D>
     }
 
     fn is_zero_gfp5(&mut self, a: GFp5Target) -> BoolTarget {
+        let inverse = self.add_virtual_gfp5_target();
+        self.add_simple_generator(PseudoInverseGenerator { a, inverse });
+        // is_zero = 1 - <a, inverse> is 0 if a != 0, as is_zero * a = 0, and 1
+        // otherwise
+        let mut dot = self.zero();
+        for (&a, &inverse) in a.0.iter().zip(inverse.0.iter()) {
+            dot = self.mul_add(a, inverse, dot);
+        }
+        let one = self.one();
+        let is_zero = self.sub(one, dot);
         let zero = self.zero();
-        let terms = vec![
-            self.is_equal(a.0[0], zero).target,
-            self.is_equal(a.0[1], zero).target,
-            self.is_equal(a.0[2], zero).target,
-            self.is_equal(a.0[3], zero).target,
-            self.is_equal(a.0[4], zero).target,
-        ];
-        let prod = self.mul_many(terms);
-        BoolTarget::new_unsafe(prod)
+        for limb in a.0 {
+            let prod = self.mul(is_zero, limb);
+            self.connect(prod, zero);
+        }
+        BoolTarget::new_unsafe(is_zero)
     }
 
     fn one_gfp5(&mut self) -> GFp5Target {
@@ -117,15 +177,8 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderGFp5<F, D>
         .into()
     }
     fn is_equal_gfp5(&mut self, a: GFp5Target, b: GFp5Target) -> BoolTarget {
-        let terms = vec![
-            self.is_equal(a.0[0], b.0[0]).target,
-            self.is_equal(a.0[1], b.0[1]).target,
-            self.is_equal(a.0[2], b.0[2]).target,
-            self.is_equal(a.0[3], b.0[3]).target,
-            self.is_equal(a.0[4], b.0[4]).target,
-        ];
-        let prod = self.mul_many(terms);
-        BoolTarget::new_unsafe(prod)
+        let diff = self.sub_gfp5(a, b);
+        self.is_zero_gfp5(diff)
     }
     fn neg_gfp5(&mut self, a: GFp5Target) -> GFp5Target {
         [
@@ -583,5 +636,23 @@ mod tests {
             assert_eq!(pis[1], F::ZERO); // a != 0
             assert_eq!(&pis[2..7], &b); // selected b
         }
+
+        // Case 3: a and b differ in the last limb only, a nonzero in it only
+        {
+            let a = [F::ZERO, F::ZERO, F::ZERO, F::ZERO, f(7)];
+            let mut b = a;
+            b[4] = f(8);
+            let mut pw = PartialWitness::<F>::new();
+            pw.set_gfp5_target(a_t, a.into()).unwrap();
+            pw.set_gfp5_target(b_t, b.into()).unwrap();
+            pw.set_bool_target(c_bool, true).unwrap();
+
+            let proof = data.prove(pw).unwrap();
+            data.verify(proof.clone()).unwrap();
+            let pis = proof.public_inputs;
+
+            assert_eq!(pis[0], F::ZERO); // a != b
+            assert_eq!(pis[1], F::ZERO); // a != 0
+        }
     }
 }
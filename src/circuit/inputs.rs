@@ -1,5 +1,6 @@
+use chrono::NaiveDate;
 use plonky2::{
-    field::extension::Extendable,
+    field::{extension::Extendable, types::Field},
     hash::hash_types::RichField,
     iop::{
         target::{BoolTarget, Target},
@@ -7,86 +8,845 @@ use plonky2::{
     },
     plonk::circuit_builder::CircuitBuilder,
 };
+use thiserror::Error;
 
 use crate::{
+    arith::Point,
     bank,
     circuit::{
         authentification::{CircuitBuilderAuthentification, PartialWitnessAuthentification},
+        certificate::{CircuitBuilderCertificate, PartialWitnessCertificate},
+        continuity::{CircuitBuilderContinuity, PartialWitnessContinuity},
         credential::{CircuitBuilderCredential, PartialWitnessCredential},
-        curve::PartialWitnessCurve,
+        curve::{CircuitBuilderCurve, PartialWitnessCurve},
         hash::{CircuitBuilderHash, PartialWitnessHash},
         merkle::{CircuitBuilderMerkleProof, PartialWitnessMerkleProof},
+        revocation::{CircuitBuilderRevocation, PartialWitnessRevocation},
+        scalar::{CircuitBuilderScalar, PartialWitnessScalar},
+        set::{CircuitBuilderSet, PartialWitnessSet},
         signature::{CircuitBuilderSignature, PartialWitnessSignature},
         string::{CircuitBuilderString, PartialWitnessString},
+        timestamp::{CircuitBuilderTimeToken, PartialWitnessTimeToken},
+    },
+    core::{
+        credential::Nationality,
+        date::{days_from_origin, today, today_for_tests, ADULT_AGE_DAYS},
+        set::{CountrySet, IssuerSet},
     },
-    core::{credential::Nationality, date::cutoff18_from_today_for_tests},
     encoding::{
         self,
         conversion::{ToPointField, ToSingleField, ToStringField},
-        LEN_HASH, LEN_POINT, LEN_PSEUDONYM, LEN_STRING,
+        LEN_HASH, LEN_POINT,
     },
-    issuer, merkle,
+    issuer, merkle, revocation,
     schnorr::keys::PublicKey,
 };
 
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Expected {expected} public inputs, got {got}")]
+    Length { expected: usize, got: usize },
+    #[error("Public inputs mismatch for {0}")]
+    Mismatch(&'static str),
+    #[error("Missing public input {0}")]
+    Missing(&'static str),
+    #[error("Invalid public input {0}")]
+    Invalid(&'static str),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
 pub struct Public<T> {
-    pub(crate) cutoff18_days: T,
-    pub(crate) nationality: T,
+    /// Days since origin, see date::today, or a past reference day, see
+    /// date::reference_day
+    pub(crate) today_days: T,
+    /// Minimal age of the holder at today_days, in days, e.g.
+    /// date::ADULT_AGE_DAYS
+    pub(crate) min_age_days: T,
+    /// None in circuits proving that the nationality is outside or inside of
+    /// a set, unless they have activation flags
+    pub(crate) nationality: Option<T>,
     pub(crate) issuer_pk: encoding::Point<T>,
     pub(crate) nonce: encoding::String<T>,
     pub(crate) service: encoding::String<T>,
     pub(crate) pseudonym: encoding::Pseudonym<T>,
     pub(crate) merkle_root: encoding::Hash<T>,
+    /// Only in circuits exposing a domain-bound nym, registered after the root
+    pub(crate) nym: Option<encoding::Pseudonym<T>>,
+    /// Only in circuits of k-show credentials, registered after the nym
+    pub(crate) rate_limit: Option<RateLimit<T>>,
+    /// First accepted date of issue, in days since origin (see
+    /// date::issued_after_from_today). Only in circuits requiring recently
+    /// issued credentials, registered after the rate limit
+    pub(crate) issued_after: Option<T>,
+    /// Country of the issuing authority, only in circuits disclosing it,
+    /// registered after issued_after
+    pub(crate) authority_country: Option<T>,
+    /// Key of the time authority signing today_days, only in circuits
+    /// taking the date from a time token, registered after the country
+    pub(crate) time_authority: Option<encoding::Point<T>>,
+    /// Only in circuits of consortium members, registered after the key
+    pub(crate) consortium: Option<Consortium<T>>,
+    /// Commitment of the excluded nationalities (see core::set), only in
+    /// circuits excluding nationalities, registered after the consortium
+    pub(crate) excluded_nationalities: Option<encoding::Hash<T>>,
+    /// Hash of the passport photo (see core::credential::PortraitHash), only
+    /// in circuits disclosing it, registered after the excluded nationalities
+    pub(crate) portrait_hash: Option<encoding::Hash<T>>,
+    /// Only in circuits exposing a credential ID, registered after the
+    /// portrait hash
+    pub(crate) credential_id: Option<CredentialId<T>>,
+    /// External account the holder signs with their authentification key,
+    /// only in circuits binding the proof to one, registered after the
+    /// credential ID
+    pub(crate) account: Option<encoding::Account<T>>,
+    /// Key of the authentification, derived from the holder key for the
+    /// service (see client::keys::derive), only in circuits with derived
+    /// keys, registered after the account
+    pub(crate) derived_key: Option<encoding::Point<T>>,
+    /// Hash of the family name supplied by the verifier (see
+    /// issuer::pseudonym::name_hash), only in circuits proving the name
+    /// matches it, registered after the derived key
+    pub(crate) name_hash: Option<encoding::Hash<T>>,
+    /// Only in circuits committing to the travel document, registered after
+    /// the name hash
+    pub(crate) document_commitment: Option<DocumentCommitment<T>>,
+    /// Credential ID of the predecessor of the credential, with the salt of
+    /// credential_id, only in circuits proving continuity, registered after
+    /// the document commitment
+    pub(crate) previous_id: Option<encoding::Hash<T>>,
+    /// Commitment of the approved issuers (see core::set::IssuerSet), only in
+    /// circuits hiding the issuer among them, whose issuer_pk is the neutral
+    /// point. Registered after the previous credential ID
+    pub(crate) issuer_set: Option<encoding::Hash<T>>,
+    /// Minimal age of the holder at today_days, in years on the calendar, only
+    /// in circuits with the calendar age, registered after the issuer set
+    pub(crate) min_age_years: Option<T>,
+    /// Commitment of the allowed nationalities (see core::set), only in
+    /// circuits with a nationality allow-set, registered after min_age_years
+    pub(crate) allowed_nationalities: Option<encoding::Hash<T>>,
+    /// Root of the tree of revoked credentials (see revocation), only in
+    /// circuits proving non-revocation, registered after the allowed
+    /// nationalities
+    pub(crate) revocation_root: Option<encoding::Hash<T>>,
+    /// Nullifier of the credential for the service (see
+    /// issuer::pseudonym::credential_nullifier), only in circuits exposing
+    /// it, registered after the revocation root
+    pub(crate) credential_nullifier: Option<encoding::Hash<T>>,
+    /// Day of the verification, in days since origin, which the credential
+    /// must not expire before. Unlike today_days, never a past reference
+    /// day. Only in circuits checking the expiration, registered after the
+    /// credential nullifier
+    pub(crate) current_date_days: Option<T>,
+    /// Only in universal circuits, registered last
+    pub(crate) flags: Option<Flags<T>>,
+}
+
+impl<T: Copy> Public<T> {
+    /// Public inputs of the statement of register, without any of the
+    /// optional features, which callers set with struct update syntax
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn register(
+        today_days: T,
+        min_age_days: T,
+        nationality: Option<T>,
+        issuer_pk: encoding::Point<T>,
+        nonce: encoding::String<T>,
+        service: encoding::String<T>,
+        pseudonym: encoding::Pseudonym<T>,
+        merkle_root: encoding::Hash<T>,
+    ) -> Self {
+        Self {
+            today_days,
+            min_age_days,
+            nationality,
+            issuer_pk,
+            nonce,
+            service,
+            pseudonym,
+            merkle_root,
+            nym: None,
+            rate_limit: None,
+            issued_after: None,
+            authority_country: None,
+            time_authority: None,
+            consortium: None,
+            excluded_nationalities: None,
+            portrait_hash: None,
+            credential_id: None,
+            account: None,
+            derived_key: None,
+            name_hash: None,
+            document_commitment: None,
+            previous_id: None,
+            issuer_set: None,
+            min_age_years: None,
+            allowed_nationalities: None,
+            revocation_root: None,
+            credential_nullifier: None,
+            current_date_days: None,
+            flags: None,
+        }
+    }
+
+    pub fn today_days(&self) -> T {
+        self.today_days
+    }
+    pub fn min_age_days(&self) -> T {
+        self.min_age_days
+    }
+    pub fn nationality(&self) -> Option<T> {
+        self.nationality
+    }
+    pub fn issuer_pk(&self) -> encoding::Point<T> {
+        self.issuer_pk
+    }
+    pub fn nonce(&self) -> encoding::String<T> {
+        self.nonce
+    }
+    pub fn service(&self) -> encoding::String<T> {
+        self.service
+    }
+    pub fn pseudonym(&self) -> encoding::Pseudonym<T> {
+        self.pseudonym
+    }
+    pub fn merkle_root(&self) -> encoding::Hash<T> {
+        self.merkle_root
+    }
+
+    /// Public inputs in registration order, see register_with
+    pub(crate) fn fields(&self) -> Vec<(&'static str, Vec<T>)> {
+        let mut fields = vec![];
+        if let Some(nationality) = self.nationality {
+            fields.push(("nationality", vec![nationality]));
+        }
+        let issuer_pk: [T; LEN_POINT] = self.issuer_pk.into();
+        fields.extend([
+            ("issuer_pk", issuer_pk.to_vec()),
+            ("today_days", vec![self.today_days]),
+            ("min_age_days", vec![self.min_age_days]),
+            ("nonce", self.nonce.0.to_vec()),
+            ("service", self.service.0.to_vec()),
+            ("pseudonym", self.pseudonym.0.to_vec()),
+            ("Merkle root", self.merkle_root.0.to_vec()),
+        ]);
+        if let Some(nym) = self.nym {
+            fields.push(("nym", nym.0.to_vec()));
+        }
+        if let Some(rate_limit) = &self.rate_limit {
+            fields.push(("epoch", vec![rate_limit.epoch]));
+            fields.push(("nullifier", rate_limit.nullifier.0.to_vec()));
+        }
+        if let Some(issued_after) = self.issued_after {
+            fields.push(("issued_after", vec![issued_after]));
+        }
+        if let Some(authority_country) = self.authority_country {
+            fields.push(("authority_country", vec![authority_country]));
+        }
+        if let Some(time_authority) = self.time_authority {
+            let time_authority: [T; LEN_POINT] = time_authority.into();
+            fields.push(("time_authority", time_authority.to_vec()));
+        }
+        if let Some(consortium) = &self.consortium {
+            fields.push(("scope", consortium.scope.0.to_vec()));
+            fields.push(("consortium nullifier", consortium.nullifier.0.to_vec()));
+        }
+        if let Some(excluded) = self.excluded_nationalities {
+            fields.push(("excluded nationalities", excluded.0.to_vec()));
+        }
+        if let Some(portrait_hash) = self.portrait_hash {
+            fields.push(("portrait_hash", portrait_hash.0.to_vec()));
+        }
+        if let Some(credential_id) = &self.credential_id {
+            fields.push(("salt", credential_id.salt.0.to_vec()));
+            fields.push(("credential ID", credential_id.id.0.to_vec()));
+        }
+        if let Some(account) = self.account {
+            let mut value = account.value.0.to_vec();
+            value.push(account.len);
+            fields.push(("account", value));
+        }
+        if let Some(derived_key) = self.derived_key {
+            let derived_key: [T; LEN_POINT] = derived_key.into();
+            fields.push(("derived_key", derived_key.to_vec()));
+        }
+        if let Some(name_hash) = self.name_hash {
+            fields.push(("name_hash", name_hash.0.to_vec()));
+        }
+        if let Some(commitment) = &self.document_commitment {
+            fields.push(("epoch salt", commitment.salt.0.to_vec()));
+            fields.push(("document commitment", commitment.commitment.0.to_vec()));
+        }
+        if let Some(previous_id) = self.previous_id {
+            fields.push(("previous credential ID", previous_id.0.to_vec()));
+        }
+        if let Some(issuer_set) = self.issuer_set {
+            fields.push(("issuer set", issuer_set.0.to_vec()));
+        }
+        if let Some(min_age_years) = self.min_age_years {
+            fields.push(("min_age_years", vec![min_age_years]));
+        }
+        if let Some(allowed) = self.allowed_nationalities {
+            fields.push(("allowed nationalities", allowed.0.to_vec()));
+        }
+        if let Some(root) = self.revocation_root {
+            fields.push(("revocation root", root.0.to_vec()));
+        }
+        if let Some(nullifier) = self.credential_nullifier {
+            fields.push(("credential nullifier", nullifier.0.to_vec()));
+        }
+        if let Some(current_date_days) = self.current_date_days {
+            fields.push(("current_date_days", vec![current_date_days]));
+        }
+        if let Some(flags) = self.flags {
+            fields.push(("activation flags", flags.to_array().to_vec()));
+        }
+        fields
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct RateLimit<T> {
+    pub(crate) epoch: T,
+    pub(crate) nullifier: encoding::Hash<T>,
+}
+
+/// See bank::consortium
+#[derive(Clone, Copy)]
+pub struct Consortium<T> {
+    pub(crate) scope: encoding::String<T>,
+    pub(crate) nullifier: encoding::Hash<T>,
+}
+
+/// See issuer::pseudonym::credential_id
+pub struct CredentialId<T> {
+    pub(crate) salt: encoding::Hash<T>,
+    pub(crate) id: encoding::Hash<T>,
+}
+
+/// See issuer::pseudonym::document_commitment
+pub struct DocumentCommitment<T> {
+    /// Salt of the issuer for the current epoch
+    pub(crate) salt: encoding::Hash<T>,
+    pub(crate) commitment: encoding::Hash<T>,
+}
+
+/// Predicates a proof of a universal circuit enforces, see
+/// Features::activation_flags. The public inputs of inactive predicates are
+/// left unchecked
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Flags<T> {
+    pub nationality: T,
+    pub majority: T,
+    pub recent_issuance: T,
+    pub authority_country: T,
+    pub nationality_exclusion: T,
+    pub calendar_age: T,
+    pub nationality_allow_set: T,
+    pub expiration: T,
+}
+
+const LEN_FLAGS: usize = 8;
+
+impl<T: Copy> Flags<T> {
+    fn to_array(self) -> [T; LEN_FLAGS] {
+        [
+            self.nationality,
+            self.majority,
+            self.recent_issuance,
+            self.authority_country,
+            self.nationality_exclusion,
+            self.calendar_age,
+            self.nationality_allow_set,
+            self.expiration,
+        ]
+    }
+}
+
+impl Flags<bool> {
+    pub fn to_field<F: Field>(self) -> Flags<F> {
+        Flags {
+            nationality: F::from_bool(self.nationality),
+            majority: F::from_bool(self.majority),
+            recent_issuance: F::from_bool(self.recent_issuance),
+            authority_country: F::from_bool(self.authority_country),
+            nationality_exclusion: F::from_bool(self.nationality_exclusion),
+            calendar_age: F::from_bool(self.calendar_age),
+            nationality_allow_set: F::from_bool(self.nationality_allow_set),
+            expiration: F::from_bool(self.expiration),
+        }
+    }
 }
 pub struct Private<T, TBool> {
     pub(crate) credential: encoding::Credential<T, TBool>,
-    pub(crate) signature: encoding::Signature<T, TBool>,
+    /// Decomposed into bits when the signature is checked
+    pub(crate) signature: encoding::SignatureLimbs<T>,
     pub(crate) authentification: encoding::Authentification<T, TBool>,
     pub(crate) merkle_path: encoding::MerklePath<{ issuer::database::SIZE }, T, TBool>,
+    /// Only in circuits where the issuer is certified by a public root key
+    pub(crate) certificate: Option<encoding::Certificate<T, TBool>>,
+    /// Only in circuits exposing a domain-bound nym, a nullifier or a
+    /// derived key, or a credential or consortium nullifier
+    pub(crate) holder_secret: Option<encoding::Scalar<TBool>>,
+    /// Number of the presentation in the epoch, only in circuits of k-show
+    /// credentials
+    pub(crate) counter: Option<T>,
+    /// Only in circuits taking the date from a time token
+    pub(crate) time_token: Option<encoding::TimeToken<T, TBool>>,
+    /// Opening of public.excluded_nationalities
+    pub(crate) excluded_nationalities: Option<encoding::CountrySet<T>>,
+    /// Salt of public.name_hash, which the verifier only sends to the holder
+    pub(crate) name_salt: Option<encoding::Hash<T>>,
+    /// Only in circuits proving continuity, see issuer::amendment
+    pub(crate) continuity: Option<encoding::Continuity<T, TBool>>,
+    /// Opening of public.issuer_set
+    pub(crate) issuer_set: Option<encoding::IssuerSet<T>>,
+    /// Opening of public.allowed_nationalities
+    pub(crate) allowed_nationalities: Option<encoding::CountrySet<T>>,
+    /// Opening of the empty leaf of the credential under
+    /// public.revocation_root
+    pub(crate) non_revocation: Option<encoding::NonRevocationPath<T>>,
+}
+
+/// Optional statements of the circuit, which change its inputs
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Features {
+    /// The issuer of the credential stays private: a root key, which
+    /// certifies it, takes its place in the public inputs
+    pub certified_issuer: bool,
+    /// Exposes nym = Hash(holder secret key, service), which is stable for a
+    /// service but unlinkable across services
+    pub domain_nym: bool,
+    /// Exposes nullifier = Hash(holder secret key, epoch, counter) with
+    /// counter < k, so that the credential can't be shown more than k times
+    /// per epoch
+    pub k_show: Option<u32>,
+    /// Exposes issued_after, and proves that the credential was issued on or
+    /// after it, e.g. for high-risk flows
+    pub recent_issuance: bool,
+    /// Exposes the country of the authority which issued the passport, e.g.
+    /// "issued by France" when the signing key is an intermediate
+    pub authority_country: bool,
+    /// Exposes the key of a time authority, whose signature of today_days
+    /// for the nonce is checked, see issuer::timestamp
+    pub time_authority: bool,
+    /// Exposes nullifier = Hash(holder secret, scope, names, birth date,
+    /// nationality), so that a consortium of banks can enforce one credential
+    /// per person in the scope, see bank::consortium
+    pub consortium: bool,
+    /// Hides the nationality, and proves that it is outside of the set
+    /// committed to by the public inputs, e.g. embargoed countries
+    pub nationality_exclusion: bool,
+    /// Exposes the hash of the passport photo, so that a verifier meeting
+    /// the holder can check it against the face presented, without the
+    /// issuer handing out the image
+    pub portrait_hash: bool,
+    /// Exposes id = Hash(credential, salt of the issuer), which is the same
+    /// at every presentation of the credential, e.g. to bind it to an
+    /// account again. Unlinkable flows leave it out
+    pub credential_id: bool,
+    /// Exposes an external account, e.g. an Ethereum address or a customer
+    /// ID, which the authentification signs, so that the proof is bound to
+    /// exactly one downstream account
+    pub account_binding: bool,
+    /// Authentifies with a key derived from the holder key for the service,
+    /// and exposes it, so that services never check the same key
+    pub derived_key: bool,
+    /// Exposes a salted hash of a family name, e.g. the one a customer
+    /// declared, and proves that it is the one of the credential, without
+    /// disclosing the name
+    pub name_hash: bool,
+    /// Exposes commitment = Hash(document number, salt of the issuer for the
+    /// epoch), so that a relying party can detect accounts opened with the
+    /// same document, without learning its number
+    pub document_commitment: bool,
+    /// Proves that the credential amends a predecessor (see
+    /// issuer::amendment), and exposes the credential ID of the predecessor,
+    /// so that a service which bound an account to it recognizes the holder.
+    /// Requires credential_id
+    pub continuity: bool,
+    /// The issuer of the credential stays private: the commitment of a set
+    /// of approved issuers takes its place in the public inputs, and the
+    /// issuer is proved to be one of them. Exclusive with certified_issuer
+    pub issuer_set: bool,
+    /// Exposes min_age_years, and proves the age on the calendar: the holder
+    /// comes of age on their birthday, however many leap days min_age_days
+    /// would have to count
+    pub calendar_age: bool,
+    /// Hides the nationality, and proves that it is in the set committed to
+    /// by the public inputs, e.g. the member states of the EU
+    pub nationality_allow_set: bool,
+    /// Exposes the root of the tree of revoked credentials, and proves that
+    /// the credential is not in it, so that verifiers needn't wait for the
+    /// issuer to re-root the tree of valid credentials
+    pub non_revocation: bool,
+    /// Exposes nullifier = Hash(holder secret key, credential, service), the
+    /// same at every presentation of the credential to the service, so that
+    /// it detects the credential being presented twice without learning
+    /// which one it is
+    pub credential_nullifier: bool,
+    /// Exposes current_date_days, and proves that the credential expires on
+    /// or after it, so that proofs can't be built from expired passports
+    pub expiration: bool,
+    /// Exposes one public flag per predicate of Features::universal, which
+    /// only holds when its flag is set, so that one circuit serves every
+    /// combination of them
+    pub activation_flags: bool,
 }
 
-pub const LEN_PUBLIC_INPUTS: usize = 1 + 1 + LEN_POINT + LEN_STRING * 2 + LEN_PSEUDONYM + LEN_HASH;
+impl Features {
+    /// Every predicate that activation flags can gate
+    pub const fn universal() -> Self {
+        Self {
+            certified_issuer: false,
+            domain_nym: false,
+            k_show: None,
+            recent_issuance: true,
+            authority_country: true,
+            time_authority: false,
+            consortium: false,
+            nationality_exclusion: true,
+            portrait_hash: false,
+            credential_id: false,
+            account_binding: false,
+            derived_key: false,
+            name_hash: false,
+            document_commitment: false,
+            continuity: false,
+            issuer_set: false,
+            calendar_age: true,
+            nationality_allow_set: true,
+            non_revocation: false,
+            credential_nullifier: false,
+            expiration: true,
+            activation_flags: true,
+        }
+    }
+}
 
 /// Registers credential and signature, and registers nationality, issuer,
 /// nonce, service & root as public inputs
 pub fn register<F: RichField + Extendable<D>, const D: usize>(
     builder: &mut CircuitBuilder<F, D>,
+) -> (Public<Target>, Private<Target, BoolTarget>) {
+    register_with(builder, Features::default())
+}
+
+/// Same as register, but the issuer of the credential stays private: a root
+/// key, which certifies it, takes its place in the public inputs
+pub fn register_certified<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+) -> (Public<Target>, Private<Target, BoolTarget>) {
+    register_with(
+        builder,
+        Features {
+            certified_issuer: true,
+            ..Features::default()
+        },
+    )
+}
+
+/// Same as register, with the inputs of the optional features
+pub fn register_with<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    features: Features,
 ) -> (Public<Target>, Private<Target, BoolTarget>) {
     let credential = builder.add_virtual_credential_target();
-    let signature = builder.add_virtual_signature_target();
+    let signature = builder.add_virtual_signature_limbs_target();
     let authentification = builder.add_virtual_authentification_target();
     let merkle_path = builder.add_virtual_merkle_proof_target();
-    let cutoff18_days = builder.add_virtual_target();
+    let today_days = builder.add_virtual_target();
+    let min_age_days = builder.add_virtual_target();
     let nonce = builder.add_virtual_string_target();
     let service = builder.add_virtual_string_target();
     let pseudonym = builder.add_virtual_hash_target();
     let merkle_root = builder.add_virtual_hash_target();
 
-    builder.register_credential_public_input(credential);
-    builder.register_public_input(cutoff18_days);
+    assert!(
+        !features.activation_flags
+            || (features.recent_issuance
+                && features.authority_country
+                && features.nationality_exclusion
+                && features.calendar_age
+                && features.nationality_allow_set
+                && features.expiration),
+        "activation flags gate the predicates of Features::universal"
+    );
+    assert!(
+        !features.continuity || features.credential_id,
+        "continuity exposes the ID of the predecessor with the salt of credential_id"
+    );
+    assert!(
+        !(features.certified_issuer && features.issuer_set),
+        "the issuer is either certified or in a set"
+    );
+    let nationality = if features.activation_flags {
+        // compared to the credential when its flag is set
+        let nationality = builder.add_virtual_target();
+        builder.register_public_input(nationality);
+        Some(nationality)
+    } else {
+        (!(features.nationality_exclusion || features.nationality_allow_set)).then(|| {
+            builder.register_public_input(credential.nationality);
+            credential.nationality
+        })
+    };
+    let (issuer_pk, certificate) = if features.certified_issuer {
+        let root_pk = builder.add_virtual_point_target();
+        builder.register_point_public_input(root_pk);
+        (root_pk, Some(builder.add_virtual_certificate_target()))
+    } else if features.issuer_set {
+        // the neutral point, so that no key is disclosed
+        let placeholder = builder.add_virtual_point_target();
+        builder.register_point_public_input(placeholder);
+        (placeholder, None)
+    } else {
+        builder.register_point_public_input(credential.issuer);
+        (credential.issuer, None)
+    };
+    builder.register_public_input(today_days);
+    builder.register_public_input(min_age_days);
     builder.register_string_public_input(nonce);
     builder.register_string_public_input(service);
     builder.register_hash_public_input(pseudonym);
     builder.register_hash_public_input(merkle_root);
 
+    let nym = features.domain_nym.then(|| {
+        let nym = builder.add_virtual_hash_target();
+        builder.register_hash_public_input(nym);
+        nym
+    });
+    let (rate_limit, counter) = if features.k_show.is_some() {
+        let epoch = builder.add_virtual_target();
+        let nullifier = builder.add_virtual_hash_target();
+        builder.register_public_input(epoch);
+        builder.register_hash_public_input(nullifier);
+        (
+            Some(RateLimit { epoch, nullifier }),
+            Some(builder.add_virtual_target()),
+        )
+    } else {
+        (None, None)
+    };
+    let issued_after = features.recent_issuance.then(|| {
+        let issued_after = builder.add_virtual_target();
+        builder.register_public_input(issued_after);
+        issued_after
+    });
+    let authority_country = features.authority_country.then(|| {
+        let country = builder.add_virtual_target();
+        builder.register_public_input(country);
+        country
+    });
+    let (time_authority, time_token) = if features.time_authority {
+        let authority = builder.add_virtual_point_target();
+        builder.register_point_public_input(authority);
+        (
+            Some(authority),
+            Some(builder.add_virtual_time_token_target()),
+        )
+    } else {
+        (None, None)
+    };
+    let consortium = features.consortium.then(|| {
+        let scope = builder.add_virtual_string_target();
+        let nullifier = builder.add_virtual_hash_target();
+        builder.register_string_public_input(scope);
+        builder.register_hash_public_input(nullifier);
+        Consortium { scope, nullifier }
+    });
+    let (excluded_nationalities, excluded_set) = if features.nationality_exclusion {
+        let commitment = builder.add_virtual_hash_target();
+        builder.register_hash_public_input(commitment);
+        (
+            Some(commitment),
+            Some(builder.add_virtual_country_set_target()),
+        )
+    } else {
+        (None, None)
+    };
+    let portrait_hash = features.portrait_hash.then(|| {
+        let portrait_hash = builder.add_virtual_hash_target();
+        builder.register_hash_public_input(portrait_hash);
+        portrait_hash
+    });
+    let credential_id = features.credential_id.then(|| {
+        let salt = builder.add_virtual_hash_target();
+        let id = builder.add_virtual_hash_target();
+        builder.register_hash_public_input(salt);
+        builder.register_hash_public_input(id);
+        CredentialId { salt, id }
+    });
+    let account = features.account_binding.then(|| {
+        let account = builder.add_virtual_account_target();
+        builder.register_account_public_input(account);
+        account
+    });
+    let derived_key = features.derived_key.then(|| {
+        let derived_key = builder.add_virtual_point_target();
+        builder.register_point_public_input(derived_key);
+        derived_key
+    });
+    let (name_hash, name_salt) = if features.name_hash {
+        let name_hash = builder.add_virtual_hash_target();
+        builder.register_hash_public_input(name_hash);
+        (Some(name_hash), Some(builder.add_virtual_hash_target()))
+    } else {
+        (None, None)
+    };
+    let document_commitment = features.document_commitment.then(|| {
+        let salt = builder.add_virtual_hash_target();
+        let commitment = builder.add_virtual_hash_target();
+        builder.register_hash_public_input(salt);
+        builder.register_hash_public_input(commitment);
+        DocumentCommitment { salt, commitment }
+    });
+    let (previous_id, continuity) = if features.continuity {
+        let previous_id = builder.add_virtual_hash_target();
+        builder.register_hash_public_input(previous_id);
+        (
+            Some(previous_id),
+            Some(builder.add_virtual_continuity_target()),
+        )
+    } else {
+        (None, None)
+    };
+    let (issuer_set_commitment, issuer_set) = if features.issuer_set {
+        let commitment = builder.add_virtual_hash_target();
+        builder.register_hash_public_input(commitment);
+        (
+            Some(commitment),
+            Some(builder.add_virtual_issuer_set_target()),
+        )
+    } else {
+        (None, None)
+    };
+    let min_age_years = features.calendar_age.then(|| {
+        let min_age_years = builder.add_virtual_target();
+        builder.register_public_input(min_age_years);
+        min_age_years
+    });
+    let (allowed_nationalities, allowed_set) = if features.nationality_allow_set {
+        let commitment = builder.add_virtual_hash_target();
+        builder.register_hash_public_input(commitment);
+        (
+            Some(commitment),
+            Some(builder.add_virtual_country_set_target()),
+        )
+    } else {
+        (None, None)
+    };
+    let (revocation_root, non_revocation) = if features.non_revocation {
+        let root = builder.add_virtual_hash_target();
+        builder.register_hash_public_input(root);
+        (
+            Some(root),
+            Some(builder.add_virtual_non_revocation_path_target()),
+        )
+    } else {
+        (None, None)
+    };
+    let credential_nullifier = features.credential_nullifier.then(|| {
+        let nullifier = builder.add_virtual_hash_target();
+        builder.register_hash_public_input(nullifier);
+        nullifier
+    });
+    let current_date_days = features.expiration.then(|| {
+        let current_date_days = builder.add_virtual_target();
+        builder.register_public_input(current_date_days);
+        current_date_days
+    });
+    let flags = features.activation_flags.then(|| {
+        let mut flag = || {
+            let flag = builder.add_virtual_bool_target_safe();
+            builder.register_public_input(flag.target);
+            flag.target
+        };
+        Flags {
+            nationality: flag(),
+            majority: flag(),
+            recent_issuance: flag(),
+            authority_country: flag(),
+            nationality_exclusion: flag(),
+            calendar_age: flag(),
+            nationality_allow_set: flag(),
+            expiration: flag(),
+        }
+    });
+    let holder_secret = (features.domain_nym
+        || features.k_show.is_some()
+        || features.derived_key
+        || features.credential_nullifier
+        || features.consortium)
+        .then(|| builder.add_virtual_scalar_target());
+
     (
         Public {
-            cutoff18_days,
-            nationality: credential.nationality,
-            issuer_pk: credential.issuer,
+            today_days,
+            min_age_days,
+            nationality,
+            issuer_pk,
             nonce,
             service,
             pseudonym,
             merkle_root,
+            nym,
+            rate_limit,
+            issued_after,
+            authority_country,
+            time_authority,
+            consortium,
+            excluded_nationalities,
+            portrait_hash,
+            credential_id,
+            account,
+            derived_key,
+            name_hash,
+            document_commitment,
+            previous_id,
+            issuer_set: issuer_set_commitment,
+            min_age_years,
+            allowed_nationalities,
+            revocation_root,
+            credential_nullifier,
+            current_date_days,
+            flags,
         },
         Private {
             credential,
             signature,
             authentification,
             merkle_path,
+            certificate,
+            holder_secret,
+            counter,
+            time_token,
+            excluded_nationalities: excluded_set,
+            name_salt,
+            continuity,
+            issuer_set,
+            allowed_nationalities: allowed_set,
+            non_revocation,
         },
     )
 }
+impl<T, TBool> Private<T, TBool> {
+    /// Whether proving needs witnesses besides the credential, see
+    /// circuit::Extra
+    pub(crate) fn needs_extra(&self) -> bool {
+        self.certificate.is_some()
+            || self.holder_secret.is_some()
+            || self.counter.is_some()
+            || self.time_token.is_some()
+            || self.excluded_nationalities.is_some()
+            || self.name_salt.is_some()
+            || self.continuity.is_some()
+            || self.issuer_set.is_some()
+            || self.allowed_nationalities.is_some()
+            || self.non_revocation.is_some()
+    }
+}
+
 impl<F: RichField> Private<F, bool> {
     /// Ommits public inputs
     pub fn set(
@@ -95,115 +855,548 @@ impl<F: RichField> Private<F, bool> {
         targets: &Private<Target, BoolTarget>,
     ) -> anyhow::Result<()> {
         pw.set_credential_private_target(targets.credential, self.credential)?;
-        pw.set_signature_target(targets.signature, self.signature)?;
+        pw.set_signature_limbs_target(targets.signature, self.signature)?;
         pw.set_authentification_target(targets.authentification, self.authentification)?;
-        pw.set_merkle_proof_target(targets.merkle_path, self.merkle_path)
+        pw.set_merkle_proof_target(targets.merkle_path, self.merkle_path)?;
+        if let (Some(target), Some(value)) = (targets.certificate, self.certificate) {
+            // the issuer is not a public input in that case
+            pw.set_point_target(targets.credential.issuer, self.credential.issuer)?;
+            pw.set_certificate_target(target, value)?;
+        }
+        if let (Some(target), Some(value)) = (targets.holder_secret, self.holder_secret) {
+            pw.set_scalar_target(target, value)?;
+        }
+        if let (Some(target), Some(value)) = (targets.counter, self.counter) {
+            pw.set_target(target, value)?;
+        }
+        if let (Some(target), Some(value)) = (targets.time_token, self.time_token) {
+            pw.set_time_token_target(target, value)?;
+        }
+        if let (Some(target), Some(value)) =
+            (targets.excluded_nationalities, self.excluded_nationalities)
+        {
+            // the nationality is not a public input in that case
+            pw.set_target(targets.credential.nationality, self.credential.nationality)?;
+            pw.set_country_set_target(target, value)?;
+        }
+        if let (Some(target), Some(value)) = (targets.name_salt, self.name_salt) {
+            PartialWitnessHash::set_hash_target(pw, target, value)?;
+        }
+        if let (Some(target), Some(value)) = (targets.continuity, self.continuity) {
+            pw.set_continuity_target(target, value)?;
+        }
+        if let (Some(target), Some(value)) = (targets.issuer_set, self.issuer_set) {
+            // the issuer is not a public input in that case
+            pw.set_point_target(targets.credential.issuer, self.credential.issuer)?;
+            pw.set_issuer_set_target(target, value)?;
+        }
+        if let (Some(target), Some(value)) =
+            (targets.allowed_nationalities, self.allowed_nationalities)
+        {
+            // the nationality is not a public input in that case either, and
+            // setting it twice to the same value is harmless
+            pw.set_target(targets.credential.nationality, self.credential.nationality)?;
+            pw.set_country_set_target(target, value)?;
+        }
+        if let (Some(target), Some(value)) = (targets.non_revocation, self.non_revocation) {
+            pw.set_non_revocation_path_target(target, value)?;
+        }
+        Ok(())
     }
 }
 
 impl<F: RichField> Public<F> {
     pub fn set(&self, pw: &mut PartialWitness<F>, targets: &Public<Target>) -> anyhow::Result<()> {
-        pw.set_target(targets.nationality, self.nationality)?;
+        if let (Some(target), Some(value)) = (targets.nationality, self.nationality) {
+            pw.set_target(target, value)?;
+        }
         pw.set_point_target(targets.issuer_pk, self.issuer_pk)?;
-        pw.set_target(targets.cutoff18_days, self.cutoff18_days)?;
+        pw.set_target(targets.today_days, self.today_days)?;
+        pw.set_target(targets.min_age_days, self.min_age_days)?;
         pw.set_string_target(targets.nonce, self.nonce)?;
         pw.set_string_target(targets.service, self.service)?;
         PartialWitnessHash::set_hash_target(pw, targets.pseudonym, self.pseudonym)?;
-        PartialWitnessHash::set_hash_target(pw, targets.merkle_root, self.merkle_root)
-    }
-
-    // TODO: distinguish error from proof verification & public input checks
-    pub(crate) fn check(self, proved: &[F]) -> anyhow::Result<()> {
-        assert!(proved.len() == LEN_PUBLIC_INPUTS);
-        anyhow::ensure!(
-            proved[0] == self.nationality,
-            "public inputs mismatch for nationality"
-        );
-        let mut start = 1;
-        let mut end = start + LEN_POINT;
-        {
-            let value: [F; LEN_POINT] = proved[start..end].try_into().unwrap();
-            let value: encoding::Point<F> = value.into();
-            anyhow::ensure!(
-                value == self.issuer_pk,
-                "public inputs mismatch for issuer_pk"
-            );
+        PartialWitnessHash::set_hash_target(pw, targets.merkle_root, self.merkle_root)?;
+        if let (Some(target), Some(value)) = (targets.nym, self.nym) {
+            PartialWitnessHash::set_hash_target(pw, target, value)?;
         }
-        anyhow::ensure!(
-            proved[LEN_POINT + 1] == self.cutoff18_days,
-            "public inputs mismatch for cutoff18_days"
-        );
-        start = LEN_POINT + 2;
-        end = start + LEN_STRING;
+        if let (Some(target), Some(value)) = (&targets.rate_limit, &self.rate_limit) {
+            pw.set_target(target.epoch, value.epoch)?;
+            PartialWitnessHash::set_hash_target(pw, target.nullifier, value.nullifier)?;
+        }
+        if let (Some(target), Some(value)) = (targets.issued_after, self.issued_after) {
+            pw.set_target(target, value)?;
+        }
+        if let (Some(target), Some(value)) = (targets.authority_country, self.authority_country) {
+            pw.set_target(target, value)?;
+        }
+        if let (Some(target), Some(value)) = (targets.time_authority, self.time_authority) {
+            pw.set_point_target(target, value)?;
+        }
+        if let (Some(target), Some(value)) = (&targets.consortium, &self.consortium) {
+            pw.set_string_target(target.scope, value.scope)?;
+            PartialWitnessHash::set_hash_target(pw, target.nullifier, value.nullifier)?;
+        }
+        if let (Some(target), Some(value)) =
+            (targets.excluded_nationalities, self.excluded_nationalities)
         {
-            let value: [F; LEN_STRING] = proved[start..end].try_into().unwrap();
-            let value: encoding::String<F> = encoding::String(value);
-            anyhow::ensure!(value == self.nonce, "public inputs mismatch for nonce");
+            PartialWitnessHash::set_hash_target(pw, target, value)?;
         }
-        start = end;
-        end = start + LEN_STRING;
+        if let (Some(target), Some(value)) = (targets.portrait_hash, self.portrait_hash) {
+            PartialWitnessHash::set_hash_target(pw, target, value)?;
+        }
+        if let (Some(target), Some(value)) = (&targets.credential_id, &self.credential_id) {
+            PartialWitnessHash::set_hash_target(pw, target.salt, value.salt)?;
+            PartialWitnessHash::set_hash_target(pw, target.id, value.id)?;
+        }
+        if let (Some(target), Some(value)) = (targets.account, self.account) {
+            pw.set_account_target(target, value)?;
+        }
+        if let (Some(target), Some(value)) = (targets.derived_key, self.derived_key) {
+            pw.set_point_target(target, value)?;
+        }
+        if let (Some(target), Some(value)) = (targets.name_hash, self.name_hash) {
+            PartialWitnessHash::set_hash_target(pw, target, value)?;
+        }
+        if let (Some(target), Some(value)) =
+            (&targets.document_commitment, &self.document_commitment)
         {
-            let value: [F; LEN_STRING] = proved[start..end].try_into().unwrap();
-            let value: encoding::String<F> = encoding::String(value);
-            anyhow::ensure!(value == self.service, "public inputs mismatch for service");
+            PartialWitnessHash::set_hash_target(pw, target.salt, value.salt)?;
+            PartialWitnessHash::set_hash_target(pw, target.commitment, value.commitment)?;
+        }
+        if let (Some(target), Some(value)) = (targets.previous_id, self.previous_id) {
+            PartialWitnessHash::set_hash_target(pw, target, value)?;
         }
-        start = end;
-        end = start + LEN_PSEUDONYM;
+        if let (Some(target), Some(value)) = (targets.issuer_set, self.issuer_set) {
+            PartialWitnessHash::set_hash_target(pw, target, value)?;
+        }
+        if let (Some(target), Some(value)) = (targets.min_age_years, self.min_age_years) {
+            pw.set_target(target, value)?;
+        }
+        if let (Some(target), Some(value)) =
+            (targets.allowed_nationalities, self.allowed_nationalities)
         {
-            let value: [F; LEN_PSEUDONYM] = proved[start..end].try_into().unwrap();
-            let value: encoding::Pseudonym<F> = encoding::Hash(value);
-            anyhow::ensure!(
-                value == self.pseudonym,
-                "public inputs mismatch for pseudonym"
-            );
+            PartialWitnessHash::set_hash_target(pw, target, value)?;
+        }
+        if let (Some(target), Some(value)) = (targets.revocation_root, self.revocation_root) {
+            PartialWitnessHash::set_hash_target(pw, target, value)?;
         }
-        // Merkle root
-        start = end;
-        end = start + LEN_HASH;
+        if let (Some(target), Some(value)) =
+            (targets.credential_nullifier, self.credential_nullifier)
         {
-            let value: [F; LEN_HASH] = proved[start..end].try_into().unwrap();
-            let value: encoding::Hash<F> = encoding::Hash(value);
-            anyhow::ensure!(
-                value == self.merkle_root,
-                "public inputs mismatch for Merkle root"
-            )
-        }
-        anyhow::ensure!(
-            end == LEN_PUBLIC_INPUTS,
-            "public inputs mismatch for lengths"
-        );
+            PartialWitnessHash::set_hash_target(pw, target, value)?;
+        }
+        if let (Some(target), Some(value)) = (targets.current_date_days, self.current_date_days) {
+            pw.set_target(target, value)?;
+        }
+        if let (Some(target), Some(value)) = (targets.flags, self.flags) {
+            for (target, value) in target.to_array().into_iter().zip(value.to_array()) {
+                pw.set_target(target, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails on the first public input which differs from the proved ones,
+    /// in registration order
+    pub(crate) fn check(&self, proved: &[F]) -> Result<()> {
+        let fields = self.fields();
+        let expected = fields.iter().map(|(_, value)| value.len()).sum();
+        if proved.len() != expected {
+            return Err(Error::Length {
+                expected,
+                got: proved.len(),
+            });
+        }
+        let mut rest = proved;
+        for (name, value) in fields {
+            let (head, tail) = rest.split_at(value.len());
+            if head != value {
+                return Err(Error::Mismatch(name));
+            }
+            rest = tail;
+        }
         Ok(())
     }
 
+    /// Same as check, for a proof by any of issuers, whose key is left in
+    /// issuer_pk. Returns the index of the issuer
+    pub(crate) fn check_any_issuer(
+        &mut self,
+        proved: &[F],
+        issuers: &[PublicKey],
+    ) -> Result<usize> {
+        for (i, issuer) in issuers.iter().enumerate() {
+            self.issuer_pk = issuer.0.to_field();
+            match self.check(proved) {
+                Ok(()) => return Ok(i),
+                Err(Error::Mismatch("issuer_pk")) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Err(Error::Mismatch("issuer_pk"))
+    }
+
+    /// Takes the public inputs of the predicates the flags leave inactive
+    /// from proved, as universal circuits don't check them: the verifier only
+    /// knows the values of the predicates it requires. Does nothing without
+    /// flags
+    pub(crate) fn take_inactive(&mut self, proved: &[F]) -> Result<()> {
+        let Some(flags) = self.flags else {
+            return Ok(());
+        };
+        let mut offsets = std::collections::HashMap::new();
+        let mut offset = 0;
+        for (name, value) in self.fields() {
+            offsets.insert(name, offset);
+            offset += value.len();
+        }
+        if proved.len() != offset {
+            return Err(Error::Length {
+                expected: offset,
+                got: proved.len(),
+            });
+        }
+        let inactive = |flag: F| flag == F::ZERO;
+        let single = |name: &str| proved[offsets[name]];
+        let hash = |name: &str| encoding::Hash(std::array::from_fn(|i| proved[offsets[name] + i]));
+        if inactive(flags.nationality) && self.nationality.is_some() {
+            self.nationality = Some(single("nationality"));
+        }
+        if inactive(flags.recent_issuance) && self.issued_after.is_some() {
+            self.issued_after = Some(single("issued_after"));
+        }
+        if inactive(flags.authority_country) && self.authority_country.is_some() {
+            self.authority_country = Some(single("authority_country"));
+        }
+        if inactive(flags.nationality_exclusion) && self.excluded_nationalities.is_some() {
+            self.excluded_nationalities = Some(hash("excluded nationalities"));
+        }
+        if inactive(flags.calendar_age) && self.min_age_years.is_some() {
+            self.min_age_years = Some(single("min_age_years"));
+        }
+        if inactive(flags.nationality_allow_set) && self.allowed_nationalities.is_some() {
+            self.allowed_nationalities = Some(hash("allowed nationalities"));
+        }
+        if inactive(flags.expiration) && self.current_date_days.is_some() {
+            self.current_date_days = Some(single("current_date_days"));
+        }
+        Ok(())
+    }
+
+    /// Proves that the holder was at least min_age_days old on a past
+    /// reference day instead of today. Not for circuits with the
+    /// time_authority feature, whose token dates today_days
+    pub fn age_on(mut self, reference_day: u32, min_age_days: u32) -> Self {
+        self.today_days = reference_day.to_field();
+        self.min_age_days = min_age_days.to_field();
+        self
+    }
+
+    /// Proves the age on the calendar, for circuits with the calendar_age
+    /// feature. min_age_days is set to 0, so that the day count of
+    /// check_majority doesn't wait for leap days
+    pub fn with_min_age_years(mut self, years: u32) -> Self {
+        self.min_age_days = F::ZERO;
+        self.min_age_years = Some(years.to_field());
+        self
+    }
+
+    /// Hides the issuer among the set, for circuits with the issuer_set
+    /// feature
+    pub fn with_issuer_set(mut self, set: &IssuerSet) -> Self {
+        self.issuer_pk = Point::NEUTRAL.to_field();
+        self.issuer_set = Some(set.commitment());
+        self
+    }
+
+    /// Hides the nationality in the set, for circuits with the
+    /// nationality_allow_set feature
+    pub fn with_allowed_nationalities(mut self, set: &CountrySet) -> Self {
+        self.nationality = None;
+        self.allowed_nationalities = Some(set.commitment());
+        self
+    }
+
+    /// For circuits with the non_revocation feature
+    pub fn with_revocation_root(mut self, root: revocation::Root) -> Self {
+        self.revocation_root = Some(root);
+        self
+    }
+
+    /// For circuits with the credential_nullifier feature, see
+    /// issuer::pseudonym::credential_nullifier
+    pub fn with_credential_nullifier(mut self, nullifier: encoding::Hash<F>) -> Self {
+        self.credential_nullifier = Some(nullifier);
+        self
+    }
+
+    /// Rejects credentials expired on day, in days since origin, for circuits
+    /// with the expiration feature, e.g. date::today()
+    pub fn with_current_date(mut self, day: u32) -> Self {
+        self.current_date_days = Some(day.to_field());
+        self
+    }
+
+    /// Sets the predicates a proof of a universal circuit enforces
+    pub fn with_flags(mut self, flags: Flags<bool>) -> Self {
+        self.flags = Some(flags.to_field());
+        self
+    }
+
     // TODO: pseudonym should be given directly and not recomputed (it shouldn’t be computable by the bank)
-    pub fn new(merkle_root: merkle::Root<F>) -> Self {
+    /// Public inputs of the test bank & holder, for the credentials of issuer_pk
+    pub fn new(merkle_root: merkle::Root<F>, issuer_pk: &PublicKey) -> Self {
         let service = bank::service();
         let client_pk = crate::client::keys::public();
         let pseudonym = issuer::pseudonym::hash_from_service(&service, &client_pk);
+        Self::register(
+            today_for_tests().to_field(),
+            ADULT_AGE_DAYS.to_field(),
+            Some(Nationality::FR.to_field()),
+            issuer_pk.0.to_field(),
+            bank::nonce().to_field(),
+            service.to_field(),
+            (&pseudonym).into(),
+            merkle_root,
+        )
+    }
 
-        Self {
-            cutoff18_days: cutoff18_from_today_for_tests().to_field(),
-            nationality: Nationality::FR.to_field(),
-            issuer_pk: issuer::keys::public().0.to_field(),
-            nonce: bank::nonce().to_field(),
-            service: service.to_field(),
-            pseudonym: (&pseudonym).into(),
+    /// Public inputs of the statement of register, for any issuer,
+    /// nationality & date, see PublicBuilder
+    pub fn builder(issuer_pk: &PublicKey, merkle_root: merkle::Root<F>) -> PublicBuilder<F> {
+        PublicBuilder {
+            issuer_pk: issuer_pk.clone(),
             merkle_root,
+            nationality: None,
+            today_days: None,
+            min_age_days: ADULT_AGE_DAYS,
+            session: None,
+            pseudonym: None,
         }
     }
+}
 
-    pub fn new_with_pk(merkle_root: merkle::Root<F>, issuer_pk: PublicKey) -> Self {
-        let service = bank::service();
-        let client_pk = crate::client::keys::public();
-        let pseudonym = issuer::pseudonym::hash_from_service(&service, &client_pk);
-        Self {
-            cutoff18_days: cutoff18_from_today_for_tests().to_field(),
-            nationality: Nationality::FR.to_field(),
-            issuer_pk: issuer_pk.0.to_field(),
-            nonce: bank::nonce().to_field(),
-            service: service.to_field(),
-            pseudonym: (&pseudonym).into(),
-            merkle_root,
+/// Validated construction of the public inputs of the statement of register.
+/// The nationality, the session and the pseudonym are required; the date
+/// defaults to today and the minimal age to date::ADULT_AGE_DAYS
+pub struct PublicBuilder<F> {
+    issuer_pk: PublicKey,
+    merkle_root: merkle::Root<F>,
+    nationality: Option<Nationality>,
+    today_days: Option<u32>,
+    min_age_days: u32,
+    /// Service & nonce
+    session: Option<(String, String)>,
+    pseudonym: Option<encoding::Pseudonym<F>>,
+}
+
+impl<F: RichField> PublicBuilder<F> {
+    pub fn nationality(mut self, nationality: Nationality) -> Self {
+        self.nationality = Some(nationality);
+        self
+    }
+
+    /// Day the age is proved on, in days since origin, see date::today and
+    /// date::reference_day
+    pub fn today_days(mut self, today_days: u32) -> Self {
+        self.today_days = Some(today_days);
+        self
+    }
+
+    /// Same as today_days, from a date
+    pub fn on(self, date: NaiveDate) -> Self {
+        self.today_days(days_from_origin(date))
+    }
+
+    pub fn min_age_days(mut self, min_age_days: u32) -> Self {
+        self.min_age_days = min_age_days;
+        self
+    }
+
+    /// Service & nonce of the challenge the authentification answers
+    pub fn session(mut self, service: &str, nonce: &str) -> Self {
+        self.session = Some((service.to_string(), nonce.to_string()));
+        self
+    }
+
+    /// Claimed pseudonym of the holder for the service
+    pub fn pseudonym(mut self, pseudonym: encoding::Pseudonym<F>) -> Self {
+        self.pseudonym = Some(pseudonym);
+        self
+    }
+
+    pub fn build(self) -> Result<Public<F>> {
+        let nationality = self.nationality.ok_or(Error::Missing("nationality"))?;
+        let (service, nonce) = self.session.ok_or(Error::Missing("session"))?;
+        let pseudonym = self.pseudonym.ok_or(Error::Missing("pseudonym"))?;
+        let today_days = self.today_days.unwrap_or_else(today);
+        if today_days > today() {
+            return Err(Error::Invalid("today_days"));
         }
+        if self.min_age_days > today_days {
+            return Err(Error::Invalid("min_age_days"));
+        }
+        Ok(Public::register(
+            today_days.to_field(),
+            self.min_age_days.to_field(),
+            Some(nationality.to_field()),
+            self.issuer_pk.0.to_field(),
+            nonce.try_to_field().map_err(|_| Error::Invalid("nonce"))?,
+            service
+                .try_to_field()
+                .map_err(|_| Error::Invalid("service"))?,
+            pseudonym,
+            self.merkle_root,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::goldilocks_field::GoldilocksField as F;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        core::credential::Credential,
+        issuer::{database::for_tests::DATABASE, keys::IssuerIdentity},
+    };
+
+    #[test]
+    fn take_inactive_leaves_the_active_predicates_checked() {
+        let issuer = IssuerIdentity::for_tests();
+        let public = |issued_after: u32, nationality: u16| {
+            let mut public = Public::<F>::new(DATABASE.root(), issuer.public_key()).with_flags(
+                Flags {
+                    nationality: true,
+                    ..Flags::default()
+                },
+            );
+            public.issued_after = Some(issued_after.to_field());
+            public.nationality = Some(F::from_canonical_u16(nationality));
+            public
+        };
+        let proved: Vec<F> = public(10, 250)
+            .fields()
+            .into_iter()
+            .flat_map(|(_, value)| value)
+            .collect();
+        let mut expected = public(0, 250);
+        expected.take_inactive(&proved).unwrap();
+        assert_eq!(expected.check(&proved), Ok(()));
+        let mut expected = public(0, 276);
+        expected.take_inactive(&proved).unwrap();
+        assert_eq!(expected.check(&proved), Err(Error::Mismatch("nationality")));
+    }
+
+    #[test]
+    fn check_rejects_malformed_public_inputs() {
+        let public = || {
+            let issuer = IssuerIdentity::for_tests();
+            let mut public =
+                Public::<F>::new(DATABASE.root(), issuer.public_key()).with_flags(Flags::default());
+            public.rate_limit = Some(RateLimit {
+                epoch: F::ONE,
+                nullifier: encoding::Hash([F::TWO; LEN_HASH]),
+            });
+            public
+        };
+        let proved: Vec<F> = public()
+            .fields()
+            .into_iter()
+            .flat_map(|(_, value)| value)
+            .collect();
+        assert_eq!(public().check(&proved), Ok(()));
+
+        let mut rng = StdRng::seed_from_u64(3);
+        for len in 0..proved.len() {
+            assert_eq!(
+                public().check(&proved[..len]),
+                Err(Error::Length {
+                    expected: proved.len(),
+                    got: len
+                })
+            );
+        }
+        for extra in 1..8 {
+            let mut oversized = proved.clone();
+            oversized.extend((0..extra).map(|_| F::from_canonical_u32(rng.random())));
+            assert!(matches!(
+                public().check(&oversized),
+                Err(Error::Length { .. })
+            ));
+        }
+        for i in 0..proved.len() {
+            let mut tampered = proved.clone();
+            tampered[i] += F::ONE;
+            assert!(matches!(public().check(&tampered), Err(Error::Mismatch(_))));
+        }
+    }
+
+    #[test]
+    fn builder_validates_the_public_inputs() {
+        let root = DATABASE.root();
+        let issuer_pk = IssuerIdentity::for_tests().public_key().clone();
+        let pseudonym =
+            issuer::pseudonym::hash_from_service(&bank::service(), &crate::client::keys::public());
+        let builder = || {
+            Public::<F>::builder(&issuer_pk, root)
+                .nationality(Nationality::FR)
+                .today_days(today_for_tests())
+                .session(&bank::service(), &bank::nonce())
+                .pseudonym(pseudonym)
+        };
+        assert_eq!(
+            builder().build().unwrap().fields(),
+            Public::<F>::new(root, &issuer_pk).fields()
+        );
+        let public = builder().build().unwrap();
+        assert_eq!(public.nationality(), Some(Nationality::FR.to_field()));
+        assert_eq!(public.merkle_root(), root);
+
+        assert!(matches!(
+            Public::<F>::builder(&issuer_pk, root)
+                .session(&bank::service(), &bank::nonce())
+                .pseudonym(pseudonym)
+                .build(),
+            Err(Error::Missing("nationality"))
+        ));
+        assert!(matches!(
+            builder().today_days(today() + 1).build(),
+            Err(Error::Invalid("today_days"))
+        ));
+        assert!(matches!(
+            builder().min_age_days(today_for_tests() + 1).build(),
+            Err(Error::Invalid("min_age_days"))
+        ));
+        assert!(matches!(
+            builder().session(&"x".repeat(1000), "nonce").build(),
+            Err(Error::Invalid("service"))
+        ));
+    }
+
+    #[test]
+    fn check_any_issuer_finds_the_issuer_of_the_proof() {
+        let issuers: Vec<PublicKey> = (0..3)
+            .map(|i| Credential::from_seed(i).2.issuer())
+            .collect();
+        let proved: Vec<F> = Public::<F>::new(DATABASE.root(), &issuers[1])
+            .fields()
+            .into_iter()
+            .flat_map(|(_, value)| value)
+            .collect();
+        let mut public = Public::<F>::new(DATABASE.root(), &issuers[0]);
+        assert_eq!(public.check_any_issuer(&proved, &issuers), Ok(1));
+        assert_eq!(public.issuer_pk, issuers[1].0.to_field());
+        assert_eq!(
+            public.check_any_issuer(&proved, &[issuers[0].clone(), issuers[2].clone()]),
+            Err(Error::Mismatch("issuer_pk"))
+        );
+        public.nationality = Some(F::ZERO);
+        assert_eq!(
+            public.check_any_issuer(&proved, &issuers),
+            Err(Error::Mismatch("nationality"))
+        );
     }
 }
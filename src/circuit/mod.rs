@@ -1,46 +1,93 @@
-// Credential requirements: age > 18, nationality = FR
+// Credential requirements, set by the public inputs and the Features of the
+// circuit: the holder is at least min_age_days old, their nationality is the
+// public one, or is in an allowed set, or outside an excluded set (both
+// committed to as public inputs), and, when the circuit is set up for it, the
+// credential is not expired and nullifiers limit its presentations (k per
+// epoch, one per person in a consortium scope, one per credential).
+// Every circuit also verifies an authentification by the holder key over the
+// service & nonce of the public inputs (see Builder::check_authentification):
+// one proof shows both the possession of the key and the validity of the
+// credential.
 
+use plonky2::field::types::Field;
 use plonky2::iop::target::BoolTarget;
 use plonky2::{
     hash::poseidon::PoseidonHash,
     iop::{target::Target, witness::PartialWitness},
     plonk::{
         circuit_builder::CircuitBuilder,
-        circuit_data::{CircuitConfig, CircuitData},
+        circuit_data::CircuitData,
         config::{GenericConfig, PoseidonGoldilocksConfig},
         proof::ProofWithPublicInputs,
     },
+    util::serialization::DefaultGateSerializer,
 };
 
 use crate::circuit::authentification::{
     AuthentificationContextTarget, CircuitBuilderAuthentification,
 };
+use crate::circuit::calendar::CircuitBuilderCalendar;
+use crate::circuit::certificate::CircuitBuilderCertificate;
+use crate::circuit::continuity::CircuitBuilderContinuity;
+use crate::circuit::curve::CircuitBuilderCurve;
+use crate::circuit::hash::CircuitBuilderHash;
 use crate::circuit::merkle::CircuitBuilderMerkleProof;
+use crate::circuit::mrz::CircuitBuilderMrz;
+use crate::circuit::nym::CircuitBuilderNym;
+use crate::circuit::revocation::CircuitBuilderRevocation;
+use crate::circuit::scalar::ScalarTarget;
+use crate::circuit::secret::{Secret, Witness};
+use crate::circuit::set::CircuitBuilderSet;
 use crate::circuit::signature::CircuitBuilderSignature;
-use crate::core::credential::Credential;
-use crate::encoding::conversion::{ToAuthentificationField, ToSignatureField};
+use crate::circuit::timestamp::CircuitBuilderTimeToken;
+use crate::core::credential::{Credential, SCHEMA_VERSION};
+use crate::core::date;
+use crate::core::set::{CountrySet, IssuerSet};
+use crate::encoding::conversion::{ToScalarField, ToSchnorrField};
 use crate::encoding::{
-    AuthentificationChallenge, MerklePath, LEN_POINT, LEN_PSEUDONYM, LEN_STRING,
+    self, AuthentificationChallenge, Hash, MerklePath, LEN_HASH, LEN_POINT, LEN_PSEUDONYM,
+    LEN_STRING,
+};
+use crate::issuer::{
+    self, amendment::ContinuityRecord, ca::Certificate, pseudonym::Salt, timestamp::TimeToken,
 };
-use crate::issuer;
 use crate::schnorr::authentification::Authentification;
+use crate::schnorr::keys::{PublicKey, SecretKey};
 use crate::schnorr::signature::Signature;
 
+#[cfg(feature = "adversarial")]
+pub mod adversarial;
 pub mod authentification;
+#[cfg(any(test, feature = "bench"))]
+pub mod bench;
+pub mod calendar;
+pub mod certificate;
+pub mod continuity;
 pub mod credential;
 pub mod curve;
+pub mod diagnostics;
 pub mod gfp5;
 pub mod hash;
 pub mod inputs;
 pub mod merkle;
+pub mod mrz;
+pub mod nym;
 pub mod passport_number;
+pub mod profile;
+pub mod revocation;
 pub mod scalar;
+pub mod schema;
 pub mod schnorr;
+pub mod secret;
+pub mod set;
 pub mod signature;
 pub mod string;
+pub mod timestamp;
+
+pub use profile::CircuitProfile;
 
-const D: usize = 2;
-type C = PoseidonGoldilocksConfig;
+pub(crate) const D: usize = 2;
+pub(crate) type C = PoseidonGoldilocksConfig;
 pub type F = <C as GenericConfig<D>>::F;
 pub type ZkProof = ProofWithPublicInputs<F, C, D>;
 
@@ -48,65 +95,485 @@ pub struct Circuit {
     pub private_inputs: inputs::Private<Target, BoolTarget>,
     pub public_inputs: inputs::Public<Target>,
     pub circuit: CircuitData<F, C, D>,
+    pub profile: CircuitProfile,
 }
+/// Assembles a circuit from the checks below, e.g. for a bespoke policy:
+/// setup registers the public inputs of the features, in the order checked by
+/// inputs::Public::check, and build freezes the circuit. Checks of features
+/// the builder was not setup with are no-ops, and build panics unless the
+/// check of every feature it was setup with was called: their public inputs
+/// would be unconstrained. Nothing else is checked unless asked for, see
+/// circuit for the checks every presentation needs.
 pub struct Builder {
     pub(crate) builder: CircuitBuilder<F, D>,
     pub(crate) public_inputs: inputs::Public<Target>,
     pub(crate) private_inputs: inputs::Private<Target, BoolTarget>,
+    pub(crate) features: inputs::Features,
+    pub(crate) profile: CircuitProfile,
+    /// Whether the holder secret was connected to the holder key, see
+    /// bind_holder_secret
+    holder_secret_bound: bool,
+    /// Features whose check was called, which build compares to features
+    checked: inputs::Features,
+}
+
+/// Witnesses of the optional features, see inputs::Features
+#[derive(Default)]
+pub struct Extra<'a> {
+    pub certificate: Option<&'a Certificate>,
+    pub holder_secret: Option<&'a SecretKey>,
+    pub counter: Option<u32>,
+    pub time_token: Option<&'a TimeToken>,
+    /// Opening of the commitment of the excluded nationalities
+    pub excluded_nationalities: Option<&'a CountrySet>,
+    /// Salt of the hash of the family name, sent by the verifier
+    pub name_salt: Option<&'a Salt>,
+    /// Link of the credential to its predecessor
+    pub continuity: Option<&'a ContinuityRecord>,
+    /// Opening of the commitment of the approved issuers
+    pub issuer_set: Option<&'a IssuerSet>,
+    /// Opening of the commitment of the allowed nationalities
+    pub allowed_nationalities: Option<&'a CountrySet>,
+    /// Opening of the empty leaf of the credential in the tree of revoked
+    /// credentials
+    pub non_revocation: Option<&'a crate::revocation::Path>,
+}
+
+impl Circuit {
+    /// Identifies the circuit, and thus the statement proved
+    pub fn digest(&self) -> Hash<F> {
+        Hash(self.circuit.verifier_only.circuit_digest.elements)
+    }
+
+    /// Whether proofs blind the witness, see CircuitProfile
+    pub fn is_zero_knowledge(&self) -> bool {
+        self.circuit.common.config.zero_knowledge
+    }
+
+    /// What a verifier needs to check proofs of this circuit, see
+    /// crate::verify_bytes
+    pub fn verifier_key_bytes(&self) -> Vec<u8> {
+        self.circuit
+            .verifier_data()
+            .to_bytes(&DefaultGateSerializer)
+            .expect("circuits only use default gates")
+    }
 }
 
 impl Builder {
     /// Setups builder & inputs
-    pub(crate) fn setup() -> Self {
-        let config = CircuitConfig::default();
-        let mut builder = CircuitBuilder::<F, D>::new(config);
-        let (public_inputs, private_inputs) = inputs::register(&mut builder);
+    pub fn setup() -> Self {
+        Self::setup_with(inputs::Features::default())
+    }
+    /// Setups builder & the inputs of the given features
+    pub fn setup_with(features: inputs::Features) -> Self {
+        Self::setup_with_profile(features, CircuitProfile::default())
+    }
+    pub fn setup_with_profile(features: inputs::Features, profile: CircuitProfile) -> Self {
+        let mut builder = CircuitBuilder::<F, D>::new(profile.config());
+        let (public_inputs, private_inputs) = inputs::register_with(&mut builder, features);
         Self {
             builder,
             public_inputs,
             private_inputs,
+            features,
+            profile,
+            holder_secret_bound: false,
+            checked: inputs::Features::default(),
         }
     }
-    pub(crate) fn build(self) -> Circuit {
+    pub fn features(&self) -> inputs::Features {
+        self.features
+    }
+
+    /// Targets of the public inputs, in which custom constraints can be
+    /// expressed with circuit_builder
+    pub fn public_inputs(&self) -> &inputs::Public<Target> {
+        &self.public_inputs
+    }
+
+    /// Targets of the attributes of the credential proved
+    pub fn credential(&self) -> &encoding::Credential<Target, BoolTarget> {
+        &self.private_inputs.credential
+    }
+
+    /// For constraints beyond the checks of the builder. verify expects the
+    /// public inputs of inputs::Public only, don't register others
+    pub fn circuit_builder(&mut self) -> &mut CircuitBuilder<F, D> {
+        &mut self.builder
+    }
+
+    pub fn build(self) -> Circuit {
+        assert_eq!(
+            self.checked, self.features,
+            "every feature the builder was setup with must be checked"
+        );
         Circuit {
             private_inputs: self.private_inputs,
             circuit: self.builder.build::<C>(),
             public_inputs: self.public_inputs,
+            profile: self.profile,
+        }
+    }
+
+    /// Credentials of other layouts are rejected, see schema::Compatibility
+    pub fn check_schema_version(&mut self) {
+        let version = self.builder.constant(F::from_canonical_u8(SCHEMA_VERSION));
+        self.builder
+            .connect(self.private_inputs.credential.schema_version, version);
+    }
+
+    /// The check digits copied from the MRZ must match the credential
+    pub fn check_mrz(&mut self) {
+        self.builder.check_mrz(&self.private_inputs.credential);
+    }
+
+    /// x, or 0 when the flag of its predicate is unset
+    fn gated(&mut self, flag: fn(&inputs::Flags<Target>) -> Target, x: Target) -> Target {
+        match &self.public_inputs.flags {
+            Some(flags) => self.builder.mul(flag(flags), x),
+            None => x,
+        }
+    }
+
+    /// Connects x & y, only when the flag of their predicate is set
+    fn connect_gated(&mut self, flag: fn(&inputs::Flags<Target>) -> Target, x: Target, y: Target) {
+        if self.public_inputs.flags.is_some() {
+            let diff = self.builder.sub(x, y);
+            let diff = self.gated(flag, diff);
+            self.builder.assert_zero(diff);
+        } else {
+            self.builder.connect(x, y);
+        }
+    }
+
+    /// Does nothing unless the builder was setup with activation flags:
+    /// otherwise the public nationality is the one of the credential
+    pub fn check_nationality(&mut self) {
+        if let (Some(_), Some(nationality)) =
+            (self.public_inputs.flags, self.public_inputs.nationality)
+        {
+            self.checked.activation_flags = true;
+            self.connect_gated(
+                |flags| flags.nationality,
+                self.private_inputs.credential.nationality,
+                nationality,
+            );
         }
     }
 
-    pub(crate) fn check_majority(&mut self) {
-        // check that dob <= cutoff18
-        let diff = self.builder.sub(
-            self.public_inputs.cutoff18_days,
-            self.private_inputs.credential.birth_date,
+    pub fn check_majority(&mut self) {
+        // check that dob + min_age <= today
+        let cutoff = self.builder.sub(
+            self.public_inputs.today_days,
+            self.public_inputs.min_age_days,
         );
-        // TODO: the range check on dob can be removed when this value is constrained to the credential. For now we leave it, and we ommit the range check on the public input cutoff18
+        let diff = self
+            .builder
+            .sub(cutoff, self.private_inputs.credential.birth_date);
+        let diff = self.gated(|flags| flags.majority, diff);
+        // TODO: the range check on dob can be removed when this value is constrained to the credential. For now we leave it.
+        // min_age > today wraps around the field, which the range check on diff rejects
         self.builder
             .range_check(self.private_inputs.credential.birth_date, 32);
+        self.builder.range_check(self.public_inputs.today_days, 32);
+        self.builder
+            .range_check(self.public_inputs.min_age_days, 32);
         self.builder.range_check(diff, 32);
     }
 
-    pub(crate) fn check_signature(&mut self) {
-        self.builder.verify_signature(
-            &self.private_inputs.credential,
-            &self.private_inputs.signature,
-        )
+    /// Same as check_majority, but the minimal age is fixed by the circuit to
+    /// `years` (see date::min_age_days): public_inputs.min_age_days must be
+    /// equal to it, so that the digest of the circuit tells the threshold
+    pub fn check_age_over(&mut self, years: u32) {
+        let min_age_days = self
+            .builder
+            .constant(F::from_canonical_u32(date::min_age_days(years)));
+        self.builder
+            .connect(self.public_inputs.min_age_days, min_age_days);
+        self.check_majority();
+    }
+
+    /// Does nothing if the builder was not setup with the calendar age: the
+    /// holder is then min_age_years old from their birthday on
+    pub fn check_calendar_age(&mut self) {
+        if let Some(years) = self.public_inputs.min_age_years {
+            self.checked.calendar_age = true;
+            let birth_date = self.private_inputs.credential.birth_date;
+            let today = self.public_inputs.today_days;
+            match self.public_inputs.flags {
+                Some(flags) => self.builder.assert_of_age_if(
+                    BoolTarget::new_unsafe(flags.calendar_age),
+                    birth_date,
+                    today,
+                    years,
+                ),
+                None => self.builder.assert_of_age(birth_date, today, years),
+            }
+        }
+    }
+
+    /// Does nothing if the builder was not setup for recent issuance
+    pub fn check_date_of_issue(&mut self) {
+        if let Some(issued_after) = self.public_inputs.issued_after {
+            self.checked.recent_issuance = true;
+            // check that issued_after <= date_of_issue
+            let date_of_issue = self.private_inputs.credential.date_of_issue;
+            let diff = self.builder.sub(date_of_issue, issued_after);
+            let diff = self.gated(|flags| flags.recent_issuance, diff);
+            self.builder.range_check(date_of_issue, 32);
+            self.builder.range_check(diff, 32);
+        }
+    }
+
+    /// Does nothing if the builder was not setup to check the expiration
+    pub fn check_not_expired(&mut self) {
+        if let Some(current_date_days) = self.public_inputs.current_date_days {
+            self.checked.expiration = true;
+            // check that current_date_days <= expiration_date
+            let expiration_date = self.private_inputs.credential.expiration_date;
+            let diff = self.builder.sub(expiration_date, current_date_days);
+            let diff = self.gated(|flags| flags.expiration, diff);
+            self.builder.range_check(expiration_date, 32);
+            self.builder.range_check(current_date_days, 32);
+            self.builder.range_check(diff, 32);
+        }
+    }
+
+    /// Does nothing if the builder was not setup to exclude nationalities
+    pub fn check_nationality_exclusion(&mut self) {
+        if let (Some(set), Some(commitment)) = (
+            self.private_inputs.excluded_nationalities,
+            self.public_inputs.excluded_nationalities,
+        ) {
+            self.checked.nationality_exclusion = true;
+            let got = self.builder.commit_country_set(&set);
+            for i in 0..LEN_HASH {
+                self.connect_gated(
+                    |flags| flags.nationality_exclusion,
+                    got.0[i],
+                    commitment.0[i],
+                );
+            }
+            let nationality = self.private_inputs.credential.nationality;
+            match self.public_inputs.flags {
+                Some(flags) => self.builder.assert_not_in_country_set_if(
+                    BoolTarget::new_unsafe(flags.nationality_exclusion),
+                    nationality,
+                    &set,
+                ),
+                None => self.builder.assert_not_in_country_set(nationality, &set),
+            }
+        }
+    }
+
+    /// Does nothing if the builder was not setup with a nationality allow-set
+    pub fn check_nationality_allow_set(&mut self) {
+        if let (Some(set), Some(commitment)) = (
+            self.private_inputs.allowed_nationalities,
+            self.public_inputs.allowed_nationalities,
+        ) {
+            self.checked.nationality_allow_set = true;
+            let got = self.builder.commit_country_set(&set);
+            for i in 0..LEN_HASH {
+                self.connect_gated(
+                    |flags| flags.nationality_allow_set,
+                    got.0[i],
+                    commitment.0[i],
+                );
+            }
+            let nationality = self.private_inputs.credential.nationality;
+            match self.public_inputs.flags {
+                Some(flags) => self.builder.assert_in_country_set_if(
+                    BoolTarget::new_unsafe(flags.nationality_allow_set),
+                    nationality,
+                    &set,
+                ),
+                None => self.builder.assert_in_country_set(nationality, &set),
+            }
+        }
+    }
+
+    /// Does nothing if the builder was not setup to disclose the country of
+    /// the issuing authority
+    pub fn check_authority_country(&mut self) {
+        if let Some(country) = self.public_inputs.authority_country {
+            self.checked.authority_country = true;
+            self.connect_gated(
+                |flags| flags.authority_country,
+                self.private_inputs.credential.issuing_authority.country,
+                country,
+            );
+        }
+    }
+
+    /// Does nothing if the builder was not setup to disclose the hash of the
+    /// passport photo
+    pub fn check_portrait_hash(&mut self) {
+        if let Some(portrait_hash) = self.public_inputs.portrait_hash {
+            self.checked.portrait_hash = true;
+            self.builder
+                .connect_hash(self.private_inputs.credential.portrait_hash, portrait_hash);
+        }
+    }
+
+    /// Does nothing if the builder was not setup to prove the family name
+    /// matches a salted hash
+    pub fn check_name_hash(&mut self) {
+        if let (Some(salt), Some(name_hash)) =
+            (self.private_inputs.name_salt, self.public_inputs.name_hash)
+        {
+            self.checked.name_hash = true;
+            let got = self
+                .builder
+                .name_hash(self.private_inputs.credential.family_name, salt);
+            self.builder.connect_hash(got, name_hash);
+        }
+    }
+
+    /// Does nothing if the builder was not setup to commit to the travel
+    /// document
+    pub fn check_document_commitment(&mut self) {
+        if let Some(commitment) = &self.public_inputs.document_commitment {
+            self.checked.document_commitment = true;
+            let got = self
+                .builder
+                .document_commitment(&self.private_inputs.credential, commitment.salt);
+            self.builder.connect_hash(got, commitment.commitment);
+        }
+    }
+
+    /// Does nothing if the builder was not setup to prove continuity
+    pub fn check_continuity(&mut self) {
+        if let (Some(continuity), Some(previous_id), Some(credential_id)) = (
+            self.private_inputs.continuity,
+            self.public_inputs.previous_id,
+            &self.public_inputs.credential_id,
+        ) {
+            self.checked.continuity = true;
+            self.builder
+                .verify_continuity(&continuity, &self.private_inputs.credential);
+            let got = self
+                .builder
+                .credential_id(&continuity.predecessor, credential_id.salt);
+            self.builder.connect_hash(got, previous_id);
+        }
+    }
+
+    /// Does nothing if the builder was not setup with a credential ID
+    pub fn check_credential_id(&mut self) {
+        if let Some(credential_id) = &self.public_inputs.credential_id {
+            self.checked.credential_id = true;
+            let got = self
+                .builder
+                .credential_id(&self.private_inputs.credential, credential_id.salt);
+            self.builder.connect_hash(got, credential_id.id);
+        }
+    }
+
+    /// Does nothing if the builder was not setup for a consortium
+    pub fn check_consortium_nullifier(&mut self) {
+        if let (Some(holder_secret), Some(consortium)) = (
+            self.private_inputs.holder_secret,
+            self.public_inputs.consortium,
+        ) {
+            self.checked.consortium = true;
+            self.bind_holder_secret(holder_secret);
+            let got = self.builder.person_nullifier(
+                holder_secret,
+                &self.private_inputs.credential,
+                consortium.scope,
+            );
+            for i in 0..LEN_HASH {
+                self.builder.connect(got.0[i], consortium.nullifier.0[i]);
+            }
+        }
+    }
+
+    /// Does nothing if the builder was not setup with a time authority
+    pub fn check_time_token(&mut self) {
+        if let (Some(token), Some(authority)) = (
+            self.private_inputs.time_token,
+            self.public_inputs.time_authority,
+        ) {
+            self.checked.time_authority = true;
+            self.builder
+                .verify_time_token(&token, self.public_inputs.nonce, authority);
+            self.builder
+                .connect(token.today, self.public_inputs.today_days);
+        }
+    }
+
+    pub fn check_signature(&mut self) {
+        let signature = self
+            .builder
+            .decompose_signature(self.private_inputs.signature);
+        self.builder
+            .verify_signature(&self.private_inputs.credential, &signature)
+    }
+
+    /// Does nothing if the builder was not setup with a certificate
+    pub fn check_issuer_certificate(&mut self) {
+        if let Some(certificate) = self.private_inputs.certificate {
+            self.checked.certified_issuer = true;
+            self.builder.verify_certificate(
+                &certificate,
+                self.private_inputs.credential.issuer,
+                self.public_inputs.issuer_pk,
+                self.public_inputs.today_days,
+            )
+        }
+    }
+
+    /// Does nothing if the builder was not setup with an issuer set
+    pub fn check_issuer_set(&mut self) {
+        if let (Some(set), Some(commitment)) = (
+            self.private_inputs.issuer_set,
+            self.public_inputs.issuer_set,
+        ) {
+            self.checked.issuer_set = true;
+            let neutral = self.builder.zero_point();
+            self.builder
+                .connect_point(self.public_inputs.issuer_pk, neutral);
+            let got = self.builder.commit_issuer_set(&set);
+            self.builder.connect_hash(got, commitment);
+            self.builder
+                .assert_in_issuer_set(self.private_inputs.credential.issuer, &set);
+        }
     }
 
-    pub(crate) fn check_authentification(&mut self) {
+    /// With derived keys, the authentification is made with the derived key,
+    /// see check_derived_key
+    pub fn check_authentification(&mut self) {
+        self.checked.account_binding = self.features.account_binding;
         let ctx = AuthentificationContextTarget {
-            public_key: self.private_inputs.credential.public_key,
+            public_key: self
+                .public_inputs
+                .derived_key
+                .unwrap_or(self.private_inputs.credential.public_key),
             challenge: AuthentificationChallenge {
                 nonce: self.public_inputs.nonce,
                 service: self.public_inputs.service,
             },
+            channel_binding: None,
+            expires_at: None,
+            account: self.public_inputs.account,
         };
         self.builder
             .verify_authentification(&ctx, &self.private_inputs.authentification);
     }
 
-    pub(crate) fn check_pseudonym(&mut self) {
+    /// The holder secret is the one of the holder key: every check using it
+    /// calls this, so that none relies on another being called
+    fn bind_holder_secret(&mut self, holder_secret: ScalarTarget) {
+        if !self.holder_secret_bound {
+            self.builder
+                .connect_secret_key(holder_secret, self.private_inputs.credential.public_key);
+            self.holder_secret_bound = true;
+        }
+    }
+
+    pub fn check_pseudonym(&mut self) {
         let mut to_hash: Vec<Target> = Vec::with_capacity(LEN_STRING + LEN_POINT);
         to_hash.extend_from_slice(&self.public_inputs.service.0);
         let public_key: [Target; LEN_POINT] = self.private_inputs.credential.public_key.into();
@@ -118,66 +585,368 @@ impl Builder {
         }
     }
 
-    pub(crate) fn check_merkle_proof(&mut self) {
+    /// Does nothing if the builder was not setup with a domain nym
+    pub fn check_domain_nym(&mut self) {
+        if let (Some(holder_secret), Some(nym)) =
+            (self.private_inputs.holder_secret, self.public_inputs.nym)
+        {
+            self.checked.domain_nym = true;
+            self.bind_holder_secret(holder_secret);
+            let got = self
+                .builder
+                .domain_nym(holder_secret, self.public_inputs.service);
+            for i in 0..LEN_PSEUDONYM {
+                self.builder.connect(got.0[i], nym.0[i]);
+            }
+        }
+    }
+
+    /// Does nothing if the builder was not setup with derived keys
+    pub fn check_derived_key(&mut self) {
+        if let (Some(holder_secret), Some(derived_key)) = (
+            self.private_inputs.holder_secret,
+            self.public_inputs.derived_key,
+        ) {
+            self.checked.derived_key = true;
+            self.bind_holder_secret(holder_secret);
+            let derived = self
+                .builder
+                .derived_key(holder_secret, self.public_inputs.service);
+            self.builder.connect_secret_key(derived, derived_key);
+        }
+    }
+
+    /// Does nothing if the builder was not setup for k-show credentials
+    pub fn check_rate_limit(&mut self) {
+        if let (Some(k), Some(holder_secret), Some(counter), Some(rate_limit)) = (
+            self.features.k_show,
+            self.private_inputs.holder_secret,
+            self.private_inputs.counter,
+            self.public_inputs.rate_limit,
+        ) {
+            self.checked.k_show = Some(k);
+            self.bind_holder_secret(holder_secret);
+            let got = self
+                .builder
+                .nullifier(holder_secret, rate_limit.epoch, counter, k);
+            for i in 0..LEN_HASH {
+                self.builder.connect(got.0[i], rate_limit.nullifier.0[i]);
+            }
+        }
+    }
+
+    /// Does nothing if the builder was not setup to expose a credential
+    /// nullifier
+    pub fn check_credential_nullifier(&mut self) {
+        if let (Some(holder_secret), Some(nullifier)) = (
+            self.private_inputs.holder_secret,
+            self.public_inputs.credential_nullifier,
+        ) {
+            self.checked.credential_nullifier = true;
+            self.bind_holder_secret(holder_secret);
+            let got = self.builder.credential_nullifier(
+                holder_secret,
+                &self.private_inputs.credential,
+                self.public_inputs.service,
+            );
+            self.builder.connect_hash(got, nullifier);
+        }
+    }
+
+    pub fn check_merkle_proof(&mut self) {
         self.builder.check_merkle_proof(
             &self.private_inputs.credential,
             self.private_inputs.merkle_path,
             self.public_inputs.merkle_root,
         );
     }
+
+    /// Does nothing if the builder was not setup to prove non-revocation
+    pub fn check_non_revocation(&mut self) {
+        if let (Some(path), Some(root)) = (
+            self.private_inputs.non_revocation,
+            self.public_inputs.revocation_root,
+        ) {
+            self.checked.non_revocation = true;
+            self.builder
+                .check_not_revoked(&self.private_inputs.credential, path, root);
+        }
+    }
 }
 
 /// Prove that client knows a credential such that:
+/// - Its MRZ check digits are consistent
 /// - Nationality = FR,
 /// - Age >= 18
 /// - Signed by issuer
-/// - User knows the private key for the credential
+/// - User knows the private key of the holder key of the credential, which
+///   authentifies the service & nonce, so that a lent credential is useless
 /// - Credential is in the Merkle tree of valid credentials
 pub fn circuit() -> Circuit {
     let mut builder = Builder::setup();
+    builder.check_schema_version();
+    builder.check_mrz();
+    builder.check_majority();
+    builder.check_signature();
+    builder.check_authentification();
+    builder.check_pseudonym();
+    builder.check_merkle_proof();
+    builder.build()
+}
+
+/// Same as circuit, for holders at least `years` old, whichever minimal age
+/// the public inputs claim, e.g. 16 or 21 depending on the jurisdiction
+pub fn circuit_age_over(years: u32) -> Circuit {
+    let mut builder = Builder::setup();
+    builder.check_schema_version();
+    builder.check_mrz();
+    builder.check_age_over(years);
+    builder.check_signature();
+    builder.check_authentification();
+    builder.check_pseudonym();
+    builder.check_merkle_proof();
+    builder.build()
+}
+
+/// Same as circuit, but the issuer is only known to be certified by the root
+/// key given as public input, which hides which intermediate issued the credential
+pub fn circuit_certified() -> Circuit {
+    let mut builder = Builder::setup_with(inputs::Features {
+        certified_issuer: true,
+        ..inputs::Features::default()
+    });
+    builder.check_schema_version();
+    builder.check_mrz();
+    builder.check_majority();
+    builder.check_signature();
+    builder.check_issuer_certificate();
+    builder.check_authentification();
+    builder.check_pseudonym();
+    builder.check_merkle_proof();
+    builder.build()
+}
+
+/// Same as circuit, but the issuer is only known to be in the set committed to
+/// by public_inputs.issuer_set, e.g. the offices of a national authority
+pub fn circuit_issuer_set() -> Circuit {
+    let mut builder = Builder::setup_with(inputs::Features {
+        issuer_set: true,
+        ..inputs::Features::default()
+    });
+    builder.check_schema_version();
+    builder.check_mrz();
+    builder.check_majority();
+    builder.check_signature();
+    builder.check_issuer_set();
+    builder.check_authentification();
+    builder.check_pseudonym();
+    builder.check_merkle_proof();
+    builder.build()
+}
+
+/// Same as circuit, and also exposes nym = Hash(holder secret key, service).
+/// Unlike the pseudonym, it can't be recomputed from the holder public key,
+/// so that services sharing their data can't link their users
+pub fn circuit_with_nym() -> Circuit {
+    let mut builder = Builder::setup_with(inputs::Features {
+        domain_nym: true,
+        ..inputs::Features::default()
+    });
+    builder.check_schema_version();
+    builder.check_mrz();
+    builder.check_majority();
+    builder.check_signature();
+    builder.check_authentification();
+    builder.check_pseudonym();
+    builder.check_domain_nym();
+    builder.check_merkle_proof();
+    builder.build()
+}
+
+/// Same as circuit, for credentials that can be shown at most k times per
+/// epoch (see issuer::rate_limit): exposes the epoch and a nullifier, which
+/// the verifier must not have seen before
+pub fn circuit_k_show(k: u32) -> Circuit {
+    let mut builder = Builder::setup_with(inputs::Features {
+        k_show: Some(k),
+        ..inputs::Features::default()
+    });
+    builder.check_schema_version();
+    builder.check_mrz();
+    builder.check_majority();
+    builder.check_signature();
+    builder.check_authentification();
+    builder.check_pseudonym();
+    builder.check_rate_limit();
+    builder.check_merkle_proof();
+    builder.build()
+}
+
+/// Same as circuit, for high-risk flows: the credential must have been issued
+/// on or after public_inputs.issued_after
+pub fn circuit_recently_issued() -> Circuit {
+    let mut builder = Builder::setup_with(inputs::Features {
+        recent_issuance: true,
+        ..inputs::Features::default()
+    });
+    builder.check_schema_version();
+    builder.check_mrz();
+    builder.check_majority();
+    builder.check_date_of_issue();
+    builder.check_signature();
+    builder.check_authentification();
+    builder.check_pseudonym();
+    builder.check_merkle_proof();
+    builder.build()
+}
+
+/// Circuit of the given features, built with profile: every check is
+/// registered, those of absent features being no-ops
+pub fn circuit_with_profile(features: inputs::Features, profile: CircuitProfile) -> Circuit {
+    let mut builder = Builder::setup_with_profile(features, profile);
+    builder.check_schema_version();
+    builder.check_mrz();
     builder.check_majority();
+    builder.check_calendar_age();
+    builder.check_nationality();
+    builder.check_date_of_issue();
+    builder.check_not_expired();
+    builder.check_nationality_exclusion();
+    builder.check_nationality_allow_set();
+    builder.check_authority_country();
+    builder.check_portrait_hash();
+    builder.check_name_hash();
+    builder.check_time_token();
     builder.check_signature();
+    builder.check_issuer_certificate();
+    builder.check_issuer_set();
     builder.check_authentification();
     builder.check_pseudonym();
+    builder.check_domain_nym();
+    builder.check_rate_limit();
+    builder.check_derived_key();
+    builder.check_consortium_nullifier();
+    builder.check_credential_nullifier();
+    builder.check_credential_id();
+    builder.check_document_commitment();
+    builder.check_continuity();
     builder.check_merkle_proof();
+    builder.check_non_revocation();
     builder.build()
 }
 
+/// One circuit for every combination of the predicates of
+/// inputs::Features::universal, which public_inputs.flags select: banks
+/// only need to trust its digest
+pub fn circuit_universal() -> Circuit {
+    circuit_with_profile(inputs::Features::universal(), CircuitProfile::default())
+}
+
+/// Witness of the private inputs, scrubbed on drop, see secret
 pub fn witness(
     credential: &Credential,
     signature: &Signature,
     authentification: &Authentification,
     merkle_path: &MerklePath<{ issuer::database::SIZE }, F, bool>,
+    extra: &Extra,
     private_inputs: &inputs::Private<Target, BoolTarget>,
-) -> anyhow::Result<PartialWitness<F>> {
-    let mut pw = PartialWitness::new();
-    let values = inputs::Private {
+) -> anyhow::Result<Witness> {
+    let mut pw = Secret::new(PartialWitness::new());
+    Secret::new(witness_values(
+        credential,
+        signature,
+        authentification,
+        merkle_path,
+        extra,
+    ))
+    .set(&mut pw, private_inputs)?;
+    Ok(pw)
+}
+
+/// Values of the private inputs, which witness sets
+pub(crate) fn witness_values(
+    credential: &Credential,
+    signature: &Signature,
+    authentification: &Authentification,
+    merkle_path: &MerklePath<{ issuer::database::SIZE }, F, bool>,
+    extra: &Extra,
+) -> inputs::Private<F, bool> {
+    inputs::Private {
         credential: credential.to_field(),
-        signature: signature.to_field(),
+        signature: signature.to_limbs(),
         authentification: authentification.to_field(),
         merkle_path: *merkle_path,
-    };
-    values.set(&mut pw, private_inputs)?;
-    Ok(pw)
+        certificate: extra.certificate.map(Certificate::to_field),
+        holder_secret: extra.holder_secret.map(|sk| sk.0.to_field()),
+        counter: extra.counter.map(F::from_canonical_u32),
+        time_token: extra.time_token.map(TimeToken::to_field),
+        excluded_nationalities: extra.excluded_nationalities.map(CountrySet::to_field),
+        name_salt: extra.name_salt.copied(),
+        continuity: extra.continuity.map(ContinuityRecord::to_field),
+        issuer_set: extra.issuer_set.map(IssuerSet::to_field),
+        allowed_nationalities: extra.allowed_nationalities.map(CountrySet::to_field),
+        non_revocation: extra.non_revocation.copied(),
+    }
 }
 
-pub fn prove(
+/// Proves with the witnesses of the features of the circuit in extra, e.g.
+/// the holder secret of circuit_with_nym, and on failure attaches the
+/// violated check, see diagnostics::diagnose
+pub fn prove_with(
     circuit: &Circuit,
     credential: &Credential,
     signature: &Signature,
     authentification: &Authentification,
     merkle_path: &MerklePath<{ issuer::database::SIZE }, F, bool>,
+    extra: &Extra,
     public_inputs: &inputs::Public<F>,
 ) -> anyhow::Result<ZkProof> {
+    // presentations from circuits of the Test profile are only made in tests
+    anyhow::ensure!(
+        circuit.is_zero_knowledge() || circuit.profile == CircuitProfile::Test,
+        "refusing to prove with a circuit which does not blind the witness"
+    );
     let mut pw = witness(
         credential,
         signature,
         authentification,
         merkle_path,
+        extra,
         &circuit.private_inputs,
     )?;
     public_inputs.set(&mut pw, &circuit.public_inputs)?;
-    circuit.circuit.prove(pw)
+    pw.prove(&circuit.circuit).map_err(|err| {
+        match diagnostics::diagnose(
+            credential,
+            signature,
+            authentification,
+            merkle_path,
+            extra,
+            public_inputs,
+        ) {
+            Some(failure) => err.context(failure),
+            None => err,
+        }
+    })
+}
+
+pub fn prove(
+    circuit: &Circuit,
+    credential: &Credential,
+    signature: &Signature,
+    authentification: &Authentification,
+    merkle_path: &MerklePath<{ issuer::database::SIZE }, F, bool>,
+    public_inputs: &inputs::Public<F>,
+) -> anyhow::Result<ZkProof> {
+    prove_with(
+        circuit,
+        credential,
+        signature,
+        authentification,
+        merkle_path,
+        &Extra::default(),
+        public_inputs,
+    )
 }
 
 pub fn verify(
@@ -187,64 +956,97 @@ pub fn verify(
 ) -> anyhow::Result<()> {
     let proved_public_inputs = proof.public_inputs.clone();
     circuit.verify(proof)?;
-    public_inputs.check(&proved_public_inputs)
+    Ok(public_inputs.check(&proved_public_inputs)?)
+}
+
+/// Same as verify, for a proof by any of issuers, whose index is returned
+pub fn verify_any_issuer(
+    circuit: &CircuitData<F, C, D>,
+    proof: ZkProof,
+    mut public_inputs: inputs::Public<F>,
+    issuers: &[PublicKey],
+) -> anyhow::Result<usize> {
+    let issuer = public_inputs.check_any_issuer(&proof.public_inputs, issuers)?;
+    circuit.verify(proof)?;
+    Ok(issuer)
 }
 
 #[cfg(test)]
 mod tests {
+    use chrono::NaiveDate;
     use plonky2::field::types::Field;
     use rand::{rngs::StdRng, SeedableRng};
 
-    use super::{circuit, inputs, prove, verify, F};
+    use super::{
+        circuit, circuit_certified, circuit_issuer_set, circuit_k_show, circuit_recently_issued,
+        circuit_with_nym, inputs, prove, prove_with, verify, Extra, F,
+    };
     use crate::{
-        bank,
-        circuit::Circuit,
+        bank::{self, consortium},
+        circuit::{circuit_with_profile, diagnostics::Failure, Circuit, CircuitProfile},
         client,
-        core::{credential::Credential, date::cutoff18_from_today_for_tests},
-        encoding::conversion::{ToPointField, ToSingleField, ToStringField},
-        issuer::{self, database::for_tests, pseudonym},
-        merkle,
+        core::{
+            codec::Codec,
+            credential::{Credential, SCHEMA_VERSION},
+            date::{self, days_from_origin, today_for_tests, ADULT_AGE_DAYS},
+            set::{CountrySet, IssuerSet},
+        },
+        encoding::{
+            conversion::{ToAccountField, ToPointField, ToSingleField, ToStringField},
+            Hash, LEN_HASH,
+        },
+        issuer::{
+            self, amendment::ContinuityRecord, ca::Certificate, database::for_tests,
+            keys::IssuerIdentity, pseudonym, rate_limit, timestamp::TimeToken,
+        },
+        merkle, revocation,
         schnorr::{
             authentification::{Authentification, Context as AuthentificationContext},
-            keys::SecretKey,
+            keys::{PublicKey, SecretKey},
             signature::{Context as SignatureContext, Signature},
         },
     };
 
     fn matching_public_inputs(credential: &Credential) -> inputs::Public<F> {
         let service = bank::service();
-        inputs::Public {
-            cutoff18_days: cutoff18_from_today_for_tests().to_field(),
-            nationality: credential.nationality().to_field(),
-            issuer_pk: credential.issuer().0.to_field(),
-            nonce: bank::nonce().to_field(),
-            service: service.to_field(),
-            pseudonym: pseudonym::hash_from_service(&service, &credential.public_key()),
-            merkle_root: for_tests::DATABASE.root(),
-        }
+        inputs::Public::register(
+            today_for_tests().to_field(),
+            ADULT_AGE_DAYS.to_field(),
+            Some(credential.nationality().to_field()),
+            credential.issuer().0.to_field(),
+            bank::nonce().to_field(),
+            service.to_field(),
+            pseudonym::hash_from_service(&service, &credential.public_key()),
+            for_tests::DATABASE.root(),
+        )
     }
 
     fn valid_credential_signature_and_authentification(
         seed: u64,
     ) -> (Credential, Signature, Authentification) {
+        let (_, credential, signature, authentification) = valid_holder_credential(seed);
+        (credential, signature, authentification)
+    }
+
+    fn valid_holder_credential(seed: u64) -> (SecretKey, Credential, Signature, Authentification) {
         assert!(
             (seed as usize) < issuer::database::SIZE,
             "testing error: seed is too big"
         );
         let (client_sk, issuer_sk, credential) = Credential::from_seed(seed);
-        let signature = Signature::sign(&issuer_sk, &SignatureContext::new(&credential));
+        let signature = Signature::prove(&issuer_sk, &SignatureContext::new(&credential));
         let service = bank::service();
         let nonce = bank::nonce();
         let auth_ctx = AuthentificationContext::new(&credential.public_key(), &service, &nonce);
-        let authentification = Authentification::sign(&client_sk, &auth_ctx);
-        (credential, signature, authentification)
+        let authentification = Authentification::prove(&client_sk, &auth_ctx);
+        (client_sk, credential, signature, authentification)
     }
 
     fn default_authentification() -> Authentification {
         let sk = client::keys::secret();
         let pk = crate::schnorr::keys::PublicKey::from(&sk);
         let ctx = AuthentificationContext::new(&pk, "any-service", "any-nonce");
-        Authentification::sign(&sk, &ctx)
+        Authentification::prove(&sk, &ctx)
     }
     fn circuit_without_signature() -> Circuit {
         let mut builder = super::Builder::setup();
@@ -253,6 +1055,52 @@ mod tests {
         builder.build()
     }
 
+    #[test]
+    fn builder_assembles_a_bespoke_circuit() {
+        // adults authenticated by their key, whatever the MRZ & schema
+        let mut builder =
+            super::Builder::setup_with_profile(inputs::Features::default(), CircuitProfile::Test);
+        builder.check_majority();
+        builder.check_signature();
+        builder.check_authentification();
+        builder.check_pseudonym();
+        builder.check_merkle_proof();
+        let c = builder.build();
+
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(2);
+        let public_inputs = matching_public_inputs(&credential);
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let proof = prove(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &public_inputs,
+        )
+        .unwrap();
+        verify(&c.circuit, proof, public_inputs).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "every feature the builder was setup with must be checked")]
+    fn builder_rejects_a_feature_left_unchecked() {
+        // the nym would be any value the prover likes
+        let mut builder = super::Builder::setup_with_profile(
+            inputs::Features {
+                domain_nym: true,
+                ..inputs::Features::default()
+            },
+            CircuitProfile::Test,
+        );
+        builder.check_majority();
+        builder.check_signature();
+        builder.build();
+    }
+
     #[test]
     fn prove_and_verify_accept_matching_inputs() {
         let (credential, signature, authentification) =
@@ -305,7 +1153,7 @@ mod tests {
         let (credential, signature, authentification) =
             valid_credential_signature_and_authentification(3);
         let mut public_inputs = matching_public_inputs(&credential);
-        public_inputs.nationality = F::from_canonical_u64(251);
+        public_inputs.nationality = Some(F::from_canonical_u64(251));
         let merkle_path = for_tests::DATABASE
             .proof(&merkle::hash::credential(&credential))
             .unwrap();
@@ -319,20 +1167,24 @@ mod tests {
             &merkle_path,
             &public_inputs,
         );
-        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<Failure>(),
+            Some(&Failure::Nationality)
+        );
     }
 
     #[test]
-    fn prove_rejects_wrong_pseudonym_public_input() {
+    fn prove_rejects_holder_under_the_minimal_age() {
         let (credential, signature, authentification) =
-            valid_credential_signature_and_authentification(4);
+            valid_credential_signature_and_authentification(3);
         let mut public_inputs = matching_public_inputs(&credential);
-        public_inputs.pseudonym.0[0] += F::ONE;
+        let age_days = today_for_tests() - days_from_origin(*credential.birth_date());
+        public_inputs.min_age_days = (age_days + 1).to_field();
         let merkle_path = for_tests::DATABASE
             .proof(&merkle::hash::credential(&credential))
             .unwrap();
 
-        let c = circuit();
+        let c = circuit_without_signature();
         let result = prove(
             &c,
             &credential,
@@ -341,7 +1193,107 @@ mod tests {
             &merkle_path,
             &public_inputs,
         );
-        assert!(result.is_err());
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<Failure>(),
+            Some(&Failure::Majority)
+        );
+    }
+
+    #[test]
+    fn prove_age_on_a_reference_date() {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(5);
+        let majority_day = days_from_origin(*credential.birth_date()) + ADULT_AGE_DAYS;
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+
+        let c = circuit_without_signature();
+        let public_inputs =
+            matching_public_inputs(&credential).age_on(majority_day, ADULT_AGE_DAYS);
+        let proof = prove(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &public_inputs,
+        )
+        .unwrap();
+        verify(&c.circuit, proof, public_inputs).unwrap();
+
+        // the day before, the holder was still too young
+        let public_inputs =
+            matching_public_inputs(&credential).age_on(majority_day - 1, ADULT_AGE_DAYS);
+        let result = prove(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &public_inputs,
+        );
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<Failure>(),
+            Some(&Failure::Majority)
+        );
+    }
+
+    #[test]
+    fn prove_age_over_a_threshold_fixed_by_the_circuit() {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(6);
+        let age_days = today_for_tests() - days_from_origin(*credential.birth_date());
+        let years = age_days / 366;
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let prove_age_over = |circuit_years, min_age_days: u32| {
+            let mut builder = super::Builder::setup_with_profile(
+                inputs::Features::default(),
+                CircuitProfile::Test,
+            );
+            builder.check_age_over(circuit_years);
+            builder.check_signature();
+            let c = builder.build();
+            let mut public_inputs = matching_public_inputs(&credential);
+            public_inputs.min_age_days = min_age_days.to_field();
+            prove(
+                &c,
+                &credential,
+                &signature,
+                &authentification,
+                &merkle_path,
+                &public_inputs,
+            )
+        };
+
+        prove_age_over(years, date::min_age_days(years)).unwrap();
+        // the public input can't lower the threshold of the circuit
+        assert!(prove_age_over(years + 2, date::min_age_days(years)).is_err());
+        assert!(prove_age_over(years + 2, date::min_age_days(years + 2)).is_err());
+    }
+
+    #[test]
+    fn prove_rejects_wrong_pseudonym_public_input() {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(4);
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.pseudonym.0[0] += F::ONE;
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+
+        let c = circuit();
+        let result = prove(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &public_inputs,
+        );
+        assert!(result.is_err());
     }
 
     #[test]
@@ -364,7 +1316,7 @@ mod tests {
         .unwrap();
 
         let mut wrong_public_inputs = matching_public_inputs(&credential);
-        wrong_public_inputs.cutoff18_days += F::ONE;
+        wrong_public_inputs.today_days += F::ONE;
         let result = verify(&c.circuit, proof, wrong_public_inputs);
         assert!(result.is_err());
     }
@@ -419,7 +1371,7 @@ mod tests {
         .unwrap();
 
         let mut wrong_public_inputs = matching_public_inputs(&credential);
-        wrong_public_inputs.nationality = F::from_canonical_u64(251);
+        wrong_public_inputs.nationality = Some(F::from_canonical_u64(251));
 
         let result = verify(&c.circuit, proof, wrong_public_inputs);
         assert!(result.is_err());
@@ -431,9 +1383,10 @@ mod tests {
     //     use std::panic::{catch_unwind, AssertUnwindSafe};
 
     //     let mut rng = StdRng::seed_from_u64(5);
-    //     let credential = Credential::random_minor(&mut rng);
+    //     let issuer = IssuerIdentity::for_tests();
+    //     let credential = Credential::random_minor(&issuer, &mut rng);
     //     let ctx = SignatureContext::new(&credential);
-    //     let signature = Signature::sign(&issuer::keys::secret(), &ctx);
+    //     let signature = issuer.sign(&credential);
     //     let authentification = default_authentification();
     //         let merkle_path = for_tests::DATABASE.proof(&merkle::hash::credential(&credential)).unwrap();
     //     let c = circuit_without_signature();
@@ -454,11 +1407,11 @@ mod tests {
     #[test]
     fn prove_rejects_signature_with_wrong_secret() {
         let mut rng = StdRng::seed_from_u64(6);
-        let issuer_sk = issuer::keys::secret();
-        let credential = Credential::random_with_issuer(&issuer_sk, &mut rng);
+        let issuer = IssuerIdentity::for_tests();
+        let credential = Credential::random_with_issuer(issuer.secret_key(), &mut rng);
         let wrong_signing_sk = SecretKey::random(&mut rng);
         let ctx = SignatureContext::new(&credential);
-        let signature = Signature::sign(&wrong_signing_sk, &ctx);
+        let signature = Signature::prove(&wrong_signing_sk, &ctx);
         let authentification = default_authentification();
         let merkle_path = {
             let credential_in_database = Credential::from_seed(0).2;
@@ -477,6 +1430,1059 @@ mod tests {
             &merkle_path,
             &public_inputs,
         );
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<Failure>(),
+            Some(&Failure::Signature)
+        );
+    }
+
+    #[test]
+    fn prove_rejects_lent_credential() {
+        let (_, credential, signature, _) = valid_holder_credential(3);
+        // the borrower authentifies with their own key for the same session
+        let (borrower_sk, _, _) = Credential::from_seed(4);
+        let borrower_ctx = AuthentificationContext::new(
+            &PublicKey::from(&borrower_sk),
+            &bank::service(),
+            &bank::nonce(),
+        );
+        let authentification = Authentification::prove(&borrower_sk, &borrower_ctx);
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let public_inputs = matching_public_inputs(&credential);
+        let c = circuit();
+
+        let result = prove(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &public_inputs,
+        );
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<Failure>(),
+            Some(&Failure::Authentification)
+        );
+    }
+
+    fn root_certificate(seed: u64, issuer: &PublicKey) -> (PublicKey, Certificate) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let root_sk = SecretKey::random(&mut rng);
+        let certificate = Certificate::issue(
+            &root_sk,
+            issuer,
+            NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2030, 1, 1).unwrap(),
+        );
+        (PublicKey::from(&root_sk), certificate)
+    }
+
+    #[test]
+    fn prove_certified_accepts_issuer_certified_by_root() {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(0);
+        let (root_pk, certificate) = root_certificate(10, &credential.issuer());
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.issuer_pk = root_pk.0.to_field();
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let c = circuit_certified();
+
+        let proof = prove_with(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &Extra {
+                certificate: Some(&certificate),
+                ..Extra::default()
+            },
+            &public_inputs,
+        )
+        .unwrap();
+        verify(&c.circuit, proof, public_inputs).unwrap();
+    }
+
+    #[test]
+    fn prove_certified_rejects_other_root() {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(1);
+        let (_, certificate) = root_certificate(11, &credential.issuer());
+        let (other_root_pk, _) = root_certificate(12, &credential.issuer());
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.issuer_pk = other_root_pk.0.to_field();
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let c = circuit_certified();
+
+        let result = prove_with(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &Extra {
+                certificate: Some(&certificate),
+                ..Extra::default()
+            },
+            &public_inputs,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn prove_issuer_set_hides_the_issuer_among_the_set() {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(1);
+        let others = [2, 3].map(|seed| Credential::from_seed(seed).2.issuer());
+        let issuers = IssuerSet::new(&[others[0].clone(), credential.issuer(), others[1].clone()]);
+        let issuers = issuers.unwrap();
+        let public_inputs = matching_public_inputs(&credential).with_issuer_set(&issuers);
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let c = circuit_issuer_set();
+
+        let proof = prove_with(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &Extra {
+                issuer_set: Some(&issuers),
+                ..Extra::default()
+            },
+            &public_inputs,
+        )
+        .unwrap();
+        verify(&c.circuit, proof, public_inputs).unwrap();
+
+        let without = IssuerSet::new(&others).unwrap();
+        let result = prove_with(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &Extra {
+                issuer_set: Some(&without),
+                ..Extra::default()
+            },
+            &matching_public_inputs(&credential).with_issuer_set(&without),
+        );
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<Failure>(),
+            Some(&Failure::Issuer)
+        );
+    }
+
+    #[test]
+    fn prove_with_nym_accepts_holder_secret() {
+        let (client_sk, credential, signature, authentification) = valid_holder_credential(2);
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.nym = Some(pseudonym::domain_nym(&bank::service(), &client_sk));
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let c = circuit_with_nym();
+
+        let proof = prove_with(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &Extra {
+                holder_secret: Some(&client_sk),
+                ..Extra::default()
+            },
+            &public_inputs,
+        )
+        .unwrap();
+        verify(&c.circuit, proof, public_inputs).unwrap();
+    }
+
+    #[test]
+    fn prove_with_nym_rejects_nym_of_other_domain() {
+        let (client_sk, credential, signature, authentification) = valid_holder_credential(3);
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.nym = Some(pseudonym::domain_nym("other-service", &client_sk));
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let c = circuit_with_nym();
+
+        let result = prove_with(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &Extra {
+                holder_secret: Some(&client_sk),
+                ..Extra::default()
+            },
+            &public_inputs,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn check_credential_nullifier_binds_the_holder_secret_alone() {
+        let (client_sk, credential, signature, authentification) = valid_holder_credential(4);
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let prove_nullifier = |sk: &SecretKey| {
+            let features = inputs::Features {
+                credential_nullifier: true,
+                ..inputs::Features::default()
+            };
+            let mut builder = super::Builder::setup_with_profile(features, CircuitProfile::Test);
+            builder.check_credential_nullifier();
+            let c = builder.build();
+            let mut public_inputs = matching_public_inputs(&credential);
+            public_inputs.credential_nullifier = Some(pseudonym::credential_nullifier(
+                &credential,
+                sk,
+                &bank::service(),
+            ));
+            prove_with(
+                &c,
+                &credential,
+                &signature,
+                &authentification,
+                &merkle_path,
+                &Extra {
+                    holder_secret: Some(sk),
+                    ..Extra::default()
+                },
+                &public_inputs,
+            )
+        };
+        prove_nullifier(&client_sk).unwrap();
+        // otherwise any secret would mint a fresh nullifier
+        let other_sk = SecretKey::random(&mut StdRng::seed_from_u64(4));
+        assert!(prove_nullifier(&other_sk).is_err());
+    }
+
+    #[test]
+    fn prove_k_show_accepts_counter_below_k() {
+        let (client_sk, credential, signature, authentification) = valid_holder_credential(4);
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.rate_limit = Some(inputs::RateLimit {
+            epoch: F::from_canonical_u32(2930),
+            nullifier: rate_limit::nullifier(&client_sk, 2930, 1),
+        });
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let c = circuit_k_show(2);
+
+        let proof = prove_with(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &Extra {
+                holder_secret: Some(&client_sk),
+                counter: Some(1),
+                ..Extra::default()
+            },
+            &public_inputs,
+        )
+        .unwrap();
+        verify(&c.circuit, proof, public_inputs).unwrap();
+    }
+
+    #[test]
+    fn prove_k_show_rejects_counter_reaching_k() {
+        let (client_sk, credential, signature, authentification) = valid_holder_credential(5);
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.rate_limit = Some(inputs::RateLimit {
+            epoch: F::from_canonical_u32(2930),
+            nullifier: rate_limit::nullifier(&client_sk, 2930, 2),
+        });
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let c = circuit_k_show(2);
+
+        let result = prove_with(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &Extra {
+                holder_secret: Some(&client_sk),
+                counter: Some(2),
+                ..Extra::default()
+            },
+            &public_inputs,
+        );
         assert!(result.is_err());
     }
+
+    #[test]
+    fn prove_recently_issued_accepts_issue_date_after_bound() {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(6);
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.issued_after = Some(days_from_origin(*credential.date_of_issue()).to_field());
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let c = circuit_recently_issued();
+
+        let proof = prove(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &public_inputs,
+        )
+        .unwrap();
+        verify(&c.circuit, proof, public_inputs).unwrap();
+    }
+
+    #[test]
+    fn prove_not_expired_through_the_expiration_date() {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(4);
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let features = inputs::Features {
+            expiration: true,
+            ..inputs::Features::default()
+        };
+        let c = circuit_with_profile(features, CircuitProfile::Test);
+        let prove_on = |day: u32| -> anyhow::Result<()> {
+            let public_inputs = matching_public_inputs(&credential).with_current_date(day);
+            let proof = prove(
+                &c,
+                &credential,
+                &signature,
+                &authentification,
+                &merkle_path,
+                &public_inputs,
+            )?;
+            verify(&c.circuit, proof, public_inputs)
+        };
+        let expiration_date = days_from_origin(*credential.expiration_date());
+        prove_on(expiration_date).unwrap();
+        let result = prove_on(expiration_date + 1);
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<Failure>(),
+            Some(&Failure::Expired)
+        );
+    }
+
+    #[test]
+    fn prove_rejects_credential_issued_before_bound() {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(7);
+        let mut public_inputs = matching_public_inputs(&credential);
+        let date_of_issue = days_from_origin(*credential.date_of_issue());
+        public_inputs.issued_after = Some((date_of_issue + 1).to_field());
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let mut builder = super::Builder::setup_with(inputs::Features {
+            recent_issuance: true,
+            ..inputs::Features::default()
+        });
+        builder.check_date_of_issue();
+        let c = builder.build();
+
+        let result = prove(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &public_inputs,
+        );
+        assert!(result.is_err());
+    }
+
+    fn prove_authority_country(seed: u64, country: F) -> anyhow::Result<()> {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(seed);
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.authority_country = Some(country);
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let features = inputs::Features {
+            authority_country: true,
+            ..inputs::Features::default()
+        };
+        let c = circuit_with_profile(features, CircuitProfile::Test);
+
+        let proof = prove(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &public_inputs,
+        )?;
+        verify(&c.circuit, proof, public_inputs)
+    }
+
+    #[test]
+    fn prove_discloses_country_of_issuing_authority() {
+        let (_, _, credential) = Credential::from_seed(3);
+        let country = credential.issuing_authority().country.to_field();
+        prove_authority_country(3, country).unwrap();
+        assert!(prove_authority_country(3, country + F::ONE).is_err());
+    }
+
+    fn prove_portrait_hash(seed: u64, portrait_hash: Hash<F>) -> anyhow::Result<()> {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(seed);
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.portrait_hash = Some(portrait_hash);
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let features = inputs::Features {
+            portrait_hash: true,
+            ..inputs::Features::default()
+        };
+        let c = circuit_with_profile(features, CircuitProfile::Test);
+
+        let proof = prove(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &public_inputs,
+        )?;
+        verify(&c.circuit, proof, public_inputs)
+    }
+
+    #[test]
+    fn prove_discloses_portrait_hash() {
+        let (_, _, credential) = Credential::from_seed(4);
+        let portrait_hash = credential.to_field::<F>().portrait_hash;
+        prove_portrait_hash(4, portrait_hash).unwrap();
+        let (_, _, other) = Credential::from_seed(5);
+        assert!(prove_portrait_hash(4, other.to_field().portrait_hash).is_err());
+    }
+
+    fn prove_credential_id(seed: u64, salt: Hash<F>, id: Hash<F>) -> anyhow::Result<()> {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(seed);
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.credential_id = Some(inputs::CredentialId { salt, id });
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let features = inputs::Features {
+            credential_id: true,
+            ..inputs::Features::default()
+        };
+        let c = circuit_with_profile(features, CircuitProfile::Test);
+
+        let proof = prove(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &public_inputs,
+        )?;
+        verify(&c.circuit, proof, public_inputs)
+    }
+
+    #[test]
+    fn prove_exposes_credential_id() {
+        let (_, _, credential) = Credential::from_seed(2);
+        let salt = Hash([F::from_canonical_u64(7); LEN_HASH]);
+        let id = pseudonym::credential_id(&credential, &salt);
+        prove_credential_id(2, salt, id).unwrap();
+        let other_salt = Hash([F::from_canonical_u64(8); LEN_HASH]);
+        assert_ne!(id, pseudonym::credential_id(&credential, &other_salt));
+        assert!(prove_credential_id(2, other_salt, id).is_err());
+    }
+
+    fn prove_document_commitment(
+        seed: u64,
+        salt: Hash<F>,
+        commitment: Hash<F>,
+    ) -> anyhow::Result<()> {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(seed);
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.document_commitment = Some(inputs::DocumentCommitment { salt, commitment });
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let features = inputs::Features {
+            document_commitment: true,
+            ..inputs::Features::default()
+        };
+        let c = circuit_with_profile(features, CircuitProfile::Test);
+
+        let proof = prove(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &public_inputs,
+        )?;
+        verify(&c.circuit, proof, public_inputs)
+    }
+
+    #[test]
+    fn prove_commits_to_the_document_for_the_epoch() {
+        let (_, _, credential) = Credential::from_seed(5);
+        let salt = Hash([F::from_canonical_u64(3); LEN_HASH]);
+        let commitment = pseudonym::document_commitment(&credential, &salt);
+        prove_document_commitment(5, salt, commitment).unwrap();
+        let next_salt = Hash([F::from_canonical_u64(4); LEN_HASH]);
+        assert_ne!(
+            commitment,
+            pseudonym::document_commitment(&credential, &next_salt)
+        );
+        assert!(prove_document_commitment(5, next_salt, commitment).is_err());
+    }
+
+    fn prove_continuity(
+        seed: u64,
+        record: &ContinuityRecord,
+        previous_id: Hash<F>,
+    ) -> anyhow::Result<()> {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(seed);
+        let salt = Hash([F::from_canonical_u64(7); LEN_HASH]);
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.credential_id = Some(inputs::CredentialId {
+            salt,
+            id: pseudonym::credential_id(&credential, &salt),
+        });
+        public_inputs.previous_id = Some(previous_id);
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let features = inputs::Features {
+            credential_id: true,
+            continuity: true,
+            ..inputs::Features::default()
+        };
+        let c = circuit_with_profile(features, CircuitProfile::Test);
+
+        let proof = prove_with(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &Extra {
+                continuity: Some(record),
+                ..Extra::default()
+            },
+            &public_inputs,
+        )?;
+        verify(&c.circuit, proof, public_inputs)
+    }
+
+    #[test]
+    fn prove_links_amended_credential_to_its_predecessor() {
+        let (_, issuer_sk, credential) = Credential::from_seed(3);
+        let (_, _, predecessor) = Credential::from_seed(6);
+        let salt = Hash([F::from_canonical_u64(7); LEN_HASH]);
+        let previous_id = pseudonym::credential_id(&predecessor, &salt);
+        let record = ContinuityRecord::sign(&issuer_sk, predecessor, &credential);
+        assert!(record.verify(&credential));
+        prove_continuity(3, &record, previous_id).unwrap();
+
+        let (_, _, other) = Credential::from_seed(7);
+        let other_id = pseudonym::credential_id(&other, &salt);
+        assert!(prove_continuity(3, &record, other_id).is_err());
+        // the record links another credential
+        assert!(prove_continuity(4, &record, previous_id).is_err());
+    }
+
+    fn prove_name_hash(seed: u64, salt: Hash<F>, name_hash: Hash<F>) -> anyhow::Result<()> {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(seed);
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.name_hash = Some(name_hash);
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let features = inputs::Features {
+            name_hash: true,
+            ..inputs::Features::default()
+        };
+        let c = circuit_with_profile(features, CircuitProfile::Test);
+
+        let proof = prove_with(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &Extra {
+                name_salt: Some(&salt),
+                ..Extra::default()
+            },
+            &public_inputs,
+        )?;
+        verify(&c.circuit, proof, public_inputs)
+    }
+
+    #[test]
+    fn prove_matches_family_name_to_salted_hash() {
+        let (_, _, credential) = Credential::from_seed(6);
+        let salt = Hash([F::from_canonical_u64(9); LEN_HASH]);
+        let name_hash = pseudonym::name_hash(credential.family_name(), &salt).unwrap();
+        prove_name_hash(6, salt, name_hash).unwrap();
+        let (_, _, other) = Credential::from_seed(7);
+        let other_hash = pseudonym::name_hash(other.family_name(), &salt).unwrap();
+        assert!(prove_name_hash(6, salt, other_hash).is_err());
+        let other_salt = Hash([F::from_canonical_u64(10); LEN_HASH]);
+        assert!(prove_name_hash(6, other_salt, name_hash).is_err());
+    }
+
+    /// With the Merkle path of the credential of path_seed
+    fn prove_account(
+        seed: u64,
+        signed: &[u8],
+        exposed: &[u8],
+        path_seed: u64,
+    ) -> anyhow::Result<()> {
+        let (client_sk, credential, signature, _) = valid_holder_credential(seed);
+        let auth_ctx = AuthentificationContext::new(
+            &credential.public_key(),
+            &bank::service(),
+            &bank::nonce(),
+        )
+        .with_account(signed)?;
+        let authentification = Authentification::prove(&client_sk, &auth_ctx);
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.account = Some(exposed.try_to_field()?);
+        let (_, _, path_credential) = Credential::from_seed(path_seed);
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&path_credential))
+            .unwrap();
+        let features = inputs::Features {
+            account_binding: true,
+            ..inputs::Features::default()
+        };
+        let c = circuit_with_profile(features, CircuitProfile::Test);
+
+        let proof = prove(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &public_inputs,
+        )?;
+        verify(&c.circuit, proof, public_inputs)
+    }
+
+    #[test]
+    fn prove_binds_external_account() {
+        let address = [0x52; 20];
+        prove_account(1, &address, &address, 1).unwrap();
+        assert!(prove_account(1, &address, b"customer-42", 1).is_err());
+    }
+
+    #[test]
+    fn prove_diagnoses_account_bound_circuits() {
+        let address = [0x52; 20];
+        // the authentification holds, the Merkle path is the one of another
+        // credential
+        assert_eq!(
+            prove_account(1, &address, &address, 2)
+                .unwrap_err()
+                .downcast_ref::<Failure>(),
+            Some(&Failure::MerkleProof)
+        );
+    }
+
+    fn prove_derived_key(seed: u64, domain: &str) -> anyhow::Result<()> {
+        let (client_sk, credential, signature, _) = valid_holder_credential(seed);
+        let derived_sk = client::keys::derive(&client_sk, domain);
+        let derived_pk = PublicKey::from(&derived_sk);
+        let auth_ctx = AuthentificationContext::new(&derived_pk, &bank::service(), &bank::nonce());
+        let authentification = Authentification::prove(&derived_sk, &auth_ctx);
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.derived_key = Some(derived_pk.0.to_field());
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let features = inputs::Features {
+            derived_key: true,
+            ..inputs::Features::default()
+        };
+        let c = circuit_with_profile(features, CircuitProfile::Test);
+
+        let proof = prove_with(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &Extra {
+                holder_secret: Some(&client_sk),
+                ..Extra::default()
+            },
+            &public_inputs,
+        )?;
+        verify(&c.circuit, proof, public_inputs)
+    }
+
+    #[test]
+    fn prove_authentifies_with_key_derived_for_service() {
+        prove_derived_key(6, &bank::service()).unwrap();
+        assert!(prove_derived_key(6, "other-service").is_err());
+    }
+
+    fn prove_time_token(seed: u64, today: NaiveDate) -> anyhow::Result<()> {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(seed);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let authority_sk = SecretKey::random(&mut rng);
+        let token = TimeToken::issue(&authority_sk, today, &bank::nonce());
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.time_authority = Some(PublicKey::from(&authority_sk).0.to_field());
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let features = inputs::Features {
+            time_authority: true,
+            ..inputs::Features::default()
+        };
+        let mut builder = super::Builder::setup_with_profile(features, CircuitProfile::Test);
+        builder.check_majority();
+        builder.check_time_token();
+        let c = builder.build();
+
+        let proof = prove_with(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &Extra {
+                time_token: Some(&token),
+                ..Extra::default()
+            },
+            &public_inputs,
+        )?;
+        verify(&c.circuit, proof, public_inputs)
+    }
+
+    fn prove_consortium_nullifier(
+        seed: u64,
+        scope: &str,
+        nullifier: Hash<F>,
+    ) -> anyhow::Result<()> {
+        let (client_sk, credential, signature, authentification) = valid_holder_credential(seed);
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.consortium = Some(inputs::Consortium {
+            scope: scope.to_string().to_field(),
+            nullifier,
+        });
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let features = inputs::Features {
+            consortium: true,
+            ..inputs::Features::default()
+        };
+        let c = circuit_with_profile(features, CircuitProfile::Test);
+
+        let proof = prove_with(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &Extra {
+                holder_secret: Some(&client_sk),
+                ..Extra::default()
+            },
+            &public_inputs,
+        )?;
+        verify(&c.circuit, proof, public_inputs)
+    }
+
+    fn prove_excluding(seed: u64, excluded: &[u16]) -> anyhow::Result<()> {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(seed);
+        let excluded = CountrySet::new(excluded).unwrap();
+        let mut public_inputs = matching_public_inputs(&credential);
+        public_inputs.nationality = None;
+        public_inputs.excluded_nationalities = Some(excluded.commitment());
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let features = inputs::Features {
+            nationality_exclusion: true,
+            ..inputs::Features::default()
+        };
+        let c = circuit_with_profile(features, CircuitProfile::Test);
+
+        let proof = prove_with(
+            &c,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &Extra {
+                excluded_nationalities: Some(&excluded),
+                ..Extra::default()
+            },
+            &public_inputs,
+        )?;
+        verify(&c.circuit, proof, public_inputs)
+    }
+
+    #[test]
+    fn prove_nationality_outside_of_excluded_set() {
+        prove_excluding(6, &[364, 408, 760]).unwrap();
+        assert!(prove_excluding(6, &[250, 408]).is_err());
+    }
+
+    #[test]
+    fn prove_nationality_in_allowed_set() {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(6);
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let features = inputs::Features {
+            nationality_allow_set: true,
+            ..inputs::Features::default()
+        };
+        let c = circuit_with_profile(features, CircuitProfile::Test);
+        let prove = |allowed: &[u16]| -> anyhow::Result<()> {
+            let allowed = CountrySet::new(allowed).unwrap();
+            let public_inputs =
+                matching_public_inputs(&credential).with_allowed_nationalities(&allowed);
+            let proof = prove_with(
+                &c,
+                &credential,
+                &signature,
+                &authentification,
+                &merkle_path,
+                &Extra {
+                    allowed_nationalities: Some(&allowed),
+                    ..Extra::default()
+                },
+                &public_inputs,
+            )?;
+            verify(&c.circuit, proof, public_inputs)
+        };
+        // e.g. a few member states of the EU
+        prove(&[250, 276, 380, 724]).unwrap();
+        assert!(prove(&[276, 380]).is_err());
+    }
+
+    #[test]
+    fn prove_credential_not_revoked_against_published_root() {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(5);
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let features = inputs::Features {
+            non_revocation: true,
+            ..inputs::Features::default()
+        };
+        let c = circuit_with_profile(features, CircuitProfile::Test);
+        let prove = |tree: &revocation::Tree, path: &revocation::Path| -> anyhow::Result<()> {
+            let public_inputs =
+                matching_public_inputs(&credential).with_revocation_root(tree.root());
+            let proof = prove_with(
+                &c,
+                &credential,
+                &signature,
+                &authentification,
+                &merkle_path,
+                &Extra {
+                    non_revocation: Some(path),
+                    ..Extra::default()
+                },
+                &public_inputs,
+            )?;
+            verify(&c.circuit, proof, public_inputs)
+        };
+        let serial = revocation::serial(&credential);
+        let mut tree = revocation::Tree::new();
+        tree.revoke(&revocation::serial(&Credential::from_seed(4).2))
+            .unwrap();
+        let path = tree.prove(&serial).unwrap();
+        prove(&tree, &path).unwrap();
+
+        tree.revoke(&serial).unwrap();
+        assert!(prove(&tree, &path).is_err());
+    }
+
+    #[test]
+    fn universal_circuit_only_enforces_flagged_predicates() {
+        let (credential, signature, authentification) =
+            valid_credential_signature_and_authentification(7);
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        // every predicate but majority & nationality is false for the credential
+        let excluded = CountrySet::new(&[credential.nationality().code()]).unwrap();
+        let allowed = CountrySet::new(&[]).unwrap();
+        let expired_on = days_from_origin(*credential.expiration_date()) + 1;
+        let c = circuit_with_profile(inputs::Features::universal(), CircuitProfile::Test);
+        let prove_flagged = |flags: inputs::Flags<bool>| -> anyhow::Result<()> {
+            let mut public_inputs = matching_public_inputs(&credential)
+                .with_flags(flags)
+                .with_current_date(expired_on);
+            public_inputs.issued_after = Some(u32::MAX.to_field());
+            public_inputs.authority_country =
+                Some(credential.issuing_authority().country.to_field() + F::ONE);
+            public_inputs.excluded_nationalities = Some(excluded.commitment());
+            public_inputs.min_age_years = Some(200u32.to_field());
+            public_inputs.allowed_nationalities = Some(allowed.commitment());
+            let proof = prove_with(
+                &c,
+                &credential,
+                &signature,
+                &authentification,
+                &merkle_path,
+                &Extra {
+                    excluded_nationalities: Some(&excluded),
+                    allowed_nationalities: Some(&allowed),
+                    ..Extra::default()
+                },
+                &public_inputs,
+            )?;
+            verify(&c.circuit, proof, public_inputs)
+        };
+
+        prove_flagged(inputs::Flags {
+            nationality: true,
+            majority: true,
+            ..inputs::Flags::default()
+        })
+        .unwrap();
+        for flags in [
+            inputs::Flags {
+                recent_issuance: true,
+                ..inputs::Flags::default()
+            },
+            inputs::Flags {
+                authority_country: true,
+                ..inputs::Flags::default()
+            },
+            inputs::Flags {
+                nationality_exclusion: true,
+                ..inputs::Flags::default()
+            },
+            inputs::Flags {
+                calendar_age: true,
+                ..inputs::Flags::default()
+            },
+            inputs::Flags {
+                nationality_allow_set: true,
+                ..inputs::Flags::default()
+            },
+            inputs::Flags {
+                expiration: true,
+                ..inputs::Flags::default()
+            },
+        ] {
+            assert!(prove_flagged(flags).is_err());
+        }
+    }
+
+    #[test]
+    fn prove_exposes_consortium_nullifier() {
+        let (client_sk, _, credential) = Credential::from_seed(5);
+        let nullifier = consortium::nullifier("EU-AML", &credential, &client_sk);
+        prove_consortium_nullifier(5, "EU-AML", nullifier).unwrap();
+        assert!(prove_consortium_nullifier(5, "US-BSA", nullifier).is_err());
+        // recomputed from the identity with another secret
+        let (other_sk, _, _) = Credential::from_seed(6);
+        let guessed = consortium::nullifier("EU-AML", &credential, &other_sk);
+        assert!(prove_consortium_nullifier(5, "EU-AML", guessed).is_err());
+    }
+
+    #[test]
+    fn prove_takes_today_from_the_time_token() {
+        let today = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        assert_eq!(days_from_origin(today), today_for_tests());
+        prove_time_token(4, today).unwrap();
+        // the prover can't pick another date than the public one
+        assert!(prove_time_token(4, today.succ_opt().unwrap()).is_err());
+    }
+
+    #[test]
+    fn prove_rejects_inconsistent_mrz_check_digits() {
+        let (_, issuer_sk, mut credential) = Credential::from_seed(2);
+        let mut check_digits = credential.mrz_check_digits();
+        check_digits.document_number = (check_digits.document_number + 1) % 10;
+        credential.set_mrz_check_digits(check_digits);
+        let signature = credential.sign(&issuer_sk);
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&Credential::from_seed(2).2))
+            .unwrap();
+        let mut builder = super::Builder::setup();
+        builder.check_mrz();
+        let c = builder.build();
+
+        let result = prove(
+            &c,
+            &credential,
+            &signature,
+            &default_authentification(),
+            &merkle_path,
+            &matching_public_inputs(&credential),
+        );
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<Failure>(),
+            Some(&Failure::Mrz)
+        );
+    }
+
+    #[test]
+    fn prove_rejects_credential_of_other_schema_version() {
+        let (_, issuer_sk, mut credential) = Credential::from_seed(8);
+        credential.set_schema_version(SCHEMA_VERSION + 1);
+        let signature = credential.sign(&issuer_sk);
+        let merkle_path = for_tests::DATABASE
+            .proof(&merkle::hash::credential(&Credential::from_seed(8).2))
+            .unwrap();
+        let mut builder = super::Builder::setup();
+        builder.check_schema_version();
+        let c = builder.build();
+
+        let result = prove(
+            &c,
+            &credential,
+            &signature,
+            &default_authentification(),
+            &merkle_path,
+            &matching_public_inputs(&credential),
+        );
+        assert_eq!(
+            result.unwrap_err().downcast_ref::<Failure>(),
+            Some(&Failure::SchemaVersion)
+        );
+    }
 }
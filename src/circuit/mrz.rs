@@ -0,0 +1,319 @@
+// In-circuit counterpart of core::mrz: the check digits the issuer copied into
+// the credential must match its passport number and dates, so that a
+// credential with inconsistent MRZ data can't be proven even if it was signed.
+
+use plonky2::{
+    field::{
+        extension::Extendable,
+        types::{Field, PrimeField64},
+    },
+    hash::hash_types::RichField,
+    iop::{
+        generator::{GeneratedValues, SimpleGenerator},
+        target::{BoolTarget, Target},
+        witness::{PartitionWitness, Witness, WitnessWrite},
+    },
+    plonk::{circuit_builder::CircuitBuilder, circuit_data::CommonCircuitData},
+    util::serialization::{Buffer, IoResult, Read, Write},
+};
+
+use crate::{
+    circuit::credential::CredentialTarget,
+    core::mrz::{LEN_DOCUMENT_NUMBER, WEIGHTS},
+    encoding,
+};
+
+pub type MrzCheckDigitsTarget = encoding::MrzCheckDigits<Target>;
+
+/// Days from 0000-03-01 to core::date ORIGIN (1900-01-01), in the proleptic
+/// Gregorian calendar: civil_from_days counts from the former
+const ORIGIN_FROM_MARCH_0000: u64 = 693_901;
+
+pub trait CircuitBuilderMrz<F: RichField + Extendable<D>, const D: usize> {
+    fn add_virtual_mrz_check_digits_target(&mut self) -> MrzCheckDigitsTarget;
+    /// Quotient & remainder of x by a constant, asserting x < divisor * 2^32
+    fn div_rem_const(&mut self, x: Target, divisor: u64) -> (Target, Target);
+    /// Year, month & day of a number of days since core::date ORIGIN
+    fn civil_from_days(&mut self, days: Target) -> (Target, Target, Target);
+    /// Values of the YYMMDD field of the date, see core::mrz::date_field
+    fn mrz_date_field(&mut self, days: Target) -> [Target; 6];
+    /// Values of the characters of the passport number, which must be digits
+    /// or uppercase letters
+    fn mrz_document_number(
+        &mut self,
+        number: encoding::PassportNumber<Target>,
+    ) -> [Target; LEN_DOCUMENT_NUMBER];
+    /// Mirrors core::mrz::check_digit, from the values of the characters
+    fn mrz_check_digit(&mut self, values: &[Target]) -> Target;
+    /// Asserts that the check digits of the credential match its data
+    fn check_mrz(&mut self, credential: &CredentialTarget);
+}
+pub trait PartialWitnessMrz<F: RichField>: Witness<F> {
+    fn get_mrz_check_digits_target(
+        &self,
+        target: MrzCheckDigitsTarget,
+    ) -> encoding::MrzCheckDigits<F>;
+    fn set_mrz_check_digits_target(
+        &mut self,
+        target: MrzCheckDigitsTarget,
+        value: encoding::MrzCheckDigits<F>,
+    ) -> anyhow::Result<()>;
+}
+
+/// Computes the quotient & remainder constrained by div_rem_const
+#[derive(Debug)]
+struct DivRemGenerator {
+    x: Target,
+    divisor: u64,
+    quotient: Target,
+    remainder: Target,
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> SimpleGenerator<F, D> for DivRemGenerator {
+    fn id(&self) -> String {
+        "DivRemGenerator".to_string()
+    }
+    fn dependencies(&self) -> Vec<Target> {
+        vec![self.x]
+    }
+    fn run_once(
+        &self,
+        witness: &PartitionWitness<F>,
+        out_buffer: &mut GeneratedValues<F>,
+    ) -> anyhow::Result<()> {
+        let x = witness.get_target(self.x).to_canonical_u64();
+        out_buffer.set_target(self.quotient, F::from_canonical_u64(x / self.divisor))?;
+        out_buffer.set_target(self.remainder, F::from_canonical_u64(x % self.divisor))
+    }
+    fn serialize(&self, dst: &mut Vec<u8>, _common_data: &CommonCircuitData<F, D>) -> IoResult<()> {
+        dst.write_target(self.x)?;
+        dst.write_usize(self.divisor as usize)?;
+        dst.write_target(self.quotient)?;
+        dst.write_target(self.remainder)
+    }
+    fn deserialize(src: &mut Buffer, _common_data: &CommonCircuitData<F, D>) -> IoResult<Self> {
+        Ok(Self {
+            x: src.read_target()?,
+            divisor: src.read_usize()? as u64,
+            quotient: src.read_target()?,
+            remainder: src.read_target()?,
+        })
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderMrz<F, D>
+    for CircuitBuilder<F, D>
+{
+    fn add_virtual_mrz_check_digits_target(&mut self) -> MrzCheckDigitsTarget {
+        MrzCheckDigitsTarget {
+            document_number: self.add_virtual_target(),
+            birth_date: self.add_virtual_target(),
+            expiration_date: self.add_virtual_target(),
+        }
+    }
+
+    fn div_rem_const(&mut self, x: Target, divisor: u64) -> (Target, Target) {
+        assert!(divisor >= 2);
+        let quotient = self.add_virtual_target();
+        let remainder = self.add_virtual_target();
+        self.add_simple_generator(DivRemGenerator {
+            x,
+            divisor,
+            quotient,
+            remainder,
+        });
+        // remainder < divisor
+        let bits = (u64::BITS - (divisor - 1).leading_zeros()) as usize;
+        self.range_check(remainder, bits);
+        let max = self.constant(F::from_canonical_u64(divisor - 1));
+        let slack = self.sub(max, remainder);
+        self.range_check(slack, bits);
+        self.range_check(quotient, 32);
+        let got = self.mul_const_add(F::from_canonical_u64(divisor), quotient, remainder);
+        self.connect(got, x);
+        (quotient, remainder)
+    }
+
+    // civil_from_days of http://howardhinnant.github.io/date_algorithms.html:
+    // years start in March, so that the leap day is the last day of the year
+    fn civil_from_days(&mut self, days: Target) -> (Target, Target, Target) {
+        let z = self.add_const(days, F::from_canonical_u64(ORIGIN_FROM_MARCH_0000));
+        // eras of 400 years
+        let (era, doe) = self.div_rem_const(z, 146_097);
+        let (a, _) = self.div_rem_const(doe, 1460);
+        let (b, _) = self.div_rem_const(doe, 36_524);
+        let (c, _) = self.div_rem_const(doe, 146_096);
+        let t = self.sub(doe, a);
+        let t = self.add(t, b);
+        let t = self.sub(t, c);
+        let (yoe, _) = self.div_rem_const(t, 365);
+        let (leap, _) = self.div_rem_const(yoe, 4);
+        let (centuries, _) = self.div_rem_const(yoe, 100);
+        let t = self.mul_const_add(F::from_canonical_u64(365), yoe, leap);
+        let t = self.sub(t, centuries);
+        let doy = self.sub(doe, t);
+        // month from March
+        let t = self.mul_const(F::from_canonical_u64(5), doy);
+        let t = self.add_const(t, F::TWO);
+        let (mp, _) = self.div_rem_const(t, 153);
+        let t = self.mul_const(F::from_canonical_u64(153), mp);
+        let t = self.add_const(t, F::TWO);
+        let (first_day, _) = self.div_rem_const(t, 5);
+        let d = self.sub(doy, first_day);
+        let d = self.add_const(d, F::ONE);
+        // January and February belong to the next year
+        let (next_year, _) = self.div_rem_const(mp, 10);
+        let t = self.mul_const(F::from_canonical_u64(12), next_year);
+        let m = self.add_const(mp, F::from_canonical_u64(3));
+        let m = self.sub(m, t);
+        let y = self.mul_const_add(F::from_canonical_u64(400), era, yoe);
+        let y = self.add(y, next_year);
+        (y, m, d)
+    }
+
+    fn mrz_date_field(&mut self, days: Target) -> [Target; 6] {
+        let (y, m, d) = self.civil_from_days(days);
+        let (_, yy) = self.div_rem_const(y, 100);
+        let (y1, y2) = self.div_rem_const(yy, 10);
+        let (m1, m2) = self.div_rem_const(m, 10);
+        let (d1, d2) = self.div_rem_const(d, 10);
+        [y1, y2, m1, m2, d1, d2]
+    }
+
+    fn mrz_document_number(
+        &mut self,
+        number: encoding::PassportNumber<Target>,
+    ) -> [Target; LEN_DOCUMENT_NUMBER] {
+        let mut bytes = Vec::with_capacity(4 * number.0.len());
+        for limb in number.0 {
+            let bits = self.split_le(limb, 32);
+            for byte in bits.chunks(8) {
+                bytes.push(self.le_sum(byte.iter()));
+            }
+        }
+        let zero = self.constant(F::from_canonical_u8(b'0'));
+        std::array::from_fn(|i| {
+            // letters start at 65, digits are below
+            let (letter, _) = self.div_rem_const(bytes[i], 65);
+            self.assert_bool(BoolTarget::new_unsafe(letter));
+            // b'0' -> 0, ..., b'9' -> 9, b'A' -> 10, ..., b'Z' -> 35
+            let value = self.sub(bytes[i], zero);
+            let gap = self.mul_const(F::from_canonical_u64(7), letter);
+            let value = self.sub(value, gap);
+            // digits are in [0, 10), letters in [10, 36)
+            let start = self.mul_const(F::from_canonical_u64(10), letter);
+            let offset = self.sub(value, start);
+            let last = self.mul_const(F::from_canonical_u64(16), letter);
+            let last = self.add_const(last, F::from_canonical_u64(9));
+            let slack = self.sub(last, offset);
+            self.range_check(offset, 5);
+            self.range_check(slack, 5);
+            value
+        })
+    }
+
+    fn mrz_check_digit(&mut self, values: &[Target]) -> Target {
+        let mut sum = self.zero();
+        for (i, &value) in values.iter().enumerate() {
+            sum = self.mul_const_add(F::from_canonical_u8(WEIGHTS[i % 3]), value, sum);
+        }
+        self.div_rem_const(sum, 10).1
+    }
+
+    fn check_mrz(&mut self, credential: &CredentialTarget) {
+        let check_digits = credential.mrz_check_digits;
+        let number = self.mrz_document_number(credential.passport_number);
+        let got = self.mrz_check_digit(&number);
+        self.connect(got, check_digits.document_number);
+        for (date, check_digit) in [
+            (credential.birth_date, check_digits.birth_date),
+            (credential.expiration_date, check_digits.expiration_date),
+        ] {
+            let field = self.mrz_date_field(date);
+            let got = self.mrz_check_digit(&field);
+            self.connect(got, check_digit);
+        }
+    }
+}
+
+impl<W: Witness<F>, F: RichField> PartialWitnessMrz<F> for W {
+    fn get_mrz_check_digits_target(
+        &self,
+        target: MrzCheckDigitsTarget,
+    ) -> encoding::MrzCheckDigits<F> {
+        encoding::MrzCheckDigits {
+            document_number: self.get_target(target.document_number),
+            birth_date: self.get_target(target.birth_date),
+            expiration_date: self.get_target(target.expiration_date),
+        }
+    }
+    fn set_mrz_check_digits_target(
+        &mut self,
+        target: MrzCheckDigitsTarget,
+        value: encoding::MrzCheckDigits<F>,
+    ) -> anyhow::Result<()> {
+        self.set_target(target.document_number, value.document_number)?;
+        self.set_target(target.birth_date, value.birth_date)?;
+        self.set_target(target.expiration_date, value.expiration_date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use plonky2::{iop::witness::PartialWitness, plonk::circuit_data::CircuitConfig};
+
+    use super::*;
+    use crate::{
+        circuit::{C, D, F},
+        core::{date::days_from_origin, mrz},
+        encoding::{conversion::ToVecField, LEN_PASSPORT_NUMBER},
+    };
+
+    #[test]
+    fn date_field_matches_native() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let mut pw = PartialWitness::new();
+        for (y, m, d) in [
+            (1900, 1, 1),
+            (1974, 8, 12),
+            (2000, 2, 29),
+            (2100, 3, 1),
+            (2999, 12, 31),
+        ] {
+            let date = NaiveDate::from_ymd_opt(y, m, d).unwrap();
+            let days = builder.add_virtual_target();
+            pw.set_target(days, F::from_canonical_u32(days_from_origin(date)))
+                .unwrap();
+            let field = builder.mrz_date_field(days);
+            for (got, c) in field.into_iter().zip(mrz::date_field(&date)) {
+                let expected = builder.constant(F::from_canonical_u8(c - b'0'));
+                builder.connect(got, expected);
+            }
+        }
+        builder.build::<C>().prove(pw).unwrap();
+    }
+
+    fn prove_document_number(number: &[u8], check_digit: u8) -> anyhow::Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let target =
+            encoding::PassportNumber(std::array::from_fn(|_| builder.add_virtual_target()));
+        let values = builder.mrz_document_number(target);
+        let got = builder.mrz_check_digit(&values);
+        let expected = builder.constant(F::from_canonical_u8(check_digit));
+        builder.connect(got, expected);
+        let mut pw = PartialWitness::new();
+        for (t, x) in target.0.iter().zip(number.to_field(LEN_PASSPORT_NUMBER)) {
+            pw.set_target(*t, x)?;
+        }
+        builder.build::<C>().prove(pw).map(|_| ())
+    }
+
+    #[test]
+    fn document_number_check_digit_matches_native() {
+        prove_document_number(b"L898902C3", 6).unwrap();
+        assert!(prove_document_number(b"L898902C3", 7).is_err());
+        // lowercase letters are not MRZ characters
+        assert!(prove_document_number(b"l898902C3", 6).is_err());
+    }
+}
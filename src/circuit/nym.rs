@@ -0,0 +1,362 @@
+use plonky2::{
+    field::{extension::Extendable, types::Field},
+    hash::{hash_types::RichField, poseidon::PoseidonHash},
+    iop::target::{BoolTarget, Target},
+    plonk::circuit_builder::CircuitBuilder,
+};
+
+use crate::{
+    circuit::{
+        credential::CredentialTarget,
+        curve::{CircuitBuilderCurve, PointTarget},
+        hash::HashTarget,
+        scalar::ScalarTarget,
+    },
+    client::keys::DERIVED_KEY_TAG,
+    encoding::{
+        self, LEN_CREDENTIAL, LEN_GIVEN_NAMES, LEN_HASH, LEN_PASSPORT_NUMBER, LEN_SCALAR,
+        LEN_SCALAR_LIMBS, LEN_STRING, LEN_VAR_STRING,
+    },
+};
+
+pub trait CircuitBuilderNym<F: RichField + Extendable<D>, const D: usize> {
+    /// Asserts that sk is the secret key of pk
+    fn connect_secret_key(&mut self, sk: ScalarTarget, pk: PointTarget);
+    /// Mirrors issuer::pseudonym::domain_nym
+    fn domain_nym(&mut self, sk: ScalarTarget, domain: encoding::String<Target>) -> HashTarget;
+    /// Mirrors issuer::rate_limit::nullifier, and asserts that counter < k
+    fn nullifier(&mut self, sk: ScalarTarget, epoch: Target, counter: Target, k: u32)
+        -> HashTarget;
+    /// Mirrors bank::consortium::nullifier
+    fn person_nullifier(
+        &mut self,
+        sk: ScalarTarget,
+        credential: &CredentialTarget,
+        scope: encoding::String<Target>,
+    ) -> HashTarget;
+    /// Mirrors issuer::pseudonym::credential_nullifier
+    fn credential_nullifier(
+        &mut self,
+        sk: ScalarTarget,
+        credential: &CredentialTarget,
+        service: encoding::String<Target>,
+    ) -> HashTarget;
+    /// Mirrors issuer::pseudonym::credential_id
+    fn credential_id(&mut self, credential: &CredentialTarget, salt: HashTarget) -> HashTarget;
+    /// Mirrors issuer::pseudonym::document_commitment
+    fn document_commitment(
+        &mut self,
+        credential: &CredentialTarget,
+        salt: HashTarget,
+    ) -> HashTarget;
+    /// Mirrors issuer::pseudonym::name_hash
+    fn name_hash(&mut self, name: encoding::VarString<Target>, salt: HashTarget) -> HashTarget;
+    /// Mirrors client::keys::derive
+    fn derived_key(&mut self, sk: ScalarTarget, domain: encoding::String<Target>) -> ScalarTarget;
+}
+
+/// The 64 little-endian bits of x, asserted to be canonical: split_le alone
+/// also accepts the bits of x + p when it fits in 64 bits
+pub(crate) fn split_canonical<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: Target,
+) -> Vec<BoolTarget> {
+    let bits = builder.split_le(x, 64);
+    let low = builder.le_sum(bits[..32].iter());
+    let high = builder.le_sum(bits[32..].iter());
+    let max = builder.constant(F::from_canonical_u32(u32::MAX));
+    let high_is_max = builder.is_equal(high, max);
+    // p = (2^32 - 1) * 2^32 + 1, so the low half of x < p is 0 when the high
+    // half is all ones
+    let overflow = builder.mul(high_is_max.target, low);
+    builder.assert_zero(overflow);
+    bits
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderNym<F, D>
+    for CircuitBuilder<F, D>
+{
+    fn connect_secret_key(&mut self, sk: ScalarTarget, pk: PointTarget) {
+        let g = self.generator();
+        let expected = self.scalar_mul(g, sk);
+        let res = self.is_equal_point(expected, pk);
+        self.assert_one(res.target);
+    }
+
+    fn domain_nym(&mut self, sk: ScalarTarget, domain: encoding::String<Target>) -> HashTarget {
+        let mut message: Vec<Target> = Vec::with_capacity(LEN_SCALAR_LIMBS + LEN_STRING);
+        for limb in sk.0.chunks(32) {
+            message.push(self.le_sum(limb.iter()));
+        }
+        message.extend_from_slice(&domain.0);
+        self.hash_n_to_hash_no_pad::<PoseidonHash>(message).into()
+    }
+
+    fn nullifier(
+        &mut self,
+        sk: ScalarTarget,
+        epoch: Target,
+        counter: Target,
+        k: u32,
+    ) -> HashTarget {
+        assert!(k > 0, "k-show credentials need k > 0");
+        // counter <= k - 1, both being 32 bits
+        let max = self.constant(F::from_canonical_u32(k - 1));
+        let diff = self.sub(max, counter);
+        self.range_check(counter, 32);
+        self.range_check(diff, 32);
+
+        let mut message: Vec<Target> = Vec::with_capacity(LEN_SCALAR_LIMBS + 2);
+        for limb in sk.0.chunks(32) {
+            message.push(self.le_sum(limb.iter()));
+        }
+        message.push(epoch);
+        message.push(counter);
+        self.hash_n_to_hash_no_pad::<PoseidonHash>(message).into()
+    }
+
+    fn person_nullifier(
+        &mut self,
+        sk: ScalarTarget,
+        credential: &CredentialTarget,
+        scope: encoding::String<Target>,
+    ) -> HashTarget {
+        let mut message: Vec<Target> = Vec::with_capacity(
+            LEN_SCALAR_LIMBS + LEN_STRING + LEN_VAR_STRING + LEN_GIVEN_NAMES + 2,
+        );
+        for limb in sk.0.chunks(32) {
+            message.push(self.le_sum(limb.iter()));
+        }
+        message.extend_from_slice(&scope.0);
+        let family_name: [Target; LEN_VAR_STRING] = credential.family_name.into();
+        message.extend_from_slice(&family_name);
+        let given_names: [Target; LEN_GIVEN_NAMES] = credential.given_names.into();
+        message.extend_from_slice(&given_names);
+        message.push(credential.birth_date);
+        message.push(credential.nationality);
+        self.hash_n_to_hash_no_pad::<PoseidonHash>(message).into()
+    }
+
+    fn credential_nullifier(
+        &mut self,
+        sk: ScalarTarget,
+        credential: &CredentialTarget,
+        service: encoding::String<Target>,
+    ) -> HashTarget {
+        let encoded: [Target; LEN_CREDENTIAL] = credential.into();
+        let serial = self.hash_n_to_hash_no_pad::<PoseidonHash>(encoded.to_vec());
+        let mut message: Vec<Target> = Vec::with_capacity(LEN_SCALAR_LIMBS + LEN_HASH + LEN_STRING);
+        for limb in sk.0.chunks(32) {
+            message.push(self.le_sum(limb.iter()));
+        }
+        message.extend_from_slice(&serial.elements);
+        message.extend_from_slice(&service.0);
+        self.hash_n_to_hash_no_pad::<PoseidonHash>(message).into()
+    }
+
+    fn credential_id(&mut self, credential: &CredentialTarget, salt: HashTarget) -> HashTarget {
+        let encoded: [Target; LEN_CREDENTIAL] = credential.into();
+        let mut message: Vec<Target> = Vec::with_capacity(LEN_CREDENTIAL + LEN_HASH);
+        message.extend_from_slice(&encoded);
+        message.extend_from_slice(&salt.0);
+        self.hash_n_to_hash_no_pad::<PoseidonHash>(message).into()
+    }
+
+    fn document_commitment(
+        &mut self,
+        credential: &CredentialTarget,
+        salt: HashTarget,
+    ) -> HashTarget {
+        let mut message: Vec<Target> = Vec::with_capacity(1 + LEN_PASSPORT_NUMBER + LEN_HASH);
+        message.push(credential.document_type);
+        message.extend_from_slice(&credential.passport_number.0);
+        message.extend_from_slice(&salt.0);
+        self.hash_n_to_hash_no_pad::<PoseidonHash>(message).into()
+    }
+
+    fn name_hash(&mut self, name: encoding::VarString<Target>, salt: HashTarget) -> HashTarget {
+        let name: [Target; LEN_VAR_STRING] = name.into();
+        let mut message: Vec<Target> = Vec::with_capacity(LEN_VAR_STRING + LEN_HASH);
+        message.extend_from_slice(&name);
+        message.extend_from_slice(&salt.0);
+        self.hash_n_to_hash_no_pad::<PoseidonHash>(message).into()
+    }
+
+    fn derived_key(&mut self, sk: ScalarTarget, domain: encoding::String<Target>) -> ScalarTarget {
+        let mut message: Vec<Target> = Vec::with_capacity(LEN_SCALAR_LIMBS + LEN_STRING + 1);
+        for limb in sk.0.chunks(32) {
+            message.push(self.le_sum(limb.iter()));
+        }
+        message.extend_from_slice(&domain.0);
+        message.push(self.constant(F::from_canonical_u32(DERIVED_KEY_TAG)));
+        let hash = self.hash_n_to_hash_no_pad::<PoseidonHash>(message);
+        let mut bits = Vec::with_capacity(LEN_SCALAR);
+        for x in hash.elements {
+            bits.extend(split_canonical(self, x));
+        }
+        // 256 bits are below the modulus, no need to assert_reduced
+        bits.resize(LEN_SCALAR, self._false());
+        let bits: [BoolTarget; LEN_SCALAR] = bits.try_into().unwrap();
+        bits.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        field::goldilocks_field::GoldilocksField as F,
+        iop::witness::{PartialWitness, WitnessWrite},
+        plonk::{circuit_data::CircuitConfig, config::PoseidonGoldilocksConfig},
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        circuit::{
+            curve::PartialWitnessCurve,
+            hash::{CircuitBuilderHash, PartialWitnessHash},
+            scalar::{CircuitBuilderScalar, PartialWitnessScalar},
+            string::{CircuitBuilderString, PartialWitnessString},
+        },
+        client::keys,
+        encoding::{
+            conversion::{ToPointField, ToScalarField, ToStringField, ToVarStringField},
+            Hash,
+        },
+        issuer::{pseudonym, rate_limit},
+        schnorr::keys::{PublicKey, SecretKey},
+    };
+
+    const D: usize = 2;
+    type Cfg = PoseidonGoldilocksConfig;
+
+    fn prove_nym(sk: &SecretKey, pk: &PublicKey, domain: &str) -> anyhow::Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let sk_t = builder.add_virtual_scalar_target();
+        let pk_t = builder.add_virtual_point_target();
+        let domain_t = builder.add_virtual_string_target();
+        let nym_t = builder.add_virtual_hash_target();
+        builder.connect_secret_key(sk_t, pk_t);
+        let got = builder.domain_nym(sk_t, domain_t);
+        builder.connect_hash(got, nym_t);
+
+        let mut pw = PartialWitness::<F>::new();
+        pw.set_scalar_target(sk_t, sk.0.to_field())?;
+        pw.set_point_target(pk_t, pk.0.to_field())?;
+        pw.set_string_target(domain_t, domain.to_string().to_field())?;
+        PartialWitnessHash::set_hash_target(&mut pw, nym_t, pseudonym::domain_nym(domain, sk))?;
+
+        let data = builder.build::<Cfg>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    fn prove_nullifier(sk: &SecretKey, epoch: u32, counter: u32, k: u32) -> anyhow::Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let sk_t = builder.add_virtual_scalar_target();
+        let epoch_t = builder.add_virtual_target();
+        let counter_t = builder.add_virtual_target();
+        let nullifier_t = builder.add_virtual_hash_target();
+        let got = builder.nullifier(sk_t, epoch_t, counter_t, k);
+        builder.connect_hash(got, nullifier_t);
+
+        let mut pw = PartialWitness::<F>::new();
+        pw.set_scalar_target(sk_t, sk.0.to_field())?;
+        pw.set_target(epoch_t, F::from_canonical_u32(epoch))?;
+        pw.set_target(counter_t, F::from_canonical_u32(counter))?;
+        PartialWitnessHash::set_hash_target(
+            &mut pw,
+            nullifier_t,
+            rate_limit::nullifier(sk, epoch, counter),
+        )?;
+
+        let data = builder.build::<Cfg>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    fn prove_derived_key(sk: &SecretKey, domain: &str, derived: &PublicKey) -> anyhow::Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let sk_t = builder.add_virtual_scalar_target();
+        let domain_t = builder.add_virtual_string_target();
+        let derived_t = builder.add_virtual_point_target();
+        let got = builder.derived_key(sk_t, domain_t);
+        builder.connect_secret_key(got, derived_t);
+
+        let mut pw = PartialWitness::<F>::new();
+        pw.set_scalar_target(sk_t, sk.0.to_field())?;
+        pw.set_string_target(domain_t, domain.to_string().to_field())?;
+        pw.set_point_target(derived_t, derived.0.to_field())?;
+
+        let data = builder.build::<Cfg>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    fn prove_name_hash(name: &str, expected: pseudonym::Pseudonym) -> anyhow::Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let name_t = builder.add_virtual_var_string_target();
+        let salt_t = builder.add_virtual_hash_target();
+        let expected_t = builder.add_virtual_hash_target();
+        let got = builder.name_hash(name_t, salt_t);
+        builder.connect_hash(got, expected_t);
+
+        let salt = Hash([F::from_canonical_u64(11); LEN_HASH]);
+        let mut pw = PartialWitness::<F>::new();
+        pw.set_var_string_target(name_t, name.to_field())?;
+        PartialWitnessHash::set_hash_target(&mut pw, salt_t, salt)?;
+        PartialWitnessHash::set_hash_target(&mut pw, expected_t, expected)?;
+
+        let data = builder.build::<Cfg>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_name_hash_matches_native() {
+        let salt = Hash([F::from_canonical_u64(11); LEN_HASH]);
+        let expected = pseudonym::name_hash("DUPONT", &salt).unwrap();
+        prove_name_hash("DUPONT", expected).unwrap();
+        assert!(prove_name_hash("DUPOND", expected).is_err());
+        assert_ne!(
+            expected,
+            pseudonym::name_hash("DUPONT", &Hash([F::from_canonical_u64(12); LEN_HASH])).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_derived_key_matches_native() {
+        let sk = SecretKey::random(&mut StdRng::seed_from_u64(5));
+        let derived = PublicKey::from(&keys::derive(&sk, "bank-a.example"));
+        prove_derived_key(&sk, "bank-a.example", &derived).unwrap();
+        assert!(prove_derived_key(&sk, "bank-b.example", &derived).is_err());
+    }
+
+    #[test]
+    fn test_domain_nym_matches_native() {
+        let sk = SecretKey::random(&mut StdRng::seed_from_u64(1));
+        let pk = PublicKey::from(&sk);
+        prove_nym(&sk, &pk, "bank-a.example").unwrap();
+    }
+
+    #[test]
+    fn test_connect_secret_key_rejects_other_key() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let sk = SecretKey::random(&mut rng);
+        let other_pk = PublicKey::from(&SecretKey::random(&mut rng));
+        assert!(prove_nym(&sk, &other_pk, "bank-a.example").is_err());
+    }
+
+    #[test]
+    fn test_nullifier_matches_native_below_k() {
+        let sk = SecretKey::random(&mut StdRng::seed_from_u64(3));
+        prove_nullifier(&sk, 2930, 0, 3).unwrap();
+        prove_nullifier(&sk, 2930, 2, 3).unwrap();
+    }
+
+    #[test]
+    fn test_nullifier_rejects_counter_above_k() {
+        let sk = SecretKey::random(&mut StdRng::seed_from_u64(4));
+        assert!(prove_nullifier(&sk, 2930, 3, 3).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+// Configurations a circuit can be built with.
+
+use plonky2::plonk::circuit_data::CircuitConfig;
+
+/// Trade-offs between proving time and proof size. Except Test, every profile
+/// targets ~100 bits of conjectured security: rate_bits * num_query_rounds +
+/// proof_of_work_bits. The blowup can't go below 8, the degree of the Poseidon
+/// gate requires rate_bits >= 3.
+///
+/// Except Test, every profile blinds the witness (zero_knowledge), which
+/// plonky2 disables by default: without it, proofs may leak information about
+/// the credential.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CircuitProfile {
+    /// plonky2 default FRI parameters: blowup 8, 28 queries
+    #[default]
+    Standard,
+    /// Blowup 8, 32 queries and almost no proof of work: skips the grinding,
+    /// proofs are a bit larger
+    FastProve,
+    /// Blowup 32, 16 queries and more proof of work: proves slower, proofs
+    /// are smaller
+    SmallProof,
+    /// INSECURE: a single FRI query and no proof of work, so that tests can
+    /// prove in a fraction of the standard time. Never use it for
+    /// presentations.
+    Test,
+}
+
+impl CircuitProfile {
+    pub fn config(self) -> CircuitConfig {
+        let mut config = CircuitConfig {
+            zero_knowledge: self.is_secure(),
+            ..CircuitConfig::default()
+        };
+        let fri = &mut config.fri_config;
+        match self {
+            Self::Standard => {}
+            Self::FastProve => {
+                fri.num_query_rounds = 32;
+                fri.proof_of_work_bits = 4;
+            }
+            Self::SmallProof => {
+                fri.rate_bits = 5;
+                fri.num_query_rounds = 16;
+                fri.proof_of_work_bits = 20;
+            }
+            Self::Test => {
+                fri.proof_of_work_bits = 0;
+                fri.num_query_rounds = 1;
+                fri.cap_height = 0;
+                config.security_bits = 0;
+            }
+        }
+        config
+    }
+
+    pub fn is_secure(self) -> bool {
+        self != Self::Test
+    }
+
+    /// Conjectured security of the proofs, in bits
+    pub fn security_bits(self) -> usize {
+        let fri = self.config().fri_config;
+        fri.rate_bits * fri.num_query_rounds + fri.proof_of_work_bits as usize
+    }
+}
+
+/// Issues a credential of the test database, then proves and verifies its
+/// presentation with the base circuit built with profile
+#[cfg(test)]
+pub(crate) fn issue_prove_verify(seed: u64, profile: CircuitProfile) -> anyhow::Result<()> {
+    use crate::circuit::{self, bench::Fixture, inputs};
+
+    let fixture = Fixture::from_seed(seed)?;
+    let circuit = circuit::circuit_with_profile(inputs::Features::default(), profile);
+    let proof = circuit::prove(
+        &circuit,
+        &fixture.credential,
+        &fixture.signature,
+        &fixture.authentification,
+        &fixture.merkle_path,
+        &fixture.public_inputs,
+    )?;
+    circuit::verify(&circuit.circuit, proof, fixture.public_inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secure_profiles_target_100_bits() {
+        for profile in [
+            CircuitProfile::Standard,
+            CircuitProfile::FastProve,
+            CircuitProfile::SmallProof,
+        ] {
+            assert!(profile.is_secure());
+            assert!(profile.config().zero_knowledge, "{profile:?}");
+            assert!(profile.security_bits() >= 100, "{profile:?}");
+        }
+        assert!(CircuitProfile::Test.security_bits() < 100);
+    }
+
+    #[test]
+    fn test_profile_runs_the_whole_pipeline() {
+        assert!(!CircuitProfile::Test.is_secure());
+        issue_prove_verify(2, CircuitProfile::Test).unwrap();
+    }
+}
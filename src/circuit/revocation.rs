@@ -0,0 +1,134 @@
+use plonky2::{
+    field::extension::Extendable,
+    hash::{hash_types::RichField, poseidon::PoseidonHash},
+    iop::{target::Target, witness::Witness},
+    plonk::circuit_builder::CircuitBuilder,
+};
+
+use crate::{
+    circuit::{
+        credential::CredentialTarget,
+        hash::{CircuitBuilderHash, HashTarget, PartialWitnessHash},
+        nym::split_canonical,
+    },
+    encoding::{self, LEN_CREDENTIAL, LEN_HASH},
+};
+
+pub type NonRevocationPathTarget = encoding::NonRevocationPath<Target>;
+
+pub trait CircuitBuilderRevocation<F: RichField + Extendable<D>, const D: usize> {
+    fn add_virtual_non_revocation_path_target(&mut self) -> NonRevocationPathTarget;
+    /// Mirrors revocation::Tree::verify, for the serial number of credential
+    fn check_not_revoked(
+        &mut self,
+        credential: &CredentialTarget,
+        path: NonRevocationPathTarget,
+        root: HashTarget,
+    );
+}
+
+pub trait PartialWitnessRevocation<F: RichField>: Witness<F> {
+    fn set_non_revocation_path_target(
+        &mut self,
+        target: NonRevocationPathTarget,
+        value: encoding::NonRevocationPath<F>,
+    ) -> anyhow::Result<()>;
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderRevocation<F, D>
+    for CircuitBuilder<F, D>
+{
+    fn add_virtual_non_revocation_path_target(&mut self) -> NonRevocationPathTarget {
+        encoding::NonRevocationPath(std::array::from_fn(|_| self.add_virtual_hash_target()))
+    }
+
+    fn check_not_revoked(
+        &mut self,
+        credential: &CredentialTarget,
+        path: NonRevocationPathTarget,
+        root: HashTarget,
+    ) {
+        let credential: [Target; LEN_CREDENTIAL] = credential.into();
+        let serial: HashTarget = self
+            .hash_n_to_hash_no_pad::<PoseidonHash>(credential.to_vec())
+            .into();
+        // a non canonical decomposition would open another leaf
+        let bits = split_canonical(self, serial.0[0]);
+        let zero = self.zero();
+        let empty = encoding::Hash([zero; LEN_HASH]);
+        let claimed_root = bits
+            .into_iter()
+            .zip(path.0)
+            .fold(empty, |acc, (bit, neighbor)| {
+                let is_left = self.not(bit);
+                self.merge_left_right(acc, is_left, neighbor)
+            });
+        self.connect_hash(claimed_root, root);
+    }
+}
+
+impl<W: Witness<F>, F: RichField> PartialWitnessRevocation<F> for W {
+    fn set_non_revocation_path_target(
+        &mut self,
+        target: NonRevocationPathTarget,
+        value: encoding::NonRevocationPath<F>,
+    ) -> anyhow::Result<()> {
+        for (target, value) in target.0.into_iter().zip(value.0) {
+            PartialWitnessHash::set_hash_target(self, target, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        field::goldilocks_field::GoldilocksField as F,
+        iop::witness::PartialWitness,
+        plonk::{circuit_data::CircuitConfig, config::PoseidonGoldilocksConfig},
+    };
+
+    use super::*;
+    use crate::{
+        circuit::credential::{CircuitBuilderCredential, PartialWitnessCredential},
+        core::credential::Credential,
+        revocation::{self, Tree},
+    };
+
+    const D: usize = 2;
+    type Cfg = PoseidonGoldilocksConfig;
+
+    fn prove_not_revoked(
+        credential: &Credential,
+        path: revocation::Path,
+        root: revocation::Root,
+    ) -> anyhow::Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let credential_t = builder.add_virtual_credential_target();
+        let path_t = builder.add_virtual_non_revocation_path_target();
+        let root_t = builder.add_virtual_hash_target();
+        builder.check_not_revoked(&credential_t, path_t, root_t);
+
+        let mut pw = PartialWitness::<F>::new();
+        pw.set_credential_target(credential_t, credential.to_field())?;
+        pw.set_non_revocation_path_target(path_t, path)?;
+        pw.set_hash_target(root_t, root)?;
+
+        let data = builder.build::<Cfg>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_check_not_revoked_matches_native() {
+        let credential = Credential::from_seed(2).2;
+        let mut tree = Tree::new();
+        tree.revoke(&revocation::serial(&Credential::from_seed(3).2))
+            .unwrap();
+        let path = tree.prove(&revocation::serial(&credential)).unwrap();
+        prove_not_revoked(&credential, path, tree.root()).unwrap();
+
+        tree.revoke(&revocation::serial(&credential)).unwrap();
+        assert!(prove_not_revoked(&credential, path, tree.root()).is_err());
+    }
+}
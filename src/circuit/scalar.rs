@@ -3,22 +3,31 @@ use std::array;
 use plonky2::{
     field::extension::Extendable,
     hash::hash_types::RichField,
-    iop::{target::BoolTarget, witness::Witness},
+    iop::{
+        target::{BoolTarget, Target},
+        witness::Witness,
+    },
     plonk::circuit_builder::CircuitBuilder,
 };
 
 use crate::{
     arith,
-    encoding::{self, LEN_SCALAR},
+    encoding::{self, LEN_SCALAR, PACKED_SCALAR_BITS},
 };
 
 pub type ScalarTarget = encoding::Scalar<BoolTarget>;
+pub type ScalarLimbsTarget = encoding::ScalarLimbs<Target>;
 
 pub trait CircuitBuilderScalar<F: RichField + Extendable<D>, const D: usize> {
     /// The Target is asserted to be 0 <= s < modulus
     fn add_virtual_scalar_target(&mut self) -> ScalarTarget;
     // fn connect_scalar(&mut self, a: ScalarTarget, b: ScalarTarget);
+    /// Registers the scalar packed, on LEN_PACKED_SCALAR elements
     fn register_scalar_public_input(&mut self, target: ScalarTarget);
+    fn add_virtual_scalar_limbs_target(&mut self) -> ScalarLimbsTarget;
+    fn pack_scalar(&mut self, target: ScalarTarget) -> ScalarLimbsTarget;
+    /// Inverse of pack_scalar, the result is asserted to be 0 <= s < modulus
+    fn unpack_scalar(&mut self, packed: ScalarLimbsTarget) -> ScalarTarget;
 }
 pub trait PartialWitnessScalar<F: RichField>: Witness<F> {
     fn get_scalar_target(&self, target: ScalarTarget) -> encoding::Scalar<bool>;
@@ -28,62 +37,95 @@ pub trait PartialWitnessScalar<F: RichField>: Witness<F> {
         target: ScalarTarget,
         value: encoding::Scalar<bool>,
     ) -> anyhow::Result<()>;
+
+    fn get_scalar_limbs_target(&self, target: ScalarLimbsTarget) -> encoding::ScalarLimbs<F>;
+
+    fn set_scalar_limbs_target(
+        &mut self,
+        target: ScalarLimbsTarget,
+        value: encoding::ScalarLimbs<F>,
+    ) -> anyhow::Result<()>;
+}
+
+/// Asserts that the little-endian bits are smaller than the modulus
+fn assert_reduced<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    bits: &[BoolTarget; LEN_SCALAR],
+) {
+    // No built in equality for bool target
+    // Safe because bits are checked & safe
+    fn eq_bool<F: RichField + Extendable<D>, const D: usize>(
+        builder: &mut CircuitBuilder<F, D>,
+        a: BoolTarget,
+        b: BoolTarget,
+    ) -> BoolTarget {
+        let a = a.target;
+        let b = b.target;
+
+        let ab = builder.mul(a, b);
+        let two_ab = builder.add(ab, ab);
+
+        let sum = builder.add(a, b);
+        let xor = builder.sub(sum, two_ab);
+        let one = builder.one();
+        let eq = builder.sub(one, xor);
+        BoolTarget::new_unsafe(eq)
+    }
+    // target was checked smaller than modulus
+    let mut lt = builder._false();
+    // until now every bits are equal
+    let mut eq = builder._true();
+    for i in (0..LEN_SCALAR).rev() {
+        let b = bits[i];
+        let n = builder.constant_bool(arith::Scalar::modulus_bit_le(i));
+
+        // lt becomes true when a strictly_less has been found and every other bigger bits have been equal until now
+        lt = {
+            let not_b = builder.not(b);
+            let strictly_less = builder.and(not_b, n);
+            let eq_and_strictly_less = builder.and(eq, strictly_less);
+            builder.or(lt, eq_and_strictly_less)
+        };
+
+        // eq stays true as long as all the seen bits have been equal
+        eq = {
+            let b_eq_n = eq_bool(builder, b, n);
+            builder.and(eq, b_eq_n)
+        }
+    }
+    builder.assert_one(lt.target);
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderScalar<F, D>
     for CircuitBuilder<F, D>
 {
     fn add_virtual_scalar_target(&mut self) -> ScalarTarget {
-        // No built in equality for bool target
-        // Safe because bits are checked & safe
-        fn eq_bool<F: RichField + Extendable<D>, const D: usize>(
-            builder: &mut CircuitBuilder<F, D>,
-            a: BoolTarget,
-            b: BoolTarget,
-        ) -> BoolTarget {
-            let a = a.target;
-            let b = b.target;
-
-            let ab = builder.mul(a, b);
-            let two_ab = builder.add(ab, ab);
-
-            let sum = builder.add(a, b);
-            let xor = builder.sub(sum, two_ab);
-            let one = builder.one();
-            let eq = builder.sub(one, xor);
-            BoolTarget::new_unsafe(eq)
-        }
         let bits = array::from_fn(|_| self.add_virtual_bool_target_safe());
-        // target was checked smaller than modulus
-        let mut lt = self._false();
-        // until now every bits are equal
-        let mut eq = self._true();
-        for i in (0..LEN_SCALAR).rev() {
-            let b = bits[i];
-            let n = self.constant_bool(arith::Scalar::modulus_bit_le(i));
-
-            // lt becomes true when a strictly_less has been found and every other bigger bits have been equal until now
-            lt = {
-                let not_b = self.not(b);
-                let strictly_less = self.and(not_b, n);
-                let eq_and_strictly_less = self.and(eq, strictly_less);
-                self.or(lt, eq_and_strictly_less)
-            };
-
-            // eq stays true as long as all the seen bits have been equal
-            eq = {
-                let b_eq_n = eq_bool(self, b, n);
-                self.and(eq, b_eq_n)
-            }
-        }
-        self.assert_one(lt.target);
+        assert_reduced(self, &bits);
         bits.into()
     }
     fn register_scalar_public_input(&mut self, target: ScalarTarget) {
-        target
-            .0
-            .iter()
-            .for_each(|&t| self.register_public_input(t.target));
+        let packed = self.pack_scalar(target);
+        self.register_public_inputs(&packed.0);
+    }
+    fn add_virtual_scalar_limbs_target(&mut self) -> ScalarLimbsTarget {
+        encoding::ScalarLimbs(self.add_virtual_target_arr())
+    }
+    fn pack_scalar(&mut self, target: ScalarTarget) -> ScalarLimbsTarget {
+        let mut chunks = target.0.chunks(PACKED_SCALAR_BITS);
+        encoding::ScalarLimbs(array::from_fn(|_| {
+            self.le_sum(chunks.next().unwrap().iter())
+        }))
+    }
+    fn unpack_scalar(&mut self, packed: ScalarLimbsTarget) -> ScalarTarget {
+        let mut bits = Vec::with_capacity(LEN_SCALAR);
+        for (i, x) in packed.0.into_iter().enumerate() {
+            let len = PACKED_SCALAR_BITS.min(LEN_SCALAR - PACKED_SCALAR_BITS * i);
+            bits.extend(self.split_le(x, len));
+        }
+        let bits: [BoolTarget; LEN_SCALAR] = bits.try_into().unwrap();
+        assert_reduced(self, &bits);
+        bits.into()
     }
 }
 impl<W: Witness<F>, F: RichField> PartialWitnessScalar<F> for W {
@@ -101,6 +143,21 @@ impl<W: Witness<F>, F: RichField> PartialWitnessScalar<F> for W {
         }
         Ok(())
     }
+
+    fn get_scalar_limbs_target(&self, target: ScalarLimbsTarget) -> encoding::ScalarLimbs<F> {
+        encoding::ScalarLimbs(target.0.map(|t| self.get_target(t)))
+    }
+
+    fn set_scalar_limbs_target(
+        &mut self,
+        target: ScalarLimbsTarget,
+        value: encoding::ScalarLimbs<F>,
+    ) -> anyhow::Result<()> {
+        for (&target, &value) in target.0.iter().zip(value.0.iter()) {
+            self.set_target(target, value)?;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -173,13 +230,10 @@ mod tests {
 
             let pis = prove_and_get_public_inputs(builder, pw);
 
-            // public inputs are bits as field elems
-            assert_eq!(pis.len(), LEN_SCALAR);
-
-            for (i, &pi) in pis.iter().enumerate() {
-                let expected = if scalar.0[i] { F::ONE } else { F::ZERO };
-                assert_eq!(pi, expected);
-            }
+            // public inputs are the packed scalar
+            let packed = encoding::ScalarLimbs(pis.try_into().unwrap());
+            assert_eq!(packed, scalar.pack::<F>());
+            assert_eq!(encoding::Scalar::unpack(&packed), Some(scalar));
         }
     }
 
@@ -201,4 +255,28 @@ mod tests {
         // If bits encode exactly modulus, lt should be false, and assert_one(lt) must fail.
         prove_err(builder, pw);
     }
+
+    #[test]
+    fn test_unpack_scalar_accepts_reduced_rejects_modulus() {
+        let minus_one = (arith::Scalar::ZERO - arith::Scalar::ONE).to_field();
+        let modulus: encoding::Scalar<bool> = modulus_bits_le().into();
+        for (scalar, reduced) in [(minus_one, true), (modulus, false)] {
+            let mut builder =
+                CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_config());
+            let packed = builder.add_virtual_scalar_limbs_target();
+            let s_t = builder.unpack_scalar(packed);
+            for b in s_t.0 {
+                builder.register_public_input(b.target);
+            }
+
+            let mut pw = PartialWitness::<F>::new();
+            pw.set_scalar_limbs_target(packed, scalar.pack()).unwrap();
+            if reduced {
+                let pis = prove_and_get_public_inputs(builder, pw);
+                assert!(pis.iter().zip(scalar.0).all(|(pi, b)| pi.is_one() == b));
+            } else {
+                prove_err(builder, pw);
+            }
+        }
+    }
 }
@@ -0,0 +1,81 @@
+// Which circuits accept which credential layouts.
+//
+// Circuits only accept credentials of core::credential::SCHEMA_VERSION, so the
+// digest of a circuit tells which schema versions it can prove.
+
+use std::collections::HashMap;
+
+use crate::{
+    circuit::{Circuit, F},
+    core::credential::{Credential, SCHEMA_VERSION},
+    encoding::Hash,
+};
+
+/// Schema version -> digests of the circuits accepting it
+#[derive(Default)]
+pub struct Compatibility(HashMap<u8, Vec<Hash<F>>>);
+
+impl Compatibility {
+    pub fn register_digest(&mut self, version: u8, digest: Hash<F>) {
+        let digests = self.0.entry(version).or_default();
+        if !digests.contains(&digest) {
+            digests.push(digest);
+        }
+    }
+
+    /// Registers a circuit built by this version of the crate
+    pub fn register(&mut self, circuit: &Circuit) {
+        self.register_digest(SCHEMA_VERSION, circuit.digest());
+    }
+
+    pub fn is_compatible(&self, version: u8, digest: &Hash<F>) -> bool {
+        self.0
+            .get(&version)
+            .is_some_and(|digests| digests.contains(digest))
+    }
+
+    /// Whether the circuit accepts credentials of the current layout; proofs
+    /// from other circuits were built against a stale layout
+    pub fn check_circuit(&self, digest: &Hash<F>) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.is_compatible(SCHEMA_VERSION, digest),
+            "circuit does not accept schema version {SCHEMA_VERSION}"
+        );
+        Ok(())
+    }
+
+    /// Whether the holder must get a credential of a newer layout to prove
+    /// with the circuit
+    pub fn needs_refresh(&self, credential: &Credential, digest: &Hash<F>) -> bool {
+        !self.is_compatible(credential.schema_version(), digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::types::Field;
+
+    use super::*;
+    use crate::encoding::LEN_HASH;
+
+    fn digest(x: u64) -> Hash<F> {
+        Hash([F::from_canonical_u64(x); LEN_HASH])
+    }
+
+    #[test]
+    fn stale_layouts_are_detected() {
+        let mut compatibility = Compatibility::default();
+        compatibility.register_digest(SCHEMA_VERSION, digest(1));
+        compatibility.register_digest(SCHEMA_VERSION - 1, digest(2));
+
+        assert!(compatibility.check_circuit(&digest(1)).is_ok());
+        assert!(compatibility.check_circuit(&digest(2)).is_err());
+        assert!(compatibility.check_circuit(&digest(3)).is_err());
+
+        let (_, _, mut credential) = Credential::from_seed(1);
+        assert!(!compatibility.needs_refresh(&credential, &digest(1)));
+        credential.set_schema_version(SCHEMA_VERSION - 1);
+        assert!(compatibility.needs_refresh(&credential, &digest(1)));
+        assert!(!compatibility.needs_refresh(&credential, &digest(2)));
+    }
+}
@@ -1,9 +1,6 @@
 use plonky2::{
     field::extension::Extendable,
-    hash::{
-        hash_types::{HashOutTarget, RichField},
-        poseidon::PoseidonHash,
-    },
+    hash::hash_types::RichField,
     iop::{
         target::{BoolTarget, Target},
         witness::Witness,
@@ -16,14 +13,19 @@ use crate::{
         curve::{CircuitBuilderCurve, PartialWitnessCurve, PointTarget},
         scalar::{CircuitBuilderScalar, PartialWitnessScalar, ScalarTarget},
     },
-    encoding::{self, LEN_POINT, LEN_SCALAR},
+    encoding::{self, LEN_POINT},
+    schnorr::xof,
 };
 
 pub type SchnorrTarget = encoding::SchnorrProof<Target, BoolTarget>;
+pub type SchnorrLimbsTarget = encoding::SchnorrLimbs<Target>;
 
 pub trait CircuitBuilderSchnorr<F: RichField + Extendable<D>, const D: usize> {
     fn add_virtual_schnorr_target(&mut self) -> SchnorrTarget;
     fn register_schnorr_public_input(&mut self, target: SchnorrTarget);
+    fn add_virtual_schnorr_limbs_target(&mut self) -> SchnorrLimbsTarget;
+    /// Decomposes s into bits, asserted to be a valid scalar
+    fn decompose_schnorr(&mut self, target: SchnorrLimbsTarget) -> SchnorrTarget;
     fn schnorr_hash_with_message(
         &mut self,
         proof: SchnorrTarget,
@@ -35,6 +37,14 @@ pub trait CircuitBuilderSchnorr<F: RichField + Extendable<D>, const D: usize> {
         e: ScalarTarget,
         pk: PointTarget,
     );
+    /// schnorr_final_verification, with table = shamir_table(-pk) shared by
+    /// every proof under pk
+    fn schnorr_final_verification_with_table(
+        &mut self,
+        proof: SchnorrTarget,
+        e: ScalarTarget,
+        table: &[PointTarget],
+    );
 }
 
 pub trait PartialWitnessSchnorr<F: RichField>: Witness<F> {
@@ -44,6 +54,11 @@ pub trait PartialWitnessSchnorr<F: RichField>: Witness<F> {
         target: SchnorrTarget,
         value: encoding::SchnorrProof<F, bool>,
     ) -> anyhow::Result<()>;
+    fn set_schnorr_limbs_target(
+        &mut self,
+        target: SchnorrLimbsTarget,
+        value: encoding::SchnorrLimbs<F>,
+    ) -> anyhow::Result<()>;
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderSchnorr<F, D>
@@ -59,6 +74,18 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderSchnorr<F, D>
         self.register_point_public_input(target.r);
         self.register_scalar_public_input(target.s);
     }
+    fn add_virtual_schnorr_limbs_target(&mut self) -> SchnorrLimbsTarget {
+        encoding::SchnorrLimbs {
+            r: self.add_virtual_point_target(),
+            s: self.add_virtual_scalar_limbs_target(),
+        }
+    }
+    fn decompose_schnorr(&mut self, target: SchnorrLimbsTarget) -> SchnorrTarget {
+        encoding::SchnorrProof {
+            r: target.r,
+            s: self.unpack_scalar(target.s),
+        }
+    }
 
     fn schnorr_hash_with_message(
         &mut self,
@@ -70,31 +97,7 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderSchnorr<F, D>
         to_hash.extend_from_slice(&r_input);
         to_hash.extend_from_slice(message);
 
-        let mut bits: Vec<BoolTarget> = Vec::with_capacity(LEN_SCALAR);
-
-        // TODO: maybe hash_n_to_m_no_pad would be more appropriate, but extra-attention needs to be put on the out of circuit version
-        let h0: HashOutTarget = self.hash_n_to_hash_no_pad::<PoseidonHash>(to_hash);
-        for i in 0..4 {
-            bits.extend(self.split_le(h0.elements[i], 64));
-        }
-
-        let mut ctr = F::ONE;
-        while bits.len() < LEN_SCALAR {
-            let ctr_t = self.constant(ctr);
-
-            let mut inp = vec![ctr_t];
-            inp.extend_from_slice(&h0.elements);
-
-            let hi: HashOutTarget = self.hash_n_to_hash_no_pad::<PoseidonHash>(inp);
-            for i in 0..4 {
-                bits.extend(self.split_le(hi.elements[i], 64));
-            }
-            ctr += F::ONE;
-        }
-
-        bits.truncate(LEN_SCALAR);
-        let bits: [BoolTarget; LEN_SCALAR] = bits.try_into().unwrap();
-        bits.into()
+        xof::expand(self, to_hash).into()
     }
 
     // Optimized Schnorr verification using Shamir (double-scalar mul) in one loop.
@@ -106,9 +109,18 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderSchnorr<F, D>
         pk: PointTarget,
     ) {
         let pk_neg = self.neg_point(pk);
+        let table = self.shamir_table(pk_neg);
+        self.schnorr_final_verification_with_table(proof, e, &table);
+    }
 
+    fn schnorr_final_verification_with_table(
+        &mut self,
+        proof: SchnorrTarget,
+        e: ScalarTarget,
+        table: &[PointTarget],
+    ) {
         // lhs = s*G + e*(-P)
-        let lhs = self.double_scalar_mul_shamir(proof.s, e, pk_neg);
+        let lhs = self.double_scalar_mul_with_table(proof.s, e, table);
 
         // lhs must equal R
         let res = self.is_equal_point(lhs, proof.r);
@@ -132,6 +144,14 @@ impl<W: Witness<F>, F: RichField> PartialWitnessSchnorr<F> for W {
         self.set_point_target(target.r, value.r)?;
         self.set_scalar_target(target.s, value.s)
     }
+    fn set_schnorr_limbs_target(
+        &mut self,
+        target: SchnorrLimbsTarget,
+        value: encoding::SchnorrLimbs<F>,
+    ) -> anyhow::Result<()> {
+        self.set_point_target(target.r, value.r)?;
+        self.set_scalar_limbs_target(target.s, value.s)
+    }
 }
 
 #[cfg(test)]
@@ -166,10 +186,10 @@ mod tests {
         let mut rng = StdRng::from_os_rng();
         let (_, sk, credential0) = credential::Credential::random(&mut rng);
         let ctx = Context::new(&credential0);
-        let sig0 = signature::Signature::sign(&sk, &ctx);
+        let sig0 = signature::Signature::prove(&sk, &ctx);
 
         let credential = credential0.to_field();
-        let sig = sig0.0.to_field();
+        let sig = sig0.to_field();
 
         let credential_t = builder.add_virtual_credential_target();
         let sig_t = builder.add_virtual_schnorr_target();
@@ -188,8 +208,7 @@ mod tests {
         let data = builder.build::<Cfg>();
         let proof = data.prove(pw).unwrap();
 
-        let e_native =
-            schnorr::transcript::hash(&sig0.0.get_nonce(), schnorr::transcript::Context::Sig(&ctx));
+        let e_native = schnorr::transcript::hash(&sig0.get_nonce(), &ctx);
 
         let public_inputs: [F; LEN_SCALAR] = proof.public_inputs.try_into().unwrap();
         let e_circuit = arith::Scalar::from_bits_le(&public_inputs.map(|x| F::is_one(&x)));
@@ -0,0 +1,151 @@
+// Buffers of a presentation scrubbed on drop. Besides the holder key, the
+// witness holds the whole credential and its signature, which would otherwise
+// linger in memory after proving.
+//
+// plonky2 consumes the witness it proves, and drops its own copies without
+// scrubbing them: only the buffers of this crate are scrubbed.
+
+use std::ops::{Deref, DerefMut};
+
+use plonky2::{iop::witness::PartialWitness, plonk::circuit_data::CircuitData};
+
+use crate::circuit::{inputs, ZkProof, C, D, F};
+
+/// Buffers Secret scrubs
+pub trait Scrub {
+    /// Overwrites the secret values with zeros
+    fn scrub(&mut self);
+}
+
+/// Scrubs its value on drop
+pub struct Secret<T: Scrub>(T);
+
+/// Witness of a presentation, see circuit::witness
+pub type Witness = Secret<PartialWitness<F>>;
+
+impl<T: Scrub> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T: Scrub> Deref for Secret<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Scrub> DerefMut for Secret<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Scrub> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.scrub();
+    }
+}
+
+impl Witness {
+    /// Proves with the witness, which circuit takes by value
+    pub fn prove(mut self, circuit: &CircuitData<F, C, D>) -> anyhow::Result<ZkProof> {
+        circuit.prove(std::mem::take(&mut self.0))
+    }
+}
+
+/// Zeroes a value made of field elements and booleans only, for which zeros
+/// are valid and nothing is on the heap
+fn scrub_flat<T: Copy>(value: &mut T) {
+    // SAFETY: only called below, on arrays and structs of field elements and
+    // booleans
+    unsafe { zeroize::zeroize_flat_type(value) }
+}
+
+fn scrub_option<T: Copy>(value: &mut Option<T>) {
+    if let Some(value) = value {
+        scrub_flat(value);
+    }
+}
+
+impl Scrub for PartialWitness<F> {
+    fn scrub(&mut self) {
+        self.target_values.values_mut().for_each(scrub_flat);
+        self.target_values.clear();
+    }
+}
+
+impl Scrub for inputs::Private<F, bool> {
+    fn scrub(&mut self) {
+        // destructured, so that new inputs aren't forgotten
+        let Self {
+            credential,
+            signature,
+            authentification,
+            merkle_path,
+            certificate,
+            holder_secret,
+            counter,
+            time_token,
+            excluded_nationalities,
+            name_salt,
+            continuity,
+            issuer_set,
+            allowed_nationalities,
+            non_revocation,
+        } = self;
+        scrub_flat(credential);
+        scrub_flat(signature);
+        scrub_flat(authentification);
+        scrub_flat(merkle_path);
+        scrub_option(certificate);
+        scrub_option(holder_secret);
+        scrub_option(counter);
+        scrub_option(time_token);
+        scrub_option(excluded_nationalities);
+        scrub_option(name_salt);
+        scrub_option(continuity);
+        scrub_option(issuer_set);
+        scrub_option(allowed_nationalities);
+        scrub_option(non_revocation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        field::types::Field,
+        iop::{target::Target, witness::WitnessWrite},
+    };
+
+    use super::*;
+    use crate::circuit::bench::Fixture;
+
+    #[test]
+    fn buffers_are_zeroed() {
+        let fixture = Fixture::from_seed(5).unwrap();
+        let mut values = crate::circuit::witness_values(
+            &fixture.credential,
+            &fixture.signature,
+            &fixture.authentification,
+            &fixture.merkle_path,
+            &Default::default(),
+        );
+        assert_ne!(values.credential.birth_date, F::ZERO);
+        values.scrub();
+        assert_eq!(values.credential.birth_date, F::ZERO);
+        assert!(values.authentification.s.0.iter().all(|bit| !bit));
+        assert!(values
+            .merkle_path
+            .path
+            .iter()
+            .all(|hash| hash.0 == [F::ZERO; 4]));
+
+        let mut pw = Secret::new(PartialWitness::new());
+        pw.set_target(Target::VirtualTarget { index: 0 }, F::ONE)
+            .unwrap();
+        pw.scrub();
+        assert!(pw.target_values.is_empty());
+    }
+}
@@ -0,0 +1,235 @@
+use plonky2::{
+    field::extension::Extendable,
+    hash::{hash_types::RichField, poseidon::PoseidonHash},
+    iop::{
+        target::{BoolTarget, Target},
+        witness::{Witness, WitnessWrite},
+    },
+    plonk::circuit_builder::CircuitBuilder,
+};
+
+use crate::{
+    circuit::{
+        curve::{CircuitBuilderCurve, PointTarget},
+        gfp5::{CircuitBuilderGFp5, PartialWitnessGFp5},
+        hash::HashTarget,
+    },
+    encoding,
+};
+
+pub type CountrySetTarget = encoding::CountrySet<Target>;
+pub type IssuerSetTarget = encoding::IssuerSet<Target>;
+
+pub trait CircuitBuilderSet<F: RichField + Extendable<D>, const D: usize> {
+    fn add_virtual_country_set_target(&mut self) -> CountrySetTarget;
+    /// Mirrors core::set::CountrySet::commitment
+    fn commit_country_set(&mut self, set: &CountrySetTarget) -> HashTarget;
+    /// Asserts that the product of the x - code is invertible
+    fn assert_not_in_country_set(&mut self, x: Target, set: &CountrySetTarget);
+    /// Same as assert_not_in_country_set, only when condition is true
+    fn assert_not_in_country_set_if(
+        &mut self,
+        condition: BoolTarget,
+        x: Target,
+        set: &CountrySetTarget,
+    );
+    /// Asserts that x is non zero, as padding codes are, and that the
+    /// product of the x - code is zero
+    fn assert_in_country_set(&mut self, x: Target, set: &CountrySetTarget);
+    /// Same as assert_in_country_set, only when condition is true
+    fn assert_in_country_set_if(
+        &mut self,
+        condition: BoolTarget,
+        x: Target,
+        set: &CountrySetTarget,
+    );
+    fn add_virtual_issuer_set_target(&mut self) -> IssuerSetTarget;
+    /// Mirrors core::set::IssuerSet::commitment
+    fn commit_issuer_set(&mut self, set: &IssuerSetTarget) -> HashTarget;
+    /// Asserts that p is a non neutral point whose encoding t / u is in the
+    /// set, i.e. that t = w * u for one of the w
+    fn assert_in_issuer_set(&mut self, p: PointTarget, set: &IssuerSetTarget);
+}
+
+pub trait PartialWitnessSet<F: RichField>: Witness<F> {
+    fn set_country_set_target(
+        &mut self,
+        target: CountrySetTarget,
+        value: encoding::CountrySet<F>,
+    ) -> anyhow::Result<()>;
+    fn set_issuer_set_target(
+        &mut self,
+        target: IssuerSetTarget,
+        value: encoding::IssuerSet<F>,
+    ) -> anyhow::Result<()>;
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderSet<F, D>
+    for CircuitBuilder<F, D>
+{
+    fn add_virtual_country_set_target(&mut self) -> CountrySetTarget {
+        encoding::CountrySet(self.add_virtual_target_arr())
+    }
+
+    fn commit_country_set(&mut self, set: &CountrySetTarget) -> HashTarget {
+        self.hash_n_to_hash_no_pad::<PoseidonHash>(set.0.to_vec())
+            .into()
+    }
+
+    fn assert_not_in_country_set(&mut self, x: Target, set: &CountrySetTarget) {
+        let product = vanishing(self, x, set);
+        // the inverse can't be computed, nor satisfy product * inverse = 1,
+        // when x is in the set
+        self.inverse(product);
+    }
+
+    fn assert_not_in_country_set_if(
+        &mut self,
+        condition: BoolTarget,
+        x: Target,
+        set: &CountrySetTarget,
+    ) {
+        let product = vanishing(self, x, set);
+        let one = self.one();
+        let product = self.select(condition, product, one);
+        self.inverse(product);
+    }
+
+    fn assert_in_country_set(&mut self, x: Target, set: &CountrySetTarget) {
+        self.inverse(x);
+        let product = vanishing(self, x, set);
+        self.assert_zero(product);
+    }
+
+    fn assert_in_country_set_if(
+        &mut self,
+        condition: BoolTarget,
+        x: Target,
+        set: &CountrySetTarget,
+    ) {
+        let one = self.one();
+        let non_zero = self.select(condition, x, one);
+        self.inverse(non_zero);
+        let product = vanishing(self, x, set);
+        let product = self.mul(condition.target, product);
+        self.assert_zero(product);
+    }
+
+    fn add_virtual_issuer_set_target(&mut self) -> IssuerSetTarget {
+        encoding::IssuerSet(std::array::from_fn(|_| self.add_virtual_gfp5_target()))
+    }
+
+    fn commit_issuer_set(&mut self, set: &IssuerSetTarget) -> HashTarget {
+        let encoded = set.0.iter().flat_map(|w| w.0).collect();
+        self.hash_n_to_hash_no_pad::<PoseidonHash>(encoded).into()
+    }
+
+    fn assert_in_issuer_set(&mut self, p: PointTarget, set: &IssuerSetTarget) {
+        self.assert_on_curve(p);
+        self.assert_non_zero_point(p);
+        let mut found = self._false();
+        for &w in &set.0 {
+            let wu = self.mul_gfp5(w, p.u);
+            let is_w = self.is_equal_gfp5(p.t, wu);
+            found = self.or(found, is_w);
+        }
+        self.assert_one(found.target);
+    }
+}
+
+/// Product of the x - code, which is 0 iff x is in the set
+fn vanishing<F: RichField + Extendable<D>, const D: usize>(
+    builder: &mut CircuitBuilder<F, D>,
+    x: Target,
+    set: &CountrySetTarget,
+) -> Target {
+    let mut product = builder.one();
+    for &code in &set.0 {
+        let diff = builder.sub(x, code);
+        product = builder.mul(product, diff);
+    }
+    product
+}
+
+impl<W: Witness<F>, F: RichField> PartialWitnessSet<F> for W {
+    fn set_country_set_target(
+        &mut self,
+        target: CountrySetTarget,
+        value: encoding::CountrySet<F>,
+    ) -> anyhow::Result<()> {
+        for (target, value) in target.0.into_iter().zip(value.0) {
+            self.set_target(target, value)?;
+        }
+        Ok(())
+    }
+
+    fn set_issuer_set_target(
+        &mut self,
+        target: IssuerSetTarget,
+        value: encoding::IssuerSet<F>,
+    ) -> anyhow::Result<()> {
+        for (target, value) in target.0.into_iter().zip(value.0) {
+            self.set_gfp5_target(target, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        field::{goldilocks_field::GoldilocksField as F, types::Field},
+        iop::witness::PartialWitness,
+        plonk::{circuit_data::CircuitConfig, config::PoseidonGoldilocksConfig},
+    };
+
+    use super::*;
+    use crate::{
+        circuit::hash::{CircuitBuilderHash, PartialWitnessHash},
+        core::set::CountrySet,
+    };
+
+    const D: usize = 2;
+    type Cfg = PoseidonGoldilocksConfig;
+
+    fn prove_membership(code: u16, set: &CountrySet, member: bool) -> anyhow::Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let x_t = builder.add_virtual_target();
+        let set_t = builder.add_virtual_country_set_target();
+        let commitment_t = builder.add_virtual_hash_target();
+        let got = builder.commit_country_set(&set_t);
+        builder.connect_hash(got, commitment_t);
+        if member {
+            builder.assert_in_country_set(x_t, &set_t);
+        } else {
+            builder.assert_not_in_country_set(x_t, &set_t);
+        }
+
+        let mut pw = PartialWitness::<F>::new();
+        pw.set_target(x_t, F::from_canonical_u16(code))?;
+        pw.set_country_set_target(set_t, set.to_field())?;
+        PartialWitnessHash::set_hash_target(&mut pw, commitment_t, set.commitment())?;
+
+        let data = builder.build::<Cfg>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_not_in_set_matches_native() {
+        let set = CountrySet::new(&[364, 408, 760]).unwrap();
+        prove_membership(250, &set, false).unwrap();
+        assert!(prove_membership(408, &set, false).is_err());
+        // padding is not a country
+        assert!(prove_membership(0, &set, false).is_err());
+    }
+
+    #[test]
+    fn test_in_set_matches_native() {
+        let set = CountrySet::new(&[250, 276, 380]).unwrap();
+        prove_membership(276, &set, true).unwrap();
+        assert!(prove_membership(408, &set, true).is_err());
+        // nor is it a member
+        assert!(prove_membership(0, &set, true).is_err());
+    }
+}
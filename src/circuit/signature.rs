@@ -11,6 +11,7 @@ use plonky2::{
 use crate::{
     circuit::{
         credential::CredentialTarget,
+        curve::{CircuitBuilderCurve, PointTarget},
         scalar::ScalarTarget,
         schnorr::{CircuitBuilderSchnorr, PartialWitnessSchnorr},
     },
@@ -18,16 +19,28 @@ use crate::{
 };
 
 pub type SignatureTarget = encoding::Signature<Target, BoolTarget>;
+pub type SignatureLimbsTarget = encoding::SignatureLimbs<Target>;
 
 pub trait CircuitBuilderSignature<F: RichField + Extendable<D>, const D: usize> {
     fn add_virtual_signature_target(&mut self) -> SignatureTarget;
     fn register_signature_public_input(&mut self, target: SignatureTarget);
+    fn add_virtual_signature_limbs_target(&mut self) -> SignatureLimbsTarget;
+    fn decompose_signature(&mut self, target: SignatureLimbsTarget) -> SignatureTarget;
     fn hash_signature(
         &mut self,
         credential: &CredentialTarget,
         signature: &SignatureTarget,
     ) -> ScalarTarget;
     fn verify_signature(&mut self, credential: &CredentialTarget, signature: &SignatureTarget);
+    /// Verifies signatures[i] over credentials[i] for every i, all issued by
+    /// issuer: -issuer and its Shamir table are computed once for the batch,
+    /// only the challenge and the ladder remain per signature
+    fn verify_signatures(
+        &mut self,
+        issuer: PointTarget,
+        credentials: &[CredentialTarget],
+        signatures: &[SignatureTarget],
+    );
 }
 pub trait PartialWitnessSignature<F: RichField>: Witness<F> {
     fn get_signature_target(&self, target: SignatureTarget) -> encoding::Signature<F, bool>;
@@ -36,16 +49,27 @@ pub trait PartialWitnessSignature<F: RichField>: Witness<F> {
         target: SignatureTarget,
         value: encoding::Signature<F, bool>,
     ) -> anyhow::Result<()>;
+    fn set_signature_limbs_target(
+        &mut self,
+        target: SignatureLimbsTarget,
+        value: encoding::SignatureLimbs<F>,
+    ) -> anyhow::Result<()>;
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderSignature<F, D>
     for CircuitBuilder<F, D>
 {
     fn add_virtual_signature_target(&mut self) -> SignatureTarget {
-        encoding::Signature(self.add_virtual_schnorr_target())
+        self.add_virtual_schnorr_target()
     }
     fn register_signature_public_input(&mut self, target: SignatureTarget) {
-        self.register_schnorr_public_input(target.0);
+        self.register_schnorr_public_input(target);
+    }
+    fn add_virtual_signature_limbs_target(&mut self) -> SignatureLimbsTarget {
+        self.add_virtual_schnorr_limbs_target()
+    }
+    fn decompose_signature(&mut self, target: SignatureLimbsTarget) -> SignatureTarget {
+        self.decompose_schnorr(target)
     }
     fn hash_signature(
         &mut self,
@@ -53,25 +77,52 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderSignature<F, D>
         signature: &SignatureTarget,
     ) -> ScalarTarget {
         let credential_input: [Target; LEN_CREDENTIAL] = credential.into();
-        self.schnorr_hash_with_message(signature.0, &credential_input)
+        self.schnorr_hash_with_message(*signature, &credential_input)
     }
     fn verify_signature(&mut self, credential: &CredentialTarget, signature: &SignatureTarget) {
         let pk = credential.issuer;
         let e = self.hash_signature(credential, signature);
-        self.schnorr_final_verification(signature.0, e, pk);
+        self.schnorr_final_verification(*signature, e, pk);
+    }
+    fn verify_signatures(
+        &mut self,
+        issuer: PointTarget,
+        credentials: &[CredentialTarget],
+        signatures: &[SignatureTarget],
+    ) {
+        assert_eq!(
+            credentials.len(),
+            signatures.len(),
+            "one signature is expected per credential"
+        );
+        let issuer_neg = self.neg_point(issuer);
+        let table = self.shamir_table(issuer_neg);
+        for (credential, signature) in credentials.iter().zip(signatures) {
+            let same_issuer = self.is_equal_point(credential.issuer, issuer);
+            self.assert_one(same_issuer.target);
+            let e = self.hash_signature(credential, signature);
+            self.schnorr_final_verification_with_table(*signature, e, &table);
+        }
     }
 }
 
 impl<W: Witness<F>, F: RichField> PartialWitnessSignature<F> for W {
     fn get_signature_target(&self, target: SignatureTarget) -> encoding::Signature<F, bool> {
-        encoding::Signature(self.get_schnorr_target(target.0))
+        self.get_schnorr_target(target)
     }
     fn set_signature_target(
         &mut self,
         target: SignatureTarget,
         value: encoding::Signature<F, bool>,
     ) -> anyhow::Result<()> {
-        self.set_schnorr_target(target.0, value.0)
+        self.set_schnorr_target(target, value)
+    }
+    fn set_signature_limbs_target(
+        &mut self,
+        target: SignatureLimbsTarget,
+        value: encoding::SignatureLimbs<F>,
+    ) -> anyhow::Result<()> {
+        self.set_schnorr_limbs_target(target, value)
     }
 }
 #[cfg(test)]
@@ -80,14 +131,17 @@ mod tests {
         arith,
         circuit::{
             credential::{CircuitBuilderCredential, PartialWitnessCredential},
-            curve::{tests::check_public_input_point, CircuitBuilderCurve},
+            curve::{tests::check_public_input_point, CircuitBuilderCurve, PartialWitnessCurve},
         },
         core::credential,
         encoding::{
-            conversion::{ToPointField, ToSignatureField},
-            LEN_FIELD,
+            conversion::{ToPointField, ToSchnorrField},
+            LEN_FIELD, LEN_PACKED_SCALAR,
+        },
+        schnorr::{
+            keys::{PublicKey, SecretKey},
+            signature::{self, Context},
         },
-        schnorr::signature::{self, Context},
     };
 
     use rand::{rngs::StdRng, SeedableRng};
@@ -110,8 +164,8 @@ mod tests {
         let s2 = builder.add_virtual_signature_target();
 
         // Sanity: the first limb of r.x should differ, and at least one scalar bit should differ.
-        assert_ne!(s1.0.r.x.0[0], s2.0.r.x.0[0]);
-        assert_ne!(s1.0.s.0[0].target, s2.0.s.0[0].target);
+        assert_ne!(s1.r.x.0[0], s2.r.x.0[0]);
+        assert_ne!(s1.s.0[0].target, s2.s.0[0].target);
     }
 
     #[test]
@@ -125,8 +179,8 @@ mod tests {
         let data = builder.build::<Cfg>();
 
         // PointTarget = 4 GFp5 = 4*5 = 20 field elements
-        // ScalarTarget = Scalar::NB_BITS public inputs (register_scalar_public_input usually registers bits)
-        let expected = 20 + arith::Scalar::NB_BITS;
+        // ScalarTarget = LEN_PACKED_SCALAR public inputs (register_scalar_public_input packs the bits)
+        let expected = 20 + LEN_PACKED_SCALAR;
 
         assert_eq!(data.common.num_public_inputs, expected);
     }
@@ -148,10 +202,10 @@ mod tests {
         // A simple point encoding
         let r_native: encoding::Point<F> = arith::Point::GENERATOR.to_field();
 
-        let sig_native = encoding::Signature(encoding::SchnorrProof {
+        let sig_native = encoding::SchnorrProof {
             r: r_native,
             s: s_native,
-        });
+        };
 
         // --- set then get ---
         let mut pw = PartialWitness::<F>::new();
@@ -160,16 +214,16 @@ mod tests {
         let got = pw.get_signature_target(sig_t);
 
         // Compare scalar
-        for (s, n) in got.0.s.0.iter().zip(sig_native.0.s.0.iter()) {
+        for (s, n) in got.s.0.iter().zip(sig_native.s.0.iter()) {
             assert_eq!(s, n);
         }
 
         // Compare point limbs
         for i in 0..LEN_FIELD {
-            assert_eq!(got.0.r.x.0[i], sig_native.0.r.x.0[i]);
-            assert_eq!(got.0.r.z.0[i], sig_native.0.r.z.0[i]);
-            assert_eq!(got.0.r.u.0[i], sig_native.0.r.u.0[i]);
-            assert_eq!(got.0.r.t.0[i], sig_native.0.r.t.0[i]);
+            assert_eq!(got.r.x.0[i], sig_native.r.x.0[i]);
+            assert_eq!(got.r.z.0[i], sig_native.r.z.0[i]);
+            assert_eq!(got.r.u.0[i], sig_native.r.u.0[i]);
+            assert_eq!(got.r.t.0[i], sig_native.r.t.0[i]);
         }
 
         // Finally, proving should succeed since we added no constraints beyond PI wiring.
@@ -186,7 +240,7 @@ mod tests {
 
         let (_, sk, credential) = credential::Credential::random(&mut rng);
         let ctx = Context::new(&credential);
-        let signature = signature::Signature::sign(&sk, &ctx);
+        let signature = signature::Signature::prove(&sk, &ctx);
 
         let expected_issuer = credential.issuer().0;
         let credential = credential.to_field();
@@ -213,4 +267,105 @@ mod tests {
         data.verify(proof.clone()).expect("verify should pass");
         check_public_input_point(&proof.public_inputs, expected_issuer);
     }
+
+    #[test]
+    fn test_verify_accepts_limbs() {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let (_, sk, credential) = credential::Credential::random(&mut rng);
+        let signature = signature::Signature::prove(&sk, &Context::new(&credential));
+        let limbs = signature.to_limbs::<F>();
+        assert_eq!(
+            limbs.unpack().unwrap().s,
+            ToSchnorrField::<F, bool>::to_field(&signature).s
+        );
+
+        let credential_t = builder.add_virtual_credential_target();
+        let limbs_t = builder.add_virtual_signature_limbs_target();
+        let signature_t = builder.decompose_signature(limbs_t);
+        builder.verify_signature(&credential_t, &signature_t);
+
+        let mut pw = PartialWitness::<F>::new();
+        pw.set_credential_target(credential_t, credential.to_field())
+            .unwrap();
+        pw.set_signature_limbs_target(limbs_t, limbs).unwrap();
+
+        let data = builder.build::<Cfg>();
+        let proof = data.prove(pw).expect("prove should pass");
+        data.verify(proof).expect("verify should pass");
+    }
+
+    /// Builds verify_signatures over n credentials of a single issuer, with
+    /// credentials[i] set to the credential of signatures[i] signed by sks[i]
+    fn prove_signatures(sks: &[&SecretKey], swap: bool) -> anyhow::Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let issuer_t = builder.add_virtual_point_target();
+        let credentials_t: Vec<_> = sks
+            .iter()
+            .map(|_| builder.add_virtual_credential_target())
+            .collect();
+        let mut signatures_t: Vec<_> = sks
+            .iter()
+            .map(|_| builder.add_virtual_signature_target())
+            .collect();
+        builder.verify_signatures(issuer_t, &credentials_t, &signatures_t);
+        if swap {
+            signatures_t.reverse();
+        }
+
+        let mut pw = PartialWitness::<F>::new();
+        pw.set_point_target(issuer_t, PublicKey::from(sks[0]).0.to_field())?;
+        for ((sk, credential_t), signature_t) in sks.iter().zip(credentials_t).zip(signatures_t) {
+            let credential = credential::Credential::random_with_issuer(sk, &mut rng);
+            let signature = signature::Signature::prove(sk, &Context::new(&credential));
+            pw.set_credential_target(credential_t, credential.to_field())?;
+            pw.set_signature_target(signature_t, signature.to_field())?;
+        }
+
+        let data = builder.build::<Cfg>();
+        data.verify(data.prove(pw)?)
+    }
+
+    #[test]
+    fn test_verify_signatures_accepts() {
+        let sk = SecretKey::random(&mut StdRng::seed_from_u64(1));
+        prove_signatures(&[&sk, &sk, &sk], false).expect("three signatures of the issuer");
+    }
+
+    #[test]
+    fn test_verify_signatures_rejects_swapped_signatures() {
+        let sk = SecretKey::random(&mut StdRng::seed_from_u64(2));
+        assert!(prove_signatures(&[&sk, &sk], true).is_err());
+    }
+
+    #[test]
+    fn test_verify_signatures_rejects_another_issuer() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let sk = SecretKey::random(&mut rng);
+        let other = SecretKey::random(&mut rng);
+        // the second credential is validly signed, by another issuer
+        assert!(prove_signatures(&[&sk, &other], false).is_err());
+    }
+
+    #[test]
+    fn test_verify_signatures_shares_the_table() {
+        let gates = |batch: bool| {
+            let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+            let issuer = builder.add_virtual_point_target();
+            let credentials = [(); 3].map(|_| builder.add_virtual_credential_target());
+            let signatures = [(); 3].map(|_| builder.add_virtual_signature_target());
+            if batch {
+                builder.verify_signatures(issuer, &credentials, &signatures);
+            } else {
+                for (credential, signature) in credentials.iter().zip(&signatures) {
+                    builder.verify_signature(credential, signature);
+                }
+            }
+            builder.num_gates()
+        };
+        assert!(gates(true) < gates(false));
+    }
 }
@@ -1,18 +1,22 @@
 use anyhow::Ok;
 use plonky2::{
-    field::extension::Extendable,
+    field::{extension::Extendable, types::Field},
     hash::hash_types::RichField,
     iop::{target::Target, witness::Witness},
     plonk::circuit_builder::CircuitBuilder,
 };
 
-use crate::encoding::{self, LEN_STRING};
+use crate::encoding::{self, LEN_STRING, MAX_STRING_BYTES};
 
 type StringTarget = encoding::String<Target>;
+pub type VarStringTarget = encoding::VarString<Target>;
 
 pub trait CircuitBuilderString<F: RichField + Extendable<D>, const D: usize> {
     fn add_virtual_string_target(&mut self) -> StringTarget;
     fn register_string_public_input(&mut self, target: StringTarget);
+    /// The length is asserted to be <= MAX_STRING_BYTES, and the bytes beyond
+    /// it to be zero
+    fn add_virtual_var_string_target(&mut self) -> VarStringTarget;
 }
 pub trait PartialWitnessString<F: RichField>: Witness<F> {
     fn get_string_target(&self, target: StringTarget) -> encoding::String<F>;
@@ -21,6 +25,12 @@ pub trait PartialWitnessString<F: RichField>: Witness<F> {
         target: StringTarget,
         value: encoding::String<F>,
     ) -> anyhow::Result<()>;
+    fn get_var_string_target(&self, target: VarStringTarget) -> encoding::VarString<F>;
+    fn set_var_string_target(
+        &mut self,
+        target: VarStringTarget,
+        value: encoding::VarString<F>,
+    ) -> anyhow::Result<()>;
 }
 
 impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderString<F, D>
@@ -36,6 +46,27 @@ impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderString<F, D>
             self.register_public_input(t);
         }
     }
+    fn add_virtual_var_string_target(&mut self) -> VarStringTarget {
+        let value = self.add_virtual_string_target();
+        let len = self.add_virtual_target();
+        let max = self.constant(F::from_canonical_usize(MAX_STRING_BYTES));
+        let diff = self.sub(max, len);
+        self.range_check(diff, 8);
+        // past is 1 from the byte at index len onwards, and 0 before
+        let mut past = self.zero();
+        for (i, limb) in value.0.into_iter().enumerate() {
+            let bits = self.split_le(limb, 32);
+            for (j, byte) in bits.chunks(8).enumerate() {
+                let index = self.constant(F::from_canonical_usize(4 * i + j));
+                let is_len = self.is_equal(len, index);
+                past = self.add(past, is_len.target);
+                let byte = self.le_sum(byte.iter());
+                let beyond = self.mul(byte, past);
+                self.assert_zero(beyond);
+            }
+        }
+        encoding::VarString { value, len }
+    }
 }
 
 impl<W: Witness<F>, F: RichField> PartialWitnessString<F> for W {
@@ -52,4 +83,64 @@ impl<W: Witness<F>, F: RichField> PartialWitnessString<F> for W {
         }
         Ok(())
     }
+    fn get_var_string_target(&self, target: VarStringTarget) -> encoding::VarString<F> {
+        encoding::VarString {
+            value: self.get_string_target(target.value),
+            len: self.get_target(target.len),
+        }
+    }
+    fn set_var_string_target(
+        &mut self,
+        target: VarStringTarget,
+        value: encoding::VarString<F>,
+    ) -> anyhow::Result<()> {
+        self.set_string_target(target.value, value.value)?;
+        self.set_target(target.len, value.len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        field::goldilocks_field::GoldilocksField as F,
+        iop::witness::PartialWitness,
+        plonk::{circuit_data::CircuitConfig, config::PoseidonGoldilocksConfig},
+    };
+
+    use super::*;
+    use crate::encoding::conversion::ToVarStringField;
+
+    const D: usize = 2;
+    type Cfg = PoseidonGoldilocksConfig;
+
+    fn prove_var_string(value: encoding::VarString<F>) -> anyhow::Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let target = builder.add_virtual_var_string_target();
+        let mut pw = PartialWitness::<F>::new();
+        pw.set_var_string_target(target, value)?;
+        let data = builder.build::<Cfg>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_var_string_accepts_exact_length() {
+        prove_var_string("Ab".to_field()).unwrap();
+        prove_var_string("".to_field()).unwrap();
+        prove_var_string("ABCDEFGHIJKLMNOPQRST".to_field()).unwrap();
+    }
+
+    #[test]
+    fn test_var_string_rejects_bytes_beyond_length() {
+        let mut value: encoding::VarString<F> = "Abc".to_field();
+        value.len = F::from_canonical_u8(2);
+        assert!(prove_var_string(value).is_err());
+    }
+
+    #[test]
+    fn test_var_string_rejects_length_above_capacity() {
+        let mut value: encoding::VarString<F> = "Abc".to_field();
+        value.len = F::from_canonical_usize(MAX_STRING_BYTES + 1);
+        assert!(prove_var_string(value).is_err());
+    }
 }
@@ -0,0 +1,133 @@
+use plonky2::{
+    field::extension::Extendable,
+    hash::hash_types::RichField,
+    iop::{
+        target::{BoolTarget, Target},
+        witness::{Witness, WitnessWrite},
+    },
+    plonk::circuit_builder::CircuitBuilder,
+};
+
+use crate::{
+    circuit::{
+        curve::PointTarget,
+        schnorr::{CircuitBuilderSchnorr, PartialWitnessSchnorr},
+    },
+    encoding::{self, LEN_POINT, LEN_STRING},
+};
+
+pub type TimeTokenTarget = encoding::TimeToken<Target, BoolTarget>;
+
+pub trait CircuitBuilderTimeToken<F: RichField + Extendable<D>, const D: usize> {
+    fn add_virtual_time_token_target(&mut self) -> TimeTokenTarget;
+    /// Verifies that `authority` signed the date of the token for `nonce`
+    fn verify_time_token(
+        &mut self,
+        token: &TimeTokenTarget,
+        nonce: encoding::String<Target>,
+        authority: PointTarget,
+    );
+}
+
+pub trait PartialWitnessTimeToken<F: RichField>: Witness<F> {
+    fn set_time_token_target(
+        &mut self,
+        target: TimeTokenTarget,
+        value: encoding::TimeToken<F, bool>,
+    ) -> anyhow::Result<()>;
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> CircuitBuilderTimeToken<F, D>
+    for CircuitBuilder<F, D>
+{
+    fn add_virtual_time_token_target(&mut self) -> TimeTokenTarget {
+        encoding::TimeToken {
+            today: self.add_virtual_target(),
+            signature: self.add_virtual_schnorr_target(),
+        }
+    }
+
+    fn verify_time_token(
+        &mut self,
+        token: &TimeTokenTarget,
+        nonce: encoding::String<Target>,
+        authority: PointTarget,
+    ) {
+        let mut message = Vec::with_capacity(1 + LEN_STRING + LEN_POINT);
+        message.push(token.today);
+        message.extend_from_slice(&nonce.0);
+        let authority_t: [Target; LEN_POINT] = authority.into();
+        message.extend_from_slice(&authority_t);
+        let e = self.schnorr_hash_with_message(token.signature, &message);
+        self.schnorr_final_verification(token.signature, e, authority);
+    }
+}
+
+impl<W: Witness<F>, F: RichField> PartialWitnessTimeToken<F> for W {
+    fn set_time_token_target(
+        &mut self,
+        target: TimeTokenTarget,
+        value: encoding::TimeToken<F, bool>,
+    ) -> anyhow::Result<()> {
+        self.set_target(target.today, value.today)?;
+        self.set_schnorr_target(target.signature, value.signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use plonky2::{
+        field::goldilocks_field::GoldilocksField as F,
+        iop::witness::PartialWitness,
+        plonk::{circuit_data::CircuitConfig, config::PoseidonGoldilocksConfig},
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        circuit::{
+            curve::{CircuitBuilderCurve, PartialWitnessCurve},
+            string::{CircuitBuilderString, PartialWitnessString},
+        },
+        encoding::conversion::{ToPointField, ToStringField},
+        issuer::timestamp::TimeToken,
+        schnorr::keys::{PublicKey, SecretKey},
+    };
+
+    const D: usize = 2;
+    type Cfg = PoseidonGoldilocksConfig;
+
+    fn prove_time_token(
+        token: &TimeToken,
+        nonce: &str,
+        authority: &PublicKey,
+    ) -> anyhow::Result<()> {
+        let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+        let token_t = builder.add_virtual_time_token_target();
+        let nonce_t = builder.add_virtual_string_target();
+        let authority_t = builder.add_virtual_point_target();
+        builder.verify_time_token(&token_t, nonce_t, authority_t);
+
+        let mut pw = PartialWitness::<F>::new();
+        pw.set_time_token_target(token_t, token.to_field())?;
+        pw.set_string_target(nonce_t, nonce.to_string().to_field())?;
+        pw.set_point_target(authority_t, authority.0.to_field())?;
+
+        let data = builder.build::<Cfg>();
+        let proof = data.prove(pw)?;
+        data.verify(proof)
+    }
+
+    #[test]
+    fn test_verify_time_token_matches_native() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let authority_sk = SecretKey::random(&mut rng);
+        let authority = PublicKey::from(&authority_sk);
+        let today = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let token = TimeToken::issue(&authority_sk, today, "nonce");
+        assert!(token.verify(&authority, "nonce"));
+        prove_time_token(&token, "nonce", &authority).unwrap();
+        assert!(prove_time_token(&token, "other nonce", &authority).is_err());
+    }
+}
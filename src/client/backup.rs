@@ -0,0 +1,250 @@
+// Shamir secret sharing of the holder secret key, so that it can be recovered
+// from any `threshold` of the `n` shares given to trusted contacts, while fewer
+// shares reveal nothing about it.
+//
+// Each byte of the encoded key is shared independently over GF(2^8).
+
+use rand::Rng;
+use thiserror::Error;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::schnorr::keys::SecretKey;
+
+const LEN_KEY: usize = 40;
+pub const VERSION: u8 = 1;
+pub const LEN_SHARE: usize = 3 + LEN_KEY;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Threshold must be between 1 and the number of shares (at most 255)")]
+    InvalidThreshold,
+    #[error("{0} shares are needed, got {1}")]
+    NotEnoughShares(usize, usize),
+    #[error("Share {0} is given twice")]
+    DuplicateShare(u8),
+    #[error("Shares were not produced by the same split")]
+    InconsistentShares,
+    #[error("Malformed share")]
+    Malformed,
+    #[error("Shares do not recombine to a valid key")]
+    InvalidKey,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Not Debug, so that shares don't end up in logs, and wiped when dropped
+#[derive(Clone, PartialEq, Eq)]
+pub struct Share {
+    threshold: u8,
+    /// x coordinate, never 0 (the secret is at 0)
+    index: u8,
+    value: [u8; LEN_KEY],
+}
+
+// GF(2^8) with the AES polynomial x^8 + x^4 + x^3 + x + 1
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut res = 0;
+    for _ in 0..8 {
+        res ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    res
+}
+
+fn gf_inv(a: u8) -> u8 {
+    // a^254 = a^-1 for a != 0
+    let mut res = 1;
+    let mut base = a;
+    let mut e = 254u8;
+    while e > 0 {
+        if e & 1 == 1 {
+            res = gf_mul(res, base);
+        }
+        base = gf_mul(base, base);
+        e >>= 1;
+    }
+    res
+}
+
+impl Drop for Share {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+impl Share {
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    pub fn to_bytes(&self) -> [u8; LEN_SHARE] {
+        let mut res = [0; LEN_SHARE];
+        res[0] = VERSION;
+        res[1] = self.threshold;
+        res[2] = self.index;
+        res[3..].copy_from_slice(&self.value);
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != LEN_SHARE || bytes[0] != VERSION || bytes[1] == 0 || bytes[2] == 0 {
+            return Err(Error::Malformed);
+        }
+        Ok(Self {
+            threshold: bytes[1],
+            index: bytes[2],
+            value: bytes[3..].try_into().unwrap(),
+        })
+    }
+}
+
+/// Splits sk into n shares, any threshold of which recover it
+pub fn split(sk: &SecretKey, threshold: u8, n: u8, rng: &mut impl Rng) -> Result<Vec<Share>> {
+    if threshold == 0 || threshold > n {
+        return Err(Error::InvalidThreshold);
+    }
+    let secret = Zeroizing::new(sk.to_bytes());
+    // coefficients[i][j] is the coefficient of degree j + 1 for byte i
+    let coefficients: Zeroizing<Vec<Vec<u8>>> = Zeroizing::new(
+        (0..LEN_KEY)
+            .map(|_| (1..threshold).map(|_| rng.random()).collect())
+            .collect(),
+    );
+    Ok((1..=n)
+        .map(|x| {
+            let value = std::array::from_fn(|i| {
+                // Horner evaluation at x, the secret being the constant term
+                coefficients[i]
+                    .iter()
+                    .rev()
+                    .chain(std::iter::once(&secret[i]))
+                    .fold(0, |acc, c| gf_mul(acc, x) ^ c)
+            });
+            Share {
+                threshold,
+                index: x,
+                value,
+            }
+        })
+        .collect())
+}
+
+/// Recovers the secret key from at least threshold shares.
+/// Shares of different splits with the same threshold can't be told apart,
+/// and recombine to an unrelated key.
+pub fn recover(shares: &[Share]) -> Result<SecretKey> {
+    let threshold = shares.first().map_or(1, Share::threshold) as usize;
+    if shares.len() < threshold {
+        return Err(Error::NotEnoughShares(threshold, shares.len()));
+    }
+    let shares = &shares[..threshold];
+    for (i, share) in shares.iter().enumerate() {
+        if share.threshold as usize != threshold {
+            return Err(Error::InconsistentShares);
+        }
+        if shares[..i].iter().any(|other| other.index == share.index) {
+            return Err(Error::DuplicateShare(share.index));
+        }
+    }
+    // Lagrange interpolation at 0: l_i = prod_{j != i} x_j / (x_j - x_i)
+    let mut secret = Zeroizing::new([0; LEN_KEY]);
+    for share in shares {
+        let mut l = 1;
+        for other in shares.iter().filter(|other| other.index != share.index) {
+            l = gf_mul(l, gf_mul(other.index, gf_inv(other.index ^ share.index)));
+        }
+        for (s, v) in secret.iter_mut().zip(share.value) {
+            *s ^= gf_mul(l, v);
+        }
+    }
+    SecretKey::from_bytes(secret.as_slice()).ok_or(Error::InvalidKey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn key(seed: u64) -> SecretKey {
+        SecretKey::random(&mut StdRng::seed_from_u64(seed))
+    }
+
+    #[test]
+    fn gf_inv_is_inverse() {
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+        }
+    }
+
+    #[test]
+    fn any_threshold_shares_recover_the_key() {
+        let sk = key(1);
+        let mut rng = StdRng::seed_from_u64(2);
+        let shares = split(&sk, 3, 5, &mut rng).unwrap();
+
+        for i in 0..5 {
+            for j in (i + 1)..5 {
+                for k in (j + 1)..5 {
+                    let subset = [shares[i].clone(), shares[j].clone(), shares[k].clone()];
+                    assert_eq!(recover(&subset).unwrap().to_bytes(), sk.to_bytes());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fewer_shares_than_threshold_are_rejected() {
+        let sk = key(3);
+        let shares = split(&sk, 3, 5, &mut StdRng::seed_from_u64(4)).unwrap();
+        assert_eq!(
+            recover(&shares[..2]).err(),
+            Some(Error::NotEnoughShares(3, 2))
+        );
+    }
+
+    #[test]
+    fn duplicate_and_mixed_shares_are_rejected() {
+        let shares = split(&key(5), 2, 3, &mut StdRng::seed_from_u64(6)).unwrap();
+        assert_eq!(
+            recover(&[shares[0].clone(), shares[0].clone()]).err(),
+            Some(Error::DuplicateShare(1))
+        );
+
+        let other = split(&key(7), 3, 3, &mut StdRng::seed_from_u64(8)).unwrap();
+        assert_eq!(
+            recover(&[shares[0].clone(), other[1].clone()]).err(),
+            Some(Error::InconsistentShares)
+        );
+    }
+
+    #[test]
+    fn share_bytes_round_trip() {
+        let shares = split(&key(9), 2, 3, &mut StdRng::seed_from_u64(10)).unwrap();
+        for share in shares {
+            assert!(Share::from_bytes(&share.to_bytes()) == Ok(share));
+        }
+        assert_eq!(
+            Share::from_bytes(&[0; LEN_SHARE]).err(),
+            Some(Error::Malformed)
+        );
+    }
+
+    #[test]
+    fn invalid_threshold_is_rejected() {
+        let mut rng = StdRng::seed_from_u64(11);
+        assert_eq!(
+            split(&key(12), 0, 3, &mut rng).err(),
+            Some(Error::InvalidThreshold)
+        );
+        assert_eq!(
+            split(&key(12), 4, 3, &mut rng).err(),
+            Some(Error::InvalidThreshold)
+        );
+    }
+}
@@ -0,0 +1,258 @@
+// Encrypted export of a wallet, to move credentials & keys to a new device.
+//
+// Layout: MAGIC || VERSION || salt || nonce || XChaCha20-Poly1305(payload)
+// The header is authenticated as associated data, and the key is derived from
+// the passphrase with Argon2id.
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use plonky2::field::{
+    goldilocks_field::GoldilocksField,
+    types::{Field, Field64, PrimeField64},
+};
+use rand::{rngs::OsRng, TryRngCore};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+use crate::{
+    core::credential::Credential,
+    encoding,
+    schnorr::{keys::SecretKey, signature::Signature},
+};
+
+const MAGIC: &[u8; 8] = b"ZKYCBNDL";
+pub const VERSION: u8 = 1;
+const LEN_SALT: usize = 16;
+const LEN_NONCE: usize = 24;
+const LEN_HEADER: usize = MAGIC.len() + 1 + LEN_SALT + LEN_NONCE;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Not a bundle")]
+    NotABundle,
+    #[error("Unsupported bundle version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Wrong passphrase or corrupted bundle")]
+    Decryption,
+    #[error("Malformed bundle content")]
+    Malformed,
+    #[error("Key derivation failed: {0}")]
+    Kdf(argon2::Error),
+    #[error("Could not get randomness: {0}")]
+    Rng(#[from] rand::rand_core::OsError),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Everything a holder needs to keep proving from another device
+#[derive(Default)]
+pub struct Bundle {
+    pub credentials: Vec<(Credential, Signature)>,
+    pub keys: Vec<SecretKey>,
+    /// Digests of the circuits already set up, to avoid rebuilding them
+    pub circuit_digests: Vec<encoding::Hash<GoldilocksField>>,
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Zeroizing<[u8; 32]>> {
+    let mut key = Zeroizing::new([0; 32]);
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut_slice())
+        .map_err(Error::Kdf)?;
+    Ok(key)
+}
+
+fn push_len(res: &mut Vec<u8>, len: usize) {
+    res.extend_from_slice(&(len as u32).to_le_bytes());
+}
+
+impl Bundle {
+    /// Holds the secret keys in the clear, wiped when dropped
+    fn to_bytes(&self) -> Zeroizing<Vec<u8>> {
+        let mut res = Zeroizing::new(vec![]);
+        push_len(&mut res, self.credentials.len());
+        for (credential, signature) in &self.credentials {
+            let credential = credential.to_bytes();
+            push_len(&mut res, credential.len());
+            res.extend_from_slice(&credential);
+            res.extend_from_slice(&signature.to_bytes());
+        }
+        push_len(&mut res, self.keys.len());
+        for key in &self.keys {
+            res.extend_from_slice(Zeroizing::new(key.to_bytes()).as_slice());
+        }
+        push_len(&mut res, self.circuit_digests.len());
+        for digest in &self.circuit_digests {
+            for x in digest.0 {
+                res.extend_from_slice(&x.to_canonical_u64().to_le_bytes());
+            }
+        }
+        res
+    }
+
+    fn from_bytes(mut bytes: &[u8]) -> Option<Self> {
+        fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+            if bytes.len() < n {
+                return None;
+            }
+            let (head, tail) = bytes.split_at(n);
+            *bytes = tail;
+            Some(head)
+        }
+        fn len(bytes: &mut &[u8]) -> Option<usize> {
+            Some(u32::from_le_bytes(take(bytes, 4)?.try_into().unwrap()) as usize)
+        }
+
+        let mut bundle = Bundle::default();
+        for _ in 0..len(&mut bytes)? {
+            let n = len(&mut bytes)?;
+            let credential = Credential::from_bytes(take(&mut bytes, n)?)?;
+            let signature = Signature::from_bytes(take(&mut bytes, 80)?).ok()?;
+            bundle.credentials.push((credential, signature));
+        }
+        for _ in 0..len(&mut bytes)? {
+            bundle
+                .keys
+                .push(SecretKey::from_bytes(take(&mut bytes, 40)?)?);
+        }
+        for _ in 0..len(&mut bytes)? {
+            let mut digest = [GoldilocksField::ZERO; encoding::LEN_HASH];
+            for x in digest.iter_mut() {
+                let v = u64::from_le_bytes(take(&mut bytes, 8)?.try_into().unwrap());
+                if v >= GoldilocksField::ORDER {
+                    return None;
+                }
+                *x = GoldilocksField::from_canonical_u64(v);
+            }
+            bundle.circuit_digests.push(encoding::Hash(digest));
+        }
+        bytes.is_empty().then_some(bundle)
+    }
+}
+
+/// Encrypts the bundle under a key derived from passphrase
+pub fn export_bundle(bundle: &Bundle, passphrase: &str) -> Result<Vec<u8>> {
+    let mut salt = [0; LEN_SALT];
+    let mut nonce = [0; LEN_NONCE];
+    OsRng.try_fill_bytes(&mut salt)?;
+    OsRng.try_fill_bytes(&mut nonce)?;
+
+    let mut res = Vec::with_capacity(LEN_HEADER);
+    res.extend_from_slice(MAGIC);
+    res.push(VERSION);
+    res.extend_from_slice(&salt);
+    res.extend_from_slice(&nonce);
+
+    let key = derive_key(passphrase, &salt)?;
+    let ciphertext = XChaCha20Poly1305::new(Key::from_slice(key.as_slice()))
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &bundle.to_bytes(),
+                aad: &res,
+            },
+        )
+        // only fails on inputs larger than what a Vec can hold
+        .expect("encryption failed");
+    res.extend_from_slice(&ciphertext);
+    Ok(res)
+}
+
+/// Decrypts a bundle produced by export_bundle
+pub fn import_bundle(blob: &[u8], passphrase: &str) -> Result<Bundle> {
+    if blob.len() < LEN_HEADER || blob[..MAGIC.len()] != MAGIC[..] {
+        return Err(Error::NotABundle);
+    }
+    let version = blob[MAGIC.len()];
+    if version != VERSION {
+        return Err(Error::UnsupportedVersion(version));
+    }
+    let (header, ciphertext) = blob.split_at(LEN_HEADER);
+    let salt = &header[MAGIC.len() + 1..MAGIC.len() + 1 + LEN_SALT];
+    let nonce = &header[MAGIC.len() + 1 + LEN_SALT..];
+
+    let key = derive_key(passphrase, salt)?;
+    let plaintext = XChaCha20Poly1305::new(Key::from_slice(key.as_slice()))
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| Error::Decryption)?;
+    Bundle::from_bytes(&plaintext).ok_or(Error::Malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schnorr::signature::Context;
+
+    fn bundle() -> Bundle {
+        let (client_sk, issuer_sk, credential) = Credential::from_seed(1);
+        let signature = Signature::prove(&issuer_sk, &Context::new(&credential));
+        Bundle {
+            credentials: vec![(credential, signature)],
+            keys: vec![client_sk],
+            circuit_digests: vec![encoding::Hash([
+                GoldilocksField::from_canonical_u64(1),
+                GoldilocksField::from_canonical_u64(2),
+                GoldilocksField::from_canonical_u64(3),
+                GoldilocksField::NEG_ONE,
+            ])],
+        }
+    }
+
+    #[test]
+    fn export_then_import_round_trip() {
+        let bundle = bundle();
+        let blob = export_bundle(&bundle, "correct horse").unwrap();
+        let imported = import_bundle(&blob, "correct horse").unwrap();
+
+        assert_eq!(imported.credentials.len(), 1);
+        let (credential, signature) = &imported.credentials[0];
+        assert_eq!(credential.to_bytes(), bundle.credentials[0].0.to_bytes());
+        assert!(credential.check(signature));
+        assert_eq!(imported.keys[0].to_bytes(), bundle.keys[0].to_bytes());
+        assert_eq!(imported.circuit_digests, bundle.circuit_digests);
+    }
+
+    #[test]
+    fn import_rejects_wrong_passphrase() {
+        let blob = export_bundle(&bundle(), "correct horse").unwrap();
+        assert!(matches!(
+            import_bundle(&blob, "battery staple"),
+            Err(Error::Decryption)
+        ));
+    }
+
+    #[test]
+    fn import_rejects_tampered_header_or_content() {
+        let blob = export_bundle(&bundle(), "correct horse").unwrap();
+
+        let mut tampered = blob.clone();
+        tampered[MAGIC.len() + 1] ^= 1; // salt
+        assert!(matches!(
+            import_bundle(&tampered, "correct horse"),
+            Err(Error::Decryption)
+        ));
+
+        let mut tampered = blob.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            import_bundle(&tampered, "correct horse"),
+            Err(Error::Decryption)
+        ));
+
+        let mut tampered = blob;
+        tampered[MAGIC.len()] = VERSION + 1;
+        assert!(matches!(
+            import_bundle(&tampered, "correct horse"),
+            Err(Error::UnsupportedVersion(_))
+        ));
+    }
+}
@@ -0,0 +1,640 @@
+// What a presentation reveals to the relying party, so that the wallet can ask
+// for informed consent before proving.
+//
+// A presentation request is a list of `key: value` lines:
+//   service: ZBanK
+//   nonce: QWERTY
+//   nationality: FR    (unless excluded_nationalities is given)
+//   issuer: <hex of the issuer public key>
+//   certified: false   (optional, issuer is then the root key)
+//   nym: false         (optional)
+//   derived_key: false (optional)
+//   k_show: 3          (optional)
+//   issued_within: 90  (optional, in days)
+//   authority_country: FR (optional)
+//   time_authority: <hex of the time authority public key> (optional)
+//   consortium: EU-AML (optional, scope of the consortium nullifier)
+//   excluded_nationalities: 364, 408 (optional, the nationality then stays hidden)
+//   portrait_hash: <hex of the hash of the photo presented> (optional)
+//   credential_id: <hex of the salt of the issuer> (optional)
+//   account: 0x<hex> or customer-42 (optional, account the proof is bound to)
+//   name_hash: <hex of the salted hash of the declared family name> (optional)
+//   name_salt: <hex of its salt> (with name_hash)
+//   document_commitment: <hex of the salt of the issuer for the epoch> (optional)
+//   continuity: false  (optional, with credential_id)
+//   min_age: 21        (optional, in years, 18 by default)
+//   age_on: 2024-05-01 (optional, past date of the age check, today by default)
+
+use chrono::NaiveDate;
+use thiserror::Error;
+
+use crate::{
+    bank::presentation::take_hash,
+    circuit::inputs::Features,
+    core::{
+        codec::Codec,
+        credential::{Nationality, PortraitHash},
+        date,
+        set::CountrySet,
+    },
+    encoding::{MAX_ACCOUNT_BYTES, MAX_STRING_BYTES},
+    issuer::pseudonym::{Pseudonym, Salt},
+    schnorr::keys::PublicKey,
+};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Line {0} is not of the form `key: value`")]
+    MalformedLine(usize),
+    #[error("Unknown field {0}")]
+    UnknownField(String),
+    #[error("Field {0} is given twice")]
+    DuplicateField(&'static str),
+    #[error("Missing field {0}")]
+    MissingField(&'static str),
+    #[error("Invalid value for {0}")]
+    InvalidValue(&'static str),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+pub struct PresentationRequest {
+    pub service: String,
+    pub nonce: String,
+    /// None when features.nationality_exclusion
+    pub nationality: Option<Nationality>,
+    /// Issuer of the credential, or the root certifying it when
+    /// features.certified_issuer
+    pub issuer: PublicKey,
+    pub features: Features,
+    /// Maximal age of the credential in days, when features.recent_issuance
+    pub issued_within: Option<u32>,
+    /// Expected country of the authority which issued the passport, when
+    /// features.authority_country
+    pub authority_country: Option<Nationality>,
+    /// Key signing the date of the day, when features.time_authority, see
+    /// issuer::timestamp
+    pub time_authority: Option<PublicKey>,
+    /// Scope of the consortium nullifier, when features.consortium
+    pub consortium: Option<String>,
+    /// Set the hidden nationality is outside of, when
+    /// features.nationality_exclusion
+    pub excluded_nationalities: Option<CountrySet>,
+    /// Hash of the photo the holder presents in person, when
+    /// features.portrait_hash
+    pub portrait_hash: Option<PortraitHash>,
+    /// Salt of the credential ID, when features.credential_id, see
+    /// issuer::pseudonym::credential_id
+    pub credential_id: Option<Salt>,
+    /// External account the proof is bound to, when features.account_binding
+    pub account: Option<Vec<u8>>,
+    /// Salt & hash of the family name the holder declared, when
+    /// features.name_hash, see issuer::pseudonym::name_hash
+    pub name_hash: Option<(Salt, Pseudonym)>,
+    /// Salt of the issuer for the epoch, when features.document_commitment,
+    /// see issuer::pseudonym::document_commitment
+    pub document_commitment: Option<Salt>,
+    /// In years
+    pub min_age: u32,
+    /// Past date the holder proves their age on, instead of today
+    pub age_on: Option<NaiveDate>,
+}
+
+/// One fact the relying party learns from the presentation
+#[derive(Clone, Debug)]
+pub enum Disclosure {
+    Nationality(Nationality),
+    /// Only the predicate, not the nationality
+    NationalityNotIn(CountrySet),
+    /// Only the predicate, not the birth date
+    MinAge {
+        years: u32,
+        on: Option<NaiveDate>,
+    },
+    Issuer(PublicKey),
+    /// The issuer stays hidden among the ones certified by this root
+    CertifiedIssuer(PublicKey),
+    /// Derived from the holder public key and the service
+    Pseudonym(String),
+    /// Derived from the holder secret key and the service
+    DomainNym(String),
+    /// Authentification key derived from the holder secret key and the
+    /// service
+    DerivedKey(String),
+    KShow(u32),
+    /// Only the predicate, not the date of issue
+    IssuedWithin(u32),
+    /// Country of the authority which issued your passport
+    AuthorityCountry(Nationality),
+    /// Derived from your identity and the scope, the same at every member
+    /// of the consortium
+    ConsortiumNullifier(String),
+    /// Hash of your passport photo, which links the presentation to it
+    PortraitHash,
+    /// The same at every presentation of the credential
+    CredentialId,
+    /// Shown as text when printable, in hex otherwise
+    Account(Vec<u8>),
+    /// Only the predicate, not your family name
+    FamilyNameMatch,
+    /// The same for every account opened with your travel document during
+    /// the epoch
+    DocumentCommitment,
+    /// The credential ID of the credential yours amends, if any
+    PreviousCredentialId,
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Either the name or the numeric code of a country
+pub(crate) fn parse_country(value: &str) -> Option<Nationality> {
+    Nationality::from_name(value)
+        .or_else(|| value.parse::<u16>().ok().and_then(Nationality::from_code))
+}
+
+pub(crate) fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+impl std::fmt::Display for Disclosure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Nationality(nationality) => {
+                write!(f, "Your nationality ({nationality}) is disclosed")
+            }
+            Self::NationalityNotIn(excluded) => write!(
+                f,
+                "That your nationality is none of {} countries is disclosed, not which one it is",
+                excluded.codes().len()
+            ),
+            Self::MinAge { years, on: None } => write!(
+                f,
+                "That you are at least {years} is disclosed, not your birth date"
+            ),
+            Self::MinAge {
+                years,
+                on: Some(date),
+            } => write!(
+                f,
+                "That you were at least {years} on {date} is disclosed, not your birth date"
+            ),
+            Self::Issuer(pk) => write!(
+                f,
+                "The issuer of your credential ({}…) is disclosed",
+                &hex(&pk.to_bytes())[..16]
+            ),
+            Self::CertifiedIssuer(root) => write!(
+                f,
+                "That your issuer is certified by the root {}… is disclosed, not which issuer",
+                &hex(&root.to_bytes())[..16]
+            ),
+            Self::Pseudonym(service) => write!(
+                f,
+                "A pseudonym for {service} is disclosed, which anyone knowing your public key can link to you"
+            ),
+            Self::DomainNym(service) => write!(
+                f,
+                "A pseudonym for {service} is disclosed, which other services can't link to you"
+            ),
+            Self::DerivedKey(service) => write!(
+                f,
+                "A key of yours for {service} is disclosed, which other services can't link to you"
+            ),
+            Self::KShow(k) => write!(
+                f,
+                "A one-time tag is disclosed: this credential can be shown {k} times per epoch"
+            ),
+            Self::IssuedWithin(days) => write!(
+                f,
+                "That your credential was issued within the last {days} days is disclosed, not when"
+            ),
+            Self::AuthorityCountry(country) => write!(
+                f,
+                "That your passport was issued by an authority of {country} is disclosed"
+            ),
+            Self::ConsortiumNullifier(scope) => write!(
+                f,
+                "A tag for {scope} is disclosed, which every bank of the consortium gets for you, whatever your credential, but no one else can compute"
+            ),
+            Self::PortraitHash => write!(
+                f,
+                "The hash of your passport photo is disclosed, to be compared with the photo you present"
+            ),
+            Self::CredentialId => write!(
+                f,
+                "An identifier of your credential is disclosed, the same every time you present it"
+            ),
+            Self::Account(account) => {
+                let account = match std::str::from_utf8(account) {
+                    Ok(text) if account.iter().all(u8::is_ascii_graphic) => text.to_string(),
+                    _ => format!("0x{}", hex(account)),
+                };
+                write!(
+                    f,
+                    "That you own the account {account} is disclosed, the proof can't be used for another one"
+                )
+            }
+            Self::FamilyNameMatch => write!(
+                f,
+                "That your family name is the one you declared to the service is disclosed"
+            ),
+            Self::DocumentCommitment => write!(
+                f,
+                "A commitment of your document number is disclosed, the same for every account you open with this document until the issuer changes its salt"
+            ),
+            Self::PreviousCredentialId => write!(
+                f,
+                "The identifier of the credential yours replaces is disclosed, so that you are recognized as the same holder"
+            ),
+        }
+    }
+}
+
+impl PresentationRequest {
+    pub fn parse(request: &str) -> Result<Self> {
+        fn set<T>(field: &mut Option<T>, name: &'static str, value: Option<T>) -> Result<()> {
+            if field.is_some() {
+                return Err(Error::DuplicateField(name));
+            }
+            *field = Some(value.ok_or(Error::InvalidValue(name))?);
+            Ok(())
+        }
+
+        // strings are encoded on LEN_STRING field elements
+        fn string(value: &str) -> Option<String> {
+            (value.len() <= MAX_STRING_BYTES).then(|| value.to_string())
+        }
+
+        // hashes are encoded on LEN_HASH canonical field elements
+        fn hash(value: &str) -> Option<Salt> {
+            from_hex(value).and_then(|bytes| {
+                let mut bytes = &bytes[..];
+                take_hash(&mut bytes).filter(|_| bytes.is_empty())
+            })
+        }
+
+        let (mut service, mut nonce, mut nationality, mut issuer) = (None, None, None, None);
+        let (mut certified, mut nym, mut k_show, mut issued_within) = (None, None, None, None);
+        let (mut authority_country, mut time_authority, mut consortium) = (None, None, None);
+        let (mut excluded_nationalities, mut min_age, mut age_on) = (None, None, None);
+        let (mut portrait_hash, mut credential_id, mut account) = (None, None, None);
+        let (mut derived_key, mut name_hash, mut name_salt) = (None, None, None);
+        let (mut document_commitment, mut continuity) = (None, None);
+        for (i, line) in request.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (key, value) = line.split_once(':').ok_or(Error::MalformedLine(i + 1))?;
+            let value = value.trim();
+            match key.trim() {
+                "service" => set(&mut service, "service", string(value))?,
+                "nonce" => set(&mut nonce, "nonce", string(value))?,
+                "nationality" => set(&mut nationality, "nationality", parse_country(value))?,
+                "issuer" => set(
+                    &mut issuer,
+                    "issuer",
+                    from_hex(value).and_then(|bytes| PublicKey::from_bytes(&bytes).ok()),
+                )?,
+                "certified" => set(&mut certified, "certified", value.parse::<bool>().ok())?,
+                "nym" => set(&mut nym, "nym", value.parse::<bool>().ok())?,
+                "derived_key" => set(&mut derived_key, "derived_key", value.parse::<bool>().ok())?,
+                "continuity" => set(&mut continuity, "continuity", value.parse::<bool>().ok())?,
+                "k_show" => set(
+                    &mut k_show,
+                    "k_show",
+                    value.parse::<u32>().ok().filter(|k| *k > 0),
+                )?,
+                "issued_within" => set(
+                    &mut issued_within,
+                    "issued_within",
+                    value.parse::<u32>().ok(),
+                )?,
+                "authority_country" => set(
+                    &mut authority_country,
+                    "authority_country",
+                    parse_country(value),
+                )?,
+                "time_authority" => set(
+                    &mut time_authority,
+                    "time_authority",
+                    from_hex(value).and_then(|bytes| PublicKey::from_bytes(&bytes).ok()),
+                )?,
+                "consortium" => set(
+                    &mut consortium,
+                    "consortium",
+                    string(value).filter(|scope| !scope.is_empty()),
+                )?,
+                "excluded_nationalities" => set(
+                    &mut excluded_nationalities,
+                    "excluded_nationalities",
+                    value
+                        .split(',')
+                        .map(|code| code.trim().parse::<u16>().ok())
+                        .collect::<Option<Vec<_>>>()
+                        .and_then(|codes| CountrySet::new(&codes).ok()),
+                )?,
+                "portrait_hash" => set(
+                    &mut portrait_hash,
+                    "portrait_hash",
+                    from_hex(value).and_then(|bytes| PortraitHash::from_bytes(&bytes)),
+                )?,
+                "credential_id" => set(&mut credential_id, "credential_id", hash(value))?,
+                "account" => set(
+                    &mut account,
+                    "account",
+                    match value.strip_prefix("0x") {
+                        Some(hex) => from_hex(hex),
+                        None => Some(value.as_bytes().to_vec()),
+                    }
+                    .filter(|bytes| !bytes.is_empty() && bytes.len() <= MAX_ACCOUNT_BYTES),
+                )?,
+                "name_hash" => set(&mut name_hash, "name_hash", hash(value))?,
+                "name_salt" => set(&mut name_salt, "name_salt", hash(value))?,
+                "document_commitment" => {
+                    set(&mut document_commitment, "document_commitment", hash(value))?
+                }
+                "min_age" => set(&mut min_age, "min_age", value.parse::<u32>().ok())?,
+                "age_on" => set(
+                    &mut age_on,
+                    "age_on",
+                    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                        .ok()
+                        .filter(|date| date::reference_day(*date).is_ok()),
+                )?,
+                key => return Err(Error::UnknownField(key.to_string())),
+            }
+        }
+        Ok(Self {
+            service: service.ok_or(Error::MissingField("service"))?,
+            nonce: nonce.ok_or(Error::MissingField("nonce"))?,
+            nationality: match (nationality.is_some(), excluded_nationalities.is_some()) {
+                (false, false) => return Err(Error::MissingField("nationality")),
+                // the circuit hides the nationality when excluding some
+                (true, true) => return Err(Error::InvalidValue("excluded_nationalities")),
+                _ => nationality,
+            },
+            issuer: issuer.ok_or(Error::MissingField("issuer"))?,
+            features: Features {
+                certified_issuer: certified.unwrap_or(false),
+                domain_nym: nym.unwrap_or(false),
+                k_show,
+                recent_issuance: issued_within.is_some(),
+                authority_country: authority_country.is_some(),
+                time_authority: time_authority.is_some(),
+                consortium: consortium.is_some(),
+                nationality_exclusion: excluded_nationalities.is_some(),
+                portrait_hash: portrait_hash.is_some(),
+                credential_id: credential_id.is_some(),
+                account_binding: account.is_some(),
+                derived_key: derived_key.unwrap_or(false),
+                name_hash: name_hash.is_some(),
+                document_commitment: document_commitment.is_some(),
+                continuity: match (continuity, &credential_id) {
+                    (Some(true), None) => return Err(Error::MissingField("credential_id")),
+                    (continuity, _) => continuity.unwrap_or(false),
+                },
+                issuer_set: false,
+                calendar_age: false,
+                nationality_allow_set: false,
+                non_revocation: false,
+                credential_nullifier: false,
+                expiration: false,
+                activation_flags: false,
+            },
+            issued_within,
+            authority_country,
+            time_authority,
+            consortium,
+            excluded_nationalities,
+            portrait_hash,
+            credential_id,
+            account,
+            name_hash: match (name_salt, name_hash) {
+                (Some(salt), Some(name_hash)) => Some((salt, name_hash)),
+                (None, None) => None,
+                (None, Some(_)) => return Err(Error::MissingField("name_salt")),
+                (Some(_), None) => return Err(Error::MissingField("name_hash")),
+            },
+            document_commitment,
+            min_age: min_age.unwrap_or(18),
+            age_on,
+        })
+    }
+
+    /// Everything the relying party learns from a proof answering the request
+    pub fn preview(&self) -> Vec<Disclosure> {
+        let mut res = vec![
+            match (&self.nationality, &self.excluded_nationalities) {
+                (_, Some(excluded)) => Disclosure::NationalityNotIn(excluded.clone()),
+                (Some(nationality), None) => Disclosure::Nationality(nationality.clone()),
+                (None, None) => unreachable!("parse requires one of them"),
+            },
+            Disclosure::MinAge {
+                years: self.min_age,
+                on: self.age_on,
+            },
+        ];
+        res.push(if self.features.certified_issuer {
+            Disclosure::CertifiedIssuer(self.issuer.clone())
+        } else {
+            Disclosure::Issuer(self.issuer.clone())
+        });
+        res.push(Disclosure::Pseudonym(self.service.clone()));
+        if self.features.domain_nym {
+            res.push(Disclosure::DomainNym(self.service.clone()));
+        }
+        if self.features.derived_key {
+            res.push(Disclosure::DerivedKey(self.service.clone()));
+        }
+        if let Some(k) = self.features.k_show {
+            res.push(Disclosure::KShow(k));
+        }
+        if let Some(days) = self.issued_within {
+            res.push(Disclosure::IssuedWithin(days));
+        }
+        if let Some(country) = &self.authority_country {
+            res.push(Disclosure::AuthorityCountry(country.clone()));
+        }
+        if let Some(scope) = &self.consortium {
+            res.push(Disclosure::ConsortiumNullifier(scope.clone()));
+        }
+        if self.portrait_hash.is_some() {
+            res.push(Disclosure::PortraitHash);
+        }
+        if self.credential_id.is_some() {
+            res.push(Disclosure::CredentialId);
+        }
+        if self.features.continuity {
+            res.push(Disclosure::PreviousCredentialId);
+        }
+        if let Some(account) = &self.account {
+            res.push(Disclosure::Account(account.clone()));
+        }
+        if self.name_hash.is_some() {
+            res.push(Disclosure::FamilyNameMatch);
+        }
+        if self.document_commitment.is_some() {
+            res.push(Disclosure::DocumentCommitment);
+        }
+        res
+    }
+}
+
+/// Parses the request and returns the human-readable summary of its
+/// disclosures, one per line
+pub fn preview(request: &str) -> Result<Vec<String>> {
+    Ok(PresentationRequest::parse(request)?
+        .preview()
+        .iter()
+        .map(Disclosure::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::issuer::keys::IssuerIdentity;
+
+    fn request(extra: &str) -> String {
+        format!(
+            "service: ZBanK\nnonce: QWERTY\nnationality: FR\nissuer: {}\n{extra}",
+            hex(&IssuerIdentity::for_tests().public_key().to_bytes())
+        )
+    }
+
+    #[test]
+    fn preview_lists_every_disclosure() {
+        let disclosures = PresentationRequest::parse(&request(
+            "nym: true\nderived_key: true\nk_show: 3\nissued_within: 90",
+        ))
+        .unwrap()
+        .preview();
+        assert!(matches!(
+            disclosures[..],
+            [
+                Disclosure::Nationality(Nationality::FR),
+                Disclosure::MinAge {
+                    years: 18,
+                    on: None
+                },
+                Disclosure::Issuer(_),
+                Disclosure::Pseudonym(_),
+                Disclosure::DomainNym(_),
+                Disclosure::DerivedKey(_),
+                Disclosure::KShow(3),
+                Disclosure::IssuedWithin(90),
+            ]
+        ));
+
+        let summary = preview(&request("authority_country: 250")).unwrap();
+        assert_eq!(summary.len(), 5);
+        assert!(summary[4].contains("authority of FR"));
+
+        let portrait_hash = PortraitHash::of(b"photo");
+        let summary = preview(&request(&format!(
+            "portrait_hash: {}",
+            hex(&portrait_hash.to_bytes())
+        )))
+        .unwrap();
+        assert_eq!(summary.len(), 5);
+        assert!(summary[4].contains("passport photo"));
+
+        let summary = preview(&request(&format!("credential_id: {}", "00".repeat(32)))).unwrap();
+        assert!(summary[4].contains("the same every time"));
+        let summary = preview(&request(&format!(
+            "credential_id: {}\ncontinuity: true",
+            "00".repeat(32)
+        )))
+        .unwrap();
+        assert!(summary[5].contains("same holder"));
+        assert_eq!(
+            PresentationRequest::parse(&request("continuity: true")).err(),
+            Some(Error::MissingField("credential_id"))
+        );
+        assert_eq!(
+            PresentationRequest::parse(&request(&format!("credential_id: {}", "ff".repeat(32))))
+                .err(),
+            Some(Error::InvalidValue("credential_id"))
+        );
+
+        let summary = preview(&request(&format!("account: 0x{}", "ab".repeat(20)))).unwrap();
+        assert!(summary[4].contains(&format!("account 0x{}", "ab".repeat(20))));
+        let summary = preview(&request("account: customer-42")).unwrap();
+        assert!(summary[4].contains("account customer-42"));
+
+        let name_hash = format!("name_hash: {}", "00".repeat(32));
+        let summary = preview(&request(&format!(
+            "{name_hash}\nname_salt: {}",
+            "01".repeat(32)
+        )))
+        .unwrap();
+        assert!(summary[4].contains("family name"));
+        assert_eq!(
+            PresentationRequest::parse(&request(&name_hash)).err(),
+            Some(Error::MissingField("name_salt"))
+        );
+
+        let summary = preview(&request(&format!(
+            "document_commitment: {}",
+            "00".repeat(32)
+        )))
+        .unwrap();
+        assert!(summary[4].contains("document number"));
+
+        let summary = preview(&request("certified: true")).unwrap();
+        assert_eq!(summary.len(), 4);
+        assert!(summary[2].contains("not which issuer"));
+
+        let excluding =
+            request("excluded_nationalities: 408, 364").replace("nationality: FR\n", "");
+        let summary = preview(&excluding).unwrap();
+        assert!(summary[0].contains("none of 2 countries"));
+        assert_eq!(
+            PresentationRequest::parse(&format!("{excluding}\nnationality: FR")).err(),
+            Some(Error::InvalidValue("excluded_nationalities"))
+        );
+
+        let summary = preview(&request("min_age: 21\nage_on: 2024-05-01")).unwrap();
+        assert!(summary[1].contains("at least 21 on 2024-05-01"));
+    }
+
+    #[test]
+    fn parse_rejects_invalid_requests() {
+        assert_eq!(
+            PresentationRequest::parse("service: ZBanK").err(),
+            Some(Error::MissingField("nonce"))
+        );
+        assert_eq!(
+            PresentationRequest::parse(&request("birth_date: true")).err(),
+            Some(Error::UnknownField("birth_date".to_string()))
+        );
+        assert_eq!(
+            PresentationRequest::parse(&request("nym: maybe")).err(),
+            Some(Error::InvalidValue("nym"))
+        );
+        assert_eq!(
+            PresentationRequest::parse(&request("age_on: 9999-01-01")).err(),
+            Some(Error::InvalidValue("age_on"))
+        );
+        assert_eq!(
+            PresentationRequest::parse(&request("consortium: a-scope-of-more-than-20-bytes")).err(),
+            Some(Error::InvalidValue("consortium"))
+        );
+        assert_eq!(
+            PresentationRequest::parse(&request("service: Other")).err(),
+            Some(Error::DuplicateField("service"))
+        );
+        assert_eq!(
+            PresentationRequest::parse("service ZBanK").err(),
+            Some(Error::MalformedLine(1))
+        );
+    }
+}
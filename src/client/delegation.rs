@@ -0,0 +1,429 @@
+// Delegated proving: a client too small to prove, e.g. a phone, hands the
+// witness of one presentation to a helper service, which proves it and sends
+// the proof back.
+//
+// The helper is trusted with privacy, not with soundness. It learns every
+// attribute of the credential, but the holder secret key never leaves the
+// client, and the authentification it receives only answers the service &
+// nonce of this presentation. The client verifies the returned proof against
+// the verifier key and the public inputs it delegated before using it, so a
+// dishonest helper can at worst make the presentation fail.
+//
+// Job:      MAGIC || VERSION || ephemeral key || nonce || XChaCha20-Poly1305(witness)
+// Response: nonce || XChaCha20-Poly1305(proof)
+// The header is authenticated as associated data, and the key is derived from
+// the Diffie-Hellman of the ephemeral key of the client and the helper key.
+// witness = circuit digest || public inputs || credential || signature
+//           || authentification || Merkle path
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use plonky2::{
+    field::types::{Field, Field64, PrimeField64},
+    iop::witness::WitnessWrite,
+};
+use rand::{rngs::OsRng, TryRngCore};
+use thiserror::Error;
+
+use crate::{
+    bank::presentation::take_hash,
+    circuit::{self, inputs, Circuit, Extra, F},
+    core::credential::Credential,
+    encoding::{self, MerklePath, LEN_HASH},
+    issuer,
+    schnorr::{
+        authentification::Authentification,
+        hash_bytes_to_scalar,
+        keys::{PublicKey, SecretKey},
+        signature::Signature,
+    },
+    verify::{self, verify_bytes},
+};
+
+const MAGIC: &[u8; 8] = b"ZKYCDLGT";
+pub const VERSION: u8 = 1;
+const LEN_KEY: usize = 40;
+const LEN_NONCE: usize = 24;
+const LEN_HEADER: usize = MAGIC.len() + 1 + LEN_KEY + LEN_NONCE;
+const DOMAIN: &[u8] = b"zkyc-delegation";
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Not a delegated job")]
+    NotAJob,
+    #[error("Unsupported job version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Wrong helper key or corrupted message")]
+    Decryption,
+    #[error("Malformed job")]
+    Malformed,
+    #[error("The job was made for another circuit")]
+    WrongCircuit,
+    #[error("The circuit needs witnesses which are never delegated, see circuit::Extra")]
+    ExtraWitness,
+    #[error("Proving failed: {0}")]
+    Proving(anyhow::Error),
+    #[error("The helper returned an invalid proof: {0}")]
+    InvalidProof(#[from] verify::Error),
+    #[error("The helper proved with a circuit which does not blind the witness")]
+    NotZeroKnowledge,
+    #[error("Could not get randomness: {0}")]
+    Rng(#[from] rand::rand_core::OsError),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Everything the helper learns, for one presentation with a circuit which
+/// needs no circuit::Extra
+pub struct Witness<'a> {
+    pub credential: &'a Credential,
+    pub signature: &'a Signature,
+    /// Answers the service & nonce of the presentation only
+    pub authentification: &'a Authentification,
+    pub merkle_path: &'a MerklePath<{ issuer::database::SIZE }, F, bool>,
+}
+
+/// What the client keeps to open the response of the helper
+pub struct Delegation {
+    key: [u8; 32],
+    circuit: encoding::Hash<F>,
+    public_inputs: Vec<u64>,
+}
+
+/// Proves delegated jobs, with its long-term key
+pub struct Helper {
+    secret_key: SecretKey,
+}
+
+/// Key of the Diffie-Hellman of secret & public, bound to the ephemeral key of
+/// the client
+fn shared_key(secret: &SecretKey, public: &PublicKey, ephemeral: &PublicKey) -> [u8; 32] {
+    let shared = PublicKey(public.0 * &secret.0);
+    let mut message = shared.to_bytes().to_vec();
+    message.extend_from_slice(&ephemeral.to_bytes());
+    let mut key = [0; 32];
+    key.copy_from_slice(&hash_bytes_to_scalar(DOMAIN, &message).encode()[..32]);
+    key
+}
+
+fn random_nonce() -> Result<[u8; LEN_NONCE]> {
+    let mut nonce = [0; LEN_NONCE];
+    OsRng.try_fill_bytes(&mut nonce)?;
+    Ok(nonce)
+}
+
+fn push_u64s(res: &mut Vec<u8>, values: &[u64]) {
+    res.extend_from_slice(&(values.len() as u32).to_le_bytes());
+    for x in values {
+        res.extend_from_slice(&x.to_le_bytes());
+    }
+}
+
+fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if bytes.len() < n {
+        return None;
+    }
+    let (head, tail) = bytes.split_at(n);
+    *bytes = tail;
+    Some(head)
+}
+
+fn take_len(bytes: &mut &[u8]) -> Option<usize> {
+    Some(u32::from_le_bytes(take(bytes, 4)?.try_into().unwrap()) as usize)
+}
+
+impl Witness<'_> {
+    fn to_bytes(&self, circuit: &encoding::Hash<F>, public_inputs: &[u64]) -> Vec<u8> {
+        let mut res = vec![];
+        push_u64s(&mut res, &circuit.0.map(|x| x.to_canonical_u64()));
+        push_u64s(&mut res, public_inputs);
+        let credential = self.credential.to_bytes();
+        res.extend_from_slice(&(credential.len() as u32).to_le_bytes());
+        res.extend_from_slice(&credential);
+        res.extend_from_slice(&self.signature.to_bytes());
+        res.extend_from_slice(&self.authentification.to_bytes());
+        for (hash, &left) in self
+            .merkle_path
+            .path
+            .iter()
+            .zip(&self.merkle_path.positions)
+        {
+            for x in hash.0 {
+                res.extend_from_slice(&x.to_canonical_u64().to_le_bytes());
+            }
+            res.push(left.into());
+        }
+        res
+    }
+}
+
+/// Witness as read by the helper
+struct Job {
+    circuit: encoding::Hash<F>,
+    public_inputs: Vec<F>,
+    credential: Credential,
+    signature: Signature,
+    authentification: Authentification,
+    merkle_path: MerklePath<{ issuer::database::SIZE }, F, bool>,
+}
+
+impl Job {
+    fn from_bytes(mut bytes: &[u8]) -> Option<Self> {
+        let bytes = &mut bytes;
+        if take_len(bytes)? != LEN_HASH {
+            return None;
+        }
+        let circuit = take_hash(bytes)?;
+        let public_inputs = (0..take_len(bytes)?)
+            .map(|_| {
+                let x = u64::from_le_bytes(take(bytes, 8)?.try_into().unwrap());
+                (x < F::ORDER).then(|| F::from_canonical_u64(x))
+            })
+            .collect::<Option<_>>()?;
+        let n = take_len(bytes)?;
+        let credential = Credential::from_bytes(take(bytes, n)?)?;
+        let signature = Signature::from_bytes(take(bytes, 80)?).ok()?;
+        let authentification = Authentification::from_bytes(take(bytes, 80)?).ok()?;
+        let mut merkle_path = MerklePath {
+            path: [encoding::Hash([F::ZERO; LEN_HASH]); issuer::database::SIZE],
+            positions: [false; issuer::database::SIZE],
+        };
+        for (hash, left) in merkle_path
+            .path
+            .iter_mut()
+            .zip(merkle_path.positions.iter_mut())
+        {
+            *hash = take_hash(bytes)?;
+            *left = match take(bytes, 1)?[0] {
+                0 => false,
+                1 => true,
+                _ => return None,
+            };
+        }
+        bytes.is_empty().then_some(Self {
+            circuit,
+            public_inputs,
+            credential,
+            signature,
+            authentification,
+            merkle_path,
+        })
+    }
+}
+
+/// Encrypts the witness of a presentation with the circuit of digest circuit
+/// (see Circuit::digest) for the helper. The helper learns the witness: only
+/// delegate to a helper trusted with the attributes of the credential
+pub fn delegate(
+    helper: &PublicKey,
+    circuit: encoding::Hash<F>,
+    witness: &Witness,
+    public_inputs: &inputs::Public<F>,
+) -> Result<(Vec<u8>, Delegation)> {
+    let public_inputs: Vec<u64> = public_inputs
+        .fields()
+        .into_iter()
+        .flat_map(|(_, value)| value)
+        .map(|x| x.to_canonical_u64())
+        .collect();
+    let ephemeral = SecretKey::new()?;
+    let ephemeral_pk = PublicKey::from(&ephemeral);
+    let key = shared_key(&ephemeral, helper, &ephemeral_pk);
+    let nonce = random_nonce()?;
+
+    let mut res = Vec::with_capacity(LEN_HEADER);
+    res.extend_from_slice(MAGIC);
+    res.push(VERSION);
+    res.extend_from_slice(&ephemeral_pk.to_bytes());
+    res.extend_from_slice(&nonce);
+    let ciphertext = XChaCha20Poly1305::new(&key.into())
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &witness.to_bytes(&circuit, &public_inputs),
+                aad: &res,
+            },
+        )
+        // only fails on inputs larger than what a Vec can hold
+        .expect("encryption failed");
+    res.extend_from_slice(&ciphertext);
+    Ok((
+        res,
+        Delegation {
+            key,
+            circuit,
+            public_inputs,
+        },
+    ))
+}
+
+impl Delegation {
+    /// Decrypts the proof of the helper, and verifies it against verifier_key
+    /// (see Circuit::verifier_key_bytes) and the delegated public inputs. Only
+    /// a proof returned by open may be presented
+    pub fn open(self, verifier_key: &[u8], response: &[u8]) -> Result<Vec<u8>> {
+        if response.len() < LEN_NONCE {
+            return Err(Error::Malformed);
+        }
+        let (nonce, ciphertext) = response.split_at(LEN_NONCE);
+        let proof = XChaCha20Poly1305::new(&self.key.into())
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: nonce,
+                },
+            )
+            .map_err(|_| Error::Decryption)?;
+        let report = verify_bytes(verifier_key, &proof, &self.public_inputs)?;
+        if report.circuit_digest != self.circuit.0.map(|x| x.to_canonical_u64()) {
+            return Err(Error::WrongCircuit);
+        }
+        if !report.zero_knowledge {
+            return Err(Error::NotZeroKnowledge);
+        }
+        Ok(proof)
+    }
+}
+
+impl Helper {
+    pub fn new(secret_key: SecretKey) -> Self {
+        Self { secret_key }
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from(&self.secret_key)
+    }
+
+    /// Decrypts the job, proves it with circuit, which must be the one it was
+    /// made for, and encrypts the proof for the client
+    pub fn prove(&self, circuit: &Circuit, job: &[u8]) -> Result<Vec<u8>> {
+        let Some(bytes) = job.strip_prefix(&MAGIC[..]) else {
+            return Err(Error::NotAJob);
+        };
+        if bytes.len() < LEN_HEADER - MAGIC.len() {
+            return Err(Error::Malformed);
+        }
+        if bytes[0] != VERSION {
+            return Err(Error::UnsupportedVersion(bytes[0]));
+        }
+        let (header, ciphertext) = job.split_at(LEN_HEADER);
+        let ephemeral_pk = PublicKey::from_bytes(&header[MAGIC.len() + 1..][..LEN_KEY])
+            .map_err(|_| Error::Malformed)?;
+        let nonce = &header[LEN_HEADER - LEN_NONCE..];
+        let key = shared_key(&self.secret_key, &ephemeral_pk, &ephemeral_pk);
+        let witness = XChaCha20Poly1305::new(&key.into())
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: header,
+                },
+            )
+            .map_err(|_| Error::Decryption)?;
+        let job = Job::from_bytes(&witness).ok_or(Error::Malformed)?;
+
+        if job.circuit != circuit.digest() {
+            return Err(Error::WrongCircuit);
+        }
+        if circuit.private_inputs.needs_extra() {
+            return Err(Error::ExtraWitness);
+        }
+        let targets: Vec<_> = circuit
+            .public_inputs
+            .fields()
+            .into_iter()
+            .flat_map(|(_, value)| value)
+            .collect();
+        if targets.len() != job.public_inputs.len() {
+            return Err(Error::Malformed);
+        }
+        let proof = (|| {
+            let mut pw = circuit::witness(
+                &job.credential,
+                &job.signature,
+                &job.authentification,
+                &job.merkle_path,
+                &Extra::default(),
+                &circuit.private_inputs,
+            )?;
+            for (&target, &value) in targets.iter().zip(&job.public_inputs) {
+                pw.set_target(target, value)?;
+            }
+            pw.prove(&circuit.circuit)
+        })()
+        .map_err(Error::Proving)?;
+
+        let nonce = random_nonce()?;
+        let ciphertext = XChaCha20Poly1305::new(&key.into())
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &proof.to_bytes(),
+                    aad: &nonce,
+                },
+            )
+            .expect("encryption failed");
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        bank,
+        issuer::{database::for_tests::DATABASE, pseudonym},
+        merkle,
+        schnorr::{authentification::Context, signature::Context as SignatureContext},
+    };
+
+    #[test]
+    fn delegated_proofs_are_verified_by_the_client() {
+        let (client_sk, issuer_sk, credential) = Credential::from_seed(2);
+        let signature = Signature::prove(&issuer_sk, &SignatureContext::new(&credential));
+        let (service, nonce) = (bank::service(), bank::nonce());
+        let authentification = Authentification::prove(
+            &client_sk,
+            &Context::new(&credential.public_key(), &service, &nonce),
+        );
+        let merkle_path = DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let witness = Witness {
+            credential: &credential,
+            signature: &signature,
+            authentification: &authentification,
+            merkle_path: &merkle_path,
+        };
+        let mut public_inputs = inputs::Public::new(DATABASE.root(), &credential.issuer());
+        let holder_pseudonym = pseudonym::hash_from_service(&service, &credential.public_key());
+        public_inputs.pseudonym = (&holder_pseudonym).into();
+
+        let circuit = circuit::circuit();
+        let helper = Helper::new(SecretKey::random(&mut StdRng::seed_from_u64(7)));
+        let (job, delegation) = delegate(
+            &helper.public_key(),
+            circuit.digest(),
+            &witness,
+            &public_inputs,
+        )
+        .unwrap();
+        let response = helper.prove(&circuit, &job).unwrap();
+        let proof = delegation
+            .open(&circuit.verifier_key_bytes(), &response)
+            .unwrap();
+        assert!(!proof.is_empty());
+
+        // only the helper the job was encrypted for can read it
+        let other = Helper::new(SecretKey::random(&mut StdRng::seed_from_u64(8)));
+        assert!(matches!(
+            other.prove(&circuit, &job),
+            Err(Error::Decryption)
+        ));
+    }
+}
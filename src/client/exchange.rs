@@ -0,0 +1,294 @@
+// DIF Presentation Exchange: maps the input descriptors of a Presentation
+// Definition onto the predicates our circuits prove, and builds the
+// Presentation Submission describing the answer.
+//
+// Only the fields a circuit can prove are understood, by their JSONPath:
+//   $.nationality                 filter {"const": "FR"} (or the numeric code)
+//   $.age                         filter {"minimum": 18}, in years
+//   $.date_of_issue               filter {"formatMinimum": "2025-01-01"}
+//   $.issuing_authority.country   filter {"const": "FR"}
+// Other fields are rejected, unless they are optional.
+
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::{
+    circuit::inputs::Features,
+    client::consent::parse_country,
+    core::{
+        codec::Codec,
+        credential::{Credential, Nationality},
+        date::{self, days_from_origin},
+    },
+};
+
+/// Format of the verifiable presentations we answer with
+pub const FORMAT: &str = "zkyc_plonky2";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Malformed presentation definition: {0}")]
+    Json(String),
+    #[error("Missing field {0}")]
+    MissingField(&'static str),
+    #[error("Field {0} can't be proved")]
+    UnsupportedField(String),
+    #[error("Invalid filter for {0}")]
+    InvalidFilter(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Predicates requested by an input descriptor
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Requirements {
+    pub nationality: Option<Nationality>,
+    /// In years
+    pub min_age: Option<u32>,
+    /// First accepted date of issue
+    pub issued_after: Option<NaiveDate>,
+    pub authority_country: Option<Nationality>,
+}
+
+#[derive(Debug)]
+pub struct InputDescriptor {
+    pub id: String,
+    pub requirements: Requirements,
+}
+
+#[derive(Debug)]
+pub struct PresentationDefinition {
+    pub id: String,
+    pub input_descriptors: Vec<InputDescriptor>,
+}
+
+fn string(value: &Value, name: &'static str) -> Result<String> {
+    value
+        .get(name)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or(Error::MissingField(name))
+}
+
+fn country(filter: &Value, path: &str) -> Result<Nationality> {
+    let country = match filter.get("const") {
+        Some(Value::String(name)) => parse_country(name),
+        Some(Value::Number(code)) => code
+            .as_u64()
+            .and_then(|code| u16::try_from(code).ok())
+            .and_then(Nationality::from_code),
+        _ => None,
+    };
+    country.ok_or_else(|| Error::InvalidFilter(path.to_string()))
+}
+
+impl Requirements {
+    fn add_field(&mut self, field: &Value) -> Result<()> {
+        let path = field
+            .get("path")
+            .and_then(Value::as_array)
+            .and_then(|paths| paths.first())
+            .and_then(Value::as_str)
+            .ok_or(Error::MissingField("path"))?;
+        let filter = field.get("filter").unwrap_or(&Value::Null);
+        let invalid = || Error::InvalidFilter(path.to_string());
+        match path {
+            "$.nationality" => self.nationality = Some(country(filter, path)?),
+            "$.issuing_authority.country" => self.authority_country = Some(country(filter, path)?),
+            "$.age" => {
+                let years = filter.get("minimum").and_then(Value::as_u64);
+                self.min_age = Some(
+                    years
+                        .and_then(|y| u32::try_from(y).ok())
+                        .ok_or_else(invalid)?,
+                )
+            }
+            "$.date_of_issue" => {
+                let date = filter
+                    .get("formatMinimum")
+                    .and_then(Value::as_str)
+                    .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+                self.issued_after = Some(date.ok_or_else(invalid)?)
+            }
+            _ if field.get("optional").and_then(Value::as_bool) == Some(true) => {}
+            _ => return Err(Error::UnsupportedField(path.to_string())),
+        }
+        Ok(())
+    }
+
+    /// Optional statements of the circuit proving the requirements
+    pub fn features(&self) -> Features {
+        Features {
+            recent_issuance: self.issued_after.is_some(),
+            authority_country: self.authority_country.is_some(),
+            ..Features::default()
+        }
+    }
+
+    /// Whether the credential satisfies the requirements as of today
+    pub fn accepts(&self, credential: &Credential) -> bool {
+        let today = date::today();
+        let birth_date = days_from_origin(*credential.birth_date());
+        self.nationality
+            .as_ref()
+            .is_none_or(|n| n.code() == credential.nationality().code())
+            && self
+                .min_age
+                .is_none_or(|years| birth_date + date::min_age_days(years) <= today)
+            && self
+                .issued_after
+                .is_none_or(|after| after <= *credential.date_of_issue())
+            && self
+                .authority_country
+                .as_ref()
+                .is_none_or(|c| c.code() == credential.issuing_authority().country.code())
+    }
+}
+
+impl PresentationDefinition {
+    pub fn parse(definition: &str) -> Result<Self> {
+        let value: Value =
+            serde_json::from_str(definition).map_err(|err| Error::Json(err.to_string()))?;
+        // the definition may come wrapped, as in presentation requests
+        let value = value.get("presentation_definition").unwrap_or(&value);
+        let input_descriptors = value
+            .get("input_descriptors")
+            .and_then(Value::as_array)
+            .ok_or(Error::MissingField("input_descriptors"))?
+            .iter()
+            .map(|descriptor| {
+                let mut requirements = Requirements::default();
+                let fields = descriptor
+                    .pointer("/constraints/fields")
+                    .and_then(Value::as_array)
+                    .map_or(&[][..], Vec::as_slice);
+                for field in fields {
+                    requirements.add_field(field)?;
+                }
+                Ok(InputDescriptor {
+                    id: string(descriptor, "id")?,
+                    requirements,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            id: string(value, "id")?,
+            input_descriptors,
+        })
+    }
+
+    /// Input descriptors the credential can't answer
+    pub fn unsatisfied(&self, credential: &Credential) -> Vec<&str> {
+        self.input_descriptors
+            .iter()
+            .filter(|descriptor| !descriptor.requirements.accepts(credential))
+            .map(|descriptor| descriptor.id.as_str())
+            .collect()
+    }
+
+    /// Presentation Submission answering every input descriptor, the i-th
+    /// with the i-th proof of the verifiable presentation
+    pub fn submission(&self, id: &str) -> String {
+        let descriptor_map: Vec<Value> = self
+            .input_descriptors
+            .iter()
+            .enumerate()
+            .map(|(i, descriptor)| {
+                json!({
+                    "id": descriptor.id,
+                    "format": FORMAT,
+                    "path": format!("$.proofs[{i}]"),
+                })
+            })
+            .collect();
+        json!({
+            "id": id,
+            "definition_id": self.id,
+            "descriptor_map": descriptor_map,
+        })
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFINITION: &str = r#"{
+        "presentation_definition": {
+            "id": "kyc",
+            "input_descriptors": [{
+                "id": "adult_french",
+                "constraints": {
+                    "limit_disclosure": "required",
+                    "fields": [
+                        {"path": ["$.nationality"], "filter": {"type": "string", "const": "FR"}},
+                        {"path": ["$.age"], "filter": {"type": "number", "minimum": 18}},
+                        {"path": ["$.email"], "optional": true}
+                    ]
+                }
+            }, {
+                "id": "recent",
+                "constraints": {
+                    "fields": [
+                        {"path": ["$.date_of_issue"], "filter": {"format": "date", "formatMinimum": "2016-01-01"}},
+                        {"path": ["$.issuing_authority.country"], "filter": {"const": 250}}
+                    ]
+                }
+            }]
+        }
+    }"#;
+
+    #[test]
+    fn parse_maps_fields_onto_predicates() {
+        let definition = PresentationDefinition::parse(DEFINITION).unwrap();
+        assert_eq!(definition.id, "kyc");
+        let [adult, recent] = &definition.input_descriptors[..] else {
+            panic!("expected two input descriptors")
+        };
+        assert_eq!(
+            adult.requirements,
+            Requirements {
+                nationality: Some(Nationality::FR),
+                min_age: Some(18),
+                ..Requirements::default()
+            }
+        );
+        assert_eq!(adult.requirements.features(), Features::default());
+        assert!(recent.requirements.features().recent_issuance);
+        assert!(recent.requirements.features().authority_country);
+
+        let (_, _, credential) = Credential::from_seed(1);
+        assert!(definition.unsatisfied(&credential).is_empty());
+
+        let submission: Value = serde_json::from_str(&definition.submission("answer")).unwrap();
+        assert_eq!(submission["definition_id"], "kyc");
+        assert_eq!(submission["descriptor_map"][1]["id"], "recent");
+        assert_eq!(submission["descriptor_map"][1]["path"], "$.proofs[1]");
+    }
+
+    #[test]
+    fn parse_rejects_what_no_circuit_proves() {
+        let definition = |field: &str| {
+            format!(
+                r#"{{"id": "kyc", "input_descriptors": [{{"id": "a", "constraints": {{"fields": [{field}]}}}}]}}"#
+            )
+        };
+        assert_eq!(
+            PresentationDefinition::parse(&definition(r#"{"path": ["$.birth_date"]}"#)).err(),
+            Some(Error::UnsupportedField("$.birth_date".to_string()))
+        );
+        assert_eq!(
+            PresentationDefinition::parse(&definition(
+                r#"{"path": ["$.age"], "filter": {"maximum": 18}}"#
+            ))
+            .err(),
+            Some(Error::InvalidFilter("$.age".to_string()))
+        );
+        assert!(matches!(
+            PresentationDefinition::parse("{"),
+            Err(Error::Json(_))
+        ));
+    }
+}
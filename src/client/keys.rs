@@ -1,8 +1,24 @@
 // FIXME: Obviously this is not a secure way to keep the keys, this should only be used for the PoC
 
+use plonky2::field::{
+    goldilocks_field::GoldilocksField,
+    types::{Field, PrimeField64},
+};
 use rand::{rngs::StdRng, SeedableRng};
 
-use crate::schnorr::keys::{PublicKey, SecretKey};
+use crate::{
+    arith::Scalar,
+    encoding::{
+        conversion::{ToField, ToScalarField, ToStringField},
+        LEN_SCALAR_LIMBS, LEN_STRING,
+    },
+    merkle::hash,
+    schnorr::keys::{PublicKey, SecretKey},
+};
+
+/// Last element hashed by derive, so that the derived key differs from
+/// issuer::pseudonym::domain_nym of the same domain, which is public
+pub(crate) const DERIVED_KEY_TAG: u32 = u32::from_le_bytes(*b"DKEY");
 
 // FIXME: TOTALLY INSECURE AND INEFFICIENT
 pub fn secret() -> SecretKey {
@@ -14,3 +30,47 @@ pub fn secret() -> SecretKey {
 pub fn public() -> PublicKey {
     PublicKey::from(&secret())
 }
+
+/// Authentification key of the holder for a relying party, from the master
+/// key bound to the credential: services never see the same key, and the
+/// circuit proves that it descends from the credential, see
+/// circuit::nym::CircuitBuilderNym::derived_key
+pub fn derive(master: &SecretKey, domain: &str) -> SecretKey {
+    let secret: [GoldilocksField; LEN_SCALAR_LIMBS] = master.0.to_field().to_field();
+    let domain = domain.to_string().to_field();
+    let mut message = Vec::with_capacity(LEN_SCALAR_LIMBS + LEN_STRING + 1);
+    message.extend_from_slice(&secret);
+    message.extend_from_slice(&domain.0);
+    message.push(GoldilocksField::from_canonical_u32(DERIVED_KEY_TAG));
+    // the 256 bits of the hash are below the order of the group
+    let mut bits = [false; Scalar::NB_BITS];
+    for (i, x) in hash::poseidon(&message).0.iter().enumerate() {
+        let x = x.to_canonical_u64();
+        for j in 0..64 {
+            bits[64 * i + j] = (x >> j) & 1 == 1;
+        }
+    }
+    SecretKey(Scalar::from_bits_le(&bits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derive_is_stable_per_domain_and_unlinkable_across_domains() {
+        let master = SecretKey::random(&mut StdRng::seed_from_u64(7));
+        let a = derive(&master, "bank-a.example");
+        assert_eq!(a.to_bytes(), derive(&master, "bank-a.example").to_bytes());
+        let keys = [
+            PublicKey::from(&master),
+            PublicKey::from(&a),
+            PublicKey::from(&derive(&master, "bank-b.example")),
+        ];
+        for (i, x) in keys.iter().enumerate() {
+            for y in &keys[i + 1..] {
+                assert_ne!(x.to_bytes(), y.to_bytes());
+            }
+        }
+    }
+}
@@ -1 +1,11 @@
+pub mod backup;
+pub mod bundle;
+pub mod consent;
+pub mod delegation;
+#[cfg(feature = "json")]
+pub mod exchange;
 pub mod keys;
+pub mod recovery;
+pub mod wallet;
+
+pub use bundle::{export_bundle, import_bundle};
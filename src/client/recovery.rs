@@ -0,0 +1,263 @@
+// Holder side of issuer::recovery: after losing their key, the holder draws a
+// new one and walks through the steps below with the issuer & the guardians.
+//
+//   new ──on_challenge──> collecting approvals ──request──> requested
+//                              (add_approval)                   │
+//                                                   finish ─────┘──> done
+
+use rand::Rng;
+use thiserror::Error;
+
+use crate::{
+    issuer::{
+        recovery::{
+            Approval, ApprovalRequest, RecoveryContext, RecoveryPolicy, RecoveryRequest,
+            RecoverySignature,
+        },
+        IssuedCredential,
+    },
+    schnorr::keys::{PublicKey, SecretKey},
+};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Unexpected {0} at this step of the recovery")]
+    UnexpectedStep(&'static str),
+    #[error("The approval is not from a guardian, or not for this recovery")]
+    InvalidApproval,
+    #[error("{0} guardian approvals are needed, got {1}")]
+    NotEnoughApprovals(usize, usize),
+    #[error("Credential {0} returned by the issuer is not signed for the new key")]
+    InvalidRebinding(usize),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+enum State {
+    New,
+    CollectingApprovals {
+        request: ApprovalRequest,
+        approvals: Vec<Approval>,
+    },
+    Requested,
+    Done,
+}
+
+pub struct Recovery {
+    old_holder: PublicKey,
+    policy: RecoveryPolicy,
+    issuer: PublicKey,
+    secret_key: SecretKey,
+    credentials: Vec<IssuedCredential>,
+    state: State,
+}
+
+impl Recovery {
+    /// Recovery of the credentials of old_holder, whose guardians are the
+    /// ones of policy, to a new key drawn from rng
+    pub fn new(
+        old_holder: PublicKey,
+        policy: RecoveryPolicy,
+        issuer: PublicKey,
+        credentials: Vec<IssuedCredential>,
+        rng: &mut impl Rng,
+    ) -> Self {
+        Self {
+            old_holder,
+            policy,
+            issuer,
+            secret_key: SecretKey::random(rng),
+            credentials,
+            state: State::New,
+        }
+    }
+
+    pub fn new_holder(&self) -> PublicKey {
+        PublicKey::from(&self.secret_key)
+    }
+
+    /// Takes the challenge of issuer::recovery::RecoveryDesk::start
+    pub fn on_challenge(&mut self, challenge: String) -> Result<()> {
+        if !matches!(self.state, State::New) {
+            return Err(Error::UnexpectedStep("challenge"));
+        }
+        self.state = State::CollectingApprovals {
+            request: ApprovalRequest {
+                old_holder: self.old_holder.clone(),
+                new_holder: self.new_holder(),
+                challenge,
+            },
+            approvals: vec![],
+        };
+        Ok(())
+    }
+
+    /// What to show the guardians
+    pub fn approval_request(&self) -> Option<&ApprovalRequest> {
+        match &self.state {
+            State::CollectingApprovals { request, .. } => Some(request),
+            _ => None,
+        }
+    }
+
+    /// Keeps the approval of a guardian, the first one of each guardian
+    /// only. Returns whether there are enough of them
+    pub fn add_approval(&mut self, approval: Approval) -> Result<bool> {
+        let State::CollectingApprovals { request, approvals } = &mut self.state else {
+            return Err(Error::UnexpectedStep("approval"));
+        };
+        if !self
+            .policy
+            .guardians()
+            .iter()
+            .any(|guardian| guardian.to_bytes() == approval.guardian().to_bytes())
+            || !approval.verify(request)
+        {
+            return Err(Error::InvalidApproval);
+        }
+        if !approvals
+            .iter()
+            .any(|other| other.guardian().to_bytes() == approval.guardian().to_bytes())
+        {
+            approvals.push(approval);
+        }
+        Ok(approvals.len() >= self.policy.threshold() as usize)
+    }
+
+    /// Message completing the recovery at the issuer, once enough guardians
+    /// approved it
+    pub fn request(&mut self) -> Result<RecoveryRequest> {
+        let State::CollectingApprovals { approvals, .. } = &self.state else {
+            return Err(Error::UnexpectedStep("request"));
+        };
+        let threshold = self.policy.threshold() as usize;
+        if approvals.len() < threshold {
+            return Err(Error::NotEnoughApprovals(threshold, approvals.len()));
+        }
+        let State::CollectingApprovals { request, approvals } =
+            std::mem::replace(&mut self.state, State::Requested)
+        else {
+            unreachable!("checked above")
+        };
+        let possession =
+            RecoverySignature::prove(&self.secret_key, &RecoveryContext::possession(&request));
+        Ok(RecoveryRequest {
+            request,
+            approvals,
+            possession,
+            credentials: std::mem::take(&mut self.credentials),
+        })
+    }
+
+    /// Checks that the issuer signed every credential again for the new key
+    pub fn finish(&mut self, rebound: Vec<IssuedCredential>) -> Result<Vec<IssuedCredential>> {
+        if !matches!(self.state, State::Requested) {
+            return Err(Error::UnexpectedStep("rebinding"));
+        }
+        let new_holder = self.new_holder().to_bytes();
+        for (i, issued) in rebound.iter().enumerate() {
+            if issued.credential.public_key().to_bytes() != new_holder
+                || issued.credential.issuer().to_bytes() != self.issuer.to_bytes()
+                || !issued.credential.check(&issued.signature)
+            {
+                return Err(Error::InvalidRebinding(i));
+            }
+        }
+        self.state = State::Done;
+        Ok(rebound)
+    }
+
+    /// The new holder key, to open a wallet with once the recovery is done
+    pub fn into_secret_key(self) -> SecretKey {
+        self.secret_key
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        core::credential::Credential,
+        issuer::{
+            database::Database,
+            issuance::MemoryLog,
+            keys::IssuerIdentity,
+            recovery::RecoveryDesk,
+            revocation::{MemoryPublisher, Revocations},
+            Issuer,
+        },
+        schnorr::signature::{Context as SignatureContext, Signature},
+    };
+
+    #[test]
+    fn recovery_walks_from_challenge_to_rebound_credentials() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (old_sk, issuer_sk, credential) = Credential::from_seed(4);
+        let signature = Signature::prove(&issuer_sk, &SignatureContext::new(&credential));
+        let mut issuer = Issuer::new(IssuerIdentity::new(issuer_sk), MemoryLog::default());
+        let mut revocations = Revocations::new(
+            Database::init(std::slice::from_ref(&credential)),
+            MemoryPublisher::default(),
+        );
+        let old_holder = PublicKey::from(&old_sk);
+        let guardians: Vec<SecretKey> = (0..3).map(|_| SecretKey::random(&mut rng)).collect();
+        let policy =
+            RecoveryPolicy::new(guardians.iter().map(PublicKey::from).collect(), 2).unwrap();
+        let mut desk = RecoveryDesk::default();
+        desk.register(&old_holder, policy.clone());
+
+        let credentials = vec![IssuedCredential {
+            credential,
+            signature,
+        }];
+        let mut recovery = Recovery::new(
+            old_holder.clone(),
+            policy,
+            issuer.public_key(),
+            credentials,
+            &mut rng,
+        );
+        assert_eq!(
+            recovery.request().err(),
+            Some(Error::UnexpectedStep("request"))
+        );
+        recovery
+            .on_challenge(desk.start(&old_holder, &mut rng).unwrap())
+            .unwrap();
+
+        let request = recovery.approval_request().unwrap().clone();
+        let outsider = SecretKey::random(&mut rng);
+        assert_eq!(
+            recovery.add_approval(request.approve(&outsider)),
+            Err(Error::InvalidApproval)
+        );
+        assert_eq!(
+            recovery.add_approval(request.approve(&guardians[1])),
+            Ok(false)
+        );
+        assert_eq!(
+            recovery.add_approval(request.approve(&guardians[1])),
+            Ok(false)
+        );
+        assert_eq!(
+            recovery.request().err(),
+            Some(Error::NotEnoughApprovals(2, 1))
+        );
+        assert_eq!(
+            recovery.add_approval(request.approve(&guardians[0])),
+            Ok(true)
+        );
+
+        let rebound = desk
+            .complete(&mut issuer, &mut revocations, &recovery.request().unwrap())
+            .unwrap();
+        let rebound = recovery.finish(rebound).unwrap();
+        let new_sk = recovery.into_secret_key();
+        assert_eq!(
+            rebound[0].credential.public_key().to_bytes(),
+            PublicKey::from(&new_sk).to_bytes()
+        );
+    }
+}
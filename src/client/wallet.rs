@@ -0,0 +1,492 @@
+// Holder side of a presentation in one call: picks the credential answering
+// the request, asks the holder to consent to what it discloses, proves, and
+// serializes the envelope checked by bank::Verifier::verify_presentation.
+// The wallet may hold several credentials of the holder key, e.g. a passport
+// and an identity card, or credentials of several issuers or schemas.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use thiserror::Error;
+
+use crate::{
+    bank::presentation::Presentation,
+    circuit::{self, inputs::Features, Circuit, CircuitProfile},
+    client::consent::{Disclosure, PresentationRequest},
+    core::{
+        credential::Credential,
+        date::{self, days_from_origin},
+        policy::{Policy, Predicate},
+    },
+    encoding::{
+        conversion::{ToPointField, ToSingleField, ToStringField},
+        MerklePath,
+    },
+    issuer::{self, pseudonym},
+    merkle,
+    schnorr::{
+        authentification::{Authentification, Context as AuthentificationContext},
+        keys::{PublicKey, SecretKey},
+        signature::Signature,
+    },
+};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("The wallet can't answer requests with these features")]
+    UnsupportedRequest,
+    #[error("The credential is not bound to the key of the wallet")]
+    ForeignCredential,
+    /// The predicates failed by each credential, in the order of the wallet
+    #[error("No credential of the wallet satisfies the request: {0:?}")]
+    NoMatchingCredential(Vec<Vec<Predicate>>),
+    #[error("The holder refused to disclose what the request asks for")]
+    ConsentRefused,
+    #[error("Proving failed: {0}")]
+    Proof(anyhow::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Answer to a presentation request
+pub struct PresentationResponse {
+    /// What the holder consented to disclose
+    pub disclosures: Vec<Disclosure>,
+    /// See bank::presentation
+    pub bytes: Vec<u8>,
+}
+
+struct Entry {
+    credential: Credential,
+    signature: Signature,
+    merkle_path: MerklePath<{ issuer::database::SIZE }, circuit::F, bool>,
+}
+
+pub struct Wallet {
+    secret_key: SecretKey,
+    entries: Vec<Entry>,
+    consent: Box<dyn FnMut(&[Disclosure]) -> bool>,
+    profile: CircuitProfile,
+    /// Built on first use, as circuits take seconds to build
+    circuits: Vec<(Features, Circuit)>,
+    /// Proofs run at once by prove_many
+    proving_threads: usize,
+}
+
+impl Wallet {
+    /// Refuses every request until a consent hook is given, see with_consent
+    pub fn new(secret_key: SecretKey) -> Self {
+        Self {
+            secret_key,
+            entries: vec![],
+            consent: Box::new(|_| false),
+            profile: CircuitProfile::default(),
+            circuits: vec![],
+            proving_threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+
+    /// Asks the holder whether to disclose what a request asks for
+    pub fn with_consent(mut self, consent: impl FnMut(&[Disclosure]) -> bool + 'static) -> Self {
+        self.consent = Box::new(consent);
+        self
+    }
+
+    /// Bounds the proofs prove_many runs at once, at least 1
+    pub fn with_proving_threads(mut self, threads: usize) -> Self {
+        self.proving_threads = threads.max(1);
+        self
+    }
+
+    /// Must match the profile of the circuits the verifier accepts
+    pub fn with_profile(mut self, profile: CircuitProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Keeps a credential of the holder key, with its path in the Merkle tree
+    /// of valid credentials, whose root presentations of it are proved against
+    pub fn add_credential(
+        &mut self,
+        credential: Credential,
+        signature: Signature,
+        merkle_path: MerklePath<{ issuer::database::SIZE }, circuit::F, bool>,
+    ) -> Result<()> {
+        if credential.public_key() != PublicKey::from(&self.secret_key) {
+            return Err(Error::ForeignCredential);
+        }
+        self.entries.push(Entry {
+            credential,
+            signature,
+            merkle_path,
+        });
+        Ok(())
+    }
+
+    /// In the order they were added
+    pub fn credentials(&self) -> impl Iterator<Item = &Credential> {
+        self.entries.iter().map(|entry| &entry.credential)
+    }
+
+    /// Forgets the credential at index, e.g. once it was superseded, see
+    /// Credential::is_reissue_of
+    pub fn remove_credential(&mut self, index: usize) -> Option<Credential> {
+        (index < self.entries.len()).then(|| self.entries.remove(index).credential)
+    }
+
+    /// The credential a proof for the policy would use: of those satisfying
+    /// it, the most recently issued, then the one expiring last
+    pub fn select(&self, policy: &Policy) -> Result<&Credential> {
+        self.select_entry(policy)
+            .map(|i| &self.entries[i].credential)
+    }
+
+    fn select_entry(&self, policy: &Policy) -> Result<usize> {
+        let outcomes: Vec<_> = self
+            .entries
+            .iter()
+            .map(|entry| policy.evaluate(&entry.credential))
+            .collect();
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| outcomes[*i].all())
+            .max_by_key(|(i, entry)| {
+                (
+                    *entry.credential.date_of_issue(),
+                    *entry.credential.expiration_date(),
+                    std::cmp::Reverse(*i),
+                )
+            })
+            .map(|(i, _)| i)
+            .ok_or_else(|| {
+                Error::NoMatchingCredential(outcomes.iter().map(|o| o.failed()).collect())
+            })
+    }
+
+    /// Index of the circuit of the features in self.circuits
+    fn circuit(&mut self, features: Features) -> usize {
+        match self.circuits.iter().position(|(f, _)| *f == features) {
+            Some(i) => i,
+            None => {
+                let circuit = circuit::circuit_with_profile(features, self.profile);
+                self.circuits.push((features, circuit));
+                self.circuits.len() - 1
+            }
+        }
+    }
+
+    /// Proves with the credential selected for the request, see select, once
+    /// the holder consented to its disclosures. Only requests without optional
+    /// features are supported, as verify_presentation.
+    pub fn respond(&mut self, request: &PresentationRequest) -> Result<PresentationResponse> {
+        let job = self.prepare(request)?;
+        job.prove(&self.entries, &self.circuits)
+    }
+
+    /// Answers several requests, e.g. of the institutions a holder onboards
+    /// with at once: credentials are selected, consents asked and circuits
+    /// built in turn, then the proofs run on up to proving_threads threads.
+    /// Answers are in the order of the requests
+    pub fn prove_many(
+        &mut self,
+        requests: &[PresentationRequest],
+    ) -> Vec<Result<PresentationResponse>> {
+        let mut responses = Vec::with_capacity(requests.len());
+        let mut jobs = vec![];
+        for request in requests {
+            match self.prepare(request) {
+                Ok(job) => {
+                    jobs.push((responses.len(), job));
+                    responses.push(None);
+                }
+                Err(err) => responses.push(Some(Err(err))),
+            }
+        }
+
+        let responses = Mutex::new(responses);
+        let next = AtomicUsize::new(0);
+        let (entries, circuits) = (&self.entries, &self.circuits);
+        thread::scope(|scope| {
+            for _ in 0..self.proving_threads.min(jobs.len()) {
+                scope.spawn(|| {
+                    while let Some((i, job)) = jobs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        let response = job.prove(entries, circuits);
+                        responses.lock().unwrap()[*i] = Some(response);
+                    }
+                });
+            }
+        });
+        responses
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|response| response.expect("every job was proved"))
+            .collect()
+    }
+
+    /// Everything but the proof, which needs neither the holder nor self
+    fn prepare(&mut self, request: &PresentationRequest) -> Result<Job> {
+        let nationality = match (&request.nationality, request.features) {
+            (Some(nationality), features) if features == Features::default() => nationality,
+            _ => return Err(Error::UnsupportedRequest),
+        };
+        let policy = Policy {
+            nationality: nationality.clone(),
+            today_days: request.age_on.map_or_else(date::today, days_from_origin),
+            min_age_days: date::min_age_days(request.min_age),
+            issuer: Some(request.issuer.clone()),
+        };
+        let holder = PublicKey::from(&self.secret_key);
+        let entry = self.select_entry(&policy)?;
+
+        let disclosures = request.preview();
+        if !(self.consent)(&disclosures) {
+            return Err(Error::ConsentRefused);
+        }
+
+        let auth_ctx = AuthentificationContext::new(&holder, &request.service, &request.nonce);
+        let authentification = Authentification::prove(&self.secret_key, &auth_ctx);
+        let pseudonym = pseudonym::hash_from_service(&request.service, &holder);
+        // the root the path of the credential was taken from
+        let Entry {
+            credential,
+            merkle_path,
+            ..
+        } = &self.entries[entry];
+        let merkle_root = merkle::Tree::root_of(credential, merkle_path);
+        let public_inputs = circuit::inputs::Public::register(
+            policy.today_days.to_field(),
+            policy.min_age_days.to_field(),
+            Some(policy.nationality.to_field()),
+            request.issuer.0.to_field(),
+            request.nonce.to_field(),
+            request.service.to_field(),
+            pseudonym,
+            merkle_root,
+        );
+        Ok(Job {
+            entry,
+            circuit: self.circuit(request.features),
+            disclosures,
+            authentification,
+            public_inputs,
+        })
+    }
+}
+
+/// A presentation to prove, see Wallet::prepare
+struct Job {
+    entry: usize,
+    circuit: usize,
+    disclosures: Vec<Disclosure>,
+    authentification: Authentification,
+    public_inputs: circuit::inputs::Public<circuit::F>,
+}
+
+impl Job {
+    fn prove(
+        &self,
+        entries: &[Entry],
+        circuits: &[(Features, Circuit)],
+    ) -> Result<PresentationResponse> {
+        let circuit = &circuits[self.circuit].1;
+        let entry = &entries[self.entry];
+        let proof = circuit::prove(
+            circuit,
+            &entry.credential,
+            &entry.signature,
+            &self.authentification,
+            &entry.merkle_path,
+            &self.public_inputs,
+        )
+        .map_err(Error::Proof)?;
+        Ok(PresentationResponse {
+            disclosures: self.disclosures.clone(),
+            bytes: Presentation::new(circuit, &proof, self.public_inputs.pseudonym, None)
+                .to_bytes(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        bank::{self, store::MemoryStore, Session, Verifier},
+        core::{codec::Codec, credential::Nationality},
+        issuer::rate_limit::RateLimit,
+        schnorr::signature::Context as SignatureContext,
+    };
+
+    fn request(credential: &Credential) -> PresentationRequest {
+        let hex: String = credential
+            .issuer()
+            .to_bytes()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        PresentationRequest::parse(&format!(
+            "service: {}\nnonce: {}\nnationality: {}\nissuer: {hex}",
+            bank::service(),
+            bank::nonce(),
+            credential.nationality().code(),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn respond_answers_verify_presentation() {
+        let (client_sk, issuer_sk, credential) = Credential::from_seed(1);
+        let signature = Signature::prove(&issuer_sk, &SignatureContext::new(&credential));
+        let merkle_path = issuer::database::for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let request = request(&credential);
+        let mut wallet = Wallet::new(client_sk).with_profile(CircuitProfile::Test);
+        wallet
+            .add_credential(credential.clone(), signature, merkle_path)
+            .unwrap();
+
+        assert!(matches!(
+            wallet.respond(&request),
+            Err(Error::ConsentRefused)
+        ));
+        let mut wallet = wallet.with_consent(|disclosures| disclosures.len() == 4);
+        let response = wallet.respond(&request).unwrap();
+
+        let policy = Policy {
+            nationality: credential.nationality().clone(),
+            today_days: date::today(),
+            min_age_days: date::ADULT_AGE_DAYS,
+            issuer: Some(credential.issuer()),
+        };
+        let session = Session {
+            service: request.service.clone(),
+            nonce: request.nonce.clone(),
+        };
+        let rate_limit = RateLimit::new(1, 7).unwrap();
+        let circuit = circuit::circuit_with_profile(Features::default(), CircuitProfile::Test);
+        let mut verifier = Verifier::new(MemoryStore::default(), rate_limit)
+            .with_root(issuer::database::for_tests::DATABASE.root())
+            .with_circuit(circuit);
+        verifier
+            .verify_presentation(&response.bytes, &policy, &session)
+            .unwrap();
+
+        let other_sk = SecretKey::random(&mut StdRng::seed_from_u64(1));
+        let mut other = Wallet::new(other_sk).with_consent(|_| true);
+        assert!(matches!(
+            other.respond(&request),
+            Err(Error::NoMatchingCredential(failed)) if failed.is_empty()
+        ));
+    }
+
+    #[test]
+    fn prove_many_answers_in_the_order_of_the_requests() {
+        let (client_sk, issuer_sk, credential) = Credential::from_seed(3);
+        let signature = Signature::prove(&issuer_sk, &SignatureContext::new(&credential));
+        let merkle_path = issuer::database::for_tests::DATABASE
+            .proof(&merkle::hash::credential(&credential))
+            .unwrap();
+        let mut wallet = Wallet::new(client_sk)
+            .with_profile(CircuitProfile::Test)
+            .with_consent(|_| true)
+            .with_proving_threads(2);
+        wallet
+            .add_credential(credential.clone(), signature, merkle_path)
+            .unwrap();
+
+        let (_, _, foreign) = Credential::from_seed(4);
+        let requests = [
+            request(&credential),
+            request(&foreign),
+            request(&credential),
+        ];
+        let responses = wallet.prove_many(&requests);
+        assert_eq!(responses.len(), 3);
+        assert!(matches!(
+            &responses[1],
+            Err(Error::NoMatchingCredential(failed)) if failed == &vec![vec![Predicate::Issuer]]
+        ));
+
+        let policy = Policy {
+            issuer: Some(credential.issuer()),
+            ..Policy::new(credential.nationality().clone())
+        };
+        let rate_limit = RateLimit::new(1, 7).unwrap();
+        let circuit = circuit::circuit_with_profile(Features::default(), CircuitProfile::Test);
+        let mut verifier = Verifier::new(MemoryStore::default(), rate_limit)
+            .with_root(issuer::database::for_tests::DATABASE.root())
+            .with_circuit(circuit);
+        let session = Session {
+            service: requests[0].service.clone(),
+            nonce: requests[0].nonce.clone(),
+        };
+        verifier
+            .verify_presentation(&responses[0].as_ref().unwrap().bytes, &policy, &session)
+            .unwrap();
+        assert!(responses[2].is_ok());
+    }
+
+    #[test]
+    fn select_picks_the_latest_satisfying_credential() {
+        let (client_sk, _, _) = Credential::from_seed(1);
+        let holder = PublicKey::from(&client_sk);
+        let mut wallet = Wallet::new(client_sk);
+        let merkle_path = |credential: &Credential| {
+            issuer::database::for_tests::DATABASE
+                .proof(&merkle::hash::credential(credential))
+                .unwrap()
+        };
+        // credentials of several issuers, rebound to the key of the wallet
+        for seed in [2, 3, 4] {
+            let (_, issuer_sk, credential) = Credential::from_seed(seed);
+            let path = merkle_path(&credential);
+            let credential = credential.with_holder(holder.clone());
+            let signature = Signature::prove(&issuer_sk, &SignatureContext::new(&credential));
+            wallet.add_credential(credential, signature, path).unwrap();
+        }
+        let (_, issuer_sk, foreign) = Credential::from_seed(5);
+        let signature = Signature::prove(&issuer_sk, &SignatureContext::new(&foreign));
+        assert!(matches!(
+            wallet.add_credential(foreign.clone(), signature, merkle_path(&foreign)),
+            Err(Error::ForeignCredential)
+        ));
+
+        let policy = Policy::new(Nationality::FR);
+        let latest = wallet
+            .credentials()
+            .max_by_key(|credential| *credential.date_of_issue())
+            .unwrap()
+            .date_of_issue();
+        assert_eq!(wallet.select(&policy).unwrap().date_of_issue(), latest);
+
+        let second = wallet.credentials().nth(1).unwrap().clone();
+        let policy = Policy {
+            issuer: Some(second.issuer()),
+            ..Policy::new(Nationality::FR)
+        };
+        assert!(wallet.select(&policy).unwrap().issuer() == second.issuer());
+
+        let policy = Policy {
+            issuer: Some(PublicKey::from(&SecretKey::random(
+                &mut StdRng::seed_from_u64(9),
+            ))),
+            ..Policy::new(Nationality::FR)
+        };
+        assert!(matches!(
+            wallet.select(&policy),
+            Err(Error::NoMatchingCredential(failed)) if failed == vec![vec![Predicate::Issuer]; 3]
+        ));
+        assert!(wallet.remove_credential(1).is_some());
+        assert_eq!(wallet.credentials().count(), 2);
+    }
+}
@@ -0,0 +1,220 @@
+// Codes of the enum-like attributes of credentials, which they are serialized,
+// signed and proven with. Every attribute lists its values with their code in
+// one table, which the registry checks for collisions and reads back: a code
+// only means something with its attribute, so that two attributes never share
+// a numeric space by accident.
+
+use std::{collections::HashMap, fmt::Display, sync::OnceLock};
+
+use plonky2::field::types::Field;
+use thiserror::Error;
+
+use crate::core::credential::{DocumentType, Gender, Nationality};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("{attribute:?} {first} and {second} share the code {code}")]
+    Collision {
+        attribute: Attribute,
+        code: u16,
+        first: String,
+        second: String,
+    },
+    #[error("{attribute:?} {value} has the reserved code {code}")]
+    Reserved {
+        attribute: Attribute,
+        code: u16,
+        value: String,
+    },
+    #[error("{attribute:?} {value} has the code {code}, which doesn't fit {bytes} bytes")]
+    Width {
+        attribute: Attribute,
+        code: u16,
+        value: String,
+        bytes: usize,
+    },
+    #[error("{0:?} is registered twice")]
+    Duplicate(Attribute),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Numeric space of the codes. The country of the issuing authority is a
+/// Nationality, and shares its space on purpose
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Attribute {
+    Gender,
+    Nationality,
+    DocumentType,
+}
+
+/// Attribute encoded by a code of its table
+pub trait Codec: Sized + Clone + PartialEq + Display + 'static {
+    const ATTRIBUTE: Attribute;
+    /// Every value with its code, checked by Registry
+    const CODES: &'static [(Self, u16)];
+    /// Codes no value may take, e.g. the padding of core::set::CountrySet
+    const RESERVED: &'static [u16] = &[];
+    /// Bytes of the code in Credential::to_bytes, little endian
+    const BYTES: usize;
+
+    fn code(&self) -> u16 {
+        Self::CODES
+            .iter()
+            .find(|(value, _)| value == self)
+            .map(|(_, code)| *code)
+            .expect("every value is registered")
+    }
+    fn from_code(code: u16) -> Option<Self> {
+        Self::CODES
+            .iter()
+            .find(|(_, c)| *c == code)
+            .map(|(value, _)| value.clone())
+    }
+    /// Value displayed as name, e.g. FR
+    fn from_name(name: &str) -> Option<Self> {
+        Self::CODES
+            .iter()
+            .find(|(value, _)| value.to_string() == name)
+            .map(|(value, _)| value.clone())
+    }
+    /// The code as a field element, as in the circuits
+    fn encode<F: Field>(&self) -> F {
+        F::from_canonical_u16(self.code())
+    }
+    fn push_code(&self, res: &mut Vec<u8>) {
+        res.extend_from_slice(&self.code().to_le_bytes()[..Self::BYTES]);
+    }
+    /// Inverse of push_code, fails on unknown codes
+    fn read_code(bytes: &[u8]) -> Option<Self> {
+        let mut code = [0; 2];
+        code[..Self::BYTES].copy_from_slice(bytes.get(..Self::BYTES)?);
+        Self::from_code(u16::from_le_bytes(code))
+    }
+}
+
+impl Codec for Gender {
+    const ATTRIBUTE: Attribute = Attribute::Gender;
+    const CODES: &'static [(Self, u16)] = &[(Self::M, 0), (Self::F, 1)];
+    const BYTES: usize = 1;
+}
+
+impl Codec for Nationality {
+    const ATTRIBUTE: Attribute = Attribute::Nationality;
+    /// ISO 3166-1 numeric
+    const CODES: &'static [(Self, u16)] = &[(Self::FR, 250)];
+    const RESERVED: &'static [u16] = &[0];
+    const BYTES: usize = 2;
+}
+
+impl Codec for DocumentType {
+    const ATTRIBUTE: Attribute = Attribute::DocumentType;
+    const CODES: &'static [(Self, u16)] = &[(Self::Passport, 0), (Self::IdentityCard, 1)];
+    const BYTES: usize = 1;
+}
+
+/// Names of the values of every attribute by code
+#[derive(Debug, Default)]
+pub struct Registry(HashMap<Attribute, HashMap<u16, String>>);
+
+impl Registry {
+    /// Registry of the attributes of credentials
+    pub fn new() -> Result<Self> {
+        let mut registry = Self::default();
+        registry.register::<Gender>()?;
+        registry.register::<Nationality>()?;
+        registry.register::<DocumentType>()?;
+        Ok(registry)
+    }
+
+    /// Fails if two values share a code, or a code is reserved or too wide
+    pub fn register<C: Codec>(&mut self) -> Result<()> {
+        if self.0.contains_key(&C::ATTRIBUTE) {
+            return Err(Error::Duplicate(C::ATTRIBUTE));
+        }
+        let mut names = HashMap::new();
+        for (value, code) in C::CODES {
+            let (attribute, code, value) = (C::ATTRIBUTE, *code, value.to_string());
+            if C::RESERVED.contains(&code) {
+                return Err(Error::Reserved {
+                    attribute,
+                    code,
+                    value,
+                });
+            }
+            if C::BYTES < 2 && code >> (8 * C::BYTES) != 0 {
+                return Err(Error::Width {
+                    attribute,
+                    code,
+                    value,
+                    bytes: C::BYTES,
+                });
+            }
+            if let Some(first) = names.insert(code, value.clone()) {
+                return Err(Error::Collision {
+                    attribute,
+                    code,
+                    first,
+                    second: value,
+                });
+            }
+        }
+        self.0.insert(C::ATTRIBUTE, names);
+        Ok(())
+    }
+
+    /// Name of the value of the attribute with the code
+    pub fn lookup(&self, attribute: Attribute, code: u16) -> Option<&str> {
+        self.0.get(&attribute)?.get(&code).map(String::as_str)
+    }
+}
+
+/// Registry of the attributes of credentials, checked once
+pub fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| Registry::new().expect("codes of credentials are consistent"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Clashing {
+        A,
+        B,
+    }
+
+    impl Display for Clashing {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{self:?}")
+        }
+    }
+
+    impl Codec for Clashing {
+        const ATTRIBUTE: Attribute = Attribute::Gender;
+        const CODES: &'static [(Self, u16)] = &[(Self::A, 3), (Self::B, 3)];
+        const BYTES: usize = 1;
+    }
+
+    #[test]
+    fn codes_round_trip_and_collisions_are_rejected() {
+        assert_eq!(registry().lookup(Attribute::Nationality, 250), Some("FR"));
+        assert_eq!(registry().lookup(Attribute::DocumentType, 250), None);
+        for document_type in [DocumentType::Passport, DocumentType::IdentityCard] {
+            let mut bytes = vec![];
+            document_type.push_code(&mut bytes);
+            assert_eq!(bytes.len(), DocumentType::BYTES);
+            assert_eq!(DocumentType::read_code(&bytes), Some(document_type));
+        }
+
+        assert!(matches!(
+            Registry::default().register::<Clashing>(),
+            Err(Error::Collision { code: 3, .. })
+        ));
+        assert_eq!(
+            Registry::new().unwrap().register::<Gender>(),
+            Err(Error::Duplicate(Attribute::Gender))
+        );
+    }
+}
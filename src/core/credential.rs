@@ -1,36 +1,164 @@
 use std::fmt::Write;
 
 use chrono::{Datelike, NaiveDate};
-use plonky2::field::types::Field;
+use plonky2::{
+    field::{
+        goldilocks_field::GoldilocksField,
+        types::{Field, Field64, PrimeField64},
+    },
+    hash::poseidon::PoseidonHash,
+    plonk::config::Hasher,
+};
 use rand::{rngs::StdRng, Rng, SeedableRng};
+use thiserror::Error;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
 
 use crate::{
     client,
-    core::date::{
-        days_from_origin, generate_birth_date, generate_birth_date_minor, generate_expiration_date,
+    core::{
+        codec::Codec,
+        date::{
+            days_from_origin, generate_birth_date, generate_birth_date_minor,
+            generate_date_of_issue, generate_expiration_date, try_days_from_origin,
+        },
+        mrz::{CheckDigits, Td1},
     },
     encoding::{
         self,
-        conversion::{ToBool, ToField, ToPointField, ToSingleField, ToStringField, ToVecField},
-        LEN_PASSPORT_NUMBER,
+        conversion::{ToBool, ToField, ToPointField, ToSingleField, ToVarStringField, ToVecField},
+        LEN_HASH, LEN_PASSPORT_NUMBER, MAX_GIVEN_NAMES, MAX_STRING_BYTES,
     },
-    issuer,
+    issuer::keys::IssuerIdentity,
     schnorr::{
         keys::{PublicKey, SecretKey},
         signature::{Context, Signature},
     },
 };
 
+/// Version of the credential layout, signed with it. Bumped whenever an
+/// attribute is added or changes encoding, which requires new circuits.
+pub const SCHEMA_VERSION: u8 = 7;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("{0} must be ASCII")]
+    NotAscii(&'static str),
+    #[error("{0} must hold between 1 and {MAX_STRING_BYTES} characters")]
+    Length(&'static str),
+    #[error("Between 1 and {MAX_GIVEN_NAMES} given names are expected, got {0}")]
+    GivenNames(usize),
+    #[error("Passport numbers are of the form 12AB34567")]
+    PassportNumber,
+    #[error("Identity card numbers are 9 digits or uppercase letters")]
+    IdentityCardNumber,
+    #[error("Inconsistent dates: {0}")]
+    Dates(&'static str),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Names and places as signed, so that the same name read by OCR, typed or
+/// read from the chip gives the same credential, whatever its Unicode form:
+/// without diacritics, which ICAO 9303 drops (É is written E), in uppercase
+/// as in the MRZ, and with single spaces between words. The rest is left in
+/// NFC, and rejected by Credential::new if it isn't ASCII.
+pub fn normalize_name(value: &str) -> String {
+    let folded: String = value
+        .nfd()
+        .filter(|&c| !is_combining_mark(c))
+        .nfc()
+        .collect::<String>()
+        .to_uppercase();
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Attributes read from the passport or identity card, see Credential::new
+pub struct DocumentData {
+    pub document_type: DocumentType,
+    pub given_names: Vec<String>,
+    pub family_name: String,
+    pub birth_date: NaiveDate,
+    pub place_of_birth: String,
+    pub gender: Gender,
+    pub nationality: Nationality,
+    pub issuing_authority: IssuingAuthority,
+    /// Number of the document, in the format of document_type
+    pub passport_number: String,
+    pub date_of_issue: NaiveDate,
+    pub expiration_date: NaiveDate,
+    /// Hash of the photo read from the chip, see PortraitHash::of
+    pub portrait_hash: PortraitHash,
+    /// Key the holder authenticates with, not read from the passport
+    pub holder: PublicKey,
+}
+
+impl DocumentData {
+    /// Data of a French identity card: the MRZ holds neither the place of
+    /// birth nor the date of issue, which are printed on the card
+    pub fn identity_card(
+        mrz: Td1,
+        place_of_birth: String,
+        issuing_authority: IssuingAuthority,
+        date_of_issue: NaiveDate,
+        portrait_hash: PortraitHash,
+        holder: PublicKey,
+    ) -> Self {
+        Self {
+            document_type: DocumentType::IdentityCard,
+            given_names: mrz.given_names,
+            family_name: mrz.family_name,
+            birth_date: mrz.birth_date,
+            place_of_birth,
+            gender: mrz.gender,
+            nationality: mrz.nationality,
+            issuing_authority,
+            passport_number: mrz.document_number,
+            date_of_issue,
+            expiration_date: mrz.expiration_date,
+            portrait_hash,
+            holder,
+        }
+    }
+}
+
+/// Attribute which differs between two credentials, see Credential::diff.
+/// Holds no value, so that changes can be logged without PII
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeChange {
+    SchemaVersion,
+    GivenNames,
+    FamilyName,
+    BirthDate,
+    PlaceOfBirth,
+    Gender,
+    Nationality,
+    IssuingAuthority,
+    /// Number or type of the document
+    Document,
+    MrzCheckDigits,
+    DateOfIssue,
+    ExpirationDate,
+    Portrait,
+    Issuer,
+    Holder,
+}
+
 #[derive(Clone)]
 pub struct Credential {
-    first_name: Name,
+    schema_version: u8,
+    given_names: GivenNames,
     family_name: Name,
     birth_date: NaiveDate,
     place_of_birth: Place,
     gender: Gender,
     nationality: Nationality,
+    issuing_authority: IssuingAuthority,
     passport_number: PassportNumber,
+    /// Copied from the MRZ, see core::mrz
+    mrz_check_digits: CheckDigits,
+    date_of_issue: NaiveDate,
     expiration_date: NaiveDate,
+    portrait_hash: PortraitHash,
     issuer: Issuer,
     public_key: PublicKey, // User's public key for authentification
 }
@@ -40,44 +168,74 @@ pub struct Credential {
 #[derive(Debug, Clone)]
 struct Name(String);
 
+/// Between 1 and MAX_GIVEN_NAMES names, the first one being the primary name
+#[derive(Debug, Clone)]
+struct GivenNames(Vec<Name>);
+
 #[derive(Debug, Clone)]
 struct Place(String);
 
 #[derive(Debug, Clone)]
 struct Issuer(PublicKey);
 
-#[derive(Debug, Clone)]
-enum Gender {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Gender {
     M,
     F,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Nationality {
     FR,
     // EN,
 }
 
+/// Authority which issued the passport, distinct from the key signing the
+/// credential, which may be an intermediate
+#[derive(Debug, Clone)]
+pub struct IssuingAuthority {
+    pub country: Nationality,
+    /// Code of the authority within the country, e.g. of the prefecture
+    pub code: u16,
+}
+
+/// Document the credential was read from, signed with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentType {
+    Passport,
+    /// French national identity card (CNI), in its format of 2021 with a TD1
+    /// MRZ
+    IdentityCard,
+}
+
+/// Number of the document, whatever its type
 #[derive(Debug, Clone)]
 enum PassportNumber {
     French(FrenchPassportNumber),
+    FrenchIdentityCard(FrenchIdentityCardNumber),
 }
 
 #[derive(Debug, Clone)]
 struct FrenchPassportNumber([u8; 9]);
 
+#[derive(Debug, Clone)]
+struct FrenchIdentityCardNumber([u8; 9]);
+
+/// Poseidon hash of the passport photo: the credential only commits to the
+/// image, which the verifier compares with the one it was handed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortraitHash([u64; LEN_HASH]);
+
+/// The circuits encode the gender as a bit, its code
 impl ToBool<bool> for Gender {
     fn to_bool(&self) -> bool {
-        match self {
-            Self::M => false,
-            Self::F => true,
-        }
+        self.code() != 0
     }
 }
 
 impl<F: Field> ToSingleField<F> for Nationality {
     fn to_field(&self) -> F {
-        self.code().to_field()
+        self.encode()
     }
 }
 
@@ -94,24 +252,77 @@ impl<F: Field> ToPointField<F> for Issuer {
     }
 }
 
-impl<F: Field> ToField<F, LEN_PASSPORT_NUMBER> for PassportNumber {
-    fn to_field(&self) -> [F; LEN_PASSPORT_NUMBER] {
-        match self {
-            Self::French(n) => n.to_field(),
-        }
+impl<F: Field> ToSingleField<F> for DocumentType {
+    fn to_field(&self) -> F {
+        self.encode()
     }
 }
 
-impl<F: Field> ToField<F, LEN_PASSPORT_NUMBER> for FrenchPassportNumber {
+impl<F: Field> ToField<F, LEN_PASSPORT_NUMBER> for PassportNumber {
     fn to_field(&self) -> [F; LEN_PASSPORT_NUMBER] {
-        self.0
-            .as_slice()
+        self.as_bytes()
             .to_field(LEN_PASSPORT_NUMBER)
             .try_into()
             .unwrap()
     }
 }
 
+impl PortraitHash {
+    /// Hash of the image bytes, prefixed by their length and packed by u32
+    pub fn of(image: &[u8]) -> Self {
+        let mut inputs = vec![GoldilocksField::from_canonical_usize(image.len())];
+        inputs.extend(image.chunks(4).map(|chunk| {
+            let mut limb = [0; 4];
+            limb[..chunk.len()].copy_from_slice(chunk);
+            GoldilocksField::from_canonical_u32(u32::from_le_bytes(limb))
+        }));
+        let hash = PoseidonHash::hash_no_pad(&inputs);
+        Self(hash.elements.map(|x| x.to_canonical_u64()))
+    }
+    fn rnd(rng: &mut impl Rng) -> Self {
+        Self::of(&rng.random::<[u8; 32]>())
+    }
+    pub fn to_bytes(&self) -> [u8; 8 * LEN_HASH] {
+        let mut res = [0; 8 * LEN_HASH];
+        for (chunk, x) in res.chunks_mut(8).zip(self.0) {
+            chunk.copy_from_slice(&x.to_le_bytes());
+        }
+        res
+    }
+    /// Fails on non-canonical elements
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != 8 * LEN_HASH {
+            return None;
+        }
+        let mut res = [0; LEN_HASH];
+        for (x, chunk) in res.iter_mut().zip(bytes.chunks(8)) {
+            *x = u64::from_le_bytes(chunk.try_into().unwrap());
+            if *x >= GoldilocksField::ORDER {
+                return None;
+            }
+        }
+        Some(Self(res))
+    }
+    fn to_field<F: Field>(self) -> encoding::Hash<F> {
+        encoding::Hash(self.0.map(F::from_canonical_u64))
+    }
+}
+
+impl GivenNames {
+    fn rnd<R: Rng>(rng: &mut R, generate_name: fn(&mut R) -> String) -> Self {
+        let count = rng.random_range(1..=MAX_GIVEN_NAMES);
+        Self((0..count).map(|_| Name(generate_name(rng))).collect())
+    }
+    fn primary(&self) -> &str {
+        &self.0[0].0
+    }
+    fn to_field<F: Field>(&self) -> encoding::GivenNames<F> {
+        encoding::GivenNames(std::array::from_fn(|i| {
+            self.0.get(i).map_or("", |name| name.0.as_str()).to_field()
+        }))
+    }
+}
+
 impl Gender {
     fn rnd(rng: &mut impl Rng) -> Self {
         match rng.random_range(0..2) {
@@ -141,17 +352,36 @@ impl Nationality {
             _ => unreachable!(),
         }
     }
-    pub fn code(&self) -> u16 {
+}
+
+impl std::fmt::Display for Nationality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Nationality::FR => 250,
+            Self::FR => f.write_str("FR"),
         }
     }
 }
 
-impl std::fmt::Display for Nationality {
+impl IssuingAuthority {
+    fn rnd(rng: &mut impl Rng) -> Self {
+        Self {
+            country: Nationality::rnd(rng),
+            code: rng.random_range(1..1000),
+        }
+    }
+    fn to_field<F: Field>(&self) -> encoding::IssuingAuthority<F> {
+        encoding::IssuingAuthority {
+            country: self.country.to_field(),
+            code: self.code.to_field(),
+        }
+    }
+}
+
+impl std::fmt::Display for DocumentType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::FR => f.write_str("FR"),
+            Self::Passport => f.write_str("Passport"),
+            Self::IdentityCard => f.write_str("Identity card"),
         }
     }
 }
@@ -160,13 +390,56 @@ impl PassportNumber {
     fn rnd(rng: &mut impl Rng) -> Self {
         Self::French(FrenchPassportNumber::rnd(rng))
     }
+
+    /// Checks the format of the number of a document of type
+    fn new(document_type: DocumentType, number: &str) -> Result<Self> {
+        let error = || match document_type {
+            DocumentType::Passport => Error::PassportNumber,
+            DocumentType::IdentityCard => Error::IdentityCardNumber,
+        };
+        let bytes: [u8; 9] = number.as_bytes().try_into().map_err(|_| error())?;
+        let res = match document_type {
+            DocumentType::Passport => Self::French(FrenchPassportNumber(bytes)),
+            DocumentType::IdentityCard => Self::FrenchIdentityCard(FrenchIdentityCardNumber(bytes)),
+        };
+        if !res.check() {
+            return Err(error());
+        }
+        Ok(res)
+    }
+
+    fn document_type(&self) -> DocumentType {
+        match self {
+            Self::French(_) => DocumentType::Passport,
+            Self::FrenchIdentityCard(_) => DocumentType::IdentityCard,
+        }
+    }
+
+    fn check(&self) -> bool {
+        match self {
+            Self::French(n) => n.check(),
+            Self::FrenchIdentityCard(n) => {
+                n.0.iter()
+                    .all(|c| c.is_ascii_digit() || c.is_ascii_uppercase())
+            }
+        }
+    }
+
+    /// Characters of the number, as written in the MRZ
+    fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::French(n) => &n.0,
+            Self::FrenchIdentityCard(n) => &n.0,
+        }
+    }
 }
 
 impl std::fmt::Display for PassportNumber {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            PassportNumber::French(number) => number.fmt(f),
+        for &b in self.as_bytes() {
+            f.write_char(b as char)?
         }
+        Ok(())
     }
 }
 
@@ -184,22 +457,13 @@ impl FrenchPassportNumber {
             .for_each(|z| *z = b'0' + rng.random_range(0..10) as u8);
         FrenchPassportNumber(res)
     }
-    fn _check(&self) -> bool {
+    fn check(&self) -> bool {
         self.0[0..2].iter().all(u8::is_ascii_digit)
             && self.0[2..4].iter().all(u8::is_ascii_uppercase)
             && self.0[4..9].iter().all(u8::is_ascii_digit)
     }
 }
 
-impl std::fmt::Display for FrenchPassportNumber {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for &b in self.0.iter() {
-            f.write_char(b as char)?
-        }
-        Ok(())
-    }
-}
-
 impl Credential {
     pub fn issuer(&self) -> PublicKey {
         self.issuer.0.clone()
@@ -210,9 +474,40 @@ impl Credential {
     pub fn nationality(&self) -> &Nationality {
         &self.nationality
     }
+    pub fn document_type(&self) -> DocumentType {
+        self.passport_number.document_type()
+    }
     pub fn birth_date(&self) -> &NaiveDate {
         &self.birth_date
     }
+    pub fn mrz_check_digits(&self) -> CheckDigits {
+        self.mrz_check_digits
+    }
+    pub fn issuing_authority(&self) -> &IssuingAuthority {
+        &self.issuing_authority
+    }
+    pub fn expiration_date(&self) -> &NaiveDate {
+        &self.expiration_date
+    }
+    pub fn date_of_issue(&self) -> &NaiveDate {
+        &self.date_of_issue
+    }
+    pub fn portrait_hash(&self) -> PortraitHash {
+        self.portrait_hash
+    }
+    pub fn schema_version(&self) -> u8 {
+        self.schema_version
+    }
+    pub fn family_name(&self) -> &str {
+        &self.family_name.0
+    }
+    /// First of the given names, the one predicates on the name apply to
+    pub fn primary_given_name(&self) -> &str {
+        self.given_names.primary()
+    }
+    pub fn given_names(&self) -> impl Iterator<Item = &str> {
+        self.given_names.0.iter().map(|name| name.0.as_str())
+    }
     pub fn random(rng: &mut impl Rng) -> (SecretKey, SecretKey, Self) {
         fn generate_name(rng: &mut impl Rng) -> String {
             let len = rng.random_range(3..20);
@@ -227,18 +522,29 @@ impl Credential {
         let issuer = Issuer(PublicKey::from(&sk));
         let sk_client = SecretKey::random(rng);
         let public_key = PublicKey::from(&sk_client);
+        let birth_date = generate_birth_date(rng);
+        let passport_number = PassportNumber::rnd(rng);
+        let expiration_date = generate_expiration_date(rng);
+        let mrz_check_digits =
+            CheckDigits::compute(passport_number.as_bytes(), &birth_date, &expiration_date)
+                .unwrap();
         (
             sk_client,
             sk,
             Credential {
-                first_name: Name(generate_name(rng)),
+                schema_version: SCHEMA_VERSION,
+                given_names: GivenNames::rnd(rng, generate_name),
                 family_name: Name(generate_name(rng)),
-                birth_date: generate_birth_date(rng),
+                birth_date,
                 place_of_birth: Place(generate_name(rng)),
                 gender: Gender::rnd(rng),
                 nationality: Nationality::rnd(rng),
-                passport_number: PassportNumber::rnd(rng),
-                expiration_date: generate_expiration_date(rng),
+                issuing_authority: IssuingAuthority::rnd(rng),
+                passport_number,
+                mrz_check_digits,
+                date_of_issue: generate_date_of_issue(rng),
+                expiration_date,
+                portrait_hash: PortraitHash::rnd(rng),
                 issuer,
                 public_key,
             },
@@ -250,7 +556,7 @@ impl Credential {
         credential.issuer = Issuer(pk);
         credential
     }
-    pub fn random_minor(rng: &mut impl Rng) -> Self {
+    pub fn random_minor(issuer: &IssuerIdentity, rng: &mut impl Rng) -> Self {
         fn generate_name(rng: &mut impl Rng) -> String {
             let len = rng.random_range(3..19);
             let mut res = String::with_capacity(len);
@@ -260,16 +566,27 @@ impl Credential {
             }
             res
         }
+        let birth_date = generate_birth_date_minor(rng);
+        let passport_number = PassportNumber::rnd(rng);
+        let expiration_date = generate_expiration_date(rng);
+        let mrz_check_digits =
+            CheckDigits::compute(passport_number.as_bytes(), &birth_date, &expiration_date)
+                .unwrap();
         Credential {
-            first_name: Name(generate_name(rng)),
+            schema_version: SCHEMA_VERSION,
+            given_names: GivenNames::rnd(rng, generate_name),
             family_name: Name(generate_name(rng)),
-            birth_date: generate_birth_date_minor(rng),
+            birth_date,
             place_of_birth: Place(generate_name(rng)),
             gender: Gender::rnd(rng),
             nationality: Nationality::rnd(rng),
-            passport_number: PassportNumber::rnd(rng),
-            expiration_date: generate_expiration_date(rng),
-            issuer: Issuer(issuer::keys::public()),
+            issuing_authority: IssuingAuthority::rnd(rng),
+            passport_number,
+            mrz_check_digits,
+            date_of_issue: generate_date_of_issue(rng),
+            expiration_date,
+            portrait_hash: PortraitHash::rnd(rng),
+            issuer: Issuer(issuer.public_key().clone()),
             public_key: client::keys::public(),
         }
     }
@@ -279,7 +596,7 @@ impl Credential {
         (sk_client, sk, credential)
     }
     pub fn switch_names_char(&mut self) {
-        let c = self.first_name.0.pop().unwrap();
+        let c = self.given_names.0[0].0.pop().unwrap();
         self.family_name.0.insert(0, c);
     }
     pub fn switch_issuer(&mut self, rng: &mut impl Rng) -> SecretKey {
@@ -288,43 +605,277 @@ impl Credential {
         self.issuer = Issuer(pk);
         sk
     }
+    /// Whether the check digits copied from the MRZ match the data
+    pub fn check_mrz(&self) -> bool {
+        CheckDigits::compute(
+            self.passport_number.as_bytes(),
+            &self.birth_date,
+            &self.expiration_date,
+        ) == Some(self.mrz_check_digits)
+    }
+    /// Credential with inconsistent MRZ data, e.g. to test that it can't be
+    /// proven
+    pub fn set_mrz_check_digits(&mut self, check_digits: CheckDigits) {
+        self.mrz_check_digits = check_digits;
+    }
+    /// Credential as issued under another layout, e.g. to test stale credentials
+    pub fn set_schema_version(&mut self, version: u8) {
+        self.schema_version = version;
+    }
+    /// Attributes of self which differ from those of other, in the order of
+    /// the encoding
+    pub fn diff(&self, other: &Self) -> Vec<AttributeChange> {
+        let authority = |c: &Self| (c.issuing_authority.country.code(), c.issuing_authority.code);
+        let document = |c: &Self| (c.document_type(), c.passport_number.to_string());
+        [
+            (
+                AttributeChange::SchemaVersion,
+                self.schema_version == other.schema_version,
+            ),
+            (
+                AttributeChange::GivenNames,
+                self.given_names().eq(other.given_names()),
+            ),
+            (
+                AttributeChange::FamilyName,
+                self.family_name() == other.family_name(),
+            ),
+            (
+                AttributeChange::BirthDate,
+                self.birth_date == other.birth_date,
+            ),
+            (
+                AttributeChange::PlaceOfBirth,
+                self.place_of_birth.0 == other.place_of_birth.0,
+            ),
+            (AttributeChange::Gender, self.gender == other.gender),
+            (
+                AttributeChange::Nationality,
+                self.nationality == other.nationality,
+            ),
+            (
+                AttributeChange::IssuingAuthority,
+                authority(self) == authority(other),
+            ),
+            (AttributeChange::Document, document(self) == document(other)),
+            (
+                AttributeChange::MrzCheckDigits,
+                self.mrz_check_digits == other.mrz_check_digits,
+            ),
+            (
+                AttributeChange::DateOfIssue,
+                self.date_of_issue == other.date_of_issue,
+            ),
+            (
+                AttributeChange::ExpirationDate,
+                self.expiration_date == other.expiration_date,
+            ),
+            (
+                AttributeChange::Portrait,
+                self.portrait_hash == other.portrait_hash,
+            ),
+            (AttributeChange::Issuer, self.issuer.0 == other.issuer.0),
+            (AttributeChange::Holder, self.public_key == other.public_key),
+        ]
+        .into_iter()
+        .filter_map(|(change, same)| (!same).then_some(change))
+        .collect()
+    }
+
+    /// Whether self may replace other as the credential of the same person:
+    /// a renewal of the document, a new holder key or issuer key, or a new
+    /// schema, issued no earlier. Changes of the identity, e.g. of the family
+    /// name after a marriage, are amendments, see issuer::amendment
+    pub fn is_reissue_of(&self, other: &Self) -> bool {
+        let identity = [
+            AttributeChange::GivenNames,
+            AttributeChange::FamilyName,
+            AttributeChange::BirthDate,
+            AttributeChange::PlaceOfBirth,
+            AttributeChange::Gender,
+            AttributeChange::Nationality,
+        ];
+        self.date_of_issue >= other.date_of_issue
+            && !self
+                .diff(other)
+                .iter()
+                .any(|change| identity.contains(change))
+    }
+
+    /// Same credential for another holder key, to be signed again by the
+    /// issuer, see issuer::Issuer::rebind
+    pub fn with_holder(&self, holder: PublicKey) -> Self {
+        Self {
+            public_key: holder,
+            ..self.clone()
+        }
+    }
+
+    /// Credential of data.holder, issued by issuer, once the data was
+    /// checked to fit the encoding: ASCII strings, see normalize_name, which
+    /// are not too long,
+    /// dates after the origin & in order, number in the format of the French
+    /// document type
+    pub fn new(data: DocumentData, issuer: PublicKey) -> Result<Self> {
+        fn name(value: String, field: &'static str) -> Result<Name> {
+            let value = normalize_name(&value);
+            if !value.is_ascii() {
+                return Err(Error::NotAscii(field));
+            }
+            if value.is_empty() || value.len() > MAX_STRING_BYTES {
+                return Err(Error::Length(field));
+            }
+            Ok(Name(value))
+        }
 
-    // TODO: fn new, with relevant checks (especially that everything is ascii, and not too long; dates’ year non negative (will overflow otherwise))
+        if !(1..=MAX_GIVEN_NAMES).contains(&data.given_names.len()) {
+            return Err(Error::GivenNames(data.given_names.len()));
+        }
+        let given_names = data
+            .given_names
+            .into_iter()
+            .map(|value| name(value, "given name"))
+            .collect::<Result<_>>()?;
+        let passport_number = PassportNumber::new(data.document_type, &data.passport_number)?;
+        try_days_from_origin(data.birth_date)
+            .map_err(|_| Error::Dates("born before the origin of dates"))?;
+        if data.date_of_issue < data.birth_date {
+            return Err(Error::Dates("issued before birth"));
+        }
+        if data.expiration_date <= data.date_of_issue {
+            return Err(Error::Dates("expires before its issue"));
+        }
+        let mrz_check_digits = CheckDigits::compute(
+            passport_number.as_bytes(),
+            &data.birth_date,
+            &data.expiration_date,
+        )
+        .expect("numbers are made of MRZ characters");
+        Ok(Self {
+            schema_version: SCHEMA_VERSION,
+            given_names: GivenNames(given_names),
+            family_name: name(data.family_name, "family name")?,
+            birth_date: data.birth_date,
+            place_of_birth: Place(name(data.place_of_birth, "place of birth")?.0),
+            gender: data.gender,
+            nationality: data.nationality,
+            issuing_authority: data.issuing_authority,
+            passport_number,
+            mrz_check_digits,
+            date_of_issue: data.date_of_issue,
+            expiration_date: data.expiration_date,
+            portrait_hash: data.portrait_hash,
+            issuer: Issuer(issuer),
+            public_key: data.holder,
+        })
+    }
 
     // assumes every field is less than 255 bytes in size
-    /// TODO: a versioning bytes could be added as a heading
+    /// Starts with the schema version
     /// Everything is represented as big endian
     pub fn as_bytes(&self) -> Vec<u8> {
-        fn push_str(res: &mut Vec<u8>, s: &str) {
-            res.push(s.len() as u8); // everything is ascii so s.len() == s.as_bytes().len()
-            res.extend_from_slice(s.as_bytes());
-        }
-        fn push_date(res: &mut Vec<u8>, date: &NaiveDate) {
-            let y = date.year() as u32;
-            let m = date.month();
-            let d = date.day();
-            let v = y * 10_000 + m * 100 + d;
-            res.extend_from_slice(&v.to_le_bytes());
-        }
-        let mut res = vec![];
-        push_str(&mut res, &self.first_name.0);
+        let mut res = vec![self.schema_version];
+        push_names(&mut res, &self.given_names);
         push_str(&mut res, &self.family_name.0);
         push_date(&mut res, &self.birth_date);
         push_str(&mut res, &self.place_of_birth.0);
-        res.push(match self.gender {
-            Gender::M => 0,
-            Gender::F => 1,
-        });
-        res.extend_from_slice(self.nationality.code().to_le_bytes().as_slice());
+        self.gender.push_code(&mut res);
+        self.nationality.push_code(&mut res);
+        push_authority(&mut res, &self.issuing_authority);
+        self.document_type().push_code(&mut res);
         push_str(&mut res, &self.passport_number.to_string());
+        res.extend_from_slice(&[
+            self.mrz_check_digits.document_number,
+            self.mrz_check_digits.birth_date,
+            self.mrz_check_digits.expiration_date,
+        ]);
+        push_date(&mut res, &self.date_of_issue);
         push_date(&mut res, &self.expiration_date);
+        res.extend_from_slice(&self.portrait_hash.to_bytes());
         res.extend_from_slice(&self.issuer.0 .0.to_affine().x.encode());
         res.extend_from_slice(&self.issuer.0 .0.to_affine().u.encode());
         res
     }
 
+    /// Same layout as as_bytes, except that the keys are canonically encoded
+    /// and the holder key is included, so that from_bytes can read it back
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut res = vec![self.schema_version];
+        push_names(&mut res, &self.given_names);
+        push_str(&mut res, &self.family_name.0);
+        push_date(&mut res, &self.birth_date);
+        push_str(&mut res, &self.place_of_birth.0);
+        self.gender.push_code(&mut res);
+        self.nationality.push_code(&mut res);
+        push_authority(&mut res, &self.issuing_authority);
+        self.document_type().push_code(&mut res);
+        push_str(&mut res, &self.passport_number.to_string());
+        res.extend_from_slice(&[
+            self.mrz_check_digits.document_number,
+            self.mrz_check_digits.birth_date,
+            self.mrz_check_digits.expiration_date,
+        ]);
+        push_date(&mut res, &self.date_of_issue);
+        push_date(&mut res, &self.expiration_date);
+        res.extend_from_slice(&self.portrait_hash.to_bytes());
+        res.extend_from_slice(&self.issuer.0.to_bytes());
+        res.extend_from_slice(&self.public_key.to_bytes());
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut reader = Reader(bytes);
+        let schema_version = reader.take(1)?[0];
+        let given_names = reader.names()?;
+        let family_name = Name(reader.string()?);
+        let birth_date = reader.date()?;
+        let place_of_birth = Place(reader.string()?);
+        let gender = reader.code()?;
+        let nationality = reader.code()?;
+        let issuing_authority = IssuingAuthority {
+            country: reader.code()?,
+            code: u16::from_le_bytes(reader.take(2)?.try_into().unwrap()),
+        };
+        let document_type = reader.code()?;
+        let passport_number = PassportNumber::new(document_type, &reader.string()?).ok()?;
+        let digits = reader.take(3)?;
+        if digits.iter().any(|&digit| digit >= 10) {
+            return None;
+        }
+        let mrz_check_digits = CheckDigits {
+            document_number: digits[0],
+            birth_date: digits[1],
+            expiration_date: digits[2],
+        };
+        let date_of_issue = reader.date()?;
+        let expiration_date = reader.date()?;
+        let portrait_hash = PortraitHash::from_bytes(reader.take(8 * LEN_HASH)?)?;
+        let issuer = Issuer(PublicKey::from_bytes(reader.take(40)?).ok()?);
+        let public_key = PublicKey::from_bytes(reader.take(40)?).ok()?;
+        if !reader.0.is_empty() {
+            return None;
+        }
+        Some(Self {
+            schema_version,
+            given_names,
+            family_name,
+            birth_date,
+            place_of_birth,
+            gender,
+            nationality,
+            issuing_authority,
+            passport_number,
+            mrz_check_digits,
+            date_of_issue,
+            expiration_date,
+            portrait_hash,
+            issuer,
+            public_key,
+        })
+    }
+
     pub fn sign(&self, sk: &SecretKey) -> Signature {
-        Signature::sign(sk, &Context::new(self))
+        Signature::prove(sk, &Context::new(self))
     }
 
     pub fn check(&self, signature: &Signature) -> bool {
@@ -333,20 +884,112 @@ impl Credential {
 
     pub fn to_field<F: Field>(&self) -> encoding::Credential<F, bool> {
         encoding::Credential {
-            first_name: self.first_name.0.to_field(),
-            family_name: self.family_name.0.to_field(),
+            schema_version: F::from_canonical_u8(self.schema_version),
+            given_names: self.given_names.to_field(),
+            family_name: self.family_name.0.as_str().to_field(),
             birth_date: self.birth_date.to_field(),
-            place_of_birth: self.place_of_birth.0.to_field(),
+            place_of_birth: self.place_of_birth.0.as_str().to_field(),
+            document_type: self.document_type().to_field(),
             gender: self.gender.to_bool(),
             nationality: self.nationality.to_field(),
+            issuing_authority: self.issuing_authority.to_field(),
             passport_number: encoding::PassportNumber(self.passport_number.to_field()),
+            mrz_check_digits: encoding::MrzCheckDigits {
+                document_number: self.mrz_check_digits.document_number.to_field(),
+                birth_date: self.mrz_check_digits.birth_date.to_field(),
+                expiration_date: self.mrz_check_digits.expiration_date.to_field(),
+            },
+            date_of_issue: self.date_of_issue.to_field(),
             expiration_date: self.expiration_date.to_field(),
+            portrait_hash: self.portrait_hash.to_field(),
             issuer: self.issuer.to_field(),
             public_key: self.public_key.0.to_field(),
         }
     }
 }
 
+fn push_str(res: &mut Vec<u8>, s: &str) {
+    res.push(s.len() as u8); // everything is ascii so s.len() == s.as_bytes().len()
+    res.extend_from_slice(s.as_bytes());
+}
+
+fn push_names(res: &mut Vec<u8>, names: &GivenNames) {
+    res.push(names.0.len() as u8);
+    for name in &names.0 {
+        push_str(res, &name.0);
+    }
+}
+
+fn push_authority(res: &mut Vec<u8>, authority: &IssuingAuthority) {
+    authority.country.push_code(res);
+    res.extend_from_slice(&authority.code.to_le_bytes());
+}
+
+fn push_date(res: &mut Vec<u8>, date: &NaiveDate) {
+    let y = date.year() as u32;
+    let m = date.month();
+    let d = date.day();
+    let v = y * 10_000 + m * 100 + d;
+    res.extend_from_slice(&v.to_le_bytes());
+}
+
+/// Reads back what push_str, push_names, push_authority & push_date wrote
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Some(head)
+    }
+    fn string(&mut self) -> Option<String> {
+        let len = self.take(1)?[0] as usize;
+        let s = self.take(len)?;
+        s.is_ascii().then(|| String::from_utf8(s.to_vec()).unwrap())
+    }
+    fn names(&mut self) -> Option<GivenNames> {
+        let count = self.take(1)?[0] as usize;
+        if !(1..=MAX_GIVEN_NAMES).contains(&count) {
+            return None;
+        }
+        let names = (0..count)
+            .map(|_| self.string().filter(|name| !name.is_empty()).map(Name))
+            .collect::<Option<_>>()?;
+        Some(GivenNames(names))
+    }
+    fn date(&mut self) -> Option<NaiveDate> {
+        let v = u32::from_le_bytes(self.take(4)?.try_into().unwrap());
+        NaiveDate::from_ymd_opt((v / 10_000) as i32, (v / 100) % 100, v % 100)
+    }
+    fn code<C: Codec>(&mut self) -> Option<C> {
+        C::read_code(self.take(C::BYTES)?)
+    }
+}
+
+impl std::fmt::Display for Credential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let given_names: Vec<&str> = self.given_names().collect();
+        writeln!(f, "{} {}", given_names.join(" "), self.family_name.0)?;
+        writeln!(
+            f,
+            "Born {} in {}, {}",
+            self.birth_date, self.place_of_birth.0, self.gender
+        )?;
+        writeln!(f, "Nationality {}", self.nationality)?;
+        write!(
+            f,
+            "{} {} issued {}, expires {}",
+            self.document_type(),
+            self.passport_number,
+            self.date_of_issue,
+            self.expiration_date
+        )
+    }
+}
+
 /// TODO: here we assume implicitely that two different credentials can’t have
 /// the same public key
 /// If kept, this choice must be ensured by the issuer: when issuing a new
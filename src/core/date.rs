@@ -1,7 +1,22 @@
-use chrono::{Datelike, NaiveDate, Utc};
+use chrono::{Datelike, Days, NaiveDate, Utc};
+use plonky2::field::types::PrimeField64;
 use rand::Rng;
+use thiserror::Error;
 
-const ORIGIN: NaiveDate = NaiveDate::from_ymd_opt(1900, 1, 1).unwrap();
+/// Dates are encoded as numbers of days from ORIGIN
+pub const ORIGIN: NaiveDate = NaiveDate::from_ymd_opt(1900, 1, 1).unwrap();
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("{0} is before the origin of encoded dates")]
+    BeforeOrigin(NaiveDate),
+    #[error("{0} days from the origin is not a representable date")]
+    OutOfRange(u64),
+    #[error("{0} is in the future")]
+    InFuture(NaiveDate),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
 
 // Deterministic "today" for tests
 const TODAY_FOR_TESTS: NaiveDate = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
@@ -27,6 +42,13 @@ pub fn generate_birth_date_minor(rng: &mut impl Rng) -> NaiveDate {
         )
 }
 
+/// The generated issue date is in the ten years before TODAY_FOR_TESTS
+pub fn generate_date_of_issue(rng: &mut impl Rng) -> NaiveDate {
+    let start_issue = NaiveDate::from_ymd_opt(2016, 1, 1).unwrap();
+    start_issue
+        + chrono::Duration::days(rng.random_range(0..(TODAY_FOR_TESTS - start_issue).num_days()))
+}
+
 pub fn generate_expiration_date(rng: &mut impl Rng) -> NaiveDate {
     // here we take the same date as end_birth_date
     let start_credential = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
@@ -44,24 +66,148 @@ pub fn from_today_for_tests(date: &NaiveDate) -> u32 {
 }
 
 /// returns the numbers of days spent from ORIGIN to date
+/// Dates before ORIGIN wrap around, see try_days_from_origin
 pub fn days_from_origin(date: NaiveDate) -> u32 {
     (date - ORIGIN).num_days() as u32
 }
 
+pub fn try_days_from_origin(date: NaiveDate) -> Result<u32> {
+    u32::try_from((date - ORIGIN).num_days()).map_err(|_| Error::BeforeOrigin(date))
+}
+
+/// Inverse of days_from_origin
+pub fn date_from_days(days: u32) -> Result<NaiveDate> {
+    ORIGIN
+        .checked_add_days(Days::new(days.into()))
+        .ok_or(Error::OutOfRange(days.into()))
+}
+
+/// Date encoded in a field element, e.g. a public input
+pub fn date_from_field<F: PrimeField64>(x: F) -> Result<NaiveDate> {
+    let days = x.to_canonical_u64();
+    u32::try_from(days)
+        .map_err(|_| Error::OutOfRange(days))
+        .and_then(date_from_days)
+}
+
+/// Minimal age in days of someone at least `years` old, counting every leap
+/// day the years may hold, so that nobody younger passes the age check
+pub const fn min_age_days(years: u32) -> u32 {
+    years * 365 + years.div_ceil(4)
+}
+
+/// Minimal age of adults, in days: 18 years hold at most 5 leap days
+pub const ADULT_AGE_DAYS: u32 = min_age_days(18);
+
+/// Whether someone born on birth_date is at least `years` old on `on`, on the
+/// calendar: they come of age on their birthday, and those born on February 29
+/// on March 1 of common years. Mirrors circuit::calendar
+pub fn is_of_age(birth_date: NaiveDate, on: NaiveDate, years: u32) -> bool {
+    let birthday = (
+        birth_date.year() + years as i32,
+        birth_date.month(),
+        birth_date.day(),
+    );
+    (on.year(), on.month(), on.day()) >= birthday
+}
+
+/// /!\ This does not use today’s date
+pub fn today_for_tests() -> u32 {
+    days_from_origin(TODAY_FOR_TESTS)
+}
+
+/// Number of days from ORIGIN to today. In the circuit we want
+/// days_from_origin(birth_date) + min_age_days <= today.
+pub fn today() -> u32 {
+    days_from_origin(Utc::now().date_naive())
+}
+
+/// Number of days from ORIGIN to a past reference date, e.g. the signature
+/// date of a contract, on which the holder proves their age instead of today
+pub fn reference_day(date: NaiveDate) -> Result<u32> {
+    let days = try_days_from_origin(date)?;
+    if days > today() {
+        return Err(Error::InFuture(date));
+    }
+    Ok(days)
+}
+
 /// /!\ This does not use today’s date
-/// returns the minimal numbers of days spent from ORIGIN to be eighteen today
-/// In the circuit we want days_from_origin(date) <= cutoff18
-pub fn cutoff18_from_today_for_tests() -> u32 {
-    cutoff18_from(TODAY_FOR_TESTS)
+/// See issued_after_from_today
+pub fn issued_after_from_today_for_tests(max_age_days: u32) -> u32 {
+    today_for_tests() - max_age_days
 }
 
-/// Returns the minimal number of days spent from ORIGIN to be eighteen today.
-/// In the circuit we want days_from_origin(date) <= cutoff18.
-pub fn cutoff18_from_today() -> u32 {
-    cutoff18_from(Utc::now().date_naive())
+/// Returns the first issue date, in days from ORIGIN, of credentials issued
+/// within the last max_age_days days.
+/// In the circuit we want issued_after <= days_from_origin(date_of_issue).
+pub fn issued_after_from_today(max_age_days: u32) -> u32 {
+    today() - max_age_days
 }
 
-fn cutoff18_from(today: NaiveDate) -> u32 {
-    let date_18 = NaiveDate::from_ymd_opt(today.year() - 18, 1, 1).unwrap();
-    days_from_origin(date_18)
+#[cfg(test)]
+mod tests {
+    use plonky2::field::{goldilocks_field::GoldilocksField as F, types::Field};
+
+    use super::*;
+
+    #[test]
+    fn dates_round_trip() {
+        for date in [
+            ORIGIN,
+            NaiveDate::from_ymd_opt(2000, 2, 29).unwrap(),
+            NaiveDate::from_ymd_opt(2999, 12, 31).unwrap(),
+        ] {
+            let days = try_days_from_origin(date).unwrap();
+            assert_eq!(date_from_days(days), Ok(date));
+            assert_eq!(date_from_field(F::from_canonical_u32(days)), Ok(date));
+        }
+    }
+
+    #[test]
+    fn encoded_credential_dates_round_trip() {
+        let (_, _, credential) = crate::core::credential::Credential::from_seed(1);
+        let encoded = credential.to_field::<F>();
+        assert_eq!(
+            date_from_field(encoded.birth_date).as_ref(),
+            Ok(credential.birth_date())
+        );
+        assert_eq!(
+            date_from_field(encoded.expiration_date).as_ref(),
+            Ok(credential.expiration_date())
+        );
+        assert!(credential
+            .to_string()
+            .contains(&credential.birth_date().to_string()));
+    }
+
+    #[test]
+    fn age_counts_from_the_birthday() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        assert!(is_of_age(date(2008, 1, 1), date(2026, 1, 1), 18));
+        assert!(!is_of_age(date(2008, 1, 1), date(2025, 12, 31), 18));
+        assert!(!is_of_age(date(2008, 2, 29), date(2026, 2, 28), 18));
+        assert!(is_of_age(date(2008, 2, 29), date(2026, 3, 1), 18));
+        // with only 4 leap days, 18 years are a day short of ADULT_AGE_DAYS
+        let (birth_date, on) = (date(2009, 1, 1), date(2027, 1, 1));
+        assert!(is_of_age(birth_date, on, 18));
+        assert!(days_from_origin(on) - days_from_origin(birth_date) < ADULT_AGE_DAYS);
+    }
+
+    #[test]
+    fn conversions_reject_out_of_range_values() {
+        let before = NaiveDate::from_ymd_opt(1899, 12, 31).unwrap();
+        assert_eq!(
+            try_days_from_origin(before),
+            Err(Error::BeforeOrigin(before))
+        );
+        assert_eq!(
+            date_from_days(u32::MAX),
+            Err(Error::OutOfRange(u32::MAX as u64))
+        );
+        assert_eq!(
+            date_from_field(F::NEG_ONE),
+            Err(Error::OutOfRange(F::NEG_ONE.to_canonical_u64()))
+        );
+    }
 }
@@ -0,0 +1,289 @@
+// Parsing of document data typed by an operator or read by OCR, before
+// issuance: dates in dd/mm/yyyy (as printed on French documents) or
+// yyyy-mm-dd, names with diacritics or MRZ fillers, document numbers with
+// separators. Errors name the field and the offending input, so that the
+// operator knows what to fix; Credential::new still checks the result.
+
+use chrono::NaiveDate;
+use thiserror::Error;
+
+use crate::{
+    core::{
+        codec::Codec,
+        credential::{
+            normalize_name, DocumentData, DocumentType, Gender, IssuingAuthority, Nationality,
+            PortraitHash,
+        },
+        mrz::LEN_DOCUMENT_NUMBER,
+    },
+    encoding::{MAX_GIVEN_NAMES, MAX_STRING_BYTES},
+    schnorr::keys::PublicKey,
+};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("{field}: {value:?} is not a date in dd/mm/yyyy or yyyy-mm-dd")]
+    DateFormat { field: &'static str, value: String },
+    #[error("{field}: {value:?} is not a day of the calendar")]
+    NoSuchDay { field: &'static str, value: String },
+    #[error("{field} is empty")]
+    Empty(&'static str),
+    #[error("{field}: unexpected {character:?} at position {position} of {value:?}")]
+    Character {
+        field: &'static str,
+        value: String,
+        character: char,
+        position: usize,
+    },
+    #[error("{field}: {value:?} is longer than {max} characters")]
+    TooLong {
+        field: &'static str,
+        value: String,
+        max: usize,
+    },
+    #[error("Between 1 and {MAX_GIVEN_NAMES} given names are expected in {0:?}")]
+    GivenNames(String),
+    #[error("{field}: unknown value {value:?}")]
+    Unknown { field: &'static str, value: String },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Alpha-3 codes of the nationalities, as in the MRZ
+const ALPHA_3: &[(&str, Nationality)] = &[("FRA", Nationality::FR)];
+
+/// Day in dd/mm/yyyy or yyyy-mm-dd, days and months on 1 or 2 digits
+pub fn parse_date(field: &'static str, value: &str) -> Result<NaiveDate> {
+    let value = value.trim();
+    let format = || Error::DateFormat {
+        field,
+        value: value.to_string(),
+    };
+    let slashes: Vec<_> = value.split('/').collect();
+    let dashes: Vec<_> = value.split('-').collect();
+    let (day, month, year) = match (&slashes[..], &dashes[..]) {
+        ([day, month, year], _) | (_, [year, month, day]) => (*day, *month, *year),
+        _ => return Err(format()),
+    };
+    let number = |digits: &str, len: std::ops::RangeInclusive<usize>| {
+        if !len.contains(&digits.len()) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format());
+        }
+        Ok(digits.parse::<u32>().expect("at most 4 digits"))
+    };
+    let (day, month, year) = (
+        number(day, 1..=2)?,
+        number(month, 1..=2)?,
+        number(year, 4..=4)?,
+    );
+    NaiveDate::from_ymd_opt(year as i32, month, day).ok_or_else(|| Error::NoSuchDay {
+        field,
+        value: value.to_string(),
+    })
+}
+
+/// Name as signed, see normalize_name. The MRZ filler < separates words
+pub fn parse_name(field: &'static str, value: &str) -> Result<String> {
+    let name = normalize_name(&value.replace('<', " "));
+    if name.is_empty() {
+        return Err(Error::Empty(field));
+    }
+    if let Some((position, character)) = name
+        .chars()
+        .enumerate()
+        .find(|(_, c)| !(c.is_ascii_uppercase() || matches!(c, ' ' | '-' | '\'')))
+    {
+        return Err(Error::Character {
+            field,
+            value: value.to_string(),
+            character,
+            position,
+        });
+    }
+    if name.len() > MAX_STRING_BYTES {
+        return Err(Error::TooLong {
+            field,
+            value: value.to_string(),
+            max: MAX_STRING_BYTES,
+        });
+    }
+    Ok(name)
+}
+
+/// Given names separated by commas, e.g. "Marie Claire, Louise", or by
+/// spaces if there is no comma
+pub fn parse_given_names(value: &str) -> Result<Vec<String>> {
+    let names: Vec<_> = if value.contains(',') {
+        value.split(',').collect()
+    } else {
+        value.split_whitespace().collect()
+    };
+    if !(1..=MAX_GIVEN_NAMES).contains(&names.len()) {
+        return Err(Error::GivenNames(value.to_string()));
+    }
+    names
+        .into_iter()
+        .map(|name| parse_name("given name", name))
+        .collect()
+}
+
+/// Document number without spaces, dashes or dots, in uppercase. Its format
+/// is checked by Credential::new
+pub fn parse_document_number(value: &str) -> Result<String> {
+    let number: String = value
+        .chars()
+        .filter(|c| !(c.is_whitespace() || matches!(c, '-' | '.')))
+        .collect::<String>()
+        .to_uppercase();
+    if let Some((position, character)) = number
+        .chars()
+        .enumerate()
+        .find(|(_, c)| !c.is_ascii_alphanumeric())
+    {
+        return Err(Error::Character {
+            field: "document number",
+            value: value.to_string(),
+            character,
+            position,
+        });
+    }
+    match number.len() {
+        0 => Err(Error::Empty("document number")),
+        len if len > LEN_DOCUMENT_NUMBER => Err(Error::TooLong {
+            field: "document number",
+            value: value.to_string(),
+            max: LEN_DOCUMENT_NUMBER,
+        }),
+        _ => Ok(number),
+    }
+}
+
+/// Name of the value, e.g. F, or its code
+fn parse_code<C: Codec>(field: &'static str, value: &str) -> Result<C> {
+    let name = value.trim().to_uppercase();
+    C::from_name(&name)
+        .or_else(|| name.parse().ok().and_then(C::from_code))
+        .ok_or_else(|| Error::Unknown {
+            field,
+            value: value.to_string(),
+        })
+}
+
+/// Also accepts alpha-3 codes, e.g. FRA
+fn parse_nationality(field: &'static str, value: &str) -> Result<Nationality> {
+    let name = value.trim().to_uppercase();
+    match ALPHA_3.iter().find(|(code, _)| *code == name) {
+        Some((_, nationality)) => Ok(nationality.clone()),
+        None => parse_code(field, value),
+    }
+}
+
+/// Attributes of a document as supplied by an operator or OCR, see parse
+pub struct RawDocument<'a> {
+    pub document_type: DocumentType,
+    /// See parse_given_names
+    pub given_names: &'a str,
+    pub family_name: &'a str,
+    pub birth_date: &'a str,
+    pub place_of_birth: &'a str,
+    /// M or F
+    pub gender: &'a str,
+    pub nationality: &'a str,
+    /// Country of the authority, with its code
+    pub issuing_authority: (&'a str, u16),
+    pub document_number: &'a str,
+    pub date_of_issue: &'a str,
+    pub expiration_date: &'a str,
+}
+
+impl RawDocument<'_> {
+    /// Fails on the first field which doesn't parse
+    pub fn parse(&self, portrait_hash: PortraitHash, holder: PublicKey) -> Result<DocumentData> {
+        let (country, code) = self.issuing_authority;
+        Ok(DocumentData {
+            document_type: self.document_type,
+            given_names: parse_given_names(self.given_names)?,
+            family_name: parse_name("family name", self.family_name)?,
+            birth_date: parse_date("birth date", self.birth_date)?,
+            place_of_birth: parse_name("place of birth", self.place_of_birth)?,
+            gender: parse_code::<Gender>("gender", self.gender)?,
+            nationality: parse_nationality("nationality", self.nationality)?,
+            issuing_authority: IssuingAuthority {
+                country: parse_nationality("issuing authority", country)?,
+                code,
+            },
+            passport_number: parse_document_number(self.document_number)?,
+            date_of_issue: parse_date("date of issue", self.date_of_issue)?,
+            expiration_date: parse_date("expiration date", self.expiration_date)?,
+            portrait_hash,
+            holder,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{core::credential::Credential, schnorr::keys::SecretKey};
+
+    #[test]
+    fn operator_input_is_cleaned_or_precisely_rejected() {
+        let raw = RawDocument {
+            document_type: DocumentType::Passport,
+            given_names: "Hélène,  Marie  Claire",
+            family_name: "d'Ormesson<<Lefèvre",
+            birth_date: "4/07/1990",
+            place_of_birth: " Besançon ",
+            gender: "f",
+            nationality: "FRA",
+            issuing_authority: ("FR", 75),
+            document_number: "12 AB-345.67",
+            date_of_issue: "2020-01-02",
+            expiration_date: "01/01/2030",
+        };
+        let holder = PublicKey::from(&SecretKey::random(&mut StdRng::seed_from_u64(1)));
+        let data = raw
+            .parse(PortraitHash::of(b"portrait"), holder.clone())
+            .unwrap();
+        assert_eq!(data.given_names, ["HELENE", "MARIE CLAIRE"]);
+        assert_eq!(data.family_name, "D'ORMESSON LEFEVRE");
+        assert_eq!(
+            data.birth_date,
+            NaiveDate::from_ymd_opt(1990, 7, 4).unwrap()
+        );
+        assert_eq!(data.place_of_birth, "BESANCON");
+        assert_eq!(data.gender, Gender::F);
+        assert_eq!(data.passport_number, "12AB34567");
+        assert!(Credential::new(data, holder).is_ok());
+
+        assert_eq!(
+            parse_date("birth date", "1990/07/04"),
+            Err(Error::DateFormat {
+                field: "birth date",
+                value: "1990/07/04".to_string()
+            })
+        );
+        assert!(matches!(
+            parse_date("expiration date", "31/02/2030"),
+            Err(Error::NoSuchDay { .. })
+        ));
+        assert!(matches!(
+            parse_name("family name", "Dup0nt"),
+            Err(Error::Character {
+                character: '0',
+                position: 3,
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse_document_number("12AB34567/"),
+            Err(Error::Character { character: '/', .. })
+        ));
+        assert!(matches!(
+            parse_nationality("nationality", "DEU"),
+            Err(Error::Unknown { .. })
+        ));
+    }
+}
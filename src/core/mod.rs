@@ -1,2 +1,7 @@
+pub mod codec;
 pub mod credential;
 pub mod date;
+pub mod ingest;
+pub mod mrz;
+pub mod policy;
+pub mod set;
@@ -0,0 +1,245 @@
+// Check digits of the machine readable zone of passports (ICAO 9303): each
+// character is valued (digits as such, A-Z from 10 to 35, the filler < as 0),
+// weighted by 7, 3, 1, 7, 3, 1, ... and the sum is taken modulo 10.
+// circuit::mrz recomputes them from the credential.
+//
+// Identity cards have a TD1 MRZ, of 3 lines of 30 characters, which Td1::parse
+// reads for the issuer.
+
+use chrono::{Datelike, NaiveDate};
+use thiserror::Error;
+
+use crate::core::credential::{Gender, Nationality};
+
+pub const WEIGHTS: [u8; 3] = [7, 3, 1];
+/// Characters of the document number of a French passport or identity card
+pub const LEN_DOCUMENT_NUMBER: usize = 9;
+/// Characters of a line of a TD1 MRZ
+pub const LEN_TD1_LINE: usize = 30;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("A TD1 MRZ has 3 lines of {LEN_TD1_LINE} characters")]
+    Format,
+    #[error("Unsupported document: {0}")]
+    Unsupported(&'static str),
+    #[error("Invalid {0} in the MRZ")]
+    Field(&'static str),
+    #[error("Wrong check digit of the {0}")]
+    CheckDigit(&'static str),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Check digits of the document number, birth date and expiration date, as
+/// printed in the MRZ and copied into the credential by the issuer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CheckDigits {
+    pub document_number: u8,
+    pub birth_date: u8,
+    pub expiration_date: u8,
+}
+
+/// Value of an MRZ character, None if it can't appear in the MRZ
+pub fn value(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'A'..=b'Z' => Some(c - b'A' + 10),
+        b'<' => Some(0),
+        _ => None,
+    }
+}
+
+pub fn check_digit(field: &[u8]) -> Option<u8> {
+    let mut sum = 0u32;
+    for (i, &c) in field.iter().enumerate() {
+        sum += value(c)? as u32 * WEIGHTS[i % 3] as u32;
+    }
+    Some((sum % 10) as u8)
+}
+
+/// YYMMDD, as dates are written in the MRZ
+pub fn date_field(date: &NaiveDate) -> [u8; 6] {
+    date.format("%y%m%d")
+        .to_string()
+        .into_bytes()
+        .try_into()
+        .unwrap()
+}
+
+impl CheckDigits {
+    pub fn compute(
+        document_number: &[u8],
+        birth_date: &NaiveDate,
+        expiration_date: &NaiveDate,
+    ) -> Option<Self> {
+        Some(Self {
+            document_number: check_digit(document_number)?,
+            birth_date: check_digit(&date_field(birth_date))?,
+            expiration_date: check_digit(&date_field(expiration_date))?,
+        })
+    }
+}
+
+/// What the MRZ of a French identity card holds, checked against its check
+/// digits
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Td1 {
+    pub document_number: String,
+    pub birth_date: NaiveDate,
+    pub gender: Gender,
+    pub expiration_date: NaiveDate,
+    pub nationality: Nationality,
+    pub family_name: String,
+    pub given_names: Vec<String>,
+}
+
+/// Date of a YYMMDD field, in the century putting it at the latest on
+/// latest
+fn parse_date(field: &[u8], latest: NaiveDate, name: &'static str) -> Result<NaiveDate> {
+    let number = |i: usize| -> Result<u32> {
+        let digits = &field[i..i + 2];
+        if !digits.iter().all(u8::is_ascii_digit) {
+            return Err(Error::Field(name));
+        }
+        Ok(((digits[0] - b'0') * 10 + (digits[1] - b'0')) as u32)
+    };
+    let (yy, m, d) = (number(0)? as i32, number(2)?, number(4)?);
+    let century = latest.year() - latest.year() % 100;
+    [century, century - 100]
+        .into_iter()
+        .filter_map(|century| NaiveDate::from_ymd_opt(century + yy, m, d))
+        .find(|date| *date <= latest)
+        .ok_or(Error::Field(name))
+}
+
+/// Words of a name field, separated by < and ended by fillers
+fn parse_words(field: &[u8]) -> Vec<String> {
+    field
+        .split(|&c| c == b'<')
+        .filter(|word| !word.is_empty())
+        .map(|word| String::from_utf8(word.to_vec()).unwrap())
+        .collect()
+}
+
+impl Td1 {
+    /// Reads the 3 lines of the MRZ of a French identity card. Birth dates
+    /// are taken at the latest on today, expiration dates in the following
+    /// 99 years
+    pub fn parse(mrz: &str, today: NaiveDate) -> Result<Self> {
+        let lines: Vec<&[u8]> = mrz.lines().map(|line| line.trim().as_bytes()).collect();
+        let [line1, line2, line3] = lines[..] else {
+            return Err(Error::Format);
+        };
+        if [line1, line2, line3]
+            .iter()
+            .any(|line| line.len() != LEN_TD1_LINE || line.iter().any(|&c| value(c).is_none()))
+        {
+            return Err(Error::Format);
+        }
+        if &line1[..2] != b"ID" {
+            return Err(Error::Unsupported("not an identity card"));
+        }
+        if &line1[2..5] != b"FRA" {
+            return Err(Error::Unsupported("not issued by France"));
+        }
+        let checked = |field: &[u8], digit: u8, name| {
+            if check_digit(field).map(|d| b'0' + d) == Some(digit) {
+                Ok(())
+            } else {
+                Err(Error::CheckDigit(name))
+            }
+        };
+        // longer numbers overflow into the optional data, which French cards
+        // don't do
+        let number = &line1[5..5 + LEN_DOCUMENT_NUMBER];
+        if number.contains(&b'<') {
+            return Err(Error::Field("document number"));
+        }
+        checked(number, line1[14], "document number")?;
+        checked(&line2[..6], line2[6], "birth date")?;
+        checked(&line2[8..14], line2[14], "expiration date")?;
+        let composite = [&line1[5..30], &line2[..7], &line2[8..15], &line2[18..29]].concat();
+        checked(&composite, line2[29], "MRZ")?;
+
+        let gender = match line2[7] {
+            b'M' => Gender::M,
+            b'F' => Gender::F,
+            _ => return Err(Error::Field("sex")),
+        };
+        if &line2[15..18] != b"FRA" {
+            return Err(Error::Unsupported("nationality"));
+        }
+        let (family_name, given_names) = match line3.windows(2).position(|w| w == b"<<") {
+            Some(i) => (parse_words(&line3[..i]), parse_words(&line3[i + 2..])),
+            None => (parse_words(line3), vec![]),
+        };
+        if family_name.is_empty() || given_names.is_empty() {
+            return Err(Error::Field("name"));
+        }
+        let latest_expiration = NaiveDate::from_ymd_opt(today.year() + 99, 12, 31).unwrap();
+        Ok(Self {
+            document_number: String::from_utf8(number.to_vec()).unwrap(),
+            birth_date: parse_date(&line2[..6], today, "birth date")?,
+            gender,
+            expiration_date: parse_date(&line2[8..14], latest_expiration, "expiration date")?,
+            nationality: Nationality::FR,
+            family_name: family_name.join(" "),
+            given_names,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_digits_of_icao_specimen() {
+        let birth_date = NaiveDate::from_ymd_opt(1974, 8, 12).unwrap();
+        let expiration_date = NaiveDate::from_ymd_opt(2012, 4, 15).unwrap();
+        assert_eq!(
+            CheckDigits::compute(b"L898902C3", &birth_date, &expiration_date),
+            Some(CheckDigits {
+                document_number: 6,
+                birth_date: 2,
+                expiration_date: 9,
+            })
+        );
+        assert_eq!(check_digit(b"L898902C<"), Some(3));
+        assert_eq!(check_digit(b"l898902C3"), None);
+    }
+
+    #[test]
+    fn td1_parse_reads_a_french_identity_card() {
+        let mrz = "IDFRAX4RTBPFW46<<<<<<<<<<<<<<<\n\
+                   9007149F3001019FRA<<<<<<<<<<<8\n\
+                   DUPONT<<MARIANNE<LOUISE<<<<<<<";
+        let today = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let td1 = Td1::parse(mrz, today).unwrap();
+        assert_eq!(
+            td1,
+            Td1 {
+                document_number: "X4RTBPFW4".to_string(),
+                birth_date: NaiveDate::from_ymd_opt(1990, 7, 14).unwrap(),
+                gender: Gender::F,
+                expiration_date: NaiveDate::from_ymd_opt(2030, 1, 1).unwrap(),
+                nationality: Nationality::FR,
+                family_name: "DUPONT".to_string(),
+                given_names: vec!["MARIANNE".to_string(), "LOUISE".to_string()],
+            }
+        );
+
+        let tampered = mrz.replacen("X4RT", "X4RU", 1);
+        assert_eq!(
+            Td1::parse(&tampered, today),
+            Err(Error::CheckDigit("document number"))
+        );
+        let passport = mrz.replacen("IDFRA", "P<FRA", 1);
+        assert_eq!(
+            Td1::parse(&passport, today),
+            Err(Error::Unsupported("not an identity card"))
+        );
+        assert_eq!(Td1::parse(&mrz[..60], today), Err(Error::Format));
+    }
+}
@@ -0,0 +1,186 @@
+// Native evaluation of the predicates proved by the circuits, so that a
+// holder can learn that a proof can never succeed before spending seconds on
+// it. Mirrors circuit::Builder::check_schema_version, check_majority,
+// check_not_expired and the public inputs nationality & issuer.
+
+use chrono::NaiveDate;
+
+use crate::{
+    core::{
+        codec::Codec,
+        credential::{Credential, Nationality, SCHEMA_VERSION},
+        date::{self, days_from_origin},
+    },
+    schnorr::keys::PublicKey,
+};
+
+/// What a relying party requires, i.e. the values of the public inputs the
+/// predicates depend on
+pub struct Policy {
+    pub nationality: Nationality,
+    /// See date::today and date::reference_day
+    pub today_days: u32,
+    /// See date::ADULT_AGE_DAYS
+    pub min_age_days: u32,
+    /// None when the issuer stays private behind a certificate
+    pub issuer: Option<PublicKey>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Predicate {
+    SchemaVersion,
+    Adult,
+    /// The credential expired before today_days
+    Expired,
+    Nationality,
+    Issuer,
+}
+
+impl std::fmt::Display for Predicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SchemaVersion => f.write_str("your credential must be refreshed"),
+            Self::Adult => f.write_str("you are not yet 18"),
+            Self::Expired => f.write_str("your credential has expired"),
+            Self::Nationality => f.write_str("your nationality is not accepted"),
+            Self::Issuer => f.write_str("your credential was not issued by the expected issuer"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PredicateOutcomes {
+    pub schema_version: bool,
+    pub adult: bool,
+    pub not_expired: bool,
+    pub nationality: bool,
+    pub issuer: bool,
+}
+
+impl PredicateOutcomes {
+    /// Whether a proof can succeed, as far as the credential is concerned
+    pub fn all(&self) -> bool {
+        self.failed().is_empty()
+    }
+
+    pub fn failed(&self) -> Vec<Predicate> {
+        [
+            (Predicate::SchemaVersion, self.schema_version),
+            (Predicate::Adult, self.adult),
+            (Predicate::Expired, self.not_expired),
+            (Predicate::Nationality, self.nationality),
+            (Predicate::Issuer, self.issuer),
+        ]
+        .into_iter()
+        .filter_map(|(predicate, holds)| (!holds).then_some(predicate))
+        .collect()
+    }
+}
+
+impl Policy {
+    /// Adults of the given nationality as of today, whatever the issuer
+    pub fn new(nationality: Nationality) -> Self {
+        Self {
+            nationality,
+            today_days: date::today(),
+            min_age_days: date::ADULT_AGE_DAYS,
+            issuer: None,
+        }
+    }
+
+    /// Holders at least `years` old, e.g. for circuit::circuit_age_over
+    pub fn with_min_age(self, years: u32) -> Self {
+        Self {
+            min_age_days: date::min_age_days(years),
+            ..self
+        }
+    }
+
+    /// Holders at least `years` old on a past reference date
+    pub fn age_on(self, reference: NaiveDate, years: u32) -> date::Result<Self> {
+        Ok(Self {
+            today_days: date::reference_day(reference)?,
+            min_age_days: date::min_age_days(years),
+            ..self
+        })
+    }
+
+    pub fn evaluate(&self, credential: &Credential) -> PredicateOutcomes {
+        PredicateOutcomes {
+            schema_version: credential.schema_version() == SCHEMA_VERSION,
+            adult: days_from_origin(*credential.birth_date()) as u64 + self.min_age_days as u64
+                <= self.today_days as u64,
+            not_expired: days_from_origin(*credential.expiration_date()) >= self.today_days,
+            nationality: credential.nationality().code() == self.nationality.code(),
+            issuer: self
+                .issuer
+                .as_ref()
+                .is_none_or(|pk| pk.to_bytes() == credential.issuer().to_bytes()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        core::date::{today_for_tests, ADULT_AGE_DAYS},
+        issuer::keys::IssuerIdentity,
+    };
+
+    fn policy(issuer: Option<PublicKey>) -> Policy {
+        Policy {
+            nationality: Nationality::FR,
+            today_days: today_for_tests(),
+            min_age_days: ADULT_AGE_DAYS,
+            issuer,
+        }
+    }
+
+    #[test]
+    fn evaluate_accepts_adults_of_the_issuer() {
+        let (_, _, credential) = Credential::from_seed(1);
+        let outcomes = policy(Some(credential.issuer())).evaluate(&credential);
+        assert!(outcomes.all());
+        assert!(policy(None).evaluate(&credential).all());
+    }
+
+    #[test]
+    fn evaluate_reports_every_failure() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut credential = Credential::random_minor(&IssuerIdentity::for_tests(), &mut rng);
+        credential.set_schema_version(SCHEMA_VERSION + 1);
+        let (_, _, other) = Credential::from_seed(3);
+
+        let outcomes = policy(Some(other.issuer())).evaluate(&credential);
+        assert_eq!(
+            outcomes.failed(),
+            [
+                Predicate::SchemaVersion,
+                Predicate::Adult,
+                Predicate::Issuer
+            ]
+        );
+        assert_eq!(Predicate::Adult.to_string(), "you are not yet 18");
+    }
+
+    #[test]
+    fn evaluate_accepts_credentials_through_their_expiration_date() {
+        let (_, _, credential) = Credential::from_seed(1);
+        let expiration_date = days_from_origin(*credential.expiration_date());
+        let evaluate_on = |today_days| {
+            Policy {
+                today_days,
+                ..policy(None)
+            }
+            .evaluate(&credential)
+        };
+        assert!(evaluate_on(expiration_date).not_expired);
+        assert_eq!(
+            evaluate_on(expiration_date + 1).failed(),
+            [Predicate::Expired]
+        );
+    }
+}
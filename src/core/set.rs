@@ -0,0 +1,147 @@
+// Sets of countries committed to by a hash, so that a circuit can prove that
+// a nationality is, or is not, in the set without disclosing it. The verifier
+// only needs the commitment, e.g. published by a regulator. Sets of issuers
+// likewise hide which of the recognized authorities signed a credential.
+
+use plonky2::field::{goldilocks_field::GoldilocksField, types::Field};
+use thiserror::Error;
+
+use crate::{
+    encoding::{self, MAX_COUNTRY_SET, MAX_ISSUER_SET},
+    merkle::hash,
+    schnorr::keys::PublicKey,
+};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("A set holds at most {MAX_COUNTRY_SET} countries, got {0}")]
+    TooLarge(usize),
+    #[error("0 is not a country code")]
+    ZeroCode,
+    #[error("A set holds between 1 and {MAX_ISSUER_SET} issuers, got {0}")]
+    IssuerCount(usize),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Country codes, with the same numbering as core::credential::Nationality
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CountrySet(Vec<u16>);
+
+impl CountrySet {
+    pub fn new(codes: &[u16]) -> Result<Self> {
+        if codes.contains(&0) {
+            return Err(Error::ZeroCode);
+        }
+        let mut codes = codes.to_vec();
+        codes.sort_unstable();
+        codes.dedup();
+        if codes.len() > MAX_COUNTRY_SET {
+            return Err(Error::TooLarge(codes.len()));
+        }
+        Ok(Self(codes))
+    }
+
+    pub fn codes(&self) -> &[u16] {
+        &self.0
+    }
+
+    pub fn contains(&self, code: u16) -> bool {
+        self.0.binary_search(&code).is_ok()
+    }
+
+    pub fn to_field<F: Field>(&self) -> encoding::CountrySet<F> {
+        encoding::CountrySet(std::array::from_fn(|i| {
+            F::from_canonical_u16(self.0.get(i).copied().unwrap_or(0))
+        }))
+    }
+
+    /// Mirrors circuit::set::CircuitBuilderSet::commit_country_set
+    pub fn commitment(&self) -> encoding::Hash<GoldilocksField> {
+        hash::poseidon(&self.to_field().0)
+    }
+}
+
+/// Issuer keys, e.g. of the offices of a national authority
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IssuerSet(Vec<PublicKey>);
+
+impl IssuerSet {
+    pub fn new(keys: &[PublicKey]) -> Result<Self> {
+        let mut keys = keys.to_vec();
+        keys.sort_unstable_by_key(PublicKey::to_bytes);
+        keys.dedup();
+        if !(1..=MAX_ISSUER_SET).contains(&keys.len()) {
+            return Err(Error::IssuerCount(keys.len()));
+        }
+        Ok(Self(keys))
+    }
+
+    pub fn keys(&self) -> &[PublicKey] {
+        &self.0
+    }
+
+    pub fn contains(&self, key: &PublicKey) -> bool {
+        self.0.contains(key)
+    }
+
+    /// Keys by their canonical encoding, which doesn't depend on the
+    /// coordinates of the points
+    pub fn to_field<F: Field>(&self) -> encoding::IssuerSet<F> {
+        encoding::IssuerSet(std::array::from_fn(|i| {
+            let key = self.0.get(i).unwrap_or(&self.0[0]);
+            encoding::GFp5(key.0.encode().0.map(|x| F::from_canonical_u64(x.to_u64())))
+        }))
+    }
+
+    /// Mirrors circuit::set::CircuitBuilderSet::commit_issuer_set
+    pub fn commitment(&self) -> encoding::Hash<GoldilocksField> {
+        let encoded: Vec<_> = self.to_field().0.iter().flat_map(|w| w.0).collect();
+        hash::poseidon(&encoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::credential::Credential;
+
+    #[test]
+    fn sets_are_canonical() {
+        let set = CountrySet::new(&[408, 364, 408]).unwrap();
+        assert_eq!(set.codes(), [364, 408]);
+        assert!(set.contains(408) && !set.contains(250));
+        assert_eq!(
+            set.commitment(),
+            CountrySet::new(&[364, 408]).unwrap().commitment()
+        );
+        assert_ne!(
+            set.commitment(),
+            CountrySet::new(&[364]).unwrap().commitment()
+        );
+
+        assert_eq!(CountrySet::new(&[0]), Err(Error::ZeroCode));
+        let codes: Vec<u16> = (1..=MAX_COUNTRY_SET as u16 + 1).collect();
+        assert_eq!(
+            CountrySet::new(&codes),
+            Err(Error::TooLarge(MAX_COUNTRY_SET + 1))
+        );
+    }
+
+    #[test]
+    fn issuer_sets_are_canonical() {
+        let keys: Vec<_> = (0..3)
+            .map(|seed| Credential::from_seed(seed).2.issuer())
+            .collect();
+        let set = IssuerSet::new(&keys).unwrap();
+        let reordered = IssuerSet::new(&[keys[2].clone(), keys[0].clone(), keys[1].clone()]);
+        assert_eq!(set.commitment(), reordered.unwrap().commitment());
+        assert!(keys.iter().all(|key| set.contains(key)));
+        assert_ne!(
+            set.commitment(),
+            IssuerSet::new(&keys[..2]).unwrap().commitment()
+        );
+
+        assert_eq!(IssuerSet::new(&[]), Err(Error::IssuerCount(0)));
+    }
+}
@@ -2,6 +2,7 @@ use plonky2::{
     field::types::{Field, PrimeField64},
     hash::hash_types::RichField,
 };
+use thiserror::Error;
 
 use super::Point;
 use crate::{
@@ -10,26 +11,56 @@ use crate::{
         field::{GFp, GFp5},
     },
     encoding::{
-        self, AuthentificationChallenge, AuthentificationChallengeRaw, LEN_CREDENTIAL, LEN_FIELD,
-        LEN_PASSPORT_NUMBER, LEN_POINT, LEN_SCALAR, LEN_STRING,
+        self, layout::Attribute, AuthentificationChallenge, AuthentificationChallengeRaw,
+        LEN_ACCOUNT, LEN_CHANNEL_BINDING, LEN_CREDENTIAL, LEN_FIELD, LEN_GIVEN_NAMES,
+        LEN_PACKED_SCALAR, LEN_POINT, LEN_SCALAR, LEN_SCALAR_LIMBS, LEN_STRING, LEN_VAR_STRING,
+        PACKED_SCALAR_BITS,
     },
     issuer::pseudonym::Pseudonym,
 };
 
+/// Conversions of data which may come off the wire, e.g. public inputs or
+/// strings of a presentation request
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Not a boolean")]
+    NotABool,
+    #[error("Expected {expected} elements, got {got}")]
+    Length { expected: usize, got: usize },
+    #[error("{bytes} bytes don't fit in {len} field elements")]
+    TooLong { bytes: usize, len: usize },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
 pub trait ToBool<TBool> {
     fn to_bool(&self) -> TBool;
 }
-impl<F: Field> ToBool<bool> for F {
-    fn to_bool(&self) -> bool {
+
+pub trait TryToBool<TBool> {
+    fn try_to_bool(&self) -> Result<TBool>;
+}
+
+impl<F: Field> TryToBool<bool> for F {
+    fn try_to_bool(&self) -> Result<bool> {
         if self.is_zero() {
-            false
+            Ok(false)
         } else if self.is_one() {
-            true
+            Ok(true)
         } else {
-            panic!("boolean conversion failed")
+            Err(Error::NotABool)
         }
     }
 }
+
+/// Slice of exactly N elements
+fn array<T: Copy, const N: usize>(value: &[T]) -> Result<[T; N]> {
+    value.try_into().map_err(|_| Error::Length {
+        expected: N,
+        got: value.len(),
+    })
+}
+
 pub trait FromBool<T> {
     fn from_bool(self) -> T;
 }
@@ -48,7 +79,21 @@ pub trait ToField<F: Field, const N: usize> {
     fn to_field(&self) -> [F; N];
 }
 pub trait ToStringField<F: Field> {
-    fn to_field(&self) -> encoding::String<F>;
+    /// Panics on strings longer than MAX_STRING_BYTES, see try_to_field
+    fn to_field(&self) -> encoding::String<F> {
+        self.try_to_field()
+            .unwrap_or_else(|err| panic!("string conversion failed: {err}"))
+    }
+    fn try_to_field(&self) -> Result<encoding::String<F>>;
+}
+
+pub trait ToVarStringField<F: Field> {
+    /// Panics on strings longer than MAX_STRING_BYTES, see try_to_field
+    fn to_field(&self) -> encoding::VarString<F> {
+        self.try_to_field()
+            .unwrap_or_else(|err| panic!("string conversion failed: {err}"))
+    }
+    fn try_to_field(&self) -> Result<encoding::VarString<F>>;
 }
 
 pub trait ToSingleField<F: Field> {
@@ -71,23 +116,28 @@ pub trait ToSchnorrField<F: Field, B: Copy> {
     fn to_field(&self) -> encoding::SchnorrProof<F, B>;
 }
 
-pub trait ToSignatureField<F: Field, B: Copy> {
-    fn to_field(&self) -> encoding::Signature<F, B>;
+pub trait ToAuthentificationContextField<F: Field> {
+    fn to_field(&self) -> encoding::AuthentificationContext<F>;
 }
 
-pub trait ToAuthentificationField<F: Field, B: Copy> {
-    fn to_field(&self) -> encoding::Authentification<F, B>;
+pub trait ToChannelBindingField<F: Field> {
+    fn to_field(&self) -> encoding::ChannelBinding<F>;
 }
 
-pub trait ToAuthentificationContextField<F: Field> {
-    fn to_field(&self) -> encoding::AuthentificationContext<F>;
+pub trait ToAccountField<F: Field> {
+    /// Fails on identifiers longer than MAX_ACCOUNT_BYTES
+    fn try_to_field(&self) -> Result<encoding::Account<F>>;
 }
 
 pub trait ToVecField<F: Field> {
     /// Buids a Vec<F> of expected_len size; if the provided bytes sequence
-    /// is too small, the result will be padded with zeroes; if it’s too big,
-    /// remaining bytes will be ignored.
-    fn to_field(&self, expected_len: usize) -> Vec<F>;
+    /// is too small, the result will be padded with zeroes. Panics if it’s
+    /// too big, see try_to_field.
+    fn to_field(&self, expected_len: usize) -> Vec<F> {
+        self.try_to_field(expected_len)
+            .unwrap_or_else(|err| panic!("input too long: {err}"))
+    }
+    fn try_to_field(&self, expected_len: usize) -> Result<Vec<F>>;
 }
 
 impl<F: Field> ToSingleField<F> for u8 {
@@ -116,22 +166,20 @@ impl<F: Field> ToSingleField<F> for GFp {
 
 // TODO: instead of writing 4 u8 on 1 u32, we could write 7 u8 in 1 u64 (if this fits in modulo)
 impl<F: Field> ToVecField<F> for &[u8] {
-    fn to_field(&self, expected_len: usize) -> Vec<F> {
-        let required_len = self.len().div_ceil(4);
-        assert!(
-            required_len <= expected_len,
-            "input too long: {} bytes require {} field elements, expected {}",
-            self.len(),
-            required_len,
-            expected_len
-        );
+    fn try_to_field(&self, expected_len: usize) -> Result<Vec<F>> {
+        if self.len().div_ceil(4) > expected_len {
+            return Err(Error::TooLong {
+                bytes: self.len(),
+                len: expected_len,
+            });
+        }
         let mut res = vec![F::ZERO; expected_len];
         for (count, chunk) in self.chunks(4).enumerate() {
             let mut buf = [0u8; 4];
             buf[..chunk.len()].copy_from_slice(chunk);
             res[count] = F::from_canonical_u32(u32::from_le_bytes(buf));
         }
-        res
+        Ok(res)
     }
 }
 
@@ -139,8 +187,87 @@ impl<F: Field> ToVecField<F> for &[u8] {
 /// for now, 20 chars max, encoded on u32 converted to field elements
 /// TODO: compress using "u48" instead to gain space
 impl<F: Field> ToStringField<F> for String {
-    fn to_field(&self) -> encoding::String<F> {
-        encoding::String(self.as_bytes().to_field(LEN_STRING).try_into().unwrap())
+    fn try_to_field(&self) -> Result<encoding::String<F>> {
+        Ok(encoding::String(array(
+            &self.as_bytes().try_to_field(LEN_STRING)?,
+        )?))
+    }
+}
+
+impl<F: Field> ToVarStringField<F> for str {
+    fn try_to_field(&self) -> Result<encoding::VarString<F>> {
+        Ok(encoding::VarString {
+            value: self.to_string().try_to_field()?,
+            len: F::from_canonical_usize(self.len()),
+        })
+    }
+}
+
+impl<T: Copy> From<encoding::VarString<T>> for [T; LEN_VAR_STRING] {
+    fn from(value: encoding::VarString<T>) -> Self {
+        std::array::from_fn(|i| {
+            if i < LEN_STRING {
+                value.value.0[i]
+            } else {
+                value.len
+            }
+        })
+    }
+}
+
+impl<T: Copy> TryFrom<&[T]> for encoding::VarString<T> {
+    type Error = Error;
+
+    fn try_from(value: &[T]) -> Result<Self> {
+        let value: [T; LEN_VAR_STRING] = array(value)?;
+        Ok(encoding::VarString {
+            value: encoding::String(std::array::from_fn(|i| value[i])),
+            len: value[LEN_STRING],
+        })
+    }
+}
+
+impl<T: Copy> From<encoding::GivenNames<T>> for [T; LEN_GIVEN_NAMES] {
+    fn from(value: encoding::GivenNames<T>) -> Self {
+        let mut res = Vec::with_capacity(LEN_GIVEN_NAMES);
+        for name in value.0 {
+            res.extend(<[T; LEN_VAR_STRING]>::from(name));
+        }
+        res.try_into()
+            .unwrap_or_else(|_| panic!("Given names don't fit the right length"))
+    }
+}
+
+impl<T: Copy> TryFrom<&[T]> for encoding::GivenNames<T> {
+    type Error = Error;
+
+    fn try_from(value: &[T]) -> Result<Self> {
+        let value: [T; LEN_GIVEN_NAMES] = array(value)?;
+        let names = value
+            .chunks(LEN_VAR_STRING)
+            .map(encoding::VarString::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(encoding::GivenNames(array(&names)?))
+    }
+}
+
+impl<F: Field> ToChannelBindingField<F> for [u8; 4 * LEN_CHANNEL_BINDING] {
+    fn to_field(&self) -> encoding::ChannelBinding<F> {
+        // 4 bytes per element, so this always fits
+        encoding::ChannelBinding(std::array::from_fn(|i| {
+            let chunk = [0, 1, 2, 3].map(|j| self[4 * i + j]);
+            F::from_canonical_u32(u32::from_le_bytes(chunk))
+        }))
+    }
+}
+
+impl<F: Field> ToAccountField<F> for [u8] {
+    fn try_to_field(&self) -> Result<encoding::Account<F>> {
+        let value = ToVecField::<F>::try_to_field(&self, LEN_ACCOUNT)?;
+        Ok(encoding::VarString {
+            value: encoding::String(array(&value)?),
+            len: F::from_canonical_usize(self.len()),
+        })
     }
 }
 
@@ -151,6 +278,96 @@ impl ToScalarField for arith::Scalar {
     }
 }
 
+/// Little endian, 32 bits per limb
+impl<F: Field> ToField<F, LEN_SCALAR_LIMBS> for encoding::Scalar<bool> {
+    fn to_field(&self) -> [F; LEN_SCALAR_LIMBS] {
+        std::array::from_fn(|i| {
+            let limb = self.0[32 * i..LEN_SCALAR.min(32 * (i + 1))]
+                .iter()
+                .rev()
+                .fold(0u32, |acc, &bit| (acc << 1) | bit as u32);
+            F::from_canonical_u32(limb)
+        })
+    }
+}
+
+impl encoding::Scalar<bool> {
+    /// Little endian, PACKED_SCALAR_BITS per element
+    pub fn pack<F: Field>(&self) -> encoding::ScalarLimbs<F> {
+        encoding::ScalarLimbs(std::array::from_fn(|i| {
+            let chunk = self.0
+                [PACKED_SCALAR_BITS * i..LEN_SCALAR.min(PACKED_SCALAR_BITS * (i + 1))]
+                .iter()
+                .rev()
+                .fold(0u64, |acc, &bit| (acc << 1) | bit as u64);
+            F::from_canonical_u64(chunk)
+        }))
+    }
+
+    /// Inverse of pack, None if an element overflows its bits or if the
+    /// scalar is not reduced
+    pub fn unpack<F: PrimeField64>(packed: &encoding::ScalarLimbs<F>) -> Option<Self> {
+        let mut bits = [false; LEN_SCALAR];
+        for (chunk, x) in bits.chunks_mut(PACKED_SCALAR_BITS).zip(&packed.0) {
+            let x = x.to_canonical_u64();
+            if x >> chunk.len() != 0 {
+                return None;
+            }
+            for (j, bit) in chunk.iter_mut().enumerate() {
+                *bit = (x >> j) & 1 == 1;
+            }
+        }
+        // lexicographic comparison from the most significant bit
+        let lt_modulus = (0..LEN_SCALAR)
+            .rev()
+            .map(|i| (bits[i], arith::Scalar::modulus_bit_le(i)))
+            .find(|(b, m)| b != m)
+            .is_some_and(|(b, _)| !b);
+        lt_modulus.then_some(encoding::Scalar(bits))
+    }
+}
+
+impl<F: PrimeField64> encoding::SchnorrLimbs<F> {
+    /// Decomposes s into bits, None if it is not a valid scalar
+    pub fn unpack(&self) -> Option<encoding::SchnorrProof<F, bool>> {
+        Some(encoding::SchnorrProof {
+            r: self.r,
+            s: encoding::Scalar::unpack(&self.s)?,
+        })
+    }
+}
+
+impl<T: Copy> From<encoding::SchnorrLimbs<T>> for [T; LEN_SIGNATURE_LIMBS] {
+    fn from(value: encoding::SchnorrLimbs<T>) -> Self {
+        let r: [T; LEN_POINT] = value.r.into();
+        std::array::from_fn(|i| {
+            if i < LEN_POINT {
+                r[i]
+            } else {
+                value.s.0[i - LEN_POINT]
+            }
+        })
+    }
+}
+
+impl<T: Copy> From<&[T; LEN_SIGNATURE_LIMBS]> for encoding::SchnorrLimbs<T> {
+    fn from(value: &[T; LEN_SIGNATURE_LIMBS]) -> Self {
+        let r: [T; LEN_POINT] = std::array::from_fn(|i| value[i]);
+        encoding::SchnorrLimbs {
+            r: r.into(),
+            s: encoding::ScalarLimbs(std::array::from_fn(|i| value[LEN_POINT + i])),
+        }
+    }
+}
+
+impl<T: Copy> TryFrom<&[T]> for encoding::SchnorrLimbs<T> {
+    type Error = Error;
+
+    fn try_from(value: &[T]) -> Result<Self> {
+        Ok((&array::<T, LEN_SIGNATURE_LIMBS>(value)?).into())
+    }
+}
+
 impl<F: Field> ToGFp5Field<F> for GFp5 {
     fn to_field(&self) -> encoding::GFp5<F> {
         encoding::GFp5(self.0.map(|x| x.to_field()))
@@ -210,19 +427,25 @@ impl<F: RichField> From<Point<F>> for arith::Point {
 
 impl<T: Copy> From<[T; LEN_POINT]> for Point<T> {
     fn from(value: [T; LEN_POINT]) -> Self {
-        let x: [T; LEN_FIELD] = value[..LEN_FIELD].try_into().unwrap();
-        let z: [T; LEN_FIELD] = value[LEN_FIELD..LEN_FIELD * 2].try_into().unwrap();
-        let u: [T; LEN_FIELD] = value[LEN_FIELD * 2..LEN_FIELD * 3].try_into().unwrap();
-        let t: [T; LEN_FIELD] = value[LEN_FIELD * 3..].try_into().unwrap();
+        let coordinate =
+            |k: usize| encoding::GFp5(std::array::from_fn(|i| value[k * LEN_FIELD + i]));
         Self {
-            x: x.into(),
-            z: z.into(),
-            u: u.into(),
-            t: t.into(),
+            x: coordinate(0),
+            z: coordinate(1),
+            u: coordinate(2),
+            t: coordinate(3),
         }
     }
 }
 
+impl<T: Copy> TryFrom<&[T]> for Point<T> {
+    type Error = Error;
+
+    fn try_from(value: &[T]) -> Result<Self> {
+        Ok(array::<T, LEN_POINT>(value)?.into())
+    }
+}
+
 impl<T: Copy> From<Point<T>> for [T; LEN_POINT] {
     fn from(value: Point<T>) -> Self {
         let mut res = Vec::with_capacity(LEN_POINT);
@@ -240,53 +463,19 @@ impl<T: Copy, TBool: Copy + FromBool<T>> From<&encoding::Credential<T, TBool>>
 {
     fn from(value: &encoding::Credential<T, TBool>) -> Self {
         let mut res = Vec::with_capacity(LEN_CREDENTIAL);
-        res.extend(value.first_name.0);
-        res.extend(value.family_name.0);
-        res.extend(value.place_of_birth.0);
-        res.extend(value.passport_number.0);
-        res.push(value.birth_date);
-        res.push(value.expiration_date);
-        res.push(value.gender.from_bool());
-        res.push(value.nationality);
-        let issuer: [T; LEN_POINT] = value.issuer.into();
-        res.extend(issuer);
-        let public_key: [T; LEN_POINT] = value.public_key.into();
-        res.extend(public_key);
+        for attribute in Attribute::ALL {
+            res.extend(value.attribute(attribute));
+        }
         res.try_into()
             .unwrap_or_else(|_| panic!("Given credential don't fit the right length"))
     }
 }
 
-const POS_BIRTH_DATE: usize = LEN_STRING * 3 + LEN_PASSPORT_NUMBER;
-const START_ISSUER: usize = POS_BIRTH_DATE + 4;
-impl<T: Copy + ToBool<TBool>, TBool: Copy> From<&[T; LEN_CREDENTIAL]>
-    for encoding::Credential<T, TBool>
-{
-    fn from(value: &[T; LEN_CREDENTIAL]) -> Self {
-        let first_name: [T; LEN_STRING] = value[0..LEN_STRING].try_into().unwrap();
-        let family_name: [T; LEN_STRING] = value[LEN_STRING..LEN_STRING * 2].try_into().unwrap();
-        let place_of_birth: [T; LEN_STRING] =
-            value[LEN_STRING * 2..LEN_STRING * 3].try_into().unwrap();
-        let passport_number = value[LEN_STRING * 3..LEN_STRING * 3 + LEN_PASSPORT_NUMBER]
-            .try_into()
-            .unwrap();
-        let issuer: [T; LEN_POINT] = value[START_ISSUER..START_ISSUER + LEN_POINT]
-            .try_into()
-            .unwrap();
-        let public_key: [T; LEN_POINT] = value[START_ISSUER + LEN_POINT..].try_into().unwrap();
+impl<T: Copy + TryToBool<TBool>, TBool: Copy> TryFrom<&[T]> for encoding::Credential<T, TBool> {
+    type Error = Error;
 
-        Self {
-            first_name: encoding::String(first_name),
-            family_name: encoding::String(family_name),
-            place_of_birth: encoding::String(place_of_birth),
-            passport_number: encoding::PassportNumber(passport_number),
-            birth_date: value[POS_BIRTH_DATE],
-            expiration_date: value[POS_BIRTH_DATE + 1],
-            gender: value[POS_BIRTH_DATE + 2].to_bool(),
-            nationality: value[POS_BIRTH_DATE + 3],
-            issuer: issuer.into(),
-            public_key: public_key.into(),
-        }
+    fn try_from(value: &[T]) -> Result<Self> {
+        Self::try_from_flat(value)
     }
 }
 
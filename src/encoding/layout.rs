@@ -0,0 +1,199 @@
+// Named layout of the flattened credential, shared by the signature transcript,
+// the Merkle leaves and the circuits: positions are derived from the order of
+// Attribute::ALL instead of being maintained by hand.
+
+use std::ops::Range;
+
+use crate::encoding::{
+    conversion::{self, FromBool, TryToBool},
+    Credential, GivenNames, Hash, IssuingAuthority, Layout, MrzCheckDigits, PassportNumber, Point,
+    VarString, LEN_GIVEN_NAMES, LEN_POINT, LEN_VAR_STRING,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Attribute {
+    GivenNames,
+    FamilyName,
+    PlaceOfBirth,
+    DocumentType,
+    PassportNumber,
+    MrzCheckDigits,
+    BirthDate,
+    DateOfIssue,
+    ExpirationDate,
+    Gender,
+    Nationality,
+    IssuingAuthority,
+    PortraitHash,
+    SchemaVersion,
+    Issuer,
+    PublicKey,
+}
+
+impl Attribute {
+    /// Order of the attributes in the flattened credential, which is the
+    /// declaration order
+    pub const ALL: [Attribute; 16] = [
+        Self::GivenNames,
+        Self::FamilyName,
+        Self::PlaceOfBirth,
+        Self::DocumentType,
+        Self::PassportNumber,
+        Self::MrzCheckDigits,
+        Self::BirthDate,
+        Self::DateOfIssue,
+        Self::ExpirationDate,
+        Self::Gender,
+        Self::Nationality,
+        Self::IssuingAuthority,
+        Self::PortraitHash,
+        Self::SchemaVersion,
+        Self::Issuer,
+        Self::PublicKey,
+    ];
+
+    /// Number of elements of the attribute
+    pub const fn len(self) -> usize {
+        match self {
+            Self::GivenNames => GivenNames::<()>::LEN,
+            Self::FamilyName | Self::PlaceOfBirth => VarString::<()>::LEN,
+            Self::PassportNumber => PassportNumber::<()>::LEN,
+            Self::IssuingAuthority => IssuingAuthority::<()>::LEN,
+            Self::MrzCheckDigits => MrzCheckDigits::<()>::LEN,
+            Self::PortraitHash => Hash::<()>::LEN,
+            Self::Issuer | Self::PublicKey => Point::<()>::LEN,
+            Self::DocumentType
+            | Self::BirthDate
+            | Self::DateOfIssue
+            | Self::ExpirationDate
+            | Self::Gender
+            | Self::Nationality
+            | Self::SchemaVersion => 1,
+        }
+    }
+
+    /// Position of the attribute in the flattened credential
+    pub const fn offset(self) -> usize {
+        // ALL follows the declaration order
+        let mut res = 0;
+        let mut i = 0;
+        while i < self as usize {
+            res += Self::ALL[i].len();
+            i += 1;
+        }
+        res
+    }
+
+    pub const fn range(self) -> Range<usize> {
+        self.offset()..self.offset() + self.len()
+    }
+
+    /// Length of the flattened credential
+    pub const fn total_len() -> usize {
+        let last = Self::ALL[Self::ALL.len() - 1];
+        last.offset() + last.len()
+    }
+}
+
+impl<T: Copy, TBool: Copy> Credential<T, TBool> {
+    /// Elements of the attribute, as laid out in the flattened credential
+    pub fn attribute(&self, attribute: Attribute) -> Vec<T>
+    where
+        TBool: FromBool<T>,
+    {
+        match attribute {
+            Attribute::GivenNames => <[T; LEN_GIVEN_NAMES]>::from(self.given_names).to_vec(),
+            Attribute::FamilyName => <[T; LEN_VAR_STRING]>::from(self.family_name).to_vec(),
+            Attribute::PlaceOfBirth => <[T; LEN_VAR_STRING]>::from(self.place_of_birth).to_vec(),
+            Attribute::DocumentType => vec![self.document_type],
+            Attribute::PassportNumber => self.passport_number.0.to_vec(),
+            Attribute::MrzCheckDigits => vec![
+                self.mrz_check_digits.document_number,
+                self.mrz_check_digits.birth_date,
+                self.mrz_check_digits.expiration_date,
+            ],
+            Attribute::BirthDate => vec![self.birth_date],
+            Attribute::DateOfIssue => vec![self.date_of_issue],
+            Attribute::ExpirationDate => vec![self.expiration_date],
+            Attribute::Gender => vec![self.gender.from_bool()],
+            Attribute::Nationality => vec![self.nationality],
+            Attribute::IssuingAuthority => {
+                vec![self.issuing_authority.country, self.issuing_authority.code]
+            }
+            Attribute::PortraitHash => self.portrait_hash.0.to_vec(),
+            Attribute::SchemaVersion => vec![self.schema_version],
+            Attribute::Issuer => <[T; LEN_POINT]>::from(self.issuer).to_vec(),
+            Attribute::PublicKey => <[T; LEN_POINT]>::from(self.public_key).to_vec(),
+        }
+    }
+
+    /// Reads a flattened credential back, failing on wrong lengths and
+    /// non-boolean genders
+    pub fn try_from_flat(value: &[T]) -> conversion::Result<Self>
+    where
+        T: TryToBool<TBool>,
+    {
+        if value.len() != Self::LEN {
+            return Err(conversion::Error::Length {
+                expected: Self::LEN,
+                got: value.len(),
+            });
+        }
+        let get = |attribute: Attribute| &value[attribute.range()];
+        let passport_number = get(Attribute::PassportNumber);
+        let portrait_hash = get(Attribute::PortraitHash);
+        Ok(Self {
+            given_names: get(Attribute::GivenNames).try_into()?,
+            family_name: get(Attribute::FamilyName).try_into()?,
+            place_of_birth: get(Attribute::PlaceOfBirth).try_into()?,
+            document_type: get(Attribute::DocumentType)[0],
+            passport_number: PassportNumber(std::array::from_fn(|i| passport_number[i])),
+            mrz_check_digits: MrzCheckDigits {
+                document_number: get(Attribute::MrzCheckDigits)[0],
+                birth_date: get(Attribute::MrzCheckDigits)[1],
+                expiration_date: get(Attribute::MrzCheckDigits)[2],
+            },
+            birth_date: get(Attribute::BirthDate)[0],
+            date_of_issue: get(Attribute::DateOfIssue)[0],
+            expiration_date: get(Attribute::ExpirationDate)[0],
+            gender: get(Attribute::Gender)[0].try_to_bool()?,
+            nationality: get(Attribute::Nationality)[0],
+            issuing_authority: IssuingAuthority {
+                country: get(Attribute::IssuingAuthority)[0],
+                code: get(Attribute::IssuingAuthority)[1],
+            },
+            portrait_hash: Hash(std::array::from_fn(|i| portrait_hash[i])),
+            schema_version: get(Attribute::SchemaVersion)[0],
+            issuer: get(Attribute::Issuer).try_into()?,
+            public_key: get(Attribute::PublicKey).try_into()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::goldilocks_field::GoldilocksField as F;
+
+    use super::*;
+    use crate::{core::credential, encoding::LEN_CREDENTIAL};
+
+    #[test]
+    fn attributes_tile_the_credential() {
+        let mut next = 0;
+        for attribute in Attribute::ALL {
+            assert_eq!(attribute.offset(), next, "{attribute:?}");
+            next += attribute.len();
+        }
+        assert_eq!(next, LEN_CREDENTIAL);
+    }
+
+    #[test]
+    fn attributes_are_read_at_their_position() {
+        let (_, _, credential) = credential::Credential::from_seed(1);
+        let credential = credential.to_field::<F>();
+        let flat: [F; LEN_CREDENTIAL] = (&credential).into();
+        for attribute in Attribute::ALL {
+            assert_eq!(flat[attribute.range()], credential.attribute(attribute));
+        }
+    }
+}
@@ -1,46 +1,121 @@
 // FIXME: arbitrary limit of 20 chars for now, each char is 1/4 of u32 -> 5 field elements (of 32 bits, to avoid overfow)
 
-// TODO: add tests for lengths
-
 use crate::arith;
 
 pub mod conversion;
+pub mod layout;
 
 pub const LEN_STRING: usize = 5;
+/// Maximal length of a string, in bytes
+pub const MAX_STRING_BYTES: usize = 4 * LEN_STRING;
+pub const LEN_VAR_STRING: usize = <VarString<()> as Layout>::LEN;
+/// Given names listed on a passport, beyond which the issuer truncates
+pub const MAX_GIVEN_NAMES: usize = 3;
+pub const LEN_GIVEN_NAMES: usize = <GivenNames<()> as Layout>::LEN;
 pub const LEN_PASSPORT_NUMBER: usize = 3;
 pub const LEN_FIELD: usize = 5;
-pub const LEN_POINT: usize = 4 * LEN_FIELD;
+pub const LEN_POINT: usize = <Point<()> as Layout>::LEN;
 pub const LEN_SCALAR: usize = arith::Scalar::NB_BITS;
+/// Scalar packed by 32 bits, e.g. to be hashed
+pub const LEN_SCALAR_LIMBS: usize = LEN_SCALAR.div_ceil(32);
+/// Bits per element of a packed scalar: 63 bits always fit in a field element
+pub const PACKED_SCALAR_BITS: usize = 63;
+/// Scalar packed to cross the public inputs boundary, or to be set in a witness
+pub const LEN_PACKED_SCALAR: usize = LEN_SCALAR.div_ceil(PACKED_SCALAR_BITS);
 
 /// size of a credential<T> in number of T elements
-pub const LEN_CREDENTIAL: usize = 3 * LEN_STRING + LEN_PASSPORT_NUMBER + 4 + LEN_POINT * 2;
+pub const LEN_CREDENTIAL: usize = <Credential<(), ()> as Layout>::LEN;
 
-pub const LEN_SIGNATURE: usize = LEN_POINT + LEN_SCALAR;
+pub const LEN_SIGNATURE: usize = <SchnorrProof<(), ()> as Layout>::LEN;
+pub const LEN_SIGNATURE_LIMBS: usize = <SchnorrLimbs<()> as Layout>::LEN;
 
 pub const LEN_HASH: usize = 4;
 
+/// Channel binding value (32 bytes, e.g. a TLS exporter), packed by u32
+pub const LEN_CHANNEL_BINDING: usize = 8;
+
+/// External account a presentation is bound to, packed by u32
+pub const LEN_ACCOUNT: usize = 8;
+/// Maximal length of an account identifier, in bytes
+pub const MAX_ACCOUNT_BYTES: usize = 4 * LEN_ACCOUNT;
+
 /// Pseudonym is the result of poseidon, so it’s convenient to set it at 4
 pub const LEN_PSEUDONYM: usize = LEN_HASH;
 
-/// Representation of a string inside a circuit
+/// Countries of a committed set, see core::set
+pub const MAX_COUNTRY_SET: usize = 16;
+
+/// Issuer keys of a committed set, see core::set
+pub const MAX_ISSUER_SET: usize = 8;
+
+/// Depth of the sparse tree of revoked credentials, one level per bit of the
+/// first element of a serial number, see revocation
+pub const REVOCATION_DEPTH: usize = 64;
+
+/// Number of T elements of a representation once flattened, e.g. to be
+/// hashed. Lengths of composite types are derived from their fields, so that
+/// changing an attribute only changes its own type.
+pub trait Layout {
+    const LEN: usize;
+}
+
+/// Representation of a string inside a circuit, on N field elements
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct String<T, const N: usize = LEN_STRING>(pub [T; N]);
+/// String with its length in bytes: bytes beyond the length are zero, so that
+/// "AB" and "AB\0" have different representations
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VarString<T, const N: usize = LEN_STRING> {
+    pub value: String<T, N>,
+    pub len: T,
+}
+/// Given names in passport order, the first one being the primary name.
+/// Unused slots are empty strings: names themselves are never empty.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub struct String<T>(pub [T; LEN_STRING]);
-/// Representation of a passport number inside a circuit.
-/// Passport number is assumed to b french (fits on 9 u8)
+pub struct GivenNames<T>(pub [VarString<T>; MAX_GIVEN_NAMES]);
+/// Representation of a document number inside a circuit.
+/// Numbers are assumed to be french (fit on 9 u8), see core::credential::DocumentType
 #[derive(Clone, Copy, Debug)]
-pub struct PassportNumber<T>(pub [T; LEN_PASSPORT_NUMBER]);
+pub struct PassportNumber<T, const N: usize = LEN_PASSPORT_NUMBER>(pub [T; N]);
+
+/// See core::mrz::CheckDigits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MrzCheckDigits<T> {
+    pub document_number: T,
+    pub birth_date: T,
+    pub expiration_date: T,
+}
+
+/// Authority which issued the passport, e.g. a prefecture, whatever the key
+/// signing the credential
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IssuingAuthority<T> {
+    /// Same codes as the nationality
+    pub country: T,
+    /// Code of the authority within the country
+    pub code: T,
+}
 
 /// Representation of a credential inside a circuit
 #[derive(Clone, Copy, Debug)]
 pub struct Credential<T, TBool> {
-    pub first_name: String<T>,
-    pub family_name: String<T>,
-    pub place_of_birth: String<T>,
+    pub given_names: GivenNames<T>,
+    pub family_name: VarString<T>,
+    pub place_of_birth: VarString<T>,
+    /// See core::credential::DocumentType
+    pub document_type: T,
     pub passport_number: PassportNumber<T>, // assumed to be french (9 u8)
-    pub birth_date: T,                      // number of days since origin
+    pub mrz_check_digits: MrzCheckDigits<T>,
+    pub birth_date: T, // number of days since origin
+    pub date_of_issue: T,
     pub expiration_date: T,
     pub gender: TBool, // boolean
     pub nationality: T,
+    pub issuing_authority: IssuingAuthority<T>,
+    /// See core::credential::PortraitHash
+    pub portrait_hash: Hash<T>,
+    /// core::credential::SCHEMA_VERSION of the layout
+    pub schema_version: T,
     pub issuer: Point<T>,
     pub public_key: Point<T>,
 }
@@ -61,7 +136,7 @@ pub struct Point<T> {
     pub t: GFp5<T>,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Scalar<T>(pub(crate) [T; LEN_SCALAR]);
 
 #[derive(Clone, Copy, Debug)]
@@ -70,11 +145,58 @@ pub struct SchnorrProof<T, TBool> {
     pub(crate) s: Scalar<TBool>,
 }
 
+pub type Signature<T, TBool> = SchnorrProof<T, TBool>;
+
+pub type Authentification<T, TBool> = SchnorrProof<T, TBool>;
+
+/// Scalar on limbs of PACKED_SCALAR_BITS, little endian. Limbs of 64 bits
+/// would not fit in a field element.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScalarLimbs<T>(pub [T; LEN_PACKED_SCALAR]);
+
+/// Wire format of a Schnorr proof: s is given by limbs, and decomposed into
+/// bits in the circuit
+#[derive(Clone, Copy, Debug)]
+pub struct SchnorrLimbs<T> {
+    pub(crate) r: Point<T>,
+    pub(crate) s: ScalarLimbs<T>,
+}
+
+pub type SignatureLimbs<T> = SchnorrLimbs<T>;
+
+/// Certificate of an issuer key by a root key. The certified key is not
+/// repeated here: in the circuit it is the issuer of the credential
+#[derive(Clone, Copy, Debug)]
+pub struct Certificate<T, TBool> {
+    pub not_before: T, // number of days since origin
+    pub not_after: T,
+    pub signature: SchnorrProof<T, TBool>,
+}
+
+/// Link of an amended credential to its predecessor. The amended credential is
+/// not repeated here: in the circuit it is the credential presented
 #[derive(Clone, Copy, Debug)]
-pub struct Signature<T, TBool>(pub(crate) SchnorrProof<T, TBool>);
+pub struct Continuity<T, TBool> {
+    pub predecessor: Credential<T, TBool>,
+    pub signature: SchnorrProof<T, TBool>,
+}
+
+/// Country codes of a committed set, padded with 0 which is no country
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CountrySet<T>(pub [T; MAX_COUNTRY_SET]);
+
+/// Canonical encodings of the issuer keys of a committed set, padded with the
+/// first one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IssuerSet<T>(pub [GFp5<T>; MAX_ISSUER_SET]);
 
+/// Date of the day signed by a time authority. The nonce it is signed for is
+/// the one of the presentation
 #[derive(Clone, Copy, Debug)]
-pub struct Authentification<T, TBool>(pub(crate) SchnorrProof<T, TBool>);
+pub struct TimeToken<T, TBool> {
+    pub today: T, // number of days since origin
+    pub signature: SchnorrProof<T, TBool>,
+}
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct AuthentificationChallengeRaw<S> {
@@ -85,18 +207,40 @@ pub struct AuthentificationChallengeRaw<S> {
 }
 
 pub type AuthentificationChallenge<T> = AuthentificationChallengeRaw<String<T>>;
+
+/// Value tying an authentification to the transport session it was produced on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChannelBinding<T>(pub [T; LEN_CHANNEL_BINDING]);
+
 // TODO: maybe service & nonce should have a longer type
 pub struct AuthentificationContext<T> {
     pub public_key: Point<T>,
     pub challenge: AuthentificationChallenge<T>,
+    /// absorbed after the public key when present
+    pub channel_binding: Option<ChannelBinding<T>>,
+    /// expiration timestamp (seconds since UNIX epoch), absorbed after the
+    /// channel binding when present
+    pub expires_at: Option<T>,
+    /// external account the holder binds the proof to, absorbed last when
+    /// present
+    pub account: Option<Account<T>>,
 }
 
+/// Identifier of an external account, e.g. an Ethereum address or a customer
+/// ID, with its length in bytes
+pub type Account<T> = VarString<T, LEN_ACCOUNT>;
+
 // FIXME: centralize every hash of the repository (this, schnorr, etc)
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Hash<T>(pub [T; LEN_HASH]);
 
 pub type Pseudonym<T> = Hash<T>;
 
+/// Siblings of the empty leaf of a serial number in the tree of revoked
+/// credentials, from the leaf up. Positions are the bits of the serial number
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NonRevocationPath<T>(pub [Hash<T>; REVOCATION_DEPTH]);
+
 // does not contain the root
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct MerklePath<const D: usize, T, TBool> {
@@ -104,3 +248,144 @@ pub struct MerklePath<const D: usize, T, TBool> {
     /// True for left, false for right
     pub positions: [TBool; D],
 }
+
+impl<T, const N: usize> Layout for String<T, N> {
+    const LEN: usize = N;
+}
+
+impl<T, const N: usize> Layout for VarString<T, N> {
+    const LEN: usize = String::<T, N>::LEN + 1;
+}
+
+impl<T> Layout for GivenNames<T> {
+    const LEN: usize = MAX_GIVEN_NAMES * VarString::<T>::LEN;
+}
+
+impl<T> Layout for MrzCheckDigits<T> {
+    const LEN: usize = 3;
+}
+
+impl<T> Layout for IssuingAuthority<T> {
+    const LEN: usize = 2;
+}
+
+impl<T, const N: usize> Layout for PassportNumber<T, N> {
+    const LEN: usize = N;
+}
+
+impl<T> Layout for GFp5<T> {
+    const LEN: usize = LEN_FIELD;
+}
+
+impl<T> Layout for Point<T> {
+    const LEN: usize = 4 * GFp5::<T>::LEN;
+}
+
+impl<T> Layout for Scalar<T> {
+    const LEN: usize = LEN_SCALAR;
+}
+
+impl<T, TBool> Layout for SchnorrProof<T, TBool> {
+    const LEN: usize = Point::<T>::LEN + Scalar::<TBool>::LEN;
+}
+
+impl<T> Layout for ScalarLimbs<T> {
+    const LEN: usize = LEN_PACKED_SCALAR;
+}
+
+impl<T> Layout for SchnorrLimbs<T> {
+    const LEN: usize = Point::<T>::LEN + ScalarLimbs::<T>::LEN;
+}
+
+impl<T> Layout for Hash<T> {
+    const LEN: usize = LEN_HASH;
+}
+
+impl<T, TBool> Layout for Credential<T, TBool> {
+    /// See layout::Attribute
+    const LEN: usize = layout::Attribute::total_len();
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::{goldilocks_field::GoldilocksField as F, types::Field};
+
+    use super::*;
+    use crate::{
+        arith,
+        core::credential,
+        encoding::conversion::{ToPointField, ToScalarField, ToVarStringField},
+    };
+
+    #[test]
+    fn credential_layout_matches_flattening() {
+        let (_, _, credential) = credential::Credential::from_seed(1);
+        let flat: Vec<F> = <[F; LEN_CREDENTIAL]>::from(&credential.to_field()).to_vec();
+        let back = Credential::<F, bool>::try_from(&flat[..]).unwrap();
+        assert_eq!(back.issuer, credential.issuer().0.to_field());
+        assert_eq!(back.public_key, credential.public_key().0.to_field());
+        assert_eq!(back.given_names, credential.to_field().given_names);
+    }
+
+    #[test]
+    fn malformed_credentials_are_rejected() {
+        let (_, _, credential) = credential::Credential::from_seed(1);
+        let mut flat: Vec<F> = <[F; LEN_CREDENTIAL]>::from(&credential.to_field()).to_vec();
+        assert_eq!(
+            Credential::<F, bool>::try_from(&flat[1..]).err(),
+            Some(conversion::Error::Length {
+                expected: LEN_CREDENTIAL,
+                got: LEN_CREDENTIAL - 1
+            })
+        );
+        flat[layout::Attribute::Gender.offset()] = F::TWO;
+        assert_eq!(
+            Credential::<F, bool>::try_from(&flat[..]).err(),
+            Some(conversion::Error::NotABool)
+        );
+        assert_eq!(
+            Point::<F>::try_from(&flat[..LEN_POINT - 1]).err(),
+            Some(conversion::Error::Length {
+                expected: LEN_POINT,
+                got: LEN_POINT - 1
+            })
+        );
+        let long = "x".repeat(MAX_STRING_BYTES + 1);
+        assert_eq!(
+            conversion::ToStringField::<F>::try_to_field(&long).err(),
+            Some(conversion::Error::TooLong {
+                bytes: MAX_STRING_BYTES + 1,
+                len: LEN_STRING
+            })
+        );
+    }
+
+    #[test]
+    fn given_names_pad_with_empty_strings() {
+        let (_, _, credential) = credential::Credential::from_seed(1);
+        let names: Vec<&str> = credential.given_names().collect();
+        let encoded = credential.to_field::<F>().given_names;
+        assert_eq!(encoded.0[0], credential.primary_given_name().to_field());
+        for (i, name) in encoded.0.iter().enumerate() {
+            let expected = names.get(i).copied().unwrap_or("");
+            assert_eq!(*name, expected.to_field(), "given name {i}");
+        }
+    }
+
+    #[test]
+    fn packed_scalars_round_trip() {
+        let minus_one = arith::Scalar::ZERO - arith::Scalar::ONE;
+        for scalar in [arith::Scalar::ZERO, arith::Scalar::ONE, minus_one] {
+            let scalar = scalar.to_field();
+            let packed = scalar.pack::<F>();
+            assert_eq!(Scalar::unpack(&packed), Some(scalar));
+        }
+
+        // the modulus itself is not reduced
+        let modulus = Scalar(std::array::from_fn(arith::Scalar::modulus_bit_le));
+        assert_eq!(Scalar::unpack(&modulus.pack::<F>()), None);
+        let mut packed = arith::Scalar::ONE.to_field().pack::<F>();
+        packed.0[0] = F::from_canonical_u64(1 << PACKED_SCALAR_BITS);
+        assert_eq!(Scalar::unpack(&packed), None);
+    }
+}
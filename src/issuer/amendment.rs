@@ -0,0 +1,82 @@
+// Amended credentials, e.g. after a name change or the renewal of the
+// passport: the issuer signs the new credential, and a continuity record
+// linking it to its predecessor. Services which bound an account to the
+// credential ID of the predecessor then recognize the holder from a proof
+// exposing it, see circuit::inputs::Features::continuity.
+
+use plonky2::{field::goldilocks_field::GoldilocksField, hash::hash_types::RichField};
+
+use crate::{
+    core::credential::Credential,
+    encoding::{self, conversion::ToSchnorrField},
+    issuer::database,
+    merkle::hash,
+    schnorr::{
+        keys::{PublicKey, SecretKey},
+        transcript::point_to_vec_goldilocks,
+        SchnorrProof, Transcript,
+    },
+};
+
+/// What the issuer of the amended credential signs
+pub struct ContinuityContext {
+    issuer: PublicKey,
+    /// See merkle::hash::credential
+    predecessor: database::Hash,
+    successor: database::Hash,
+}
+
+pub type ContinuitySignature = SchnorrProof<ContinuityContext>;
+
+impl ContinuityContext {
+    pub fn new(predecessor: &Credential, successor: &Credential) -> Self {
+        Self {
+            issuer: successor.issuer(),
+            predecessor: hash::credential(predecessor),
+            successor: hash::credential(successor),
+        }
+    }
+}
+
+impl Transcript for ContinuityContext {
+    fn public_key(&self) -> &PublicKey {
+        &self.issuer
+    }
+    fn transcript(&self) -> Vec<GoldilocksField> {
+        let mut f_message = self.predecessor.0.to_vec();
+        f_message.extend_from_slice(&self.successor.0);
+        f_message.extend_from_slice(&point_to_vec_goldilocks(&self.issuer.0));
+        f_message
+    }
+}
+
+/// Kept by the holder along with the amended credential
+pub struct ContinuityRecord {
+    pub predecessor: Credential,
+    pub signature: ContinuitySignature,
+}
+
+impl ContinuityRecord {
+    /// Signed with the key of the issuer of successor
+    pub fn sign(issuer_sk: &SecretKey, predecessor: Credential, successor: &Credential) -> Self {
+        let signature =
+            ContinuitySignature::prove(issuer_sk, &ContinuityContext::new(&predecessor, successor));
+        Self {
+            predecessor,
+            signature,
+        }
+    }
+
+    /// Whether the issuer of successor linked it to the predecessor
+    pub fn verify(&self, successor: &Credential) -> bool {
+        self.signature
+            .verify(&ContinuityContext::new(&self.predecessor, successor))
+    }
+
+    pub fn to_field<F: RichField>(&self) -> encoding::Continuity<F, bool> {
+        encoding::Continuity {
+            predecessor: self.predecessor.to_field(),
+            signature: self.signature.to_field(),
+        }
+    }
+}
@@ -0,0 +1,352 @@
+// Issuer hierarchy: a pinned root key certifies intermediate issuer keys for a
+// validity period, and credentials are signed by the intermediates.
+
+use chrono::NaiveDate;
+use plonky2::{
+    field::{goldilocks_field::GoldilocksField, types::Field},
+    hash::hash_types::RichField,
+};
+use thiserror::Error;
+
+use crate::{
+    core::{credential::Credential, date::days_from_origin},
+    encoding::{
+        self,
+        conversion::{ToSchnorrField, ToSingleField},
+    },
+    schnorr::{
+        keys::{PublicKey, SecretKey},
+        signature::{self, Signature},
+        transcript::point_to_vec_goldilocks,
+        SchnorrProof, Transcript,
+    },
+};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Certificate chain is empty")]
+    EmptyChain,
+    #[error("Certificate {0} is not signed by its issuer")]
+    InvalidCertificate(usize),
+    #[error("Certificate {0} is not valid at this date")]
+    OutOfValidity(usize),
+    #[error("Credential issuer is not certified by the chain")]
+    UncertifiedIssuer,
+    #[error("Credential signature is invalid")]
+    InvalidSignature,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// What the issuer of a certificate signs
+pub struct CertificateContext {
+    issuer: PublicKey,
+    subject: PublicKey,
+    not_before: NaiveDate,
+    not_after: NaiveDate,
+}
+
+/// Signature of an issuer key by the key above it in the hierarchy
+pub type CertificateSignature = SchnorrProof<CertificateContext>;
+
+/// Certifies that `subject` may issue credentials between `not_before` and
+/// `not_after` (both included)
+pub struct Certificate {
+    subject: PublicKey,
+    not_before: NaiveDate,
+    not_after: NaiveDate,
+    signature: CertificateSignature,
+}
+
+/// Certificates ordered from the one signed by the root to the one certifying
+/// the credential issuer
+pub struct Chain(Vec<Certificate>);
+
+fn same_key(a: &PublicKey, b: &PublicKey) -> bool {
+    a.0.equals(b.0) == u64::MAX
+}
+
+impl CertificateContext {
+    pub fn new(
+        issuer: &PublicKey,
+        subject: &PublicKey,
+        not_before: NaiveDate,
+        not_after: NaiveDate,
+    ) -> Self {
+        Self {
+            issuer: issuer.clone(),
+            subject: subject.clone(),
+            not_before,
+            not_after,
+        }
+    }
+
+    pub fn subject(&self) -> &PublicKey {
+        &self.subject
+    }
+
+    pub fn not_before(&self) -> NaiveDate {
+        self.not_before
+    }
+
+    pub fn not_after(&self) -> NaiveDate {
+        self.not_after
+    }
+}
+
+impl Transcript for CertificateContext {
+    fn public_key(&self) -> &PublicKey {
+        &self.issuer
+    }
+    fn transcript(&self) -> Vec<GoldilocksField> {
+        let mut f_message = point_to_vec_goldilocks(&self.subject.0).to_vec();
+        f_message.push(GoldilocksField::from_canonical_u32(days_from_origin(
+            self.not_before,
+        )));
+        f_message.push(GoldilocksField::from_canonical_u32(days_from_origin(
+            self.not_after,
+        )));
+        f_message.extend_from_slice(&point_to_vec_goldilocks(&self.issuer.0));
+        f_message
+    }
+}
+
+impl Certificate {
+    pub fn issue(
+        issuer_sk: &SecretKey,
+        subject: &PublicKey,
+        not_before: NaiveDate,
+        not_after: NaiveDate,
+    ) -> Self {
+        let issuer = PublicKey::from(issuer_sk);
+        let ctx = CertificateContext::new(&issuer, subject, not_before, not_after);
+        Self {
+            subject: subject.clone(),
+            not_before,
+            not_after,
+            signature: CertificateSignature::prove(issuer_sk, &ctx),
+        }
+    }
+
+    pub fn subject(&self) -> &PublicKey {
+        &self.subject
+    }
+
+    pub fn not_before(&self) -> NaiveDate {
+        self.not_before
+    }
+
+    pub fn not_after(&self) -> NaiveDate {
+        self.not_after
+    }
+
+    pub fn signature(&self) -> &CertificateSignature {
+        &self.signature
+    }
+
+    /// Context the certificate was signed over, assuming it was issued by `issuer`
+    pub fn context(&self, issuer: &PublicKey) -> CertificateContext {
+        CertificateContext::new(issuer, &self.subject, self.not_before, self.not_after)
+    }
+
+    pub fn is_valid_at(&self, date: NaiveDate) -> bool {
+        self.not_before <= date && date <= self.not_after
+    }
+
+    /// Checks the signature of the certificate, not its validity period
+    pub fn verify(&self, issuer: &PublicKey) -> bool {
+        self.signature.verify(&self.context(issuer))
+    }
+
+    pub fn to_field<F: RichField>(&self) -> encoding::Certificate<F, bool> {
+        encoding::Certificate {
+            not_before: self.not_before.to_field(),
+            not_after: self.not_after.to_field(),
+            signature: self.signature.to_field(),
+        }
+    }
+}
+
+impl Chain {
+    pub fn new(certificates: Vec<Certificate>) -> Self {
+        Self(certificates)
+    }
+
+    pub fn certificates(&self) -> &[Certificate] {
+        &self.0
+    }
+
+    /// Key certified by the last certificate, which is expected to sign credentials
+    pub fn leaf(&self) -> Option<&PublicKey> {
+        self.0.last().map(Certificate::subject)
+    }
+
+    /// Checks that every certificate is signed by the key certified just
+    /// before it, starting from `root`, and is valid at `date`.
+    /// Returns the leaf key.
+    pub fn verify(&self, root: &PublicKey, date: NaiveDate) -> Result<&PublicKey> {
+        let mut issuer = root;
+        for (i, certificate) in self.0.iter().enumerate() {
+            if !certificate.verify(issuer) {
+                return Err(Error::InvalidCertificate(i));
+            }
+            if !certificate.is_valid_at(date) {
+                return Err(Error::OutOfValidity(i));
+            }
+            issuer = certificate.subject();
+        }
+        self.leaf().ok_or(Error::EmptyChain)
+    }
+
+    /// Checks the chain up to `root`, that the credential was issued by its
+    /// leaf, and the signature of the credential
+    pub fn verify_credential(
+        &self,
+        root: &PublicKey,
+        credential: &Credential,
+        signature: &Signature,
+        date: NaiveDate,
+    ) -> Result<()> {
+        let leaf = self.verify(root, date)?;
+        if !same_key(leaf, &credential.issuer()) {
+            return Err(Error::UncertifiedIssuer);
+        }
+        if !signature.verify(&signature::Context::new(credential)) {
+            return Err(Error::InvalidSignature);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn keypair(rng: &mut StdRng) -> (SecretKey, PublicKey) {
+        let sk = SecretKey::random(rng);
+        let pk = PublicKey::from(&sk);
+        (sk, pk)
+    }
+
+    /// root -> intermediate, and a credential signed by the intermediate
+    fn setup(seed: u64) -> (PublicKey, SecretKey, Chain, Credential, Signature) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (root_sk, root_pk) = keypair(&mut rng);
+        let (intermediate_sk, intermediate_pk) = keypair(&mut rng);
+        let certificate = Certificate::issue(
+            &root_sk,
+            &intermediate_pk,
+            date(2025, 1, 1),
+            date(2030, 1, 1),
+        );
+        let credential = Credential::random_with_issuer(&intermediate_sk, &mut rng);
+        let signature = credential.sign(&intermediate_sk);
+        (
+            root_pk,
+            root_sk,
+            Chain::new(vec![certificate]),
+            credential,
+            signature,
+        )
+    }
+
+    #[test]
+    fn chain_accepts_credential_from_certified_intermediate() {
+        let (root, _, chain, credential, signature) = setup(1);
+        assert_eq!(
+            chain.verify_credential(&root, &credential, &signature, date(2026, 1, 1)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn chain_rejects_other_root() {
+        let (_, _, chain, credential, signature) = setup(2);
+        let mut rng = StdRng::seed_from_u64(100);
+        let (_, other_root) = keypair(&mut rng);
+        assert_eq!(
+            chain.verify_credential(&other_root, &credential, &signature, date(2026, 1, 1)),
+            Err(Error::InvalidCertificate(0))
+        );
+    }
+
+    #[test]
+    fn chain_rejects_out_of_validity() {
+        let (root, _, chain, credential, signature) = setup(3);
+        assert_eq!(
+            chain.verify_credential(&root, &credential, &signature, date(2024, 12, 31)),
+            Err(Error::OutOfValidity(0))
+        );
+        assert_eq!(
+            chain.verify_credential(&root, &credential, &signature, date(2030, 1, 2)),
+            Err(Error::OutOfValidity(0))
+        );
+    }
+
+    #[test]
+    fn chain_rejects_uncertified_issuer() {
+        let (root, _, chain, _, _) = setup(4);
+        let mut rng = StdRng::seed_from_u64(101);
+        let (_, issuer_sk, credential) = Credential::random(&mut rng);
+        let signature = credential.sign(&issuer_sk);
+        assert_eq!(
+            chain.verify_credential(&root, &credential, &signature, date(2026, 1, 1)),
+            Err(Error::UncertifiedIssuer)
+        );
+    }
+
+    #[test]
+    fn chain_of_two_intermediates() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let (root_sk, root_pk) = keypair(&mut rng);
+        let (regional_sk, regional_pk) = keypair(&mut rng);
+        let (_, local_pk) = keypair(&mut rng);
+        let validity = (date(2025, 1, 1), date(2030, 1, 1));
+
+        let chain = Chain::new(vec![
+            Certificate::issue(&root_sk, &regional_pk, validity.0, validity.1),
+            Certificate::issue(&regional_sk, &local_pk, validity.0, validity.1),
+        ]);
+        let leaf = chain.verify(&root_pk, date(2026, 1, 1)).unwrap();
+        assert!(same_key(leaf, &local_pk));
+
+        // the root did not certify the local issuer directly
+        let skipping = Chain::new(vec![Certificate::issue(
+            &regional_sk,
+            &local_pk,
+            validity.0,
+            validity.1,
+        )]);
+        assert_eq!(
+            skipping.verify(&root_pk, date(2026, 1, 1)).err(),
+            Some(Error::InvalidCertificate(0))
+        );
+    }
+
+    #[test]
+    fn certificate_is_bound_to_its_validity() {
+        let (root, root_sk, _, _, _) = setup(6);
+        let mut rng = StdRng::seed_from_u64(102);
+        let (_, subject) = keypair(&mut rng);
+        let mut certificate =
+            Certificate::issue(&root_sk, &subject, date(2025, 1, 1), date(2026, 1, 1));
+        assert!(certificate.verify(&root));
+
+        certificate.not_after = date(2040, 1, 1);
+        assert!(!certificate.verify(&root));
+    }
+
+    #[test]
+    fn empty_chain_is_rejected() {
+        let (root, _, _, _, _) = setup(7);
+        assert_eq!(
+            Chain::new(vec![]).verify(&root, date(2026, 1, 1)).err(),
+            Some(Error::EmptyChain)
+        );
+    }
+}
@@ -1,9 +1,12 @@
+use chrono::NaiveDate;
+
 use crate::{circuit, core::credential::Credential, encoding, merkle};
 
 // TODO: for now, SIZE is very small for tests
 pub const SIZE: usize = 8;
 
 /// Database with a capacity SIZE, as a Merkle Tree
+#[derive(Clone)]
 pub struct Database(merkle::Tree<SIZE, circuit::F>);
 
 pub type Proof = merkle::Proof<SIZE, circuit::F>;
@@ -22,6 +25,23 @@ impl Database {
     pub fn proof(&self, credential_hash: &Hash) -> merkle::Result<Proof> {
         self.0.prove(credential_hash)
     }
+
+    /// Fills the first empty leaf with credential, which changes the root
+    pub fn add(&mut self, credential: &Credential) -> merkle::Result<()> {
+        self.0.add(credential)
+    }
+
+    /// Empties the leaf of credential, which changes the root
+    pub fn revoke(&mut self, credential: &Credential) -> merkle::Result<()> {
+        self.0.revoke(credential)
+    }
+
+    /// Empties the leaves of the credentials expired on today, freeing them
+    /// for new credentials, and returns them
+    pub fn remove_expired(&mut self, today: NaiveDate) -> Vec<Credential> {
+        self.0
+            .retain(|credential| *credential.expiration_date() >= today)
+    }
 }
 
 pub mod for_tests {
@@ -0,0 +1,445 @@
+// Issuer side of a credential in one call: checks the data read from the
+// passport, builds & signs the credential, and records it in the issuance
+// log, from which the Merkle tree of valid credentials is rebuilt.
+//
+// Issuances are made by an operator, against whom quotas may be enforced, see
+// issuer::quota.
+
+use chrono::{NaiveDate, Utc};
+use thiserror::Error;
+
+#[cfg(feature = "metrics")]
+use crate::metrics::Metrics;
+use crate::{
+    core::credential::{self, Credential, DocumentData},
+    issuer::{
+        amendment::ContinuityRecord,
+        database,
+        keys::IssuerIdentity,
+        quota::{self, Quotas},
+    },
+    merkle::hash,
+    schnorr::{keys::PublicKey, signature::Signature},
+};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Invalid document data: {0}")]
+    InvalidData(#[from] credential::Error),
+    #[error("Issuance log unavailable: {0}")]
+    Log(String),
+    #[error("The credential was not signed by this issuer")]
+    ForeignCredential,
+    #[error(transparent)]
+    Quota(#[from] quota::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Entry of the issuance log
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    /// See merkle::hash::credential
+    pub credential: database::Hash,
+    pub issued_on: NaiveDate,
+}
+
+pub trait IssuanceLog {
+    fn record(&mut self, record: Record) -> Result<()>;
+}
+
+/// Keeps the records in order of issuance, for tests to inspect what was
+/// issued; an issuer needs a durable log to answer audits
+#[derive(Default)]
+pub struct MemoryLog(pub Vec<Record>);
+
+impl IssuanceLog for MemoryLog {
+    fn record(&mut self, record: Record) -> Result<()> {
+        self.0.push(record);
+        Ok(())
+    }
+}
+
+pub struct IssuedCredential {
+    pub credential: Credential,
+    pub signature: Signature,
+}
+
+impl IssuedCredential {
+    /// Length of the credential, credential, then signature, as in the
+    /// entries of client::bundle
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let credential = self.credential.to_bytes();
+        let mut res = Vec::with_capacity(4 + credential.len() + 80);
+        res.extend_from_slice(&(credential.len() as u32).to_le_bytes());
+        res.extend_from_slice(&credential);
+        res.extend_from_slice(&self.signature.to_bytes());
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (len, bytes) = bytes.split_first_chunk::<4>()?;
+        let len = u32::from_le_bytes(*len) as usize;
+        if bytes.len() != len + 80 {
+            return None;
+        }
+        let (credential, signature) = bytes.split_at(len);
+        Some(Self {
+            credential: Credential::from_bytes(credential)?,
+            signature: Signature::from_bytes(signature).ok()?,
+        })
+    }
+}
+
+pub struct Issuer<L: IssuanceLog> {
+    identity: IssuerIdentity,
+    log: L,
+    quotas: Option<Quotas>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<Metrics>,
+}
+
+impl<L: IssuanceLog> Issuer<L> {
+    pub fn new(identity: IssuerIdentity, log: L) -> Self {
+        Self {
+            identity,
+            log,
+            quotas: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    /// Enforces quotas on every issuance
+    pub fn with_quotas(mut self, quotas: Quotas) -> Self {
+        self.quotas = Some(quotas);
+        self
+    }
+
+    /// Counts issuances in metrics, by outcome
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    #[cfg(feature = "metrics")]
+    fn count(&self, outcome: &'static str) {
+        if let Some(metrics) = &self.metrics {
+            metrics.issuance(outcome);
+        }
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        self.identity.public_key().clone()
+    }
+
+    pub fn log(&self) -> &L {
+        &self.log
+    }
+
+    /// Nothing is signed unless the data is valid and within the quotas of
+    /// operator, and nothing is returned unless it was logged
+    pub fn issue(&mut self, operator: &str, data: DocumentData) -> Result<IssuedCredential> {
+        let issued = Credential::new(data, self.public_key())
+            .map_err(Error::from)
+            .and_then(|credential| {
+                if let Some(quotas) = &mut self.quotas {
+                    quotas.consume(operator, &credential, Utc::now().date_naive())?;
+                }
+                self.sign_and_record(credential)
+            });
+        #[cfg(feature = "metrics")]
+        self.count(if issued.is_ok() { "issued" } else { "rejected" });
+        issued
+    }
+
+    fn sign_and_record(&mut self, credential: Credential) -> Result<IssuedCredential> {
+        let signature = self.identity.sign(&credential);
+        self.log.record(Record {
+            credential: hash::credential(&credential),
+            issued_on: Utc::now().date_naive(),
+        })?;
+        Ok(IssuedCredential {
+            credential,
+            signature,
+        })
+    }
+
+    /// Whether the credential was signed by this issuer
+    pub fn has_signed(&self, issued: &IssuedCredential) -> bool {
+        issued.credential.issuer().to_bytes() == self.public_key().to_bytes()
+            && issued.credential.check(&issued.signature)
+    }
+
+    /// Issues the credential of data amending predecessor, e.g. after a name
+    /// change or the renewal of the passport, and the record linking them
+    pub fn amend(
+        &mut self,
+        operator: &str,
+        predecessor: &IssuedCredential,
+        data: DocumentData,
+    ) -> Result<(IssuedCredential, ContinuityRecord)> {
+        if !self.has_signed(predecessor) {
+            return Err(Error::ForeignCredential);
+        }
+        let issued = self.issue(operator, data)?;
+        let record = ContinuityRecord::sign(
+            self.identity.secret_key(),
+            predecessor.credential.clone(),
+            &issued.credential,
+        );
+        Ok((issued, record))
+    }
+
+    /// Signs a credential of this issuer again for another holder key, e.g.
+    /// after a recovery (see issuer::recovery). Logged as issuance
+    pub fn rebind(
+        &mut self,
+        issued: &IssuedCredential,
+        holder: &PublicKey,
+    ) -> Result<IssuedCredential> {
+        if !self.has_signed(issued) {
+            return Err(Error::ForeignCredential);
+        }
+        let rebound = self.sign_and_record(issued.credential.with_holder(holder.clone()))?;
+        #[cfg(feature = "metrics")]
+        self.count("rebound");
+        Ok(rebound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        core::{
+            credential::{
+                normalize_name, AttributeChange, DocumentType, Gender, IssuingAuthority,
+                Nationality, PortraitHash,
+            },
+            mrz::Td1,
+        },
+        issuer::quota::{MemoryQuotaStore, QuotaPolicy},
+        schnorr::keys::SecretKey,
+    };
+
+    const OPERATOR: &str = "operator-1";
+
+    fn data() -> DocumentData {
+        let holder = SecretKey::random(&mut StdRng::seed_from_u64(2));
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        DocumentData {
+            document_type: DocumentType::Passport,
+            given_names: vec!["Marianne".to_string(), "Louise".to_string()],
+            family_name: "Dupont".to_string(),
+            birth_date: date(1990, 7, 14),
+            place_of_birth: "Paris".to_string(),
+            gender: Gender::F,
+            nationality: Nationality::FR,
+            issuing_authority: IssuingAuthority {
+                country: Nationality::FR,
+                code: 75,
+            },
+            passport_number: "12AB34567".to_string(),
+            date_of_issue: date(2020, 1, 2),
+            expiration_date: date(2030, 1, 1),
+            portrait_hash: PortraitHash::of(b"portrait read from the chip"),
+            holder: PublicKey::from(&holder),
+        }
+    }
+
+    #[test]
+    fn issue_signs_and_logs_valid_data() {
+        let issuer_sk = SecretKey::random(&mut StdRng::seed_from_u64(1));
+        let mut issuer = Issuer::new(IssuerIdentity::new(issuer_sk), MemoryLog::default());
+
+        let issued = issuer.issue(OPERATOR, data()).unwrap();
+        assert!(issued.credential.check(&issued.signature));
+        assert!(issued.credential.check_mrz());
+        assert_eq!(
+            issuer.log().0[0].credential,
+            hash::credential(&issued.credential)
+        );
+        let decoded = IssuedCredential::from_bytes(&issued.to_bytes()).unwrap();
+        assert!(decoded.credential == issued.credential);
+
+        let invalid = [
+            (
+                DocumentData {
+                    family_name: "Дюпон".to_string(),
+                    ..data()
+                },
+                credential::Error::NotAscii("family name"),
+            ),
+            (
+                DocumentData {
+                    passport_number: "12AB3456X".to_string(),
+                    ..data()
+                },
+                credential::Error::PassportNumber,
+            ),
+            (
+                DocumentData {
+                    given_names: vec![],
+                    ..data()
+                },
+                credential::Error::GivenNames(0),
+            ),
+            (
+                DocumentData {
+                    expiration_date: NaiveDate::from_ymd_opt(2019, 1, 1).unwrap(),
+                    ..data()
+                },
+                credential::Error::Dates("expires before its issue"),
+            ),
+        ];
+        for (data, err) in invalid {
+            assert_eq!(
+                issuer.issue(OPERATOR, data).err(),
+                Some(Error::InvalidData(err))
+            );
+        }
+        assert_eq!(issuer.log().0.len(), 1);
+    }
+
+    #[test]
+    fn issue_normalizes_names() {
+        let issuer_sk = SecretKey::random(&mut StdRng::seed_from_u64(1));
+        let mut issuer = Issuer::new(IssuerIdentity::new(issuer_sk), MemoryLog::default());
+        // the same names, precomposed & typed or decomposed & read by OCR
+        let typed = DocumentData {
+            given_names: vec!["Marianne".to_string(), "Hélène".to_string()],
+            family_name: "Dupré".to_string(),
+            ..data()
+        };
+        let scanned = DocumentData {
+            given_names: vec![" MARIANNE".to_string(), "HE\u{301}LE\u{300}NE ".to_string()],
+            family_name: "dupre\u{301}".to_string(),
+            ..data()
+        };
+        let typed = issuer.issue(OPERATOR, typed).unwrap();
+        let scanned = issuer.issue(OPERATOR, scanned).unwrap();
+        assert_eq!(typed.credential.as_bytes(), scanned.credential.as_bytes());
+        assert_eq!(normalize_name("Saint  Étienne"), "SAINT ETIENNE");
+    }
+
+    #[test]
+    fn amend_links_the_new_credential_to_its_predecessor() {
+        let issuer_sk = SecretKey::random(&mut StdRng::seed_from_u64(1));
+        let mut issuer = Issuer::new(IssuerIdentity::new(issuer_sk), MemoryLog::default());
+        let predecessor = issuer.issue(OPERATOR, data()).unwrap();
+
+        let married = DocumentData {
+            family_name: "Martin".to_string(),
+            ..data()
+        };
+        let (amended, record) = issuer.amend(OPERATOR, &predecessor, married).unwrap();
+        assert!(record.verify(&amended.credential));
+        assert!(record.predecessor == predecessor.credential);
+        assert!(!record.verify(&predecessor.credential));
+        assert_eq!(
+            amended.credential.diff(&predecessor.credential),
+            vec![AttributeChange::FamilyName]
+        );
+        assert!(!amended.credential.is_reissue_of(&predecessor.credential));
+
+        let renewed = DocumentData {
+            passport_number: "34CD56789".to_string(),
+            date_of_issue: NaiveDate::from_ymd_opt(2029, 6, 1).unwrap(),
+            expiration_date: NaiveDate::from_ymd_opt(2039, 6, 1).unwrap(),
+            ..data()
+        };
+        let renewed = issuer.issue(OPERATOR, renewed).unwrap().credential;
+        assert_eq!(
+            renewed.diff(&predecessor.credential),
+            vec![
+                AttributeChange::Document,
+                AttributeChange::MrzCheckDigits,
+                AttributeChange::DateOfIssue,
+                AttributeChange::ExpirationDate,
+            ]
+        );
+        assert!(renewed.is_reissue_of(&predecessor.credential));
+        assert!(!predecessor.credential.is_reissue_of(&renewed));
+        assert!(predecessor
+            .credential
+            .diff(&predecessor.credential)
+            .is_empty());
+
+        let (_, other_issuer_sk, foreign) = Credential::from_seed(5);
+        let foreign = IssuedCredential {
+            signature: foreign.sign(&other_issuer_sk),
+            credential: foreign,
+        };
+        assert_eq!(
+            issuer.amend(OPERATOR, &foreign, data()).err(),
+            Some(Error::ForeignCredential)
+        );
+    }
+
+    #[test]
+    fn issue_enforces_the_quotas_of_the_operator() {
+        let issuer_sk = SecretKey::random(&mut StdRng::seed_from_u64(1));
+        let policy = QuotaPolicy::new(5, 1, 1).unwrap();
+        let mut issuer = Issuer::new(IssuerIdentity::new(issuer_sk), MemoryLog::default())
+            .with_quotas(Quotas::new(policy, MemoryQuotaStore::default()));
+
+        issuer.issue(OPERATOR, data()).unwrap();
+        assert_eq!(
+            issuer.issue("operator-2", data()).err(),
+            Some(Error::Quota(quota::Error::Document))
+        );
+        assert_eq!(issuer.log().0.len(), 1);
+    }
+
+    #[test]
+    fn issue_reads_identity_cards_from_their_mrz() {
+        let issuer_sk = SecretKey::random(&mut StdRng::seed_from_u64(1));
+        let mut issuer = Issuer::new(IssuerIdentity::new(issuer_sk), MemoryLog::default());
+        let mrz = "IDFRAX4RTBPFW46<<<<<<<<<<<<<<<\n\
+                   9007149F3001019FRA<<<<<<<<<<<8\n\
+                   DUPONT<<MARIANNE<LOUISE<<<<<<<";
+        let td1 = Td1::parse(mrz, NaiveDate::from_ymd_opt(2026, 1, 1).unwrap()).unwrap();
+        let passport = data();
+        let card = DocumentData::identity_card(
+            td1,
+            passport.place_of_birth,
+            passport.issuing_authority,
+            NaiveDate::from_ymd_opt(2021, 3, 4).unwrap(),
+            passport.portrait_hash,
+            passport.holder,
+        );
+
+        let issued = issuer.issue(OPERATOR, card).unwrap();
+        assert_eq!(
+            issued.credential.document_type(),
+            DocumentType::IdentityCard
+        );
+        assert!(issued.credential.check(&issued.signature));
+        assert!(issued.credential.check_mrz());
+        let decoded = Credential::from_bytes(&issued.credential.to_bytes()).unwrap();
+        assert_eq!(decoded.document_type(), DocumentType::IdentityCard);
+
+        // the same number is not a passport number, nor the other way around
+        let passport_with_card_number = DocumentData {
+            passport_number: "X4RTBPFW4".to_string(),
+            ..data()
+        };
+        assert_eq!(
+            issuer.issue(OPERATOR, passport_with_card_number).err(),
+            Some(Error::InvalidData(credential::Error::PassportNumber))
+        );
+        let card_with_lowercase_number = DocumentData {
+            document_type: DocumentType::IdentityCard,
+            passport_number: "x4rtbpfw4".to_string(),
+            ..data()
+        };
+        assert_eq!(
+            issuer.issue(OPERATOR, card_with_lowercase_number).err(),
+            Some(Error::InvalidData(credential::Error::IdentityCardNumber))
+        );
+    }
+}
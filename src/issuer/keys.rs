@@ -1,15 +1,48 @@
-// FIXME: Obviously this is not a secure way to keep the keys, this should only be used for the PoC
+// Keys of an issuer, passed explicitly wherever the issuer signs or is
+// expected, so that one process can serve several issuers.
 
-use rand::{rngs::StdRng, SeedableRng};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
-use crate::schnorr::keys::{PublicKey, SecretKey};
+use crate::{
+    core::credential::Credential,
+    schnorr::{
+        keys::{PublicKey, SecretKey},
+        signature::Signature,
+    },
+};
 
-// FIXME: TOTALLY INSECURE AND INEFFICIENT
-pub fn secret() -> SecretKey {
-    let mut rng = StdRng::seed_from_u64(42);
-    SecretKey::random(&mut rng)
+pub struct IssuerIdentity {
+    secret_key: SecretKey,
+    public_key: PublicKey,
 }
 
-pub fn public() -> PublicKey {
-    PublicKey::from(&secret())
+impl IssuerIdentity {
+    pub fn new(secret_key: SecretKey) -> Self {
+        let public_key = PublicKey::from(&secret_key);
+        Self {
+            secret_key,
+            public_key,
+        }
+    }
+
+    pub fn random(rng: &mut impl Rng) -> Self {
+        Self::new(SecretKey::random(rng))
+    }
+
+    // FIXME: TOTALLY INSECURE, the fixed issuer of the PoC
+    pub fn for_tests() -> Self {
+        Self::random(&mut StdRng::seed_from_u64(42))
+    }
+
+    pub fn secret_key(&self) -> &SecretKey {
+        &self.secret_key
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    pub fn sign(&self, credential: &Credential) -> Signature {
+        credential.sign(&self.secret_key)
+    }
 }
@@ -1,3 +1,13 @@
+pub mod amendment;
+pub mod ca;
 pub mod database;
+pub mod issuance;
 pub mod keys;
 pub mod pseudonym;
+pub mod quota;
+pub mod rate_limit;
+pub mod recovery;
+pub mod revocation;
+pub mod timestamp;
+
+pub use issuance::{IssuedCredential, Issuer};
@@ -1,17 +1,25 @@
 use plonky2::field::goldilocks_field::GoldilocksField;
 
 use crate::{
+    core::credential::{normalize_name, Credential},
     encoding::{
         self,
-        conversion::{ToPointField, ToStringField},
-        LEN_POINT, LEN_STRING,
+        conversion::{self, ToField, ToPointField, ToScalarField, ToStringField, ToVarStringField},
+        LEN_CREDENTIAL, LEN_HASH, LEN_PASSPORT_NUMBER, LEN_POINT, LEN_SCALAR_LIMBS, LEN_STRING,
+        LEN_VAR_STRING,
     },
     merkle::hash,
-    schnorr::keys::PublicKey,
+    schnorr::keys::{PublicKey, SecretKey},
 };
 
 pub type Pseudonym = encoding::Pseudonym<GoldilocksField>;
+/// Published by the issuer along with its key, see credential_id and
+/// document_commitment, or drawn by a verifier, see name_hash
+pub type Salt = encoding::Hash<GoldilocksField>;
 
+/// # Panics
+///
+/// If service is longer than MAX_STRING_BYTES, see ToStringField::try_to_field
 pub fn hash_from_service(service: &str, public_key: &PublicKey) -> Pseudonym {
     let service = service.to_string().to_field();
     let public_key = public_key.0.to_field();
@@ -22,12 +30,89 @@ pub fn hash_from_service(service: &str, public_key: &PublicKey) -> Pseudonym {
     hash::poseidon(&message)
 }
 
+/// Pseudonym of the holder for a relying party: stable for a given domain,
+/// unlinkable across domains, and only computable with the holder secret key
+///
+/// # Panics
+///
+/// If domain is longer than MAX_STRING_BYTES, see ToStringField::try_to_field
+pub fn domain_nym(domain: &str, sk: &SecretKey) -> Pseudonym {
+    let secret: [GoldilocksField; LEN_SCALAR_LIMBS] = sk.0.to_field().to_field();
+    let domain = domain.to_string().to_field();
+    let mut message = Vec::with_capacity(LEN_SCALAR_LIMBS + LEN_STRING);
+    message.extend_from_slice(&secret);
+    message.extend_from_slice(&domain.0);
+    hash::poseidon(&message)
+}
+
+/// Nullifier of the credential for a service: the same at every presentation
+/// of the credential to the service, so that the service detects reuse, but
+/// unlinkable across services & credentials, and only computable with the
+/// holder secret key, which the credential commits to with its holder key
+///
+/// # Panics
+///
+/// If service is longer than MAX_STRING_BYTES, see ToStringField::try_to_field
+pub fn credential_nullifier(credential: &Credential, sk: &SecretKey, service: &str) -> Pseudonym {
+    let secret: [GoldilocksField; LEN_SCALAR_LIMBS] = sk.0.to_field().to_field();
+    let service = service.to_string().to_field();
+    let mut message = Vec::with_capacity(LEN_SCALAR_LIMBS + LEN_HASH + LEN_STRING);
+    message.extend_from_slice(&secret);
+    message.extend_from_slice(&hash::credential::<GoldilocksField>(credential).0);
+    message.extend_from_slice(&service.0);
+    hash::poseidon(&message)
+}
+
+/// Identifier of the credential, the same at every presentation of it, e.g.
+/// to bind it to an account again. Rotating the salt unlinks every ID
+pub fn credential_id(credential: &Credential, salt: &Salt) -> Pseudonym {
+    let encoded: [GoldilocksField; LEN_CREDENTIAL] = (&credential.to_field()).into();
+    let mut message = Vec::with_capacity(LEN_CREDENTIAL + LEN_HASH);
+    message.extend_from_slice(&encoded);
+    message.extend_from_slice(&salt.0);
+    hash::poseidon(&message)
+}
+
+/// Hash of the family name a customer declared to a verifier, which the holder
+/// proves their credential matches, see circuit::inputs::Features::name_hash.
+/// The verifier only sends the salt to the holder, so that the public inputs
+/// can't be tested against a list of names. The name is normalized as the
+/// signed one is, and rejected if it's too long to be in a credential
+pub fn name_hash(family_name: &str, salt: &Salt) -> conversion::Result<Pseudonym> {
+    let name: encoding::VarString<GoldilocksField> =
+        normalize_name(family_name).as_str().try_to_field()?;
+    let name: [GoldilocksField; LEN_VAR_STRING] = name.into();
+    let mut message = Vec::with_capacity(LEN_VAR_STRING + LEN_HASH);
+    message.extend_from_slice(&name);
+    message.extend_from_slice(&salt.0);
+    Ok(hash::poseidon(&message))
+}
+
+/// Commitment of the travel document of the credential, the same for every
+/// credential issued from it while the issuer keeps the salt, so that a relying
+/// party can tell accounts opened with the same document. The issuer rotates
+/// the salt every epoch, which unlinks the commitments across epochs
+pub fn document_commitment(credential: &Credential, salt: &Salt) -> Pseudonym {
+    let encoded = credential.to_field::<GoldilocksField>();
+    let mut message = Vec::with_capacity(1 + LEN_PASSPORT_NUMBER + LEN_HASH);
+    message.push(encoded.document_type);
+    message.extend_from_slice(&encoded.passport_number.0);
+    message.extend_from_slice(&salt.0);
+    hash::poseidon(&message)
+}
+
 #[cfg(test)]
 mod tests {
     use rand::{rngs::StdRng, SeedableRng};
 
-    use super::hash_from_service;
-    use crate::schnorr::keys::SecretKey;
+    use plonky2::field::{goldilocks_field::GoldilocksField, types::Field};
+
+    use super::{credential_nullifier, domain_nym, hash_from_service, name_hash};
+    use crate::{
+        core::credential::Credential,
+        encoding::{Hash, LEN_HASH},
+        schnorr::keys::SecretKey,
+    };
 
     #[test]
     fn pseudonym_changes_with_service_or_public_key() {
@@ -44,4 +129,42 @@ mod tests {
         assert_ne!(h1.0, h2.0, "pseudonym should depend on the service");
         assert_ne!(h1.0, h3.0, "pseudonym should depend on the public key");
     }
+
+    #[test]
+    fn domain_nym_changes_with_domain_or_secret() {
+        let mut rng = StdRng::seed_from_u64(124);
+        let sk1 = SecretKey::random(&mut rng);
+        let sk2 = SecretKey::random(&mut rng);
+
+        let h1 = domain_nym("bank-a.example", &sk1);
+        let h2 = domain_nym("bank-b.example", &sk1);
+        let h3 = domain_nym("bank-a.example", &sk2);
+
+        assert_eq!(h1, domain_nym("bank-a.example", &sk1));
+        assert_ne!(h1, h2, "nym should depend on the domain");
+        assert_ne!(h1, h3, "nym should depend on the secret key");
+    }
+
+    #[test]
+    fn credential_nullifier_changes_with_credential_or_service() {
+        let (sk, _, credential) = Credential::from_seed(1);
+        let (_, _, other) = Credential::from_seed(2);
+
+        let n1 = credential_nullifier(&credential, &sk, "bank-a.example");
+        let n2 = credential_nullifier(&credential, &sk, "bank-b.example");
+        let n3 = credential_nullifier(&other, &sk, "bank-a.example");
+
+        assert_eq!(n1, credential_nullifier(&credential, &sk, "bank-a.example"));
+        assert_ne!(n1, n2, "nullifier should depend on the service");
+        assert_ne!(n1, n3, "nullifier should depend on the credential");
+    }
+
+    #[test]
+    fn name_hash_normalizes_the_declared_name() {
+        let salt = Hash([GoldilocksField::from_canonical_u64(9); LEN_HASH]);
+        let expected = name_hash("DUPRE", &salt).unwrap();
+        assert_eq!(name_hash("Dupré", &salt).unwrap(), expected);
+        assert_eq!(name_hash("  dupre ", &salt).unwrap(), expected);
+        assert!(name_hash(&"A".repeat(1000), &salt).is_err());
+    }
 }
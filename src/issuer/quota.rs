@@ -0,0 +1,185 @@
+// Issuance quotas, so that a compromised operator account can't silently
+// mass-issue credentials: each operator, and each travel document, may only
+// be issued a limited number of credentials per window of days. Counts are
+// kept in a QuotaStore, which services back with their database.
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use thiserror::Error;
+
+use crate::{
+    core::{credential::Credential, date::days_from_origin},
+    encoding::LEN_PASSPORT_NUMBER,
+    issuer::database,
+    merkle::hash,
+};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Operator {0} reached their issuance quota")]
+    Operator(String),
+    #[error("The document reached its issuance quota")]
+    Document,
+    #[error("Quota store unavailable: {0}")]
+    Store(String),
+    #[error("A quota window must last at least one day")]
+    EmptyWindow,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuotaPolicy {
+    /// Credentials an operator may issue per window
+    per_operator: u32,
+    /// Credentials a travel document may be issued per window
+    per_document: u32,
+    /// Length of a window, in days
+    window_days: u32,
+}
+
+impl QuotaPolicy {
+    pub fn new(per_operator: u32, per_document: u32, window_days: u32) -> Result<Self> {
+        if window_days == 0 {
+            return Err(Error::EmptyWindow);
+        }
+        Ok(Self {
+            per_operator,
+            per_document,
+            window_days,
+        })
+    }
+
+    pub fn per_operator(&self) -> u32 {
+        self.per_operator
+    }
+
+    pub fn per_document(&self) -> u32 {
+        self.per_document
+    }
+
+    pub fn window_days(&self) -> u32 {
+        self.window_days
+    }
+
+    /// Window containing date, counted from the origin of core::date
+    pub fn window(&self, date: NaiveDate) -> u32 {
+        days_from_origin(date) / self.window_days
+    }
+}
+
+/// What issuances are counted against
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Subject {
+    Operator(String),
+    /// See document_key
+    Document(database::Hash),
+}
+
+/// Hash of the type & number of the document of the credential, so that
+/// stores don't keep document numbers
+pub fn document_key(credential: &Credential) -> database::Hash {
+    let encoded = credential.to_field::<GoldilocksField>();
+    let mut message = Vec::with_capacity(1 + LEN_PASSPORT_NUMBER);
+    message.push(encoded.document_type);
+    message.extend_from_slice(&encoded.passport_number.0);
+    hash::poseidon(&message)
+}
+
+pub trait QuotaStore {
+    /// Issuances of subject recorded in window
+    fn count(&self, subject: &Subject, window: u32) -> Result<u32>;
+    fn increment(&mut self, subject: &Subject, window: u32) -> Result<()>;
+}
+
+/// Counts per subject & window, reset when the issuer restarts, which gives
+/// every operator a fresh quota: only for tests
+#[derive(Default)]
+pub struct MemoryQuotaStore(HashMap<(Subject, u32), u32>);
+
+impl QuotaStore for MemoryQuotaStore {
+    fn count(&self, subject: &Subject, window: u32) -> Result<u32> {
+        Ok(self.0.get(&(subject.clone(), window)).copied().unwrap_or(0))
+    }
+
+    fn increment(&mut self, subject: &Subject, window: u32) -> Result<()> {
+        *self.0.entry((subject.clone(), window)).or_default() += 1;
+        Ok(())
+    }
+}
+
+pub struct Quotas {
+    policy: QuotaPolicy,
+    store: Box<dyn QuotaStore>,
+}
+
+impl Quotas {
+    pub fn new(policy: QuotaPolicy, store: impl QuotaStore + 'static) -> Self {
+        Self {
+            policy,
+            store: Box::new(store),
+        }
+    }
+
+    pub fn policy(&self) -> &QuotaPolicy {
+        &self.policy
+    }
+
+    /// Counts the issuance of credential by operator on today, unless it
+    /// exceeds a quota, in which case nothing is counted
+    pub fn consume(
+        &mut self,
+        operator: &str,
+        credential: &Credential,
+        today: NaiveDate,
+    ) -> Result<()> {
+        let window = self.policy.window(today);
+        let by = Subject::Operator(operator.to_string());
+        let document = Subject::Document(document_key(credential));
+        if self.store.count(&by, window)? >= self.policy.per_operator {
+            return Err(Error::Operator(operator.to_string()));
+        }
+        if self.store.count(&document, window)? >= self.policy.per_document {
+            return Err(Error::Document);
+        }
+        self.store.increment(&by, window)?;
+        self.store.increment(&document, window)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consume_stops_at_the_quotas_of_the_window() {
+        let policy = QuotaPolicy::new(2, 1, 1).unwrap();
+        let mut quotas = Quotas::new(policy, MemoryQuotaStore::default());
+        let today = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let credentials: Vec<Credential> = (0..4).map(|i| Credential::from_seed(i).2).collect();
+
+        quotas.consume("alice", &credentials[0], today).unwrap();
+        assert_eq!(
+            quotas.consume("bob", &credentials[0], today),
+            Err(Error::Document)
+        );
+        quotas.consume("alice", &credentials[1], today).unwrap();
+        assert_eq!(
+            quotas.consume("alice", &credentials[2], today),
+            Err(Error::Operator("alice".to_string()))
+        );
+        // rejected issuances are not counted
+        quotas.consume("bob", &credentials[2], today).unwrap();
+        quotas.consume("bob", &credentials[3], today).unwrap();
+
+        let tomorrow = today.succ_opt().unwrap();
+        quotas.consume("alice", &credentials[0], tomorrow).unwrap();
+    }
+
+    #[test]
+    fn policy_rejects_an_empty_window() {
+        assert_eq!(QuotaPolicy::new(2, 1, 0), Err(Error::EmptyWindow));
+    }
+}
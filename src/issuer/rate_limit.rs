@@ -0,0 +1,108 @@
+// k-show credentials: a credential may only be presented k times per epoch.
+// Each presentation reveals nullifier = Hash(secret, epoch, counter) with
+// counter < k, so that a verifier seeing the same nullifier twice knows the
+// credential was shown more than k times in that epoch.
+
+use chrono::NaiveDate;
+use plonky2::field::{goldilocks_field::GoldilocksField, types::Field};
+use thiserror::Error;
+
+use crate::{
+    core::date::days_from_origin,
+    encoding::{
+        self,
+        conversion::{ToField, ToScalarField},
+        LEN_SCALAR_LIMBS,
+    },
+    merkle::hash,
+    schnorr::keys::SecretKey,
+};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("At least one presentation must be allowed per epoch")]
+    NoPresentation,
+    #[error("An epoch must last at least one day")]
+    EmptyEpoch,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+pub type Nullifier = encoding::Hash<GoldilocksField>;
+
+/// Issuer parameters of k-show credentials
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Number of presentations allowed per epoch
+    k: u32,
+    /// Length of an epoch, in days
+    epoch_days: u32,
+}
+
+impl RateLimit {
+    pub fn new(k: u32, epoch_days: u32) -> Result<Self> {
+        if k == 0 {
+            return Err(Error::NoPresentation);
+        }
+        if epoch_days == 0 {
+            return Err(Error::EmptyEpoch);
+        }
+        Ok(Self { k, epoch_days })
+    }
+
+    pub fn k(&self) -> u32 {
+        self.k
+    }
+
+    pub fn epoch_days(&self) -> u32 {
+        self.epoch_days
+    }
+
+    /// Epoch containing date, counted from the origin of core::date
+    pub fn epoch(&self, date: NaiveDate) -> u32 {
+        days_from_origin(date) / self.epoch_days
+    }
+}
+
+/// Mirrors circuit::nym::CircuitBuilderNym::nullifier
+pub fn nullifier(sk: &SecretKey, epoch: u32, counter: u32) -> Nullifier {
+    let secret: [GoldilocksField; LEN_SCALAR_LIMBS] = sk.0.to_field().to_field();
+    let mut message = Vec::with_capacity(LEN_SCALAR_LIMBS + 2);
+    message.extend_from_slice(&secret);
+    message.push(GoldilocksField::from_canonical_u32(epoch));
+    message.push(GoldilocksField::from_canonical_u32(counter));
+    hash::poseidon(&message)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn nullifier_changes_with_epoch_and_counter() {
+        let sk = SecretKey::random(&mut StdRng::seed_from_u64(1));
+        let n = nullifier(&sk, 10, 0);
+        assert_eq!(n, nullifier(&sk, 10, 0));
+        assert_ne!(n, nullifier(&sk, 11, 0));
+        assert_ne!(n, nullifier(&sk, 10, 1));
+    }
+
+    #[test]
+    fn epoch_groups_days() {
+        let limit = RateLimit::new(3, 7).unwrap();
+        let date = |d| NaiveDate::from_ymd_opt(1900, 1, d).unwrap();
+        assert_eq!(limit.epoch(date(1)), 0);
+        assert_eq!(limit.epoch(date(7)), 0);
+        assert_eq!(limit.epoch(date(8)), 1);
+        assert_eq!(limit.epoch(date(14)), 1);
+        assert_eq!(limit.epoch(date(15)), 2);
+    }
+
+    #[test]
+    fn new_rejects_empty_limits() {
+        assert_eq!(RateLimit::new(0, 7), Err(Error::NoPresentation));
+        assert_eq!(RateLimit::new(3, 0), Err(Error::EmptyEpoch));
+    }
+}
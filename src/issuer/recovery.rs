@@ -0,0 +1,489 @@
+// Social recovery of a lost holder key. At issuance, the holder names
+// guardians, `threshold` of which must approve a recovery. To recover, the
+// holder draws a new key, gets a challenge from the issuer, collects the
+// approvals of the guardians for it, and the issuer signs every credential of
+// the old key again for the new one. See client::recovery for the holder side.
+//
+// The credentials of the old key are revoked as the new ones replace them in
+// the Merkle tree of valid credentials, so that the lost key proves nothing
+// anymore.
+
+use std::collections::HashMap;
+
+use plonky2::field::goldilocks_field::GoldilocksField;
+use rand::Rng;
+use thiserror::Error;
+
+use crate::{
+    encoding::{self, conversion::ToStringField},
+    issuer::{
+        issuance::{self, IssuanceLog, IssuedCredential, Issuer},
+        revocation::{self, Revocations, RootPublisher},
+    },
+    merkle::hash,
+    schnorr::{
+        keys::{PublicKey, SecretKey},
+        transcript::{message_to_goldilocks, point_to_vec_goldilocks},
+        SchnorrProof, Transcript,
+    },
+};
+
+// Only used natively, so the transcripts keep their tag
+const APPROVAL_TAG: &[u8] = b"ZKYC_RECOVERY_APPROVAL_V1";
+const POSSESSION_TAG: &[u8] = b"ZKYC_RECOVERY_POSSESSION_V1";
+
+pub const LEN_APPROVAL: usize = 40 + 80;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Guardians must be distinct, and the threshold between 1 and their number")]
+    InvalidPolicy,
+    #[error("No recovery policy is registered for this holder key")]
+    UnknownHolder,
+    #[error("No recovery of this holder key was started")]
+    NotStarted,
+    #[error("Approval {0} is not from a guardian, or not for this recovery")]
+    InvalidApproval(usize),
+    #[error("{0} guardian approvals are needed, got {1}")]
+    NotEnoughApprovals(usize, usize),
+    #[error("The new holder key did not sign the recovery")]
+    InvalidPossession,
+    #[error("Credential {0} is not a credential of the issuer for the old key")]
+    ForeignCredential(usize),
+    #[error("Credential {0} is not in the tree of valid credentials")]
+    NotValid(usize),
+    #[error("Re-issuance failed: {0}")]
+    Issuance(#[from] issuance::Error),
+    #[error("Replacing the credentials failed: {0}")]
+    Revocation(#[from] revocation::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+fn same_key(a: &PublicKey, b: &PublicKey) -> bool {
+    a.to_bytes() == b.to_bytes()
+}
+
+/// Guardians of a holder key, threshold of which approve its recovery
+#[derive(Clone, Debug)]
+pub struct RecoveryPolicy {
+    guardians: Vec<PublicKey>,
+    threshold: u8,
+}
+
+impl RecoveryPolicy {
+    pub fn new(guardians: Vec<PublicKey>, threshold: u8) -> Result<Self> {
+        let distinct = guardians
+            .iter()
+            .enumerate()
+            .all(|(i, guardian)| !guardians[..i].iter().any(|other| same_key(other, guardian)));
+        if !distinct || threshold == 0 || threshold as usize > guardians.len() {
+            return Err(Error::InvalidPolicy);
+        }
+        Ok(Self {
+            guardians,
+            threshold,
+        })
+    }
+
+    pub fn guardians(&self) -> &[PublicKey] {
+        &self.guardians
+    }
+
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    fn is_guardian(&self, key: &PublicKey) -> bool {
+        self.guardians
+            .iter()
+            .any(|guardian| same_key(guardian, key))
+    }
+}
+
+/// What the guardians and the new key sign: the recovery of old_holder to
+/// new_holder, for the challenge of the issuer
+pub struct RecoveryContext {
+    tag: &'static [u8],
+    signer: PublicKey,
+    request: ApprovalRequest,
+}
+
+/// Signature of a guardian approving a recovery, or of the new key
+pub type RecoverySignature = SchnorrProof<RecoveryContext>;
+
+impl RecoveryContext {
+    pub fn approval(guardian: &PublicKey, request: &ApprovalRequest) -> Self {
+        Self {
+            tag: APPROVAL_TAG,
+            signer: guardian.clone(),
+            request: request.clone(),
+        }
+    }
+
+    /// Signed by the new key, so that nobody recovers to a key they don't own
+    pub fn possession(request: &ApprovalRequest) -> Self {
+        Self {
+            tag: POSSESSION_TAG,
+            signer: request.new_holder.clone(),
+            request: request.clone(),
+        }
+    }
+}
+
+impl Transcript for RecoveryContext {
+    fn public_key(&self) -> &PublicKey {
+        &self.signer
+    }
+    fn transcript(&self) -> Vec<GoldilocksField> {
+        let mut f_message = message_to_goldilocks(self.tag);
+        f_message.extend_from_slice(&point_to_vec_goldilocks(&self.request.old_holder.0));
+        f_message.extend_from_slice(&point_to_vec_goldilocks(&self.request.new_holder.0));
+        let challenge: encoding::String<GoldilocksField> = self.request.challenge.to_field();
+        f_message.extend_from_slice(&challenge.0);
+        f_message.extend_from_slice(&point_to_vec_goldilocks(&self.signer.0));
+        f_message
+    }
+}
+
+/// Shown to the guardians by the holder, who check out of band that the
+/// holder asks for it before approving
+#[derive(Clone, Debug)]
+pub struct ApprovalRequest {
+    pub old_holder: PublicKey,
+    pub new_holder: PublicKey,
+    /// See RecoveryDesk::start
+    pub challenge: String,
+}
+
+impl ApprovalRequest {
+    pub fn approve(&self, guardian_sk: &SecretKey) -> Approval {
+        let guardian = PublicKey::from(guardian_sk);
+        let signature =
+            RecoverySignature::prove(guardian_sk, &RecoveryContext::approval(&guardian, self));
+        Approval {
+            guardian,
+            signature,
+        }
+    }
+}
+
+/// Sent by a guardian to the holder
+pub struct Approval {
+    guardian: PublicKey,
+    signature: RecoverySignature,
+}
+
+impl Approval {
+    pub fn guardian(&self) -> &PublicKey {
+        &self.guardian
+    }
+
+    pub fn verify(&self, request: &ApprovalRequest) -> bool {
+        self.signature
+            .verify(&RecoveryContext::approval(&self.guardian, request))
+    }
+
+    /// Guardian key, then signature
+    pub fn to_bytes(&self) -> [u8; LEN_APPROVAL] {
+        let mut res = [0; LEN_APPROVAL];
+        res[..40].copy_from_slice(&self.guardian.to_bytes());
+        res[40..].copy_from_slice(&self.signature.to_bytes());
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != LEN_APPROVAL {
+            return None;
+        }
+        Some(Self {
+            guardian: PublicKey::from_bytes(&bytes[..40]).ok()?,
+            signature: RecoverySignature::from_bytes(&bytes[40..]).ok()?,
+        })
+    }
+}
+
+/// Sent by the holder to the issuer to complete a recovery
+pub struct RecoveryRequest {
+    pub request: ApprovalRequest,
+    pub approvals: Vec<Approval>,
+    /// See RecoveryContext::possession
+    pub possession: RecoverySignature,
+    /// Credentials of the old key to bind to the new one
+    pub credentials: Vec<IssuedCredential>,
+}
+
+enum State {
+    Registered(RecoveryPolicy),
+    Started {
+        policy: RecoveryPolicy,
+        challenge: String,
+    },
+}
+
+impl State {
+    fn policy(&self) -> &RecoveryPolicy {
+        match self {
+            Self::Registered(policy) | Self::Started { policy, .. } => policy,
+        }
+    }
+}
+
+/// Issuer side: the recovery policy of every holder key, and the recoveries
+/// in progress
+#[derive(Default)]
+pub struct RecoveryDesk {
+    holders: HashMap<[u8; 40], State>,
+}
+
+impl RecoveryDesk {
+    /// At issuance, where the holder key is trusted. Replaces the policy
+    /// already registered for the key, if any
+    pub fn register(&mut self, holder: &PublicKey, policy: RecoveryPolicy) {
+        self.holders
+            .insert(holder.to_bytes(), State::Registered(policy));
+    }
+
+    pub fn policy(&self, holder: &PublicKey) -> Option<&RecoveryPolicy> {
+        self.holders.get(&holder.to_bytes()).map(State::policy)
+    }
+
+    /// Challenge the approvals of a recovery of holder are bound to, so that
+    /// they can't be replayed. Starting again drops the previous challenge
+    pub fn start(&mut self, holder: &PublicKey, rng: &mut impl Rng) -> Result<String> {
+        let state = self
+            .holders
+            .get_mut(&holder.to_bytes())
+            .ok_or(Error::UnknownHolder)?;
+        let challenge = format!("{:016x}", rng.random::<u64>());
+        *state = State::Started {
+            policy: state.policy().clone(),
+            challenge: challenge.clone(),
+        };
+        Ok(challenge)
+    }
+
+    /// Checks the approvals & the signature of the new key, then has issuer
+    /// sign every credential again for the new key, and replaces the old
+    /// credentials by the new ones in the trees of revocations. The policy
+    /// then protects the new key, and the old one can't be recovered anymore
+    pub fn complete<L: IssuanceLog, P: RootPublisher>(
+        &mut self,
+        issuer: &mut Issuer<L>,
+        revocations: &mut Revocations<P>,
+        recovery: &RecoveryRequest,
+    ) -> Result<Vec<IssuedCredential>> {
+        let request = &recovery.request;
+        let (policy, challenge) = match self.holders.get(&request.old_holder.to_bytes()) {
+            None => return Err(Error::UnknownHolder),
+            Some(State::Registered(_)) => return Err(Error::NotStarted),
+            Some(State::Started { policy, challenge }) => (policy, challenge),
+        };
+        if *challenge != request.challenge {
+            return Err(Error::NotStarted);
+        }
+        if !recovery
+            .possession
+            .verify(&RecoveryContext::possession(request))
+        {
+            return Err(Error::InvalidPossession);
+        }
+        for (i, approval) in recovery.approvals.iter().enumerate() {
+            let duplicate = recovery.approvals[..i]
+                .iter()
+                .any(|other| same_key(&other.guardian, &approval.guardian));
+            if duplicate || !policy.is_guardian(&approval.guardian) || !approval.verify(request) {
+                return Err(Error::InvalidApproval(i));
+            }
+        }
+        if recovery.approvals.len() < policy.threshold as usize {
+            return Err(Error::NotEnoughApprovals(
+                policy.threshold as usize,
+                recovery.approvals.len(),
+            ));
+        }
+        // nothing is signed again unless every credential is
+        for (i, issued) in recovery.credentials.iter().enumerate() {
+            if !same_key(&issued.credential.public_key(), &request.old_holder)
+                || !issuer.has_signed(issued)
+            {
+                return Err(Error::ForeignCredential(i));
+            }
+            if revocations
+                .database()
+                .proof(&hash::credential(&issued.credential))
+                .is_err()
+            {
+                return Err(Error::NotValid(i));
+            }
+        }
+        let rebound = recovery
+            .credentials
+            .iter()
+            .map(|issued| issuer.rebind(issued, &request.new_holder))
+            .collect::<issuance::Result<Vec<_>>>()?;
+        let old: Vec<_> = recovery
+            .credentials
+            .iter()
+            .map(|issued| issued.credential.clone())
+            .collect();
+        let new: Vec<_> = rebound
+            .iter()
+            .map(|issued| issued.credential.clone())
+            .collect();
+        revocations.replace(&old, &new)?;
+
+        let state = self
+            .holders
+            .remove(&request.old_holder.to_bytes())
+            .expect("checked above");
+        self.holders.insert(
+            request.new_holder.to_bytes(),
+            State::Registered(state.policy().clone()),
+        );
+        Ok(rebound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{
+        core::credential::Credential,
+        issuer::{
+            database::Database, issuance::MemoryLog, keys::IssuerIdentity,
+            revocation::MemoryPublisher,
+        },
+        revocation::serial,
+        schnorr::signature::{Context as SignatureContext, Signature},
+    };
+
+    #[test]
+    fn policy_rejects_duplicates_and_bad_thresholds() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let a = PublicKey::from(&SecretKey::random(&mut rng));
+        let b = PublicKey::from(&SecretKey::random(&mut rng));
+        assert!(RecoveryPolicy::new(vec![a.clone(), b.clone()], 2).is_ok());
+        for (guardians, threshold) in [
+            (vec![a.clone(), b.clone()], 0),
+            (vec![a.clone(), b.clone()], 3),
+            (vec![a.clone(), a.clone()], 1),
+        ] {
+            assert_eq!(
+                RecoveryPolicy::new(guardians, threshold).err(),
+                Some(Error::InvalidPolicy)
+            );
+        }
+    }
+
+    #[test]
+    fn complete_rebinds_credentials_once_approved() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let (old_sk, issuer_sk, credential) = Credential::from_seed(3);
+        let signature = Signature::prove(&issuer_sk, &SignatureContext::new(&credential));
+        let mut issuer = Issuer::new(IssuerIdentity::new(issuer_sk), MemoryLog::default());
+        let mut revocations = Revocations::new(
+            Database::init(std::slice::from_ref(&credential)),
+            MemoryPublisher::default(),
+        );
+        let old_holder = PublicKey::from(&old_sk);
+        let guardians: Vec<SecretKey> = (0..3).map(|_| SecretKey::random(&mut rng)).collect();
+        let policy = RecoveryPolicy::new(guardians.iter().map(PublicKey::from).collect(), 2);
+        let mut desk = RecoveryDesk::default();
+        desk.register(&old_holder, policy.unwrap());
+
+        let new_sk = SecretKey::random(&mut rng);
+        let request = ApprovalRequest {
+            old_holder: old_holder.clone(),
+            new_holder: PublicKey::from(&new_sk),
+            challenge: desk.start(&old_holder, &mut rng).unwrap(),
+        };
+        let recovery = |approvals: Vec<Approval>, credential: &Credential| RecoveryRequest {
+            request: request.clone(),
+            approvals,
+            possession: RecoverySignature::prove(&new_sk, &RecoveryContext::possession(&request)),
+            credentials: vec![IssuedCredential {
+                credential: credential.clone(),
+                signature: Signature::from_bytes(&signature.to_bytes()).unwrap(),
+            }],
+        };
+
+        let outsider = SecretKey::random(&mut rng);
+        let invalid = [
+            (
+                vec![request.approve(&guardians[0])],
+                Error::NotEnoughApprovals(2, 1),
+            ),
+            (
+                vec![
+                    request.approve(&guardians[0]),
+                    request.approve(&guardians[0]),
+                ],
+                Error::InvalidApproval(1),
+            ),
+            (
+                vec![request.approve(&guardians[0]), request.approve(&outsider)],
+                Error::InvalidApproval(1),
+            ),
+        ];
+        for (approvals, err) in invalid {
+            assert_eq!(
+                desk.complete(
+                    &mut issuer,
+                    &mut revocations,
+                    &recovery(approvals, &credential)
+                )
+                .err(),
+                Some(err)
+            );
+        }
+        let approvals = || {
+            vec![
+                request.approve(&guardians[2]),
+                request.approve(&guardians[0]),
+            ]
+        };
+        let mut tampered = credential.clone();
+        tampered.switch_names_char();
+        assert_eq!(
+            desk.complete(
+                &mut issuer,
+                &mut revocations,
+                &recovery(approvals(), &tampered)
+            )
+            .err(),
+            Some(Error::ForeignCredential(0))
+        );
+        assert!(issuer.log().0.is_empty());
+
+        let rebound = desk
+            .complete(
+                &mut issuer,
+                &mut revocations,
+                &recovery(approvals(), &credential),
+            )
+            .unwrap();
+        assert!(rebound[0].credential.check(&rebound[0].signature));
+        assert!(same_key(
+            &rebound[0].credential.public_key(),
+            &request.new_holder
+        ));
+        assert_eq!(issuer.log().0.len(), 1);
+        // the lost key proves nothing anymore, the new one does
+        let database = revocations.database();
+        assert!(database.proof(&hash::credential(&credential)).is_err());
+        assert!(database
+            .proof(&hash::credential(&rebound[0].credential))
+            .is_ok());
+        assert!(revocations.revoked().is_revoked(&serial(&credential)));
+        assert_eq!(revocations.publisher().roots, vec![database.root()]);
+        // the policy moved to the new key
+        assert!(desk.policy(&old_holder).is_none());
+        assert!(desk.policy(&request.new_holder).is_some());
+
+        let bytes = request.approve(&guardians[1]).to_bytes();
+        assert!(Approval::from_bytes(&bytes).unwrap().verify(&request));
+    }
+}
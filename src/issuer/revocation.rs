@@ -0,0 +1,424 @@
+// Revocation of a credential asked by its holder (lost phone, identity
+// theft): the holder signs the request with the holder key, and the issuer
+// empties the leaf of the credential in the Merkle tree of valid credentials,
+// then publishes the new root, against which presentations are proved. The
+// serial number of the credential also goes into the sparse tree of revoked
+// credentials (see crate::revocation), whose root is published as well, for
+// circuits proving non-revocation.
+//
+// Revoking is idempotent, so the requests carry no challenge: a replayed
+// request is rejected, the credential not being valid anymore.
+//
+// Expired credentials can't be presented anyway, so Revocations::prune_expired
+// periodically folds them out of the tree into a list of tombstones, freeing
+// their leaves for new credentials. Tombstones are dropped TOMBSTONE_DAYS after
+// the expiration. Revoked credentials stay in the sparse tree once expired:
+// circuits may leave the expiration unchecked (see circuit::inputs::Flags),
+// and removing the serial would make the credential presentable again.
+
+use chrono::NaiveDate;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use thiserror::Error;
+
+use crate::{
+    core::credential::Credential,
+    issuer::database::{self, Database, Root},
+    merkle::{self, hash},
+    revocation::{self, Tree},
+    schnorr::{
+        keys::{PublicKey, SecretKey},
+        transcript::{message_to_goldilocks, point_to_vec_goldilocks},
+        SchnorrProof, Transcript,
+    },
+};
+
+// Only used natively, so the transcript keeps its tag
+const REVOCATION_TAG: &[u8] = b"ZKYC_REVOCATION_V1";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("The holder key of the credential did not sign the request")]
+    InvalidSignature,
+    #[error("The credential is not in the tree of valid credentials")]
+    NotValid,
+    #[error("The credential is already in the tree of valid credentials")]
+    AlreadyValid,
+    #[error("The tree of valid credentials is full")]
+    CapacityExceeded,
+    #[error("Root publication failed: {0}")]
+    Publication(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// What the holder signs: the revocation of the credential
+pub struct RevocationContext {
+    holder: PublicKey,
+    credential: database::Hash,
+}
+
+pub type RevocationSignature = SchnorrProof<RevocationContext>;
+
+impl RevocationContext {
+    pub fn new(credential: &Credential) -> Self {
+        Self {
+            holder: credential.public_key(),
+            credential: hash::credential(credential),
+        }
+    }
+}
+
+impl Transcript for RevocationContext {
+    fn public_key(&self) -> &PublicKey {
+        &self.holder
+    }
+    fn transcript(&self) -> Vec<GoldilocksField> {
+        let mut f_message = message_to_goldilocks(REVOCATION_TAG);
+        f_message.extend_from_slice(&self.credential.0);
+        f_message.extend_from_slice(&point_to_vec_goldilocks(&self.holder.0));
+        f_message
+    }
+}
+
+/// Sent by the holder to the issuer
+pub struct RevocationRequest {
+    pub credential: Credential,
+    pub signature: RevocationSignature,
+}
+
+impl RevocationRequest {
+    /// Signed with the holder key of credential
+    pub fn new(holder_sk: &SecretKey, credential: Credential) -> Self {
+        let signature = RevocationSignature::prove(holder_sk, &RevocationContext::new(&credential));
+        Self {
+            credential,
+            signature,
+        }
+    }
+
+    pub fn verify(&self) -> bool {
+        self.signature
+            .verify(&RevocationContext::new(&self.credential))
+    }
+
+    /// Signature, then credential
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut res = self.signature.to_bytes().to_vec();
+        res.extend_from_slice(&self.credential.to_bytes());
+        res
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (signature, credential) = bytes.split_at_checked(80)?;
+        Some(Self {
+            credential: Credential::from_bytes(credential)?,
+            signature: RevocationSignature::from_bytes(signature).ok()?,
+        })
+    }
+}
+
+/// Where the roots of the tree of valid credentials, and of the tree of
+/// revoked credentials, are published for holders & verifiers
+pub trait RootPublisher {
+    fn publish(&mut self, root: &Root) -> Result<()>;
+    fn publish_revoked(&mut self, root: &revocation::Root) -> Result<()>;
+}
+
+/// Keeps every published root, oldest first, so that tests can check what
+/// reached holders & verifiers
+#[derive(Default)]
+pub struct MemoryPublisher {
+    pub roots: Vec<Root>,
+    pub revoked_roots: Vec<revocation::Root>,
+}
+
+impl RootPublisher for MemoryPublisher {
+    fn publish(&mut self, root: &Root) -> Result<()> {
+        self.roots.push(*root);
+        Ok(())
+    }
+
+    fn publish_revoked(&mut self, root: &revocation::Root) -> Result<()> {
+        self.revoked_roots.push(*root);
+        Ok(())
+    }
+}
+
+/// How long tombstones are kept after the expiration of their credential
+pub const TOMBSTONE_DAYS: i64 = 365;
+
+/// Credential which left the tree by expiring rather than by revocation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tombstone {
+    /// See merkle::hash::credential
+    pub credential: database::Hash,
+    pub expired_on: NaiveDate,
+}
+
+/// Issuer side: the trees of valid and of revoked credentials, and where
+/// their roots go
+pub struct Revocations<P: RootPublisher> {
+    database: Database,
+    revoked: Tree,
+    publisher: P,
+    tombstones: Vec<Tombstone>,
+}
+
+impl<P: RootPublisher> Revocations<P> {
+    pub fn new(database: Database, publisher: P) -> Self {
+        Self {
+            database,
+            revoked: Tree::new(),
+            publisher,
+            tombstones: vec![],
+        }
+    }
+
+    pub fn database(&self) -> &Database {
+        &self.database
+    }
+
+    /// Holders take their non-revocation path from it, see Tree::prove
+    pub fn revoked(&self) -> &Tree {
+        &self.revoked
+    }
+
+    pub fn publisher(&self) -> &P {
+        &self.publisher
+    }
+
+    pub fn tombstones(&self) -> &[Tombstone] {
+        &self.tombstones
+    }
+
+    /// Checks the signature of the holder, revokes the credential and
+    /// publishes the new roots. Returns the one of the valid credentials
+    pub fn revoke_by_holder(&mut self, request: &RevocationRequest) -> Result<Root> {
+        if !request.verify() {
+            return Err(Error::InvalidSignature);
+        }
+        self.database
+            .revoke(&request.credential)
+            .map_err(|_| Error::NotValid)?;
+        // valid credentials were never revoked
+        self.revoked
+            .revoke(&revocation::serial(&request.credential))
+            .map_err(|_| Error::NotValid)?;
+        let root = self.database.root();
+        self.publisher.publish(&root)?;
+        self.publisher.publish_revoked(&self.revoked.root())?;
+        Ok(root)
+    }
+
+    /// Replaces the credentials of old by the ones of new, e.g. rebound to
+    /// another holder key after a recovery: old ones are revoked in both
+    /// trees, new ones become valid, and the new roots are published. Nothing
+    /// changes unless every old credential is valid, once, and every new one
+    /// fits in the tree. Returns the root of the valid credentials
+    pub fn replace(&mut self, old: &[Credential], new: &[Credential]) -> Result<Root> {
+        // staged on a copy of the tree of valid credentials, bounded by SIZE
+        let mut database = self.database.clone();
+        for credential in old {
+            database.revoke(credential).map_err(|_| Error::NotValid)?;
+        }
+        for credential in new {
+            database.add(credential).map_err(|err| match err {
+                merkle::Error::CapacityExceeded => Error::CapacityExceeded,
+                _ => Error::AlreadyValid,
+            })?;
+        }
+        let serials: Vec<_> = old.iter().map(revocation::serial).collect();
+        self.revoked
+            .check_revocable(&serials)
+            .map_err(|_| Error::NotValid)?;
+        self.database = database;
+        for serial in &serials {
+            self.revoked
+                .revoke(serial)
+                .expect("checked by check_revocable");
+        }
+        let root = self.database.root();
+        self.publisher.publish(&root)?;
+        self.publisher.publish_revoked(&self.revoked.root())?;
+        Ok(root)
+    }
+
+    /// Job folding the credentials expired on today into tombstones, and
+    /// dropping the tombstones older than TOMBSTONE_DAYS. Publishes & returns
+    /// the new root, if any credential expired. The tree of revoked
+    /// credentials is left as is, see the top of the file
+    pub fn prune_expired(&mut self, today: NaiveDate) -> Result<Option<Root>> {
+        self.tombstones
+            .retain(|tombstone| (today - tombstone.expired_on).num_days() <= TOMBSTONE_DAYS);
+        let expired = self.database.remove_expired(today);
+        if expired.is_empty() {
+            return Ok(None);
+        }
+        self.tombstones
+            .extend(expired.iter().map(|credential| Tombstone {
+                credential: hash::credential(credential),
+                expired_on: *credential.expiration_date(),
+            }));
+        let root = self.database.root();
+        self.publisher.publish(&root)?;
+        Ok(Some(root))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn revoke_by_holder_publishes_a_root_without_the_credential() {
+        let credentials: Vec<Credential> = (0..3).map(|i| Credential::from_seed(i).2).collect();
+        let mut revocations =
+            Revocations::new(Database::init(&credentials), MemoryPublisher::default());
+        let root = revocations.database().root();
+        let (holder_sk, _, credential) = Credential::from_seed(1);
+        let (other_sk, _, _) = Credential::from_seed(2);
+
+        let forged = RevocationRequest::new(&other_sk, credential.clone());
+        assert_eq!(
+            revocations.revoke_by_holder(&forged),
+            Err(Error::InvalidSignature)
+        );
+
+        let request = RevocationRequest::new(&holder_sk, credential.clone());
+        let request = RevocationRequest::from_bytes(&request.to_bytes()).unwrap();
+        let new_root = revocations.revoke_by_holder(&request).unwrap();
+        assert_ne!(new_root, root);
+        assert_eq!(revocations.publisher().roots, vec![new_root]);
+        assert_eq!(
+            revocations.publisher().revoked_roots,
+            vec![revocations.revoked().root()]
+        );
+        let serial = revocation::serial(&credential);
+        assert_eq!(
+            revocations.revoked().prove(&serial),
+            Err(revocation::Error::Revoked)
+        );
+        assert!(revocations
+            .revoked()
+            .prove(&revocation::serial(&credentials[0]))
+            .is_ok());
+        assert!(revocations
+            .database()
+            .proof(&hash::credential(&credential))
+            .is_err());
+        assert!(revocations
+            .database()
+            .proof(&hash::credential(&credentials[0]))
+            .is_ok());
+
+        assert_eq!(revocations.revoke_by_holder(&request), Err(Error::NotValid));
+        assert_eq!(revocations.publisher().roots.len(), 1);
+    }
+
+    #[test]
+    fn replace_changes_nothing_unless_it_succeeds() {
+        // a full tree of valid credentials
+        let credentials: Vec<Credential> = (0..database::SIZE as u64)
+            .map(|i| Credential::from_seed(i).2)
+            .collect();
+        let mut revocations =
+            Revocations::new(Database::init(&credentials), MemoryPublisher::default());
+        let root = revocations.database().root();
+        let revoked_root = revocations.revoked().root();
+        let new: Vec<Credential> = (100..102).map(|i| Credential::from_seed(i).2).collect();
+        let unchanged = |revocations: &Revocations<MemoryPublisher>| {
+            revocations.database().root() == root
+                && revocations.revoked().root() == revoked_root
+                && revocations.publisher().roots.is_empty()
+        };
+
+        assert_eq!(
+            revocations.replace(&credentials[..1], &new),
+            Err(Error::CapacityExceeded)
+        );
+        assert!(unchanged(&revocations));
+        let twice = [credentials[0].clone(), credentials[0].clone()];
+        assert_eq!(
+            revocations.replace(&twice, &new[..1]),
+            Err(Error::NotValid)
+        );
+        assert!(unchanged(&revocations));
+        assert_eq!(
+            revocations.replace(&credentials[..1], &credentials[1..2]),
+            Err(Error::AlreadyValid)
+        );
+        assert!(unchanged(&revocations));
+
+        let root = revocations.replace(&credentials[..1], &new[..1]).unwrap();
+        assert_eq!(revocations.publisher().roots, vec![root]);
+        assert!(revocations
+            .revoked()
+            .is_revoked(&revocation::serial(&credentials[0])));
+    }
+
+    #[test]
+    fn prune_expired_folds_expired_credentials_into_tombstones() {
+        let credentials: Vec<Credential> = (0..6).map(|i| Credential::from_seed(i).2).collect();
+        let mut revocations =
+            Revocations::new(Database::init(&credentials), MemoryPublisher::default());
+        let mut expirations: Vec<NaiveDate> = credentials
+            .iter()
+            .map(|credential| *credential.expiration_date())
+            .collect();
+        expirations.sort();
+        assert_eq!(revocations.prune_expired(expirations[0]), Ok(None));
+
+        // the credential expiring first is valid through its expiration date
+        let today = expirations[0].succ_opt().unwrap();
+        let root = revocations.prune_expired(today).unwrap().unwrap();
+        assert_eq!(revocations.publisher().roots, vec![root]);
+        for credential in &credentials {
+            let expired = *credential.expiration_date() < today;
+            let proof = revocations.database().proof(&hash::credential(credential));
+            assert_eq!(proof.is_err(), expired);
+            assert_eq!(
+                revocations
+                    .tombstones()
+                    .iter()
+                    .any(|tombstone| tombstone.credential == hash::credential(credential)),
+                expired
+            );
+        }
+        assert_eq!(revocations.prune_expired(today), Ok(None));
+    }
+
+    #[test]
+    fn prune_expired_drops_old_tombstones_and_keeps_revoked_serials() {
+        let credentials: Vec<Credential> = (0..6).map(|i| Credential::from_seed(i).2).collect();
+        let mut revocations =
+            Revocations::new(Database::init(&credentials), MemoryPublisher::default());
+        revocations
+            .replace(&credentials[..1], &[])
+            .expect("valid credential");
+        let last = credentials
+            .iter()
+            .map(|credential| *credential.expiration_date())
+            .max()
+            .unwrap();
+
+        let today = last.succ_opt().unwrap();
+        assert!(revocations.prune_expired(today).unwrap().is_some());
+        assert_eq!(revocations.tombstones().len(), credentials.len() - 1);
+        assert!(revocations
+            .revoked()
+            .is_revoked(&revocation::serial(&credentials[0])));
+
+        // kept TOMBSTONE_DAYS after the expiration, dropped the day after
+        let kept = last + chrono::Duration::days(TOMBSTONE_DAYS);
+        assert_eq!(revocations.prune_expired(kept), Ok(None));
+        assert!(revocations
+            .tombstones()
+            .iter()
+            .any(|tombstone| tombstone.expired_on == last));
+        assert_eq!(revocations.prune_expired(kept.succ_opt().unwrap()), Ok(None));
+        assert!(revocations.tombstones().is_empty());
+        assert!(revocations
+            .revoked()
+            .is_revoked(&revocation::serial(&credentials[0])));
+    }
+}
@@ -0,0 +1,120 @@
+// Trusted time: a designated time authority signs the date of the day for the
+// nonce of a presentation, and the circuit uses it as "today". Offline, neither
+// the prover nor the verifier can then pick the date of the age checks.
+
+use chrono::NaiveDate;
+use plonky2::{
+    field::{goldilocks_field::GoldilocksField, types::Field},
+    hash::hash_types::RichField,
+};
+
+use crate::{
+    core::date::days_from_origin,
+    encoding::{
+        self,
+        conversion::{ToSchnorrField, ToSingleField, ToStringField},
+    },
+    schnorr::{
+        keys::{PublicKey, SecretKey},
+        transcript::point_to_vec_goldilocks,
+        SchnorrProof, Transcript,
+    },
+};
+
+/// What the time authority signs
+pub struct TimeTokenContext {
+    authority: PublicKey,
+    today: NaiveDate,
+    nonce: String,
+}
+
+/// Signature of a date by the time authority
+pub type TimeTokenSignature = SchnorrProof<TimeTokenContext>;
+
+/// Attests that `today` was the date of the day when the presentation of
+/// `nonce` was requested
+pub struct TimeToken {
+    today: NaiveDate,
+    signature: TimeTokenSignature,
+}
+
+impl TimeTokenContext {
+    pub fn new(authority: &PublicKey, today: NaiveDate, nonce: &str) -> Self {
+        Self {
+            authority: authority.clone(),
+            today,
+            nonce: nonce.to_string(),
+        }
+    }
+}
+
+impl Transcript for TimeTokenContext {
+    fn public_key(&self) -> &PublicKey {
+        &self.authority
+    }
+    fn transcript(&self) -> Vec<GoldilocksField> {
+        let mut f_message = vec![GoldilocksField::from_canonical_u32(days_from_origin(
+            self.today,
+        ))];
+        let nonce: encoding::String<GoldilocksField> = self.nonce.to_field();
+        f_message.extend_from_slice(&nonce.0);
+        f_message.extend_from_slice(&point_to_vec_goldilocks(&self.authority.0));
+        f_message
+    }
+}
+
+impl TimeToken {
+    pub fn issue(authority_sk: &SecretKey, today: NaiveDate, nonce: &str) -> Self {
+        let authority = PublicKey::from(authority_sk);
+        let ctx = TimeTokenContext::new(&authority, today, nonce);
+        Self {
+            today,
+            signature: TimeTokenSignature::prove(authority_sk, &ctx),
+        }
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.today
+    }
+
+    pub fn signature(&self) -> &TimeTokenSignature {
+        &self.signature
+    }
+
+    /// Checks that `authority` signed the token for the presentation of `nonce`
+    pub fn verify(&self, authority: &PublicKey, nonce: &str) -> bool {
+        self.signature
+            .verify(&TimeTokenContext::new(authority, self.today, nonce))
+    }
+
+    pub fn to_field<F: RichField>(&self) -> encoding::TimeToken<F, bool> {
+        encoding::TimeToken {
+            today: self.today.to_field(),
+            signature: self.signature.to_field(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn time_token_is_bound_to_authority_date_and_nonce() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let authority_sk = SecretKey::random(&mut rng);
+        let authority = PublicKey::from(&authority_sk);
+        let today = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let mut token = TimeToken::issue(&authority_sk, today, "nonce");
+        assert!(token.verify(&authority, "nonce"));
+        assert!(!token.verify(&authority, "other nonce"));
+
+        let other = PublicKey::from(&SecretKey::random(&mut rng));
+        assert!(!token.verify(&other, "nonce"));
+
+        token.today = today.succ_opt().unwrap();
+        assert!(!token.verify(&authority, "nonce"));
+    }
+}
@@ -1,3 +1,4 @@
+pub mod archive;
 pub mod arith;
 pub mod bank;
 pub mod circuit;
@@ -6,7 +7,16 @@ pub mod core;
 pub mod encoding;
 pub mod issuer;
 pub mod merkle;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod revocation;
 pub mod schnorr;
+#[cfg(feature = "json")]
+pub mod vectors;
+pub mod verify;
+
+pub use archive::verify_archive;
+pub use verify::{verify_bytes, VerificationReport};
 
 #[cfg(test)]
 mod tests {
@@ -21,7 +31,7 @@ mod tests {
         let mut rng = StdRng::from_os_rng();
         let (_, sk, credential) = Credential::random(&mut rng);
         let ctx = Context::new(&credential);
-        let signature = Signature::sign(&sk, &ctx);
+        let signature = Signature::prove(&sk, &ctx);
         let b = signature.verify(&ctx);
         assert!(b)
     }
@@ -10,6 +10,7 @@ pub type Proof<const D: usize, F> = MerklePath<D, F, bool>;
 
 pub type Root<F> = Hash<F>;
 
+#[derive(Clone)]
 pub enum Leaf {
     Credential(Credential),
     Empty, // hashes to 0
@@ -29,6 +30,7 @@ pub enum Error {
 pub type Result<T> = std::result::Result<T, Error>;
 
 // D = log₂(nb leaves) = nb levels - 1
+#[derive(Clone)]
 pub struct Tree<const D: usize, F: Field> {
     leaves: Vec<Leaf>,
     // contains D + 1 vectors, stored from leaves to root
@@ -162,6 +164,23 @@ impl<const D: usize, F: RichField> Tree<D, F> {
         }
     }
 
+    /// Empties the leaves of the credentials for which keep is false, and
+    /// returns them. Nodes are hashed once, whatever the number of leaves
+    pub fn retain(&mut self, mut keep: impl FnMut(&Credential) -> bool) -> Vec<Credential> {
+        let mut removed = vec![];
+        for leaf in self.leaves.iter_mut() {
+            if matches!(leaf, Leaf::Credential(credential) if !keep(credential)) {
+                if let Leaf::Credential(credential) = std::mem::replace(leaf, Leaf::Empty) {
+                    removed.push(credential);
+                }
+            }
+        }
+        if !removed.is_empty() {
+            *self = Self::from_no_duplicate(std::mem::take(&mut self.leaves));
+        }
+        removed
+    }
+
     pub fn prove(&self, credential_hash: &Hash<F>) -> Result<Proof<D, F>> {
         let position = self.find_hash(credential_hash);
         match position {
@@ -179,15 +198,19 @@ impl<const D: usize, F: RichField> Tree<D, F> {
     }
 
     pub fn verify(root: Root<F>, credential: &Credential, proof: Proof<D, F>) -> bool {
+        Self::root_of(credential, &proof) == root
+    }
+
+    /// Root of the tree in which proof opens credential
+    pub fn root_of(credential: &Credential, proof: &Proof<D, F>) -> Root<F> {
         let MerklePath { positions, path } = proof;
         let credential_hash = hash::credential(credential);
-        let claimed_root = positions
+        positions
             .iter()
             .zip(path.iter())
             .fold(credential_hash, |acc, (is_left, neighbor)| {
                 hash::merge_left_right(&acc, *is_left, neighbor)
-            });
-        claimed_root == root
+            })
     }
 }
 
@@ -317,6 +340,24 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn retain_matches_revoking_one_by_one() {
+        let credentials: Vec<Credential> =
+            (50..54).map(|seed| Credential::from_seed(seed).2).collect();
+        let mut retained = Tree::<2, GoldilocksField>::from(&credentials)
+            .expect("distinct credentials should build a tree");
+        let mut revoked = Tree::<2, GoldilocksField>::from(&credentials)
+            .expect("distinct credentials should build a tree");
+
+        let removed = retained.retain(|credential| credential != &credentials[1]);
+        assert!(removed == vec![credentials[1].clone()]);
+        revoked
+            .revoke(&credentials[1])
+            .expect("revoking an existing credential should succeed");
+        assert_eq!(retained.root(), revoked.root());
+        assert!(retained.retain(|_| true).is_empty());
+    }
+
     #[test]
     fn empty_tree_root_matches_recursive_empty_hashes() {
         let credentials = vec![];
@@ -0,0 +1,208 @@
+// Counters & histograms of the issuer and bank services, for operators to
+// alert on anomalies such as spikes of invalid proofs. Rendered in the
+// Prometheus text format by Metrics::render, for the scrape endpoint of the
+// service.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::bank::events::{Event, EventSink};
+
+/// Upper bounds of the buckets of zkyc_verification_seconds
+pub const LATENCY_BUCKETS: [f64; 10] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+/// Upper bounds of the buckets of zkyc_proof_bytes
+pub const SIZE_BUCKETS: [f64; 7] = [
+    16384.0, 32768.0, 65536.0, 131072.0, 262144.0, 524288.0, 1048576.0,
+];
+
+struct Histogram {
+    bounds: &'static [f64],
+    /// Not cumulative, the last one counting values above every bound
+    counts: Vec<u64>,
+    sum: f64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len() + 1],
+            sum: 0.0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        let i = self.bounds.partition_point(|&bound| bound < value);
+        self.counts[i] += 1;
+        self.sum += value;
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} histogram");
+        let mut cumulative = 0;
+        for (bound, count) in self.bounds.iter().zip(&self.counts) {
+            cumulative += count;
+            let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {cumulative}");
+        }
+        let total: u64 = self.counts.iter().sum();
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {total}");
+        let _ = writeln!(out, "{name}_sum {}\n{name}_count {total}", self.sum);
+    }
+}
+
+fn render_counter(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    label: &str,
+    values: &BTreeMap<&'static str, u64>,
+) {
+    let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} counter");
+    for (value, count) in values {
+        let _ = writeln!(out, "{name}{{{label}=\"{value}\"}} {count}");
+    }
+}
+
+struct Registry {
+    /// By outcome: issued, rejected or rebound
+    issuances: BTreeMap<&'static str, u64>,
+    /// By outcome: accepted, cached or rejected
+    verifications: BTreeMap<&'static str, u64>,
+    /// By reason: invalid_proof, nullifier_reused or stale_circuit
+    failures: BTreeMap<&'static str, u64>,
+    verification_seconds: Histogram,
+    proof_bytes: Histogram,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self {
+            issuances: BTreeMap::new(),
+            verifications: BTreeMap::new(),
+            failures: BTreeMap::new(),
+            verification_seconds: Histogram::new(&LATENCY_BUCKETS),
+            proof_bytes: Histogram::new(&SIZE_BUCKETS),
+        }
+    }
+}
+
+/// Shared by the services recording into it, see Issuer::with_metrics and
+/// Verifier::with_metrics
+#[derive(Clone, Default)]
+pub struct Metrics(Arc<Mutex<Registry>>);
+
+impl Metrics {
+    fn registry(&self) -> std::sync::MutexGuard<'_, Registry> {
+        // counters stay usable after a panic while recording
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    pub(crate) fn issuance(&self, outcome: &'static str) {
+        *self.registry().issuances.entry(outcome).or_default() += 1;
+    }
+
+    /// A proof verified, cache aside
+    pub(crate) fn verified(&self, latency: Duration, proof_bytes: usize) {
+        let mut registry = self.registry();
+        registry.verification_seconds.observe(latency.as_secs_f64());
+        registry.proof_bytes.observe(proof_bytes as f64);
+    }
+
+    pub fn render(&self) -> String {
+        let registry = self.registry();
+        let mut out = String::new();
+        render_counter(
+            &mut out,
+            "zkyc_issuances_total",
+            "Credentials signed by the issuer, by outcome",
+            "outcome",
+            &registry.issuances,
+        );
+        render_counter(
+            &mut out,
+            "zkyc_verifications_total",
+            "Presentations checked by the bank, by outcome",
+            "outcome",
+            &registry.verifications,
+        );
+        render_counter(
+            &mut out,
+            "zkyc_verification_failures_total",
+            "Presentations rejected by the bank, by reason",
+            "reason",
+            &registry.failures,
+        );
+        registry.verification_seconds.render(
+            &mut out,
+            "zkyc_verification_seconds",
+            "Time to verify a proof",
+        );
+        registry
+            .proof_bytes
+            .render(&mut out, "zkyc_proof_bytes", "Size of the verified proofs");
+        out
+    }
+}
+
+impl EventSink for Metrics {
+    fn emit(&mut self, event: &Event) {
+        let (outcome, reason) = match event {
+            Event::VerificationSucceeded { cached: false, .. } => ("accepted", None),
+            Event::VerificationSucceeded { cached: true, .. } => ("cached", None),
+            Event::VerificationFailed { .. } => ("rejected", Some("invalid_proof")),
+            Event::NullifierReused { .. } => ("rejected", Some("nullifier_reused")),
+            Event::StaleCircuit { .. } => ("rejected", Some("stale_circuit")),
+        };
+        let mut registry = self.registry();
+        *registry.verifications.entry(outcome).or_default() += 1;
+        if let Some(reason) = reason {
+            *registry.failures.entry(reason).or_default() += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::types::Field;
+
+    use super::*;
+    use crate::{circuit, encoding};
+
+    #[test]
+    fn render_exposes_counters_and_cumulative_buckets() {
+        let mut metrics = Metrics::default();
+        metrics.issuance("issued");
+        metrics.issuance("issued");
+        metrics.issuance("rejected");
+        let circuit = encoding::Hash([circuit::F::ONE; encoding::LEN_HASH]);
+        metrics.clone().emit(&Event::StaleCircuit { circuit });
+        metrics.emit(&Event::VerificationSucceeded {
+            circuit,
+            cached: false,
+        });
+        metrics.verified(Duration::from_millis(30), 100_000);
+        metrics.verified(Duration::from_secs(20), 10);
+
+        let text = metrics.render();
+        for line in [
+            "zkyc_issuances_total{outcome=\"issued\"} 2",
+            "zkyc_issuances_total{outcome=\"rejected\"} 1",
+            "zkyc_verifications_total{outcome=\"accepted\"} 1",
+            "zkyc_verifications_total{outcome=\"rejected\"} 1",
+            "zkyc_verification_failures_total{reason=\"stale_circuit\"} 1",
+            "zkyc_verification_seconds_bucket{le=\"0.025\"} 0",
+            "zkyc_verification_seconds_bucket{le=\"0.05\"} 1",
+            "zkyc_verification_seconds_bucket{le=\"5\"} 1",
+            "zkyc_verification_seconds_bucket{le=\"+Inf\"} 2",
+            "zkyc_verification_seconds_count 2",
+            "zkyc_proof_bytes_bucket{le=\"16384\"} 1",
+            "zkyc_proof_bytes_bucket{le=\"131072\"} 2",
+        ] {
+            assert!(text.lines().any(|l| l == line), "missing {line} in\n{text}");
+        }
+    }
+}
@@ -0,0 +1,171 @@
+// Sparse Merkle tree of revoked credentials, keyed by serial number: a holder
+// proves that the leaf of their serial number is empty against the root the
+// issuer publishes, without disclosing which leaf it is.
+//
+// Unlike the tree of valid credentials (see merkle), only revoked credentials
+// are stored, so its depth doesn't bound their number: the hashes of empty
+// subtrees are computed once per depth.
+//
+// Leaves are indexed by the first element of the serial number. Two serial
+// numbers sharing it would share a leaf, which can only revoke a credential
+// wrongly, never hide a revocation.
+
+use std::collections::HashMap;
+
+use plonky2::field::types::PrimeField64;
+use thiserror::Error;
+
+use crate::{
+    circuit::F,
+    core::credential::Credential,
+    encoding::{self, NonRevocationPath, REVOCATION_DEPTH},
+    merkle::hash,
+};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("The credential is already revoked")]
+    AlreadyRevoked,
+    #[error("The credential is revoked")]
+    Revoked,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Serial number of a credential, i.e. its hash, see merkle::hash::credential
+pub type Serial = encoding::Hash<F>;
+pub type Root = encoding::Hash<F>;
+pub type Path = NonRevocationPath<F>;
+
+pub fn serial(credential: &Credential) -> Serial {
+    hash::credential(credential)
+}
+
+/// Index of the leaf of serial, whose bits are the positions of its path
+fn index(serial: &Serial) -> u64 {
+    serial.0[0].to_canonical_u64()
+}
+
+pub struct Tree {
+    /// Nodes of non-empty subtrees, by depth from the leaves and index
+    nodes: HashMap<(usize, u64), encoding::Hash<F>>,
+    /// Hash of an empty subtree, by depth
+    empty: [encoding::Hash<F>; REVOCATION_DEPTH + 1],
+}
+
+impl Default for Tree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tree {
+    pub fn new() -> Self {
+        let mut empty = [hash::empty(); REVOCATION_DEPTH + 1];
+        for depth in 0..REVOCATION_DEPTH {
+            empty[depth + 1] = hash::merge_left_right(&empty[depth], true, &empty[depth]);
+        }
+        Self {
+            nodes: HashMap::new(),
+            empty,
+        }
+    }
+
+    fn node(&self, depth: usize, index: u64) -> encoding::Hash<F> {
+        self.nodes
+            .get(&(depth, index))
+            .copied()
+            .unwrap_or(self.empty[depth])
+    }
+
+    pub fn root(&self) -> Root {
+        self.node(REVOCATION_DEPTH, 0)
+    }
+
+    pub fn is_revoked(&self, serial: &Serial) -> bool {
+        self.nodes.contains_key(&(0, index(serial)))
+    }
+
+    /// Fails unless every serial number can be revoked in turn: none is
+    /// revoked yet, and no two of them share a leaf
+    pub fn check_revocable(&self, serials: &[Serial]) -> Result<()> {
+        let mut leaves = std::collections::HashSet::new();
+        for serial in serials {
+            if self.is_revoked(serial) || !leaves.insert(index(serial)) {
+                return Err(Error::AlreadyRevoked);
+            }
+        }
+        Ok(())
+    }
+
+    /// The serial number becomes the leaf, which changes the root
+    pub fn revoke(&mut self, serial: &Serial) -> Result<()> {
+        if self.is_revoked(serial) {
+            return Err(Error::AlreadyRevoked);
+        }
+        let mut i = index(serial);
+        let mut h = *serial;
+        self.nodes.insert((0, i), h);
+        for depth in 0..REVOCATION_DEPTH {
+            let neighbor = self.node(depth, i ^ 1);
+            h = hash::merge_left_right(&h, i.is_multiple_of(2), &neighbor);
+            i /= 2;
+            self.nodes.insert((depth + 1, i), h);
+        }
+        Ok(())
+    }
+
+    /// Siblings of the empty leaf of serial
+    pub fn prove(&self, serial: &Serial) -> Result<Path> {
+        if self.is_revoked(serial) {
+            return Err(Error::Revoked);
+        }
+        let i = index(serial);
+        Ok(NonRevocationPath(std::array::from_fn(|depth| {
+            self.node(depth, (i >> depth) ^ 1)
+        })))
+    }
+
+    /// Mirrors circuit::revocation::CircuitBuilderRevocation::check_not_revoked
+    pub fn verify(root: Root, serial: &Serial, path: &Path) -> bool {
+        let i = index(serial);
+        let claimed_root =
+            path.0
+                .iter()
+                .enumerate()
+                .fold(hash::empty(), |acc, (depth, neighbor)| {
+                    hash::merge_left_right(&acc, (i >> depth).is_multiple_of(2), neighbor)
+                });
+        claimed_root == root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_credentials_left_out_prove_non_revocation() {
+        let serials: Vec<Serial> = (0..3)
+            .map(|i| serial(&Credential::from_seed(i).2))
+            .collect();
+        let mut tree = Tree::new();
+        let empty_root = tree.root();
+        let path = tree.prove(&serials[0]).unwrap();
+        assert!(Tree::verify(empty_root, &serials[0], &path));
+
+        tree.revoke(&serials[1]).unwrap();
+        assert_eq!(tree.revoke(&serials[1]), Err(Error::AlreadyRevoked));
+        assert_ne!(tree.root(), empty_root);
+        assert_eq!(tree.prove(&serials[1]), Err(Error::Revoked));
+        // the path of the empty leaf doesn't open the new root
+        let stale = Tree::new().prove(&serials[1]).unwrap();
+        assert!(!Tree::verify(tree.root(), &serials[1], &stale));
+
+        for serial in [serials[0], serials[2]] {
+            let path = tree.prove(&serial).unwrap();
+            assert!(Tree::verify(tree.root(), &serial, &path));
+            assert!(!Tree::verify(empty_root, &serial, &path));
+        }
+    }
+}
@@ -2,29 +2,44 @@ use plonky2::field::goldilocks_field::GoldilocksField;
 use plonky2::hash::hash_types::RichField;
 
 use crate::encoding;
-use crate::encoding::conversion::ToAuthentificationContextField;
-use crate::encoding::conversion::ToAuthentificationField;
+use crate::encoding::conversion::ToChannelBindingField;
 use crate::encoding::conversion::ToPointField;
-use crate::encoding::conversion::ToSchnorrField;
 use crate::encoding::conversion::ToStringField;
+use crate::encoding::conversion::{self, ToAccountField, ToAuthentificationContextField};
 use crate::encoding::AuthentificationChallenge;
 use crate::encoding::AuthentificationChallengeRaw;
+use crate::encoding::LEN_CHANNEL_BINDING;
 
 use super::core::SchnorrProof;
+use super::keys::{PublicKey, SecretKey};
+
 /// Authentification will be used by the user to prove that they knows the secret key tied to some public key
 /// TODO: public key for authentification should depend on the service
-use super::keys::{PublicKey, SecretKey};
-use super::transcript;
+pub type Authentification = SchnorrProof<Context>;
+
+/// Proof by the service that it knows the secret key of its public key
+pub type ServiceAuthentification = SchnorrProof<ServiceContext>;
+
+/// Channel binding of the transport session (e.g. TLS exporter), 32 bytes
+pub type ChannelBinding = [u8; 4 * LEN_CHANNEL_BINDING];
 
 pub struct Context {
     public_key: PublicKey,
     // TODO: ensure everything is ascii ?
-    challenge: AuthentificationChallengeRaw<encoding::String<GoldilocksField>>, // TODO: session_id, channel_id
+    challenge: AuthentificationChallengeRaw<encoding::String<GoldilocksField>>, // TODO: session_id
+    channel_binding: Option<encoding::ChannelBinding<GoldilocksField>>,
+    expires_at: Option<u32>,
+    account: Option<encoding::Account<GoldilocksField>>,
 }
 
 impl Context {
     /// Creates a new context. Creates a copy of public_key and takes ownership
     /// of service & nonce
+    ///
+    /// # Panics
+    ///
+    /// If service or nonce is longer than MAX_STRING_BYTES, see
+    /// ToStringField::try_to_field
     pub fn new(public_key: &PublicKey, service: &str, nonce: &str) -> Self {
         Self {
             public_key: public_key.clone(),
@@ -32,9 +47,18 @@ impl Context {
                 service: service.to_string().to_field(),
                 nonce: nonce.to_string().to_field(),
             },
+            channel_binding: None,
+            expires_at: None,
+            account: None,
         }
     }
 
+    /// Context of a challenge received from the service
+    ///
+    /// # Panics
+    ///
+    /// If service or nonce is longer than MAX_STRING_BYTES, see
+    /// ToStringField::try_to_field
     pub fn from_challenge(
         public_key: &PublicKey,
         challenge: &AuthentificationChallengeRaw<String>,
@@ -45,9 +69,57 @@ impl Context {
                 service: challenge.service.to_field(),
                 nonce: challenge.nonce.to_field(),
             },
+            channel_binding: None,
+            expires_at: None,
+            account: None,
+        }
+    }
+
+    /// Context of a challenge already encoded, e.g. read from public inputs
+    pub(crate) fn from_encoded(
+        public_key: &PublicKey,
+        challenge: AuthentificationChallengeRaw<encoding::String<GoldilocksField>>,
+    ) -> Self {
+        Self {
+            public_key: public_key.clone(),
+            challenge,
+            channel_binding: None,
+            expires_at: None,
+            account: None,
         }
     }
 
+    /// Binds the context to a transport session, so that an authentification
+    /// produced on one connection can't be relayed over another one
+    pub fn with_channel_binding(mut self, channel_binding: &ChannelBinding) -> Self {
+        self.channel_binding = Some(channel_binding.to_field());
+        self
+    }
+
+    /// Makes authentifications produced for this context invalid after
+    /// `expires_at` (seconds since UNIX epoch), even if the nonce is replayed
+    pub fn with_expiry(mut self, expires_at: u32) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    /// Binds the context to an external account, e.g. an Ethereum address or
+    /// a customer ID, so that the proof can't be claimed for another one.
+    /// Fails on identifiers longer than MAX_ACCOUNT_BYTES
+    pub fn with_account(mut self, account: &[u8]) -> conversion::Result<Self> {
+        self.account = Some(account.try_to_field()?);
+        Ok(self)
+    }
+
+    /// Account already encoded, e.g. read from public inputs
+    pub(crate) fn with_encoded_account(
+        mut self,
+        account: Option<encoding::Account<GoldilocksField>>,
+    ) -> Self {
+        self.account = account;
+        self
+    }
+
     pub fn public_key(&self) -> &PublicKey {
         &self.public_key
     }
@@ -60,29 +132,114 @@ impl Context {
         &self.challenge.nonce
     }
 
-    pub fn to_context(&self) -> transcript::Context<'_> {
-        transcript::Context::Auth(self)
+    pub fn channel_binding(&self) -> Option<&encoding::ChannelBinding<GoldilocksField>> {
+        self.channel_binding.as_ref()
+    }
+
+    pub fn expires_at(&self) -> Option<u32> {
+        self.expires_at
+    }
+
+    pub fn account(&self) -> Option<&encoding::Account<GoldilocksField>> {
+        self.account.as_ref()
+    }
+
+    /// A context without expiration never expires
+    pub fn is_expired_at(&self, now: u32) -> bool {
+        self.expires_at.is_some_and(|expires_at| now > expires_at)
     }
 }
 
-// TODO: faire de la signature une schnorr proof plutôt que l’inverse
-pub struct Authentification(SchnorrProof);
+/// Context of the service side of a mutual authentification: the service proves
+/// knowledge of its own secret key over (service, nonce, client public key), so
+/// that a rogue reader can't harvest client authentifications
+pub struct ServiceContext {
+    service_key: PublicKey,
+    client_key: PublicKey,
+    challenge: AuthentificationChallengeRaw<encoding::String<GoldilocksField>>,
+}
 
-impl Authentification {
-    /// returns a proof of knowledge of a secret key for the corresponding public key
-    pub fn sign(sk: &SecretKey, ctx: &Context) -> Self {
-        Self(SchnorrProof::prove(sk, ctx.to_context()))
+impl ServiceContext {
+    /// # Panics
+    ///
+    /// If service or nonce is longer than MAX_STRING_BYTES, see
+    /// ToStringField::try_to_field
+    pub fn new(
+        service_key: &PublicKey,
+        client_key: &PublicKey,
+        challenge: &AuthentificationChallengeRaw<String>,
+    ) -> Self {
+        Self {
+            service_key: service_key.clone(),
+            client_key: client_key.clone(),
+            challenge: AuthentificationChallengeRaw {
+                service: challenge.service.to_field(),
+                nonce: challenge.nonce.to_field(),
+            },
+        }
+    }
+
+    /// The key of the service, which signs this context
+    pub fn public_key(&self) -> &PublicKey {
+        &self.service_key
+    }
+
+    pub fn client_key(&self) -> &PublicKey {
+        &self.client_key
+    }
+
+    pub fn service(&self) -> &encoding::String<GoldilocksField> {
+        &self.challenge.service
+    }
+
+    pub fn nonce(&self) -> &encoding::String<GoldilocksField> {
+        &self.challenge.nonce
+    }
+}
+
+/// First message of a mutual authentification, sent by the service
+pub struct ServiceHello {
+    pub challenge: AuthentificationChallengeRaw<String>,
+    pub proof: ServiceAuthentification,
+}
+
+impl ServiceHello {
+    pub fn new(
+        service_sk: &SecretKey,
+        client_key: &PublicKey,
+        challenge: AuthentificationChallengeRaw<String>,
+    ) -> Self {
+        let service_key = PublicKey::from(service_sk);
+        let ctx = ServiceContext::new(&service_key, client_key, &challenge);
+        let proof = ServiceAuthentification::prove(service_sk, &ctx);
+        Self { challenge, proof }
     }
 
-    /// verifies the authentification proof
-    pub fn verify(&self, ctx: &Context) -> bool {
-        self.0.verify(ctx.to_context())
+    /// Checks that the hello was produced by the holder of `service_key`,
+    /// for this client
+    pub fn verify(&self, service_key: &PublicKey, client_key: &PublicKey) -> bool {
+        let ctx = ServiceContext::new(service_key, client_key, &self.challenge);
+        self.proof.verify(&ctx)
     }
 }
 
-impl<F: RichField> ToAuthentificationField<F, bool> for Authentification {
-    fn to_field(&self) -> encoding::Authentification<F, bool> {
-        encoding::Authentification(self.0.to_field())
+impl Authentification {
+    /// Answers a mutual authentification: returns None if the service could
+    /// not prove it holds the secret key of `service_key`
+    pub fn respond(sk: &SecretKey, service_key: &PublicKey, hello: &ServiceHello) -> Option<Self> {
+        let pk = PublicKey::from(sk);
+        if !hello.verify(service_key, &pk) {
+            return None;
+        }
+        let ctx = Context::from_challenge(&pk, &hello.challenge);
+        Some(Self::prove(sk, &ctx))
+    }
+
+    /// verifies the authentification proof, and that the context has not
+    /// expired at `now` (seconds since UNIX epoch). Unlike verify, accepts
+    /// contexts with an expiration
+    pub fn verify_at(&self, ctx: &Context, now: u32) -> bool {
+        !ctx.is_expired_at(now) && self.verify_ignoring_expiry(ctx)
     }
 }
 
@@ -96,13 +253,22 @@ impl<F: RichField> ToAuthentificationContextField<F> for Context {
                 ),
                 nonce: encoding::String(self.challenge.nonce.0.map(|x| F::from_canonical_u64(x.0))),
             },
+            channel_binding: self
+                .channel_binding
+                .map(|b| encoding::ChannelBinding(b.0.map(|x| F::from_canonical_u64(x.0)))),
+            expires_at: self.expires_at.map(F::from_canonical_u32),
+            account: self.account.map(|account| encoding::VarString {
+                value: encoding::String(account.value.0.map(|x| F::from_canonical_u64(x.0))),
+                len: F::from_canonical_u64(account.len.0),
+            }),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Authentification, Context};
+    use super::{Authentification, Context, ServiceHello};
+    use crate::encoding::{AuthentificationChallengeRaw, MAX_STRING_BYTES};
     use crate::schnorr::keys::{PublicKey, SecretKey};
     use rand::{rngs::StdRng, SeedableRng};
 
@@ -118,7 +284,7 @@ mod tests {
         let (sk, pk) = keypair_from_seed(1);
         let ctx = Context::new(&pk, "service-A", "nonce-1");
 
-        let auth = Authentification::sign(&sk, &ctx);
+        let auth = Authentification::prove(&sk, &ctx);
         assert!(auth.verify(&ctx));
     }
 
@@ -127,7 +293,7 @@ mod tests {
         let (sk, pk) = keypair_from_seed(2);
 
         let ctx_good = Context::new(&pk, "service-A", "nonce-1");
-        let auth = Authentification::sign(&sk, &ctx_good);
+        let auth = Authentification::prove(&sk, &ctx_good);
 
         let ctx_bad = Context::new(&pk, "service-B", "nonce-1");
         assert!(!auth.verify(&ctx_bad));
@@ -138,7 +304,7 @@ mod tests {
         let (sk, pk) = keypair_from_seed(3);
 
         let ctx_good = Context::new(&pk, "service-A", "nonce-1");
-        let auth = Authentification::sign(&sk, &ctx_good);
+        let auth = Authentification::prove(&sk, &ctx_good);
 
         let ctx_bad = Context::new(&pk, "service-A", "nonce-2");
         assert!(!auth.verify(&ctx_bad));
@@ -150,9 +316,135 @@ mod tests {
         let (_sk2, pk2) = keypair_from_seed(5);
 
         let ctx1 = Context::new(&pk1, "service-A", "nonce-1");
-        let auth = Authentification::sign(&sk1, &ctx1);
+        let auth = Authentification::prove(&sk1, &ctx1);
 
         let ctx_other_pk = Context::new(&pk2, "service-A", "nonce-1");
         assert!(!auth.verify(&ctx_other_pk));
     }
+
+    #[test]
+    fn verify_fails_if_channel_binding_changes_or_is_dropped() {
+        let (sk, pk) = keypair_from_seed(6);
+
+        let ctx_good = Context::new(&pk, "service-A", "nonce-1").with_channel_binding(&[1; 32]);
+        let auth = Authentification::prove(&sk, &ctx_good);
+        assert!(auth.verify(&ctx_good));
+
+        let ctx_other_channel =
+            Context::new(&pk, "service-A", "nonce-1").with_channel_binding(&[2; 32]);
+        assert!(!auth.verify(&ctx_other_channel));
+
+        let ctx_unbound = Context::new(&pk, "service-A", "nonce-1");
+        assert!(!auth.verify(&ctx_unbound));
+    }
+
+    #[test]
+    fn verify_at_rejects_expired_context() {
+        let (sk, pk) = keypair_from_seed(7);
+
+        let ctx = Context::new(&pk, "service-A", "nonce-1").with_expiry(1_000);
+        let auth = Authentification::prove(&sk, &ctx);
+        assert!(auth.verify_at(&ctx, 999));
+        assert!(auth.verify_at(&ctx, 1_000));
+        assert!(!auth.verify_at(&ctx, 1_001));
+    }
+
+    #[test]
+    fn verify_rejects_context_with_expiry() {
+        let (sk, pk) = keypair_from_seed(7);
+
+        let ctx = Context::new(&pk, "service-A", "nonce-1").with_expiry(u32::MAX);
+        let auth = Authentification::prove(&sk, &ctx);
+        assert!(!auth.verify(&ctx));
+        assert!(auth.verify_at(&ctx, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "string conversion failed")]
+    fn new_panics_on_overlong_service() {
+        let (_sk, pk) = keypair_from_seed(1);
+        let service = "s".repeat(MAX_STRING_BYTES + 1);
+        Context::new(&pk, &service, "nonce-1");
+    }
+
+    #[test]
+    fn verify_fails_if_expiry_is_extended() {
+        let (sk, pk) = keypair_from_seed(8);
+
+        let ctx_good = Context::new(&pk, "service-A", "nonce-1").with_expiry(1_000);
+        let auth = Authentification::prove(&sk, &ctx_good);
+
+        let ctx_extended = Context::new(&pk, "service-A", "nonce-1").with_expiry(2_000);
+        assert!(!auth.verify_at(&ctx_extended, 1_500));
+
+        let ctx_without_expiry = Context::new(&pk, "service-A", "nonce-1");
+        assert!(!auth.verify(&ctx_without_expiry));
+    }
+
+    #[test]
+    fn verify_fails_if_account_changes_or_is_dropped() {
+        let (sk, pk) = keypair_from_seed(9);
+
+        let bound = |account: &[u8]| {
+            Context::new(&pk, "service-A", "nonce-1")
+                .with_account(account)
+                .unwrap()
+        };
+        let auth = Authentification::prove(&sk, &bound(b"customer-42"));
+        assert!(auth.verify(&bound(b"customer-42")));
+        assert!(!auth.verify(&bound(b"customer-43")));
+        // the length is absorbed, so trailing zeroes make another account
+        assert!(!auth.verify(&bound(b"customer-42\0")));
+        assert!(!auth.verify(&Context::new(&pk, "service-A", "nonce-1")));
+
+        let too_long = [0; crate::encoding::MAX_ACCOUNT_BYTES + 1];
+        assert!(Context::new(&pk, "service-A", "nonce-1")
+            .with_account(&too_long)
+            .is_err());
+    }
+
+    fn challenge(service: &str, nonce: &str) -> AuthentificationChallengeRaw<String> {
+        AuthentificationChallengeRaw {
+            service: service.to_string(),
+            nonce: nonce.to_string(),
+        }
+    }
+
+    #[test]
+    fn mutual_auth_round_trip() {
+        let (service_sk, service_pk) = keypair_from_seed(10);
+        let (client_sk, client_pk) = keypair_from_seed(11);
+
+        let hello = ServiceHello::new(&service_sk, &client_pk, challenge("service-A", "nonce-1"));
+        let auth = Authentification::respond(&client_sk, &service_pk, &hello)
+            .expect("service proof should be accepted");
+
+        let ctx = Context::new(&client_pk, "service-A", "nonce-1");
+        assert!(auth.verify(&ctx));
+    }
+
+    #[test]
+    fn client_refuses_rogue_service() {
+        let (_service_sk, service_pk) = keypair_from_seed(12);
+        let (rogue_sk, _rogue_pk) = keypair_from_seed(13);
+        let (client_sk, client_pk) = keypair_from_seed(14);
+
+        let hello = ServiceHello::new(&rogue_sk, &client_pk, challenge("service-A", "nonce-1"));
+        assert!(Authentification::respond(&client_sk, &service_pk, &hello).is_none());
+    }
+
+    #[test]
+    fn service_hello_is_bound_to_client_and_challenge() {
+        let (service_sk, service_pk) = keypair_from_seed(15);
+        let (_client_sk, client_pk) = keypair_from_seed(16);
+        let (_other_sk, other_pk) = keypair_from_seed(17);
+
+        let mut hello =
+            ServiceHello::new(&service_sk, &client_pk, challenge("service-A", "nonce-1"));
+        assert!(hello.verify(&service_pk, &client_pk));
+        assert!(!hello.verify(&service_pk, &other_pk));
+
+        hello.challenge.nonce = "nonce-2".to_string();
+        assert!(!hello.verify(&service_pk, &client_pk));
+    }
 }
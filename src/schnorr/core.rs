@@ -1,8 +1,14 @@
 // Unifies signature and authentification code.
 // The difference between these two protocol is what is hashed for fiat shamir
 
+use std::marker::PhantomData;
+
 use plonky2::hash::hash_types::RichField;
+use rand::Rng;
+use zeroize::Zeroize;
 
+#[cfg(feature = "precompute-large")]
+use crate::schnorr::precompute;
 use crate::{
     arith::{Point, Scalar},
     encoding::{
@@ -10,45 +16,124 @@ use crate::{
         conversion::{ToPointField, ToSchnorrField},
     },
     schnorr::{
-        keys::SecretKey,
-        transcript::{hash, Context},
+        keys::{Error, Result, SecretKey},
+        transcript::{hash, Transcript},
     },
 };
 
-pub struct SchnorrProof {
+/// Schnorr proof of knowledge of a secret key, bound to a context C which
+/// determines the transcript (a signed credential, an authentification
+/// challenge, …)
+pub struct SchnorrProof<C> {
     r: Point,
     s: Scalar,
+    context: PhantomData<C>,
 }
 
-impl SchnorrProof {
+impl<C: Transcript> SchnorrProof<C> {
     #[cfg(test)]
     pub fn get_nonce(&self) -> Point {
         self.r
     }
 
     /// returns a proof of knowledge of a secret key for the corresponding public key
-    pub fn prove(sk: &SecretKey, ctx: Context) -> Self {
+    pub fn prove(sk: &SecretKey, ctx: &C) -> Self {
         // TODO: handle the error more carefully
-        let k = Scalar::random().unwrap();
+        Self::prove_with_nonce(sk, ctx, Scalar::random().unwrap())
+    }
+
+    /// Same as prove, with the nonce drawn from rng. Only for reproducible
+    /// proofs (see vectors): a nonce reused with two contexts leaks the key.
+    pub fn prove_with_rng(sk: &SecretKey, ctx: &C, rng: &mut impl Rng) -> Self {
+        Self::prove_with_nonce(sk, ctx, Scalar::random_from_rng(rng))
+    }
+
+    fn prove_with_nonce(sk: &SecretKey, ctx: &C, k: Scalar) -> Self {
         let r = Point::mulgen(k);
         let e = hash(&r, ctx);
         let s = k + (sk.0 * e);
         assert!(s.iszero() == 0);
-        Self { r, s }
+        Self {
+            r,
+            s,
+            context: PhantomData,
+        }
     }
 
-    /// verifies the signature produced by sign for the given message
-    pub fn verify(&self, ctx: Context) -> bool {
+    /// Fiat-Shamir challenge of the proof for the given context
+    pub fn challenge(&self, ctx: &C) -> Scalar {
+        hash(&self.r, ctx)
+    }
+
+    /// verifies the proof produced by prove for the given context. Fails if
+    /// the context expires, see Authentification::verify_at
+    pub fn verify(&self, ctx: &C) -> bool {
+        ctx.expires_at().is_none() && self.verify_ignoring_expiry(ctx)
+    }
+
+    /// Same as verify, without looking at the expiration of the context
+    pub(crate) fn verify_ignoring_expiry(&self, ctx: &C) -> bool {
         assert!(self.s.iszero() == 0);
-        let pk = ctx.public_key().0;
         let e = hash(&self.r, ctx);
-        let gs = Point::mulgen(self.s);
-        let gr = self.r + (pk * e);
+        #[cfg(not(feature = "precompute-large"))]
+        let (gs, pke) = (Point::mulgen(self.s), ctx.public_key().0 * e);
+        #[cfg(feature = "precompute-large")]
+        let (gs, pke) = (
+            precompute::mulgen_vartime(self.s),
+            if C::LONG_LIVED_KEY {
+                precompute::mul_key_vartime(ctx.public_key(), e)
+            } else {
+                ctx.public_key().0 * e
+            },
+        );
+        let gr = self.r + pke;
         gs.equals(gr) == u64::MAX
     }
 }
 
-impl<F: RichField> ToSchnorrField<F, bool> for SchnorrProof {
+impl<C> SchnorrProof<C> {
+    /// r (canonical point encoding) followed by s
+    pub fn to_bytes(&self) -> [u8; 80] {
+        let mut res = [0; 80];
+        res[..40].copy_from_slice(&self.r.encode().encode());
+        res[40..].copy_from_slice(&self.s.encode());
+        res
+    }
+
+    /// Fails on invalid nonces, see Point::decode_checked, and on non
+    /// canonical or null s
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != 80 {
+            return Err(Error::Length {
+                expected: 80,
+                got: bytes.len(),
+            });
+        }
+        let r = Point::decode_checked(&bytes[..40])?;
+        let (s, c) = Scalar::decode(&bytes[40..]);
+        if c != u64::MAX || s.iszero() != 0 {
+            return Err(Error::Scalar);
+        }
+        Ok(Self {
+            r,
+            s,
+            context: PhantomData,
+        })
+    }
+
+    /// Wire format of the proof, s being given by limbs
+    pub fn to_limbs<F: RichField>(&self) -> encoding::SchnorrLimbs<F> {
+        let mut bits = encoding::Scalar(self.s.to_bits_le());
+        let s = bits.pack();
+        bits.0.zeroize();
+        encoding::SchnorrLimbs {
+            r: self.r.to_field(),
+            s,
+        }
+    }
+}
+
+impl<C, F: RichField> ToSchnorrField<F, bool> for SchnorrProof<C> {
     fn to_field(&self) -> encoding::SchnorrProof<F, bool> {
         encoding::SchnorrProof {
             r: self.r.to_field(),
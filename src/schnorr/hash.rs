@@ -1,44 +1,66 @@
-use plonky2::field::goldilocks_field::GoldilocksField;
-use plonky2::field::types::{Field, PrimeField64};
-use plonky2::hash::poseidon::PoseidonHash;
-use plonky2::plonk::config::Hasher;
+use plonky2::field::{goldilocks_field::GoldilocksField, types::Field};
 
 use crate::arith::Scalar;
-use crate::encoding::LEN_SCALAR;
-
-fn u64_to_bits_le(mut v: u64, out: &mut Vec<bool>, n: usize) {
-    for _ in 0..n {
-        out.push((v & 1) == 1);
-        v >>= 1;
-    }
-}
+use crate::schnorr::transcript::message_to_goldilocks;
+use crate::schnorr::xof::{self, Native};
 
 /// Performs poseidon on the provided message to return a scalar.
 /// This function is not safe for nonce generation
 pub fn poseidon_xof_bits_native(base_inputs: &[GoldilocksField]) -> Scalar {
-    let mut bits = Vec::with_capacity(LEN_SCALAR);
+    let bits = xof::expand(&mut Native, base_inputs.to_vec());
+    // reduced modulo n, which circuits can skip: e*P is the same point
+    Scalar::from_bits_le(&bits)
+}
 
-    // h0
-    let h0 = PoseidonHash::hash_no_pad(base_inputs);
-    for &x in &h0.elements {
-        u64_to_bits_le(x.to_canonical_u64(), &mut bits, 64);
-    }
+/// Derives a scalar from field elements under a domain tag, e.g.
+/// b"ZKYC_NULLIFIER_V1", with the XOF of the Schnorr challenges, see
+/// schnorr::xof. The XOF absorbs
+///   len(domain) || domain packed by message_to_goldilocks || inputs
+/// so that distinct (domain, inputs) pairs never share a preimage. Schnorr
+/// challenges are untagged (see transcript::hash), each new use of this
+/// function must come with its own tag.
+/// The output is reduced modulo n: Scalar::encode / Scalar::to_bits_le
+/// give the same scalar in other implementations.
+pub fn hash_to_scalar(domain: &[u8], inputs: &[GoldilocksField]) -> Scalar {
+    let domain_limbs = message_to_goldilocks(domain);
+    let mut to_hash = Vec::with_capacity(1 + domain_limbs.len() + inputs.len());
+    to_hash.push(GoldilocksField::from_canonical_usize(domain.len()));
+    to_hash.extend(domain_limbs);
+    to_hash.extend_from_slice(inputs);
+    poseidon_xof_bits_native(&to_hash)
+}
+
+/// hash_to_scalar of a byte message, packed as len(message) || message by
+/// message_to_goldilocks, so that trailing zero bytes change the scalar
+pub fn hash_bytes_to_scalar(domain: &[u8], message: &[u8]) -> Scalar {
+    let mut inputs = vec![GoldilocksField::from_canonical_usize(message.len())];
+    inputs.extend(message_to_goldilocks(message));
+    hash_to_scalar(domain, &inputs)
+}
 
-    // blocs additionnels h1, h2, ...
-    let mut ctr = GoldilocksField::ONE;
-    while bits.len() < LEN_SCALAR {
-        let mut inp = vec![ctr];
-        inp.extend_from_slice(&h0.elements);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let hi = PoseidonHash::hash_no_pad(&inp);
-        for &x in &hi.elements {
-            u64_to_bits_le(x.to_canonical_u64(), &mut bits, 64);
+    #[test]
+    fn hash_to_scalar_separates_domains_and_lengths() {
+        let x = hash_bytes_to_scalar(b"ZKYC_TEST_V1", b"message");
+        assert_eq!(
+            x.equals(hash_bytes_to_scalar(b"ZKYC_TEST_V1", b"message")),
+            u64::MAX
+        );
+        let others = [
+            hash_bytes_to_scalar(b"ZKYC_TEST_V2", b"message"),
+            hash_bytes_to_scalar(b"ZKYC_TEST_V1", b"message\0"),
+            hash_bytes_to_scalar(b"ZKYC_TEST_V1\0", b"message"),
+            hash_to_scalar(b"ZKYC_TEST_V1", &message_to_goldilocks(b"message")),
+        ];
+        for y in others {
+            assert_eq!(x.equals(y), 0);
         }
-        ctr += GoldilocksField::ONE;
+        // canonical, so that encodings are portable
+        let (decoded, c) = Scalar::decode(&x.encode());
+        assert_eq!(c, u64::MAX);
+        assert_eq!(decoded.equals(x), u64::MAX);
     }
-
-    bits.truncate(LEN_SCALAR);
-    // FIXME: check if ignoring overflow here is ok
-    let bits: [bool; LEN_SCALAR] = bits.try_into().unwrap();
-    Scalar::from_bits_le(&bits)
 }
@@ -1,5 +1,20 @@
-use crate::arith::{Point, Scalar};
+use crate::arith::{curve, Point, Scalar};
 use rand::{rand_core, Rng};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error(transparent)]
+    Point(#[from] curve::Error),
+    #[error("Neutral public key")]
+    NeutralKey,
+    #[error("Expected {expected} bytes, got {got}")]
+    Length { expected: usize, got: usize },
+    #[error("Non canonical or null scalar")]
+    Scalar,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
 
 pub struct SecretKey(pub(crate) Scalar);
 
@@ -15,12 +30,44 @@ impl SecretKey {
     pub fn random(rng: &mut impl Rng) -> Self {
         Self(Scalar::random_from_rng(rng))
     }
+    pub fn to_bytes(&self) -> [u8; 40] {
+        self.0.encode()
+    }
+    /// Fails on non canonical encodings and on zero
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != 40 {
+            return None;
+        }
+        let (key, c) = Scalar::decode(bytes);
+        (c == u64::MAX && key.iszero() == 0).then_some(Self(key))
+    }
 }
 
+impl PartialEq for PublicKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.equals(other.0) == u64::MAX
+    }
+}
+
+impl Eq for PublicKey {}
+
 impl PublicKey {
     pub fn from(sk: &SecretKey) -> Self {
         Self(Point::mulgen(sk.0))
     }
+    /// Canonical encoding of the point
+    pub fn to_bytes(&self) -> [u8; 40] {
+        self.0.encode().encode()
+    }
+    /// Fails on invalid encodings and on the neutral point, see
+    /// Point::decode_checked
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let point = Point::decode_checked(bytes)?;
+        if point.isneutral() != 0 {
+            return Err(Error::NeutralKey);
+        }
+        Ok(Self(point))
+    }
 }
 
 #[cfg(test)]
@@ -39,6 +86,38 @@ mod tests {
         assert!(pk.0.equals(expected) == u64::MAX);
     }
 
+    #[test]
+    fn keys_bytes_round_trip() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let sk = SecretKey::random(&mut rng);
+        let pk = PublicKey::from(&sk);
+
+        let sk2 = SecretKey::from_bytes(&sk.to_bytes()).unwrap();
+        assert!(sk.0.equals(sk2.0) == u64::MAX);
+        let pk2 = PublicKey::from_bytes(&pk.to_bytes()).unwrap();
+        assert!(pk.0.equals(pk2.0) == u64::MAX);
+
+        assert!(SecretKey::from_bytes(&[0; 40]).is_none());
+        assert_eq!(PublicKey::from_bytes(&[0; 40]), Err(Error::NeutralKey));
+        assert_eq!(
+            PublicKey::from_bytes(&[0xff; 40]),
+            Err(Error::Point(curve::Error::NonCanonical))
+        );
+        assert_eq!(
+            PublicKey::from_bytes(&pk.to_bytes()[1..]),
+            Err(Error::Point(curve::Error::Length(39)))
+        );
+        // about half of the field elements are not the encoding of a point
+        let mut w = pk.to_bytes();
+        while Point::decode_checked(&w).is_ok() {
+            w[0] = w[0].wrapping_add(1);
+        }
+        assert_eq!(
+            PublicKey::from_bytes(&w),
+            Err(Error::Point(curve::Error::NotInGroup))
+        );
+    }
+
     #[test]
     fn secret_key_random_is_deterministic_for_seeded_rng() {
         let mut rng1 = StdRng::seed_from_u64(123456);
@@ -2,5 +2,12 @@ pub mod authentification;
 mod core;
 pub mod hash;
 pub mod keys;
+#[cfg(feature = "precompute-large")]
+mod precompute;
 pub mod signature;
 pub mod transcript;
+pub mod xof;
+
+pub use self::core::SchnorrProof;
+pub use hash::{hash_bytes_to_scalar, hash_to_scalar};
+pub use transcript::Transcript;
@@ -0,0 +1,72 @@
+// Larger fixed-base tables for verifiers (feature precompute-large): the
+// generator table has a wider window than the built-in one of mulgen, and
+// each long-lived key (see Transcript::LONG_LIVED_KEY) gets its own table on
+// first use. Lookups are variable-time, so only for public scalars.
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::{Arc, LazyLock, Mutex, PoisonError},
+};
+
+use crate::{
+    arith::{FixedBaseTable, Point, Scalar},
+    schnorr::keys::PublicKey,
+};
+
+/// 8 * 2^7 points, i.e. 80 kB
+const GENERATOR_WINDOW: usize = 8;
+/// 8 * 2^5 points, i.e. 20 kB per key
+const KEY_WINDOW: usize = 6;
+/// Beyond which keys are multiplied without table
+const MAX_KEYS: usize = 64;
+
+static GENERATOR: LazyLock<FixedBaseTable> = LazyLock::new(|| {
+    FixedBaseTable::new(Point::GENERATOR, GENERATOR_WINDOW).expect("valid window")
+});
+
+static KEYS: LazyLock<Mutex<HashMap<[u8; 40], Arc<FixedBaseTable>>>> =
+    LazyLock::new(Default::default);
+
+pub(crate) fn mulgen_vartime(s: Scalar) -> Point {
+    GENERATOR.mul_vartime(s)
+}
+
+pub(crate) fn mul_key_vartime(pk: &PublicKey, e: Scalar) -> Point {
+    let table = {
+        let mut keys = KEYS.lock().unwrap_or_else(PoisonError::into_inner);
+        let len = keys.len();
+        match keys.entry(pk.to_bytes()) {
+            Entry::Occupied(entry) => Some(entry.get().clone()),
+            Entry::Vacant(entry) if len < MAX_KEYS => {
+                let table = FixedBaseTable::new(pk.0, KEY_WINDOW).expect("valid window");
+                Some(entry.insert(Arc::new(table)).clone())
+            }
+            Entry::Vacant(_) => None,
+        }
+    };
+    match table {
+        Some(table) => table.mul_vartime(e),
+        None => pk.0 * e,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::schnorr::keys::SecretKey;
+
+    #[test]
+    fn tables_match_the_multiplications() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let pk = PublicKey::from(&SecretKey::random(&mut rng));
+        for _ in 0..4 {
+            let e = Scalar::random_from_rng(&mut rng);
+            assert!(mulgen_vartime(e).equals(Point::mulgen(e)) == u64::MAX);
+            // built on first use, then cached
+            assert!(mul_key_vartime(&pk, e).equals(pk.0 * e) == u64::MAX);
+        }
+        assert!(KEYS.lock().unwrap().contains_key(&pk.to_bytes()));
+    }
+}
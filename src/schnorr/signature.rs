@@ -1,19 +1,17 @@
 use plonky2::field::goldilocks_field::GoldilocksField;
-use plonky2::hash::hash_types::RichField;
 
 use crate::core::credential::Credential;
 use crate::encoding;
-use crate::encoding::conversion::{ToSchnorrField, ToSignatureField};
 
 use super::core::SchnorrProof;
-/// Signature will be used by the authority to sign the credential
-/// We expect the authority to sign a lot of messages with the same secret key
-use super::keys::{PublicKey, SecretKey};
-use super::transcript;
+use super::keys::PublicKey;
 
 type Message = [GoldilocksField; encoding::LEN_CREDENTIAL];
 
-pub struct Signature(pub(crate) SchnorrProof);
+/// Signature will be used by the authority to sign the credential
+/// We expect the authority to sign a lot of messages with the same secret key
+pub type Signature = SchnorrProof<Context>;
+
 pub struct Context {
     public_key: PublicKey,
     message: Message,
@@ -36,30 +34,6 @@ impl Context {
     pub fn message(&self) -> &Message {
         &self.message
     }
-
-    pub fn to_context(&self) -> transcript::Context<'_> {
-        transcript::Context::Sig(self)
-    }
-}
-
-impl Signature {
-    /// returns a signature of the given message with the given secret key
-    // TODO: pk is not needed for the prover, maybe it could be better to
-    // remove it from here
-    pub fn sign(sk: &SecretKey, ctx: &Context) -> Self {
-        Self(SchnorrProof::prove(sk, ctx.to_context()))
-    }
-
-    /// verifies the signature produced by sign for the given message
-    pub fn verify(&self, ctx: &Context) -> bool {
-        self.0.verify(ctx.to_context())
-    }
-}
-
-impl<F: RichField> ToSignatureField<F, bool> for Signature {
-    fn to_field(&self) -> encoding::Signature<F, bool> {
-        encoding::Signature(self.0.to_field())
-    }
 }
 
 #[cfg(test)]
@@ -84,7 +58,7 @@ mod tests {
         let (_, sk, credential) = Credential::from_seed(1);
         let ctx = Context::new(&credential);
 
-        let sig = Signature::sign(&sk, &ctx);
+        let sig = Signature::prove(&sk, &ctx);
         assert!(sig.verify(&ctx));
     }
 
@@ -93,7 +67,7 @@ mod tests {
         let (_, sk, mut credential) = Credential::from_seed(2);
 
         let ctx_good = Context::new(&credential);
-        let sig = Signature::sign(&sk, &ctx_good);
+        let sig = Signature::prove(&sk, &ctx_good);
 
         credential.switch_names_char();
 
@@ -108,7 +82,7 @@ mod tests {
         let (sk1, cred1, _sk2, cred2) = same_credential_different_issuer(4);
 
         let ctx1 = Context::new(&cred1);
-        let sig = Signature::sign(&sk1, &ctx1);
+        let sig = Signature::prove(&sk1, &ctx1);
 
         let ctx_other_pk = Context::new(&cred2);
         assert!(!sig.verify(&ctx_other_pk));
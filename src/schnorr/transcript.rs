@@ -5,19 +5,27 @@ use crate::{
 };
 use plonky2::field::{goldilocks_field::GoldilocksField, types::Field};
 
-pub enum Context<'a> {
-    Auth(&'a authentification::Context),
-    Sig(&'a signature::Context),
-}
-impl<'a> Context<'a> {
-    pub fn public_key(&'a self) -> &'a PublicKey {
-        match self {
-            Self::Auth(ctx) => ctx.public_key(),
-            Self::Sig(ctx) => ctx.public_key(),
-        }
+/// What, besides the nonce, is hashed to derive the Fiat-Shamir challenge of
+/// a Schnorr proof. Signatures and authentifications only differ by their
+/// transcript.
+pub trait Transcript {
+    /// public key of the prover
+    fn public_key(&self) -> &PublicKey;
+    /// message absorbed after the nonce
+    fn transcript(&self) -> Vec<GoldilocksField>;
+    /// Whether the key is used for many proofs, e.g. by an issuer, so that
+    /// verifiers may precompute tables for it
+    const LONG_LIVED_KEY: bool = false;
+    /// Seconds since UNIX epoch after which the context is expired, if any:
+    /// SchnorrProof::verify rejects such contexts, which need the time
+    fn expires_at(&self) -> Option<u32> {
+        None
     }
 }
 
+// Only used natively, so unlike the other transcripts it keeps its tag
+const SERVICE_TAG: &[u8] = b"ZKYC_SCHNORR_SRV_CHALLENGE_V1";
+
 pub fn point_to_vec_goldilocks(x: &Point) -> [GoldilocksField; LEN_POINT] {
     // TODO: Use encode ?
     x.to_field().into()
@@ -39,38 +47,97 @@ pub fn message_to_goldilocks(message: &[u8]) -> Vec<GoldilocksField> {
     goldilocks_vec
 }
 
+/// Absorbs whether an optional field is present and its length before its
+/// value, so that a field can't be read as another, or as nothing. Mirrored
+/// by circuit::authentification
+fn push_optional(message: &mut Vec<GoldilocksField>, value: Option<&[GoldilocksField]>) {
+    message.push(GoldilocksField::from_bool(value.is_some()));
+    let value = value.unwrap_or_default();
+    message.push(GoldilocksField::from_canonical_usize(value.len()));
+    message.extend_from_slice(value);
+}
+
+impl Transcript for authentification::Context {
+    fn public_key(&self) -> &PublicKey {
+        self.public_key()
+    }
+    fn expires_at(&self) -> Option<u32> {
+        self.expires_at()
+    }
+    fn transcript(&self) -> Vec<GoldilocksField> {
+        let mut f_message = Vec::new();
+        f_message.extend_from_slice(
+            &self
+                .service()
+                .0
+                .map(|x| GoldilocksField::from_canonical_u64(x.0)),
+        );
+        f_message.extend_from_slice(
+            &self
+                .nonce()
+                .0
+                .map(|x| GoldilocksField::from_canonical_u64(x.0)),
+        );
+        // Public key is already in the credential
+        f_message.extend_from_slice(&point_to_vec_goldilocks(&self.public_key().0));
+        push_optional(
+            &mut f_message,
+            self.channel_binding().map(|channel_binding| &channel_binding.0[..]),
+        );
+        let expires_at = self.expires_at().map(GoldilocksField::from_canonical_u32);
+        push_optional(&mut f_message, expires_at.as_ref().map(std::slice::from_ref));
+        let account = self.account().map(|account| {
+            let mut value = account.value.0.to_vec();
+            value.push(account.len);
+            value
+        });
+        push_optional(&mut f_message, account.as_deref());
+        f_message
+    }
+}
+
+impl Transcript for authentification::ServiceContext {
+    fn public_key(&self) -> &PublicKey {
+        self.public_key()
+    }
+    fn transcript(&self) -> Vec<GoldilocksField> {
+        let mut f_message = message_to_goldilocks(SERVICE_TAG);
+        f_message.extend_from_slice(
+            &self
+                .service()
+                .0
+                .map(|x| GoldilocksField::from_canonical_u64(x.0)),
+        );
+        f_message.extend_from_slice(
+            &self
+                .nonce()
+                .0
+                .map(|x| GoldilocksField::from_canonical_u64(x.0)),
+        );
+        f_message.extend_from_slice(&point_to_vec_goldilocks(&self.client_key().0));
+        f_message.extend_from_slice(&point_to_vec_goldilocks(&self.public_key().0));
+        f_message
+    }
+}
+
+impl Transcript for signature::Context {
+    const LONG_LIVED_KEY: bool = true;
+    fn public_key(&self) -> &PublicKey {
+        self.public_key()
+    }
+    fn transcript(&self) -> Vec<GoldilocksField> {
+        self.message()
+            .map(|x| GoldilocksField::from_canonical_u64(x.0))
+            .to_vec()
+    }
+}
+
 // FIXME: Add the tag back, (was removed for simplification in the circuit)
-pub fn hash(nonce: &Point, ctx: Context) -> Scalar {
-    // let tag = match ctx {
-    //     Context::Auth(_) => b"ZKYC_SCHNORR_AUT_CHALLENGE_V1",
-    //     Context::Sig(_) => b"ZKYC_SCHNORR_SIG_CHALLENGE_V1",
-    // };
+pub fn hash(nonce: &Point, ctx: &impl Transcript) -> Scalar {
+    // let tag = b"ZKYC_SCHNORR_AUT_CHALLENGE_V1" or b"ZKYC_SCHNORR_SIG_CHALLENGE_V1";
     // let mut f_message = message_to_goldilocks(tag);
-    let mut f_message = Vec::new();
-    match ctx {
-        Context::Auth(ctx) => {
-            f_message.extend_from_slice(
-                &ctx.service()
-                    .0
-                    .map(|x| GoldilocksField::from_canonical_u64(x.0)),
-            );
-            f_message.extend_from_slice(
-                &ctx.nonce()
-                    .0
-                    .map(|x| GoldilocksField::from_canonical_u64(x.0)),
-            );
-            // Public key is already in the credential
-            f_message.extend_from_slice(&point_to_vec_goldilocks(&ctx.public_key().0));
-        }
-        Context::Sig(ctx) => {
-            f_message.extend_from_slice(
-                &ctx.message()
-                    .map(|x| GoldilocksField::from_canonical_u64(x.0)),
-            );
-        }
-    };
     let mut to_hash = point_to_vec_goldilocks(nonce).to_vec();
-    to_hash.extend_from_slice(&f_message);
+    to_hash.extend(ctx.transcript());
     hash::poseidon_xof_bits_native(&to_hash)
 }
 #[cfg(test)]
@@ -122,8 +189,8 @@ mod tests {
         let ctx1 = signature::Context::new(&cred1);
         let ctx2 = signature::Context::new(&cred2);
 
-        let e1 = hash(&r, ctx1.to_context());
-        let e2 = hash(&r, ctx2.to_context());
+        let e1 = hash(&r, &ctx1);
+        let e2 = hash(&r, &ctx2);
 
         assert!(
             e1.equals(e2) == 0,
@@ -140,9 +207,9 @@ mod tests {
         let ctx2 = authentification::Context::new(&pk, "svcB", "nonce1");
         let ctx3 = authentification::Context::new(&pk, "svcA", "nonce2");
 
-        let e1 = hash(&r, ctx1.to_context());
-        let e2 = hash(&r, ctx2.to_context());
-        let e3 = hash(&r, ctx3.to_context());
+        let e1 = hash(&r, &ctx1);
+        let e2 = hash(&r, &ctx2);
+        let e3 = hash(&r, &ctx3);
 
         assert!(
             e1.equals(e2) == 0,
@@ -154,6 +221,45 @@ mod tests {
         );
     }
 
+    #[test]
+    fn auth_transcript_frames_the_channel_binding() {
+        let pk = pk_from_seed(7);
+        let unbound = authentification::Context::new(&pk, "svcA", "nonce1");
+        let zero_bound = authentification::Context::new(&pk, "svcA", "nonce1")
+            .with_channel_binding(&[0; 32]);
+        let r = nonce_point_from_seed(8);
+        assert!(
+            hash(&r, &unbound).equals(hash(&r, &zero_bound)) == 0,
+            "a zero channel binding must not read as none"
+        );
+    }
+
+    #[test]
+    fn auth_transcript_frames_the_expiry() {
+        let pk = pk_from_seed(9);
+        let no_expiry = authentification::Context::new(&pk, "svcA", "nonce1");
+        let zero_expiry = authentification::Context::new(&pk, "svcA", "nonce1").with_expiry(0);
+        let r = nonce_point_from_seed(10);
+        assert!(
+            hash(&r, &no_expiry).equals(hash(&r, &zero_expiry)) == 0,
+            "a zero expiry must not read as none"
+        );
+    }
+
+    #[test]
+    fn auth_transcript_frames_the_account() {
+        let pk = pk_from_seed(11);
+        let unbound = authentification::Context::new(&pk, "svcA", "nonce1");
+        let empty_account = authentification::Context::new(&pk, "svcA", "nonce1")
+            .with_account(&[])
+            .unwrap();
+        let r = nonce_point_from_seed(12);
+        assert!(
+            hash(&r, &unbound).equals(hash(&r, &empty_account)) == 0,
+            "an empty account must not read as none"
+        );
+    }
+
     #[test]
     fn challenge_is_bound_to_public_key() {
         let r = nonce_point_from_seed(4242);
@@ -164,8 +270,8 @@ mod tests {
         let ctx_pk1 = authentification::Context::new(&pk1, &msg, &msg);
         let ctx_pk2 = authentification::Context::new(&pk2, &msg, &msg);
 
-        let e1 = hash(&r, ctx_pk1.to_context());
-        let e2 = hash(&r, ctx_pk2.to_context());
+        let e1 = hash(&r, &ctx_pk1);
+        let e2 = hash(&r, &ctx_pk2);
 
         assert!(
             e1.equals(e2) == 0,
@@ -180,8 +286,8 @@ mod tests {
         let ctx_pk1 = signature::Context::new(&cred_pk1);
         let ctx_pk2 = signature::Context::new(&cred_pk2);
 
-        let e1 = hash(&r, ctx_pk1.to_context());
-        let e2 = hash(&r, ctx_pk2.to_context());
+        let e1 = hash(&r, &ctx_pk1);
+        let e2 = hash(&r, &ctx_pk2);
 
         assert!(
             e1.equals(e2) == 0,
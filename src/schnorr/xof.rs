@@ -0,0 +1,159 @@
+// Specification of the XOF deriving Schnorr challenges, shared by the native
+// and in-circuit verifiers so that they can't drift:
+//   absorb len(inputs) || inputs into a Poseidon sponge
+//   squeeze ceil(LEN_SCALAR / 64) elements
+//   challenge = first LEN_SCALAR little-endian bits of the squeezed elements
+//
+// The sponge is plonky2's hash_n_to_m_no_pad, under which inputs and
+// inputs || 0 collide: the length prefix binds the challenge to the length of
+// the transcript. In circuits the length is a constant, the transcript having
+// a fixed shape. The squeezed elements fit in the rate, so the challenge costs
+// the permutations of the absorption only.
+
+use plonky2::{
+    field::{
+        extension::Extendable,
+        goldilocks_field::GoldilocksField,
+        types::{Field, PrimeField64},
+    },
+    hash::{
+        hash_types::RichField,
+        hashing::hash_n_to_m_no_pad,
+        poseidon::{PoseidonHash, PoseidonPermutation},
+    },
+    iop::target::{BoolTarget, Target},
+    plonk::circuit_builder::CircuitBuilder,
+};
+
+use crate::encoding::LEN_SCALAR;
+
+/// Elements squeezed for a challenge
+const LEN_SQUEEZE: usize = LEN_SCALAR.div_ceil(64);
+
+/// Operations the XOF is built from
+pub trait Sponge {
+    type Element: Copy;
+    type Bit;
+    /// Absorbs the inputs, then squeezes num_outputs elements
+    fn absorb_squeeze(
+        &mut self,
+        inputs: Vec<Self::Element>,
+        num_outputs: usize,
+    ) -> Vec<Self::Element>;
+    fn integer(&mut self, i: u64) -> Self::Element;
+    /// Little-endian bits of the element
+    fn bits(&mut self, x: Self::Element) -> Vec<Self::Bit>;
+}
+
+/// Expands the inputs into LEN_SCALAR bits
+pub fn expand<S: Sponge>(sponge: &mut S, inputs: Vec<S::Element>) -> [S::Bit; LEN_SCALAR] {
+    let mut prefixed = Vec::with_capacity(1 + inputs.len());
+    prefixed.push(sponge.integer(inputs.len() as u64));
+    prefixed.extend(inputs);
+    let mut bits = Vec::with_capacity(64 * LEN_SQUEEZE);
+    for x in sponge.absorb_squeeze(prefixed, LEN_SQUEEZE) {
+        bits.extend(sponge.bits(x));
+    }
+    bits.truncate(LEN_SCALAR);
+    bits.try_into().ok().unwrap()
+}
+
+pub struct Native;
+
+impl Sponge for Native {
+    type Element = GoldilocksField;
+    type Bit = bool;
+
+    fn absorb_squeeze(
+        &mut self,
+        inputs: Vec<GoldilocksField>,
+        num_outputs: usize,
+    ) -> Vec<GoldilocksField> {
+        hash_n_to_m_no_pad::<GoldilocksField, PoseidonPermutation<GoldilocksField>>(
+            &inputs,
+            num_outputs,
+        )
+    }
+    fn integer(&mut self, i: u64) -> GoldilocksField {
+        GoldilocksField::from_canonical_u64(i)
+    }
+    fn bits(&mut self, x: GoldilocksField) -> Vec<bool> {
+        let x = x.to_canonical_u64();
+        (0..64).map(|i| (x >> i) & 1 == 1).collect()
+    }
+}
+
+impl<F: RichField + Extendable<D>, const D: usize> Sponge for CircuitBuilder<F, D> {
+    type Element = Target;
+    type Bit = BoolTarget;
+
+    fn absorb_squeeze(&mut self, inputs: Vec<Target>, num_outputs: usize) -> Vec<Target> {
+        self.hash_n_to_m_no_pad::<PoseidonHash>(inputs, num_outputs)
+    }
+    fn integer(&mut self, i: u64) -> Target {
+        self.constant(F::from_canonical_u64(i))
+    }
+    fn bits(&mut self, x: Target) -> Vec<BoolTarget> {
+        self.split_le(x, 64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        iop::witness::{PartialWitness, WitnessWrite},
+        plonk::{
+            circuit_data::CircuitConfig,
+            config::{Hasher, PoseidonGoldilocksConfig},
+        },
+    };
+
+    use super::*;
+
+    type F = GoldilocksField;
+    const D: usize = 2;
+
+    #[test]
+    fn circuit_matches_native() {
+        for len in [0, 1, 12, 40] {
+            let inputs: Vec<F> = (0..len).map(|i| F::from_canonical_u64(7 * i + 3)).collect();
+            let expected = expand(&mut Native, inputs.clone());
+
+            let mut builder = CircuitBuilder::<F, D>::new(CircuitConfig::default());
+            let targets = builder.add_virtual_targets(len as usize);
+            let bits = expand(&mut builder, targets.clone());
+            for b in bits {
+                builder.register_public_input(b.target);
+            }
+            let mut pw = PartialWitness::<F>::new();
+            for (t, x) in targets.iter().zip(&inputs) {
+                pw.set_target(*t, *x).unwrap();
+            }
+            let data = builder.build::<PoseidonGoldilocksConfig>();
+            let proof = data.prove(pw).unwrap();
+
+            let bits: Vec<bool> = proof.public_inputs.iter().map(F::is_one).collect();
+            assert_eq!(bits, expected, "inputs of length {len}");
+        }
+    }
+
+    #[test]
+    fn expansion_starts_with_h0() {
+        let inputs = vec![F::ONE, F::TWO];
+        let h0 = PoseidonHash::hash_no_pad(&[F::TWO, F::ONE, F::TWO]).elements;
+        let bits = expand(&mut Native, inputs);
+        let low: u64 = (0..64).map(|i| (bits[i] as u64) << i).sum();
+        assert_eq!(low, h0[0].to_canonical_u64());
+    }
+
+    #[test]
+    fn expansion_binds_the_length() {
+        let inputs = vec![F::ONE, F::TWO];
+        let padded = vec![F::ONE, F::TWO, F::ZERO];
+        assert_eq!(
+            PoseidonHash::hash_no_pad(&inputs),
+            PoseidonHash::hash_no_pad(&padded)
+        );
+        assert_ne!(expand(&mut Native, inputs), expand(&mut Native, padded));
+    }
+}
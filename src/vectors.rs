@@ -0,0 +1,362 @@
+// Deterministic test vectors, so that independent implementations (e.g. a JS
+// verifier) can be checked against this crate byte-for-byte.
+//
+// A vector is derived from a seed below issuer::database::SIZE: the credential
+// of Credential::from_seed, its signature and an authentification to the test
+// bank, whose Schnorr nonces are drawn from rngs seeded by the seed and a tag.
+// Field elements are canonical u64s, bytes are hex.
+//
+// Proofs are optional, as they take seconds. plonky2 doesn't promise to
+// reproduce a proof, so recorded proofs are verified rather than regenerated.
+
+use plonky2::field::{
+    goldilocks_field::GoldilocksField,
+    types::{Field64, PrimeField64},
+};
+use rand::{rngs::StdRng, SeedableRng};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::{
+    bank,
+    circuit::{self, Circuit, ZkProof},
+    client::consent::{from_hex, hex},
+    core::{
+        credential::Credential,
+        date::{today_for_tests, ADULT_AGE_DAYS},
+    },
+    encoding::{
+        conversion::{ToPointField, ToSingleField, ToStringField},
+        LEN_HASH,
+    },
+    issuer::{database::for_tests::DATABASE, pseudonym},
+    merkle,
+    schnorr::{
+        authentification::{Authentification, Context as AuthentificationContext},
+        signature::{Context as SignatureContext, Signature},
+        transcript::Transcript,
+    },
+};
+
+/// Bumped whenever the derivation of a vector changes
+pub const VERSION: u64 = 1;
+
+const SIGNATURE_TAG: &[u8; 8] = b"ZKYCVSIG";
+const AUTHENTIFICATION_TAG: &[u8; 8] = b"ZKYCVAUT";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Malformed vectors: {0}")]
+    Json(String),
+    #[error("Unsupported vectors version {0}")]
+    UnsupportedVersion(u64),
+    #[error("Missing or invalid field {0}")]
+    InvalidField(&'static str),
+    #[error("Seed {0} is not in the test database")]
+    Seed(u64),
+    #[error("Vector {seed} differs on {field}")]
+    Mismatch { seed: u64, field: &'static str },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Vector {
+    pub seed: u64,
+    /// Credential::to_bytes
+    pub credential: String,
+    /// Transcript of the signature, absorbed after its nonce
+    pub message: Vec<u64>,
+    pub signature: String,
+    /// Fiat-Shamir challenge of the signature, Scalar::encode
+    pub signature_challenge: String,
+    pub service: String,
+    pub nonce: String,
+    /// Transcript of the authentification, absorbed after its nonce
+    pub authentification_message: Vec<u64>,
+    pub authentification: String,
+    pub authentification_challenge: String,
+    pub pseudonym: [u64; LEN_HASH],
+    pub merkle_root: [u64; LEN_HASH],
+    /// ZkProof::to_bytes, see with_proof
+    pub proof: Option<String>,
+}
+
+fn rng(tag: &[u8; 8], seed: u64) -> StdRng {
+    let mut bytes = [0; 32];
+    bytes[..8].copy_from_slice(tag);
+    bytes[8..16].copy_from_slice(&seed.to_le_bytes());
+    StdRng::from_seed(bytes)
+}
+
+fn canonical(x: &[GoldilocksField]) -> Vec<u64> {
+    x.iter().map(|x| x.to_canonical_u64()).collect()
+}
+
+fn canonical_hash(x: &crate::encoding::Hash<GoldilocksField>) -> [u64; LEN_HASH] {
+    x.0.map(|x| x.to_canonical_u64())
+}
+
+/// Everything from_seed derives, but the proof
+struct Derived {
+    credential: Credential,
+    signature: Signature,
+    authentification: Authentification,
+}
+
+fn derive(seed: u64) -> Result<Derived> {
+    if seed as usize >= crate::issuer::database::SIZE {
+        return Err(Error::Seed(seed));
+    }
+    let (client_sk, issuer_sk, credential) = Credential::from_seed(seed);
+    let signature = Signature::prove_with_rng(
+        &issuer_sk,
+        &SignatureContext::new(&credential),
+        &mut rng(SIGNATURE_TAG, seed),
+    );
+    let auth_ctx =
+        AuthentificationContext::new(&credential.public_key(), &bank::service(), &bank::nonce());
+    let authentification = Authentification::prove_with_rng(
+        &client_sk,
+        &auth_ctx,
+        &mut rng(AUTHENTIFICATION_TAG, seed),
+    );
+    Ok(Derived {
+        credential,
+        signature,
+        authentification,
+    })
+}
+
+/// Public inputs of the proofs of the vectors, with the base circuit
+fn public_inputs(credential: &Credential) -> circuit::inputs::Public<circuit::F> {
+    let service = bank::service();
+    circuit::inputs::Public::register(
+        today_for_tests().to_field(),
+        ADULT_AGE_DAYS.to_field(),
+        Some(credential.nationality().to_field()),
+        credential.issuer().0.to_field(),
+        bank::nonce().to_field(),
+        service.to_field(),
+        pseudonym::hash_from_service(&service, &credential.public_key()),
+        DATABASE.root(),
+    )
+}
+
+impl Vector {
+    /// Fails on seeds outside of the test database
+    pub fn from_seed(seed: u64) -> Result<Self> {
+        let Derived {
+            credential,
+            signature,
+            authentification,
+        } = derive(seed)?;
+        let signature_ctx = SignatureContext::new(&credential);
+        let service = bank::service();
+        let nonce = bank::nonce();
+        let auth_ctx = AuthentificationContext::new(&credential.public_key(), &service, &nonce);
+        Ok(Self {
+            seed,
+            credential: hex(&credential.to_bytes()),
+            message: canonical(&signature_ctx.transcript()),
+            signature: hex(&signature.to_bytes()),
+            signature_challenge: hex(&signature.challenge(&signature_ctx).encode()),
+            authentification_message: canonical(&auth_ctx.transcript()),
+            authentification: hex(&authentification.to_bytes()),
+            authentification_challenge: hex(&authentification.challenge(&auth_ctx).encode()),
+            pseudonym: canonical_hash(&pseudonym::hash_from_service(
+                &service,
+                &credential.public_key(),
+            )),
+            merkle_root: canonical_hash(&DATABASE.root()),
+            service,
+            nonce,
+            proof: None,
+        })
+    }
+
+    /// Adds a proof of the base circuit (circuit_with_profile with the
+    /// default features), for today_for_tests and the adult age
+    pub fn with_proof(mut self, circuit: &Circuit) -> anyhow::Result<Self> {
+        let Derived {
+            credential,
+            signature,
+            authentification,
+        } = derive(self.seed)?;
+        let merkle_path = DATABASE.proof(&merkle::hash::credential(&credential))?;
+        let proof = circuit::prove(
+            circuit,
+            &credential,
+            &signature,
+            &authentification,
+            &merkle_path,
+            &public_inputs(&credential),
+        )?;
+        self.proof = Some(hex(&proof.to_bytes()));
+        Ok(self)
+    }
+
+    /// Recomputes the vector from its seed, and verifies its proof, if any,
+    /// with the circuit
+    pub fn check(&self, circuit: Option<&Circuit>) -> Result<()> {
+        let expected = Self::from_seed(self.seed)?;
+        let mismatch = |field| Error::Mismatch {
+            seed: self.seed,
+            field,
+        };
+        let fields = [
+            ("credential", self.credential == expected.credential),
+            ("message", self.message == expected.message),
+            ("signature", self.signature == expected.signature),
+            (
+                "signature_challenge",
+                self.signature_challenge == expected.signature_challenge,
+            ),
+            ("service", self.service == expected.service),
+            ("nonce", self.nonce == expected.nonce),
+            (
+                "authentification_message",
+                self.authentification_message == expected.authentification_message,
+            ),
+            (
+                "authentification",
+                self.authentification == expected.authentification,
+            ),
+            (
+                "authentification_challenge",
+                self.authentification_challenge == expected.authentification_challenge,
+            ),
+            ("pseudonym", self.pseudonym == expected.pseudonym),
+            ("merkle_root", self.merkle_root == expected.merkle_root),
+        ];
+        if let Some((field, _)) = fields.into_iter().find(|(_, ok)| !ok) {
+            return Err(mismatch(field));
+        }
+        if let (Some(proof), Some(circuit)) = (&self.proof, circuit) {
+            let (credential, _, _) = Credential::from_seed(self.seed);
+            let proof = from_hex(proof)
+                .and_then(|bytes| ZkProof::from_bytes(bytes, &circuit.circuit.common).ok())
+                .ok_or(Error::InvalidField("proof"))?;
+            circuit::verify(&circuit.circuit, proof, public_inputs(&credential))
+                .map_err(|_| mismatch("proof"))?;
+        }
+        Ok(())
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "seed": self.seed,
+            "credential": self.credential,
+            "message": self.message,
+            "signature": self.signature,
+            "signature_challenge": self.signature_challenge,
+            "service": self.service,
+            "nonce": self.nonce,
+            "authentification_message": self.authentification_message,
+            "authentification": self.authentification,
+            "authentification_challenge": self.authentification_challenge,
+            "pseudonym": self.pseudonym,
+            "merkle_root": self.merkle_root,
+            "proof": self.proof,
+        })
+    }
+
+    fn from_json(value: &Value) -> Result<Self> {
+        fn string(value: &Value, field: &'static str) -> Result<String> {
+            value[field]
+                .as_str()
+                .map(str::to_string)
+                .ok_or(Error::InvalidField(field))
+        }
+        fn u64s(value: &Value, field: &'static str) -> Result<Vec<u64>> {
+            value[field]
+                .as_array()
+                .and_then(|xs| xs.iter().map(Value::as_u64).collect())
+                .ok_or(Error::InvalidField(field))
+        }
+        fn hash(value: &Value, field: &'static str) -> Result<[u64; LEN_HASH]> {
+            u64s(value, field)?
+                .try_into()
+                .map_err(|_| Error::InvalidField(field))
+        }
+        let proof = match &value["proof"] {
+            Value::Null => None,
+            _ => Some(string(value, "proof")?),
+        };
+        Ok(Self {
+            seed: value["seed"].as_u64().ok_or(Error::InvalidField("seed"))?,
+            credential: string(value, "credential")?,
+            message: u64s(value, "message")?,
+            signature: string(value, "signature")?,
+            signature_challenge: string(value, "signature_challenge")?,
+            service: string(value, "service")?,
+            nonce: string(value, "nonce")?,
+            authentification_message: u64s(value, "authentification_message")?,
+            authentification: string(value, "authentification")?,
+            authentification_challenge: string(value, "authentification_challenge")?,
+            pseudonym: hash(value, "pseudonym")?,
+            merkle_root: hash(value, "merkle_root")?,
+            proof,
+        })
+    }
+}
+
+/// JSON document of the vectors, tagged with VERSION
+pub fn emit(vectors: &[Vector]) -> String {
+    let doc = json!({
+        "version": VERSION,
+        "field_order": GoldilocksField::ORDER,
+        "vectors": vectors.iter().map(Vector::to_json).collect::<Vec<_>>(),
+    });
+    serde_json::to_string_pretty(&doc).expect("json values always serialize")
+}
+
+/// Inverse of emit. Only parses: see Vector::check.
+pub fn load(json: &str) -> Result<Vec<Vector>> {
+    let doc: Value = serde_json::from_str(json).map_err(|err| Error::Json(err.to_string()))?;
+    let version = doc["version"]
+        .as_u64()
+        .ok_or(Error::InvalidField("version"))?;
+    if version != VERSION {
+        return Err(Error::UnsupportedVersion(version));
+    }
+    doc["vectors"]
+        .as_array()
+        .ok_or(Error::InvalidField("vectors"))?
+        .iter()
+        .map(Vector::from_json)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::{inputs::Features, CircuitProfile};
+
+    #[test]
+    fn loaded_vectors_check() {
+        let circuit = circuit::circuit_with_profile(Features::default(), CircuitProfile::Test);
+        let vectors = vec![
+            Vector::from_seed(1).unwrap(),
+            Vector::from_seed(2).unwrap().with_proof(&circuit).unwrap(),
+        ];
+        assert_eq!(vectors[0], Vector::from_seed(1).unwrap());
+        let loaded = load(&emit(&vectors)).unwrap();
+        assert_eq!(loaded, vectors);
+        for vector in &loaded {
+            vector.check(Some(&circuit)).unwrap();
+        }
+
+        let mut tampered = loaded[0].clone();
+        tampered.message[0] += 1;
+        assert_eq!(
+            tampered.check(None),
+            Err(Error::Mismatch {
+                seed: 1,
+                field: "message"
+            })
+        );
+        let size = crate::issuer::database::SIZE as u64;
+        assert_eq!(Vector::from_seed(size), Err(Error::Seed(size)));
+    }
+}
@@ -0,0 +1,193 @@
+// Verification over byte slices, for integrators who don't want to depend on
+// plonky2 types: the verifier key comes from Circuit::verifier_key_bytes, the
+// proof from ZkProof::to_bytes, and public inputs are canonical u64s.
+
+use chrono::NaiveDate;
+use plonky2::{
+    field::types::{Field, Field64, PrimeField64},
+    plonk::{circuit_data::VerifierCircuitData, proof::ProofWithPublicInputs},
+    util::serialization::DefaultGateSerializer,
+};
+use thiserror::Error;
+
+use crate::{
+    circuit::{self, F},
+    core::date,
+    encoding::LEN_HASH,
+};
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("Malformed verifier key")]
+    MalformedVerifierKey,
+    #[error("Malformed proof")]
+    MalformedProof,
+    #[error("Public input {0} is not a field element")]
+    NonCanonicalInput(usize),
+    #[error("Expected {expected} public inputs, the proof has {proved}")]
+    PublicInputsLength { expected: usize, proved: usize },
+    #[error("Public input {0} does not match the proof")]
+    PublicInputMismatch(usize),
+    #[error("Invalid proof")]
+    InvalidProof,
+    #[error("The proof has no public input {0}")]
+    NoPublicInput(usize),
+    #[error(transparent)]
+    Date(#[from] date::Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// What was checked by a successful verification
+#[derive(Debug, PartialEq, Eq)]
+pub struct VerificationReport {
+    /// Identifies the statement proved, see Circuit::digest
+    pub circuit_digest: [u64; LEN_HASH],
+    pub num_public_inputs: usize,
+    /// Canonical values of the public inputs proved
+    pub public_inputs: Vec<u64>,
+    /// Whether the prover had to blind the witness. Verifiers of
+    /// presentations should reject proofs without it.
+    pub zero_knowledge: bool,
+}
+
+impl VerificationReport {
+    /// Public input at index, e.g. today_days of the majority check, read as
+    /// a date
+    pub fn date(&self, index: usize) -> Result<NaiveDate> {
+        let value = self
+            .public_inputs
+            .get(index)
+            .ok_or(Error::NoPublicInput(index))?;
+        Ok(date::date_from_field(F::from_canonical_u64(*value))?)
+    }
+}
+
+/// Verifies the proof against the verifier key, and checks that it was made
+/// for exactly the expected public inputs
+pub fn verify_bytes(
+    verifier_key: &[u8],
+    proof: &[u8],
+    expected_public_inputs: &[u64],
+) -> Result<VerificationReport> {
+    let verifier = VerifierCircuitData::<F, circuit::C, { circuit::D }>::from_bytes(
+        verifier_key.to_vec(),
+        &DefaultGateSerializer,
+    )
+    .map_err(|_| Error::MalformedVerifierKey)?;
+    let proof = ProofWithPublicInputs::from_bytes(proof.to_vec(), &verifier.common)
+        .map_err(|_| Error::MalformedProof)?;
+
+    if let Some(i) = expected_public_inputs.iter().position(|&x| x >= F::ORDER) {
+        return Err(Error::NonCanonicalInput(i));
+    }
+    if proof.public_inputs.len() != expected_public_inputs.len() {
+        return Err(Error::PublicInputsLength {
+            expected: expected_public_inputs.len(),
+            proved: proof.public_inputs.len(),
+        });
+    }
+    if let Some(i) = proof
+        .public_inputs
+        .iter()
+        .zip(expected_public_inputs)
+        .position(|(proved, &expected)| proved.to_canonical_u64() != expected)
+    {
+        return Err(Error::PublicInputMismatch(i));
+    }
+
+    let report = VerificationReport {
+        circuit_digest: verifier
+            .verifier_only
+            .circuit_digest
+            .elements
+            .map(|x| x.to_canonical_u64()),
+        num_public_inputs: proof.public_inputs.len(),
+        public_inputs: expected_public_inputs.to_vec(),
+        zero_knowledge: verifier.common.config.zero_knowledge,
+    };
+    verifier.verify(proof).map_err(|_| Error::InvalidProof)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::{
+        iop::witness::{PartialWitness, WitnessWrite},
+        plonk::{
+            circuit_builder::CircuitBuilder,
+            circuit_data::{CircuitConfig, CircuitData},
+        },
+    };
+
+    use super::*;
+
+    /// Proves knowledge of a square root of the public input
+    fn square_proof(x: u64) -> (Vec<u8>, Vec<u8>, u64) {
+        let mut builder = CircuitBuilder::<F, { circuit::D }>::new(CircuitConfig::default());
+        let root = builder.add_virtual_target();
+        let square = builder.square(root);
+        builder.register_public_input(square);
+        let data: CircuitData<F, circuit::C, { circuit::D }> = builder.build();
+
+        let mut pw = PartialWitness::new();
+        pw.set_target(root, F::from_canonical_u64(x)).unwrap();
+        let proof = data.prove(pw).unwrap();
+        let verifier_key = data
+            .verifier_data()
+            .to_bytes(&DefaultGateSerializer)
+            .unwrap();
+        (verifier_key, proof.to_bytes(), x * x)
+    }
+
+    #[test]
+    fn verify_bytes_checks_proof_and_inputs() {
+        let (verifier_key, proof, square) = square_proof(12);
+
+        let report = verify_bytes(&verifier_key, &proof, &[square]).unwrap();
+        assert_eq!(report.num_public_inputs, 1);
+        assert!(!report.zero_knowledge, "plonky2 default config is not zk");
+        assert_eq!(report.date(0), Ok(date::ORIGIN + chrono::Days::new(square)));
+        assert_eq!(report.date(1), Err(Error::NoPublicInput(1)));
+
+        assert_eq!(
+            verify_bytes(&verifier_key, &proof, &[square + 1]),
+            Err(Error::PublicInputMismatch(0))
+        );
+        assert_eq!(
+            verify_bytes(&verifier_key, &proof, &[]),
+            Err(Error::PublicInputsLength {
+                expected: 0,
+                proved: 1
+            })
+        );
+        assert_eq!(
+            verify_bytes(&verifier_key, &proof, &[u64::MAX]),
+            Err(Error::NonCanonicalInput(0))
+        );
+        assert_eq!(
+            verify_bytes(&verifier_key, &proof[1..], &[square]),
+            Err(Error::MalformedProof)
+        );
+        assert_eq!(
+            verify_bytes(&verifier_key[1..], &proof, &[square]),
+            Err(Error::MalformedVerifierKey)
+        );
+    }
+
+    #[test]
+    fn verify_bytes_rejects_proofs_of_other_circuits() {
+        let (verifier_key, _, _) = square_proof(3);
+        // same statement, but proved for another circuit
+        let mut builder = CircuitBuilder::<F, { circuit::D }>::new(CircuitConfig::default());
+        let root = builder.add_virtual_target();
+        let cube = builder.exp_u64(root, 3);
+        builder.register_public_input(cube);
+        let data: CircuitData<F, circuit::C, { circuit::D }> = builder.build();
+        let mut pw = PartialWitness::new();
+        pw.set_target(root, F::from_canonical_u64(2)).unwrap();
+        let proof = data.prove(pw).unwrap();
+
+        assert!(verify_bytes(&verifier_key, &proof.to_bytes(), &[8]).is_err());
+    }
+}